@@ -0,0 +1,165 @@
+//! Baselines for the query hot paths, measured on copies of the fixture match in `tests/fixtures`.
+//! Run with `cargo bench`; `cargo bench -- --save-baseline <name>` and `--baseline <name>` compare runs.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use riven::models::match_v5::Match;
+use std::hint::black_box;
+use std::path::Path;
+
+use ptg::achievements::find_achievements;
+use ptg::api_client::{
+    build_match_details, ApiCallStats, MatchDetails, OverallOutput, PlayerIdentity, QuerySummary, UnavailableMatches,
+};
+use ptg::duo_timeline::DuoTimeline;
+use ptg::sessions::detect_sessions;
+use ptg::index::MatchIndex;
+use ptg::ranked::{QueueBreakdown, RankedSummary};
+use ptg::stats::{CarryTally, MatchStats};
+use ptg::templates::render_template_file;
+
+const FIXTURE_MATCH: &str = include_str!("../tests/fixtures/match.json");
+const PLAYER1_PUUID: &str = "fixture-puuid-0";
+const PLAYER2_PUUID: &str = "fixture-puuid-3";
+const SIZES: [usize; 3] = [10, 100, 1000];
+
+/// `count` copies of the fixture match with distinct IDs and start times, newest first.
+/// Player2 only took part in every other match, like a duo partner in a longer history.
+fn fixture_matches(count: usize) -> Vec<Match> {
+    let template: Match = serde_json::from_str(FIXTURE_MATCH).expect("fixture match should parse");
+    (0..count)
+        .map(|i| {
+            let mut match_data = template.clone();
+            match_data.metadata.match_id = format!("EUW1_{}", 7_000_000_000 + i);
+            match_data.info.game_start_timestamp -= i as i64 * 3_600_000;
+            if i % 2 == 1 {
+                let stranger = format!("stranger-puuid-{}", i);
+                match_data.info.participants[3].puuid = stranger.clone();
+                match_data.metadata.participants[3] = stranger;
+            }
+            match_data
+        })
+        .collect()
+}
+
+/// Scans each match for both players and builds the output records of the shared ones, like `run_query`.
+fn shared_match_details(matches: &[Match]) -> Vec<MatchDetails> {
+    matches
+        .iter()
+        .filter_map(|match_data| {
+            let info = &match_data.info;
+            let p1_data = info.participants.iter().find(|p| p.puuid == PLAYER1_PUUID)?;
+            let p2_data = info.participants.iter().find(|p| p.puuid == PLAYER2_PUUID)?;
+            Some(build_match_details(&match_data.metadata.match_id, info, p1_data, p2_data, "euw", None, None, None))
+        })
+        .collect()
+}
+
+fn fixture_output(matches: &[Match]) -> OverallOutput {
+    let found_matches = shared_match_details(matches);
+    let identity = |game_name: &str| PlayerIdentity {
+        game_name: game_name.to_string(),
+        tag_line: "EUW".to_string(),
+        profile: None,
+        formerly_known_as: Vec::new(),
+        nickname: None,
+    };
+    OverallOutput {
+        query_summary: QuerySummary {
+            player1: identity("Fixture0"),
+            player2: identity("Fixture3"),
+            regional_route: "EUROPE".to_string(),
+            checked_matches_count: matches.len() as u32,
+            matches_played_together_count: found_matches.len() as u32,
+            player1_wins_together_count: found_matches.len() as u32,
+            player1_puuid_found: true,
+            player2_puuid_found: true,
+            api_calls: ApiCallStats::default(),
+            ranked: RankedSummary::default(),
+            carries: CarryTally::from_matches(&found_matches),
+            per_queue: QueueBreakdown::default(),
+            unavailable_matches: UnavailableMatches::default(),
+            outline_only_matches_count: 0,
+            likely_afk_matches_count: 0,
+            venn: None,
+            widened_to_days: None,
+        },
+        stats: MatchStats::from_matches(&found_matches),
+        duo_timeline: DuoTimeline::from_matches(&found_matches),
+        sessions: detect_sessions(&found_matches),
+        achievements: find_achievements(&found_matches),
+        found_matches,
+        warnings: Vec::new(),
+    }
+}
+
+fn match_intersection(c: &mut Criterion) {
+    let mut group = c.benchmark_group("match_intersection");
+    for size in SIZES {
+        let matches = fixture_matches(size);
+        let index = MatchIndex::open_at(Path::new(":memory:")).unwrap();
+        for match_data in &matches {
+            index.insert_match(match_data).unwrap();
+        }
+        group.bench_with_input(BenchmarkId::new("shared_match_ids", size), &index, |b, index| {
+            b.iter(|| index.shared_match_ids_since(black_box(PLAYER1_PUUID), black_box(PLAYER2_PUUID), 0).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("has_participant", size), &matches, |b, matches| {
+            b.iter(|| {
+                matches
+                    .iter()
+                    .filter(|m| index.has_participant(&m.metadata.match_id, black_box(PLAYER2_PUUID)).unwrap())
+                    .count()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn participant_scanning(c: &mut Criterion) {
+    let mut group = c.benchmark_group("participant_scanning");
+    for size in SIZES {
+        let matches = fixture_matches(size);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &matches, |b, matches| {
+            b.iter(|| shared_match_details(black_box(matches)))
+        });
+    }
+    group.finish();
+}
+
+fn stats_aggregation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("stats_aggregation");
+    for size in SIZES {
+        let found_matches = shared_match_details(&fixture_matches(size));
+        group.bench_with_input(BenchmarkId::new("match_stats", size), &found_matches, |b, found_matches| {
+            b.iter(|| MatchStats::from_matches(black_box(found_matches)))
+        });
+        group.bench_with_input(BenchmarkId::new("achievements", size), &found_matches, |b, found_matches| {
+            b.iter(|| find_achievements(black_box(found_matches)))
+        });
+    }
+    group.finish();
+}
+
+fn output_rendering(c: &mut Criterion) {
+    let template_path = std::env::temp_dir().join("ptg-bench-template.md");
+    std::fs::write(
+        &template_path,
+        "{% for m in foundMatches %}| {{ m.matchId }} | {{ m.player1Details.champion }} | {{ m.player2Details.champion }} |\n{% endfor %}",
+    )
+    .unwrap();
+
+    let mut group = c.benchmark_group("output_rendering");
+    for size in SIZES {
+        let output = fixture_output(&fixture_matches(size));
+        group.bench_with_input(BenchmarkId::new("json", size), &output, |b, output| {
+            b.iter(|| black_box(output).write_json(std::io::sink()).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("template", size), &output, |b, output| {
+            b.iter(|| render_template_file(&template_path, black_box(output)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, match_intersection, participant_scanning, stats_aggregation, output_rendering);
+criterion_main!(benches);
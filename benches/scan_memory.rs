@@ -0,0 +1,112 @@
+//! Heap use of a deep scan over the local index, with and without checking the participant table
+//! before a payload is deserialized. Run with `cargo bench --bench scan_memory`; it prints the peak
+//! heap and the bytes allocated in total for each way of scanning.
+
+use riven::models::match_v5::Match;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ptg::index::MatchIndex;
+
+const FIXTURE_MATCH: &str = include_str!("../tests/fixtures/match.json");
+const PLAYER1_PUUID: &str = "fixture-puuid-0";
+const PLAYER2_PUUID: &str = "fixture-puuid-3";
+const SCAN_SIZE: usize = 100;
+
+/// The system allocator, keeping track of the bytes in use, their peak and every byte handed out.
+struct CountingAllocator;
+
+static IN_USE: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+static TOTAL: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        // SAFETY: forwards the caller's layout to the system allocator unchanged.
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let in_use = IN_USE.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(in_use, Ordering::Relaxed);
+            TOTAL.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        // SAFETY: `ptr` was allocated by `alloc` above with the same layout.
+        unsafe { System.dealloc(ptr, layout) };
+        IN_USE.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// An index of `count` copies of the fixture match. Player2 only took part in those `player2_in` picks.
+fn fixture_index(count: usize, player2_in: fn(usize) -> bool) -> (MatchIndex, Vec<String>) {
+    let template: Match = serde_json::from_str(FIXTURE_MATCH).expect("fixture match should parse");
+    let index = MatchIndex::open_at(Path::new(":memory:")).unwrap();
+    let mut match_ids = Vec::new();
+    for i in 0..count {
+        let mut match_data = template.clone();
+        match_data.metadata.match_id = format!("EUW1_{}", 7_000_000_000 + i);
+        match_data.info.game_start_timestamp -= i as i64 * 3_600_000;
+        if !player2_in(i) {
+            let stranger = format!("stranger-puuid-{}", i);
+            match_data.info.participants[3].puuid = stranger.clone();
+            match_data.metadata.participants[3] = stranger;
+        }
+        index.insert_match(&match_data).unwrap();
+        match_ids.push(match_data.metadata.match_id);
+    }
+    (index, match_ids)
+}
+
+/// Runs `scan` and returns its result, the peak heap above the baseline and the bytes allocated during it.
+fn measure(scan: impl FnOnce() -> usize) -> (usize, usize, usize) {
+    let baseline = IN_USE.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+    let total_before = TOTAL.load(Ordering::Relaxed);
+    let shared = black_box(scan());
+    (shared, PEAK.load(Ordering::Relaxed) - baseline, TOTAL.load(Ordering::Relaxed) - total_before)
+}
+
+/// Deserializes every payload, as scans did before the participant table was consulted.
+fn scan_every_payload(index: &MatchIndex, match_ids: &[String]) -> usize {
+    match_ids
+        .iter()
+        .filter_map(|match_id| index.get_match(match_id).unwrap())
+        .filter(|match_data| match_data.info.participants.iter().any(|p| p.puuid == PLAYER2_PUUID))
+        .count()
+}
+
+/// Deserializes only the payloads the participant table lists player2 in, like `run_query`.
+fn scan_shared_payloads(index: &MatchIndex, match_ids: &[String]) -> usize {
+    match_ids
+        .iter()
+        .filter(|match_id| index.has_participant(match_id, PLAYER2_PUUID).unwrap())
+        .filter_map(|match_id| index.get_match(match_id).unwrap())
+        .filter(|match_data| match_data.info.participants.iter().any(|p| p.puuid == PLAYER1_PUUID))
+        .count()
+}
+
+fn main() {
+    let heading = format!("scan of {} indexed matches", SCAN_SIZE);
+    println!("{:<48}{:>8}{:>14}{:>16}", heading, "shared", "peak heap", "allocated");
+    let every_other: fn(usize) -> bool = |i| i % 2 == 0;
+    for (label, player2_in) in [("player2 in every other match", every_other), ("player2 in none", |_| false)] {
+        let (index, match_ids) = fixture_index(SCAN_SIZE, player2_in);
+        for (scan, name) in [(scan_every_payload as fn(&MatchIndex, &[String]) -> usize, "every payload"), (scan_shared_payloads, "shared payloads")] {
+            let (shared, peak, allocated) = measure(|| scan(&index, &match_ids));
+            println!(
+                "{:<48}{:>8}{:>10} KiB{:>12} KiB",
+                format!("{}, {}", label, name),
+                shared,
+                peak / 1024,
+                allocated / 1024
+            );
+        }
+    }
+}
@@ -0,0 +1,18 @@
+//! Match payloads stored in the match index, which can be corrupted or edited on disk,
+//! parsed and turned into output records the way a query does.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ptg::api_client::build_match_details;
+use riven::models::match_v5::Match;
+
+fuzz_target!(|input: &[u8]| {
+    let Ok(match_data) = serde_json::from_slice::<Match>(input) else {
+        return;
+    };
+    let info = &match_data.info;
+    if let [p1_data, p2_data, ..] = info.participants.as_slice() {
+        let details = build_match_details(&match_data.metadata.match_id, info, p1_data, p2_data, "euw", None, None, None);
+        let _ = serde_json::to_string(&details);
+    }
+});
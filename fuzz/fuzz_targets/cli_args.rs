@@ -0,0 +1,11 @@
+//! Argument parsing, including the joining of split Riot IDs and the value parsers for Riot IDs,
+//! regions and export formats.
+#![no_main]
+
+use clap::Parser;
+use libfuzzer_sys::fuzz_target;
+use ptg::cli::{join_split_riot_ids, Cli};
+
+fuzz_target!(|args: Vec<String>| {
+    let _ = Cli::try_parse_from(join_split_riot_ids(std::iter::once("ptg".to_string()).chain(args).collect()));
+});
@@ -0,0 +1,16 @@
+//! The config file is edited by hand; it is read as TOML, or as JSON for the legacy format.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ptg::config::Config;
+
+fuzz_target!(|input: &str| {
+    for config in [toml::from_str::<Config>(input).ok(), serde_json::from_str::<Config>(input).ok()]
+        .into_iter()
+        .flatten()
+    {
+        // Saving writes the parsed config back as TOML.
+        let _ = toml::to_string_pretty(&config);
+        let _ = config.effective_api_keys();
+    }
+});
@@ -0,0 +1,14 @@
+//! Riot IDs come straight from the command line and the contact book.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ptg::riot_id::RiotId;
+
+fuzz_target!(|input: &str| {
+    if let Ok(riot_id) = input.parse::<RiotId>() {
+        // Only Riot IDs are round-tripped; a PUUID is resolved to one before it is used.
+        if riot_id.unresolved_puuid().is_none() {
+            assert_eq!(riot_id.to_string(), input);
+        }
+    }
+});
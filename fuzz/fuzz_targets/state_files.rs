@@ -0,0 +1,15 @@
+//! The JSON files kept next to the config and caches: account cache, notes and contacts.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use ptg::cache::AccountCache;
+use ptg::contacts::ContactBook;
+use ptg::notes::NoteBook;
+
+fuzz_target!(|input: &[u8]| {
+    let _ = serde_json::from_slice::<AccountCache>(input);
+    if let Ok(contacts) = serde_json::from_slice::<ContactBook>(input) {
+        let _ = contacts.favorites().count();
+    }
+    let _ = serde_json::from_slice::<NoteBook>(input);
+});
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+
+use crate::api_client::MatchDetails;
+use crate::stats::VisionDuel;
+
+/// A fun highlight found across the shared matches, with the matches that earned it.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Achievement {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub match_ids: Vec<String>,
+}
+
+fn matching_ids(matches: &[MatchDetails], predicate: impl Fn(&MatchDetails) -> bool) -> Vec<String> {
+    matches
+        .iter()
+        .filter(|match_details| predicate(match_details))
+        .map(|match_details| match_details.match_id.clone())
+        .collect()
+}
+
+/// Collects every achievement earned at least once in `matches`.
+pub fn find_achievements(matches: &[MatchDetails]) -> Vec<Achievement> {
+    let mut achievements = Vec::new();
+
+    let same_champion = matching_ids(matches, |m| m.same_champion);
+    if !same_champion.is_empty() {
+        achievements.push(Achievement {
+            id: "sameChampion".to_string(),
+            title: "Copycats".to_string(),
+            description: format!("Both players picked the same champion in {} game(s).", same_champion.len()),
+            match_ids: same_champion,
+        });
+    }
+
+    let mirror_roles = matching_ids(matches, |m| m.mirror_roles);
+    if !mirror_roles.is_empty() {
+        achievements.push(Achievement {
+            id: "mirrorRoles".to_string(),
+            title: "Mirror Match".to_string(),
+            description: format!("Played the same role on opposite teams in {} game(s).", mirror_roles.len()),
+            match_ids: mirror_roles,
+        });
+    }
+
+    if let Some(achievement) = most_supportive(matches) {
+        achievements.push(achievement);
+    }
+    if let Some(achievement) = vision_duel(matches) {
+        achievements.push(achievement);
+    }
+
+    achievements
+}
+
+/// Compares how many of each other's kills the players assisted on, over games with a fetched timeline.
+fn most_supportive(matches: &[MatchDetails]) -> Option<Achievement> {
+    let (mut assisted_by_player1, mut assisted_by_player2) = (0, 0);
+    let match_ids = matching_ids(matches, |m| {
+        m.kill_participation.as_ref().is_some_and(|p| p.player1_kills_assisted_by_player2 + p.player2_kills_assisted_by_player1 > 0)
+    });
+    for participation in matches.iter().filter_map(|m| m.kill_participation.as_ref()) {
+        assisted_by_player1 += participation.player2_kills_assisted_by_player1;
+        assisted_by_player2 += participation.player1_kills_assisted_by_player2;
+    }
+    if match_ids.is_empty() {
+        return None;
+    }
+    let verdict = match assisted_by_player1.cmp(&assisted_by_player2) {
+        std::cmp::Ordering::Greater => "Player 1 is the more supportive friend.",
+        std::cmp::Ordering::Less => "Player 2 is the more supportive friend.",
+        std::cmp::Ordering::Equal => "Equally supportive.",
+    };
+    Some(Achievement {
+        id: "mostSupportive".to_string(),
+        title: "Most Supportive Friend".to_string(),
+        description: format!(
+            "Player 1 assisted on {} of Player 2's kills and Player 2 on {} of Player 1's. {}",
+            assisted_by_player1, assisted_by_player2, verdict
+        ),
+        match_ids,
+    })
+}
+
+/// Settles which player had the higher vision score in more of the shared games.
+fn vision_duel(matches: &[MatchDetails]) -> Option<Achievement> {
+    let duel = VisionDuel::from_matches(matches);
+    let player1_leads = match duel.player1_wins.cmp(&duel.player2_wins) {
+        std::cmp::Ordering::Greater => true,
+        std::cmp::Ordering::Less => false,
+        std::cmp::Ordering::Equal => return None,
+    };
+    let match_ids = matching_ids(matches, |m| {
+        let (p1, p2) = (m.player1_details.vision_score, m.player2_details.vision_score);
+        if player1_leads { p1 > p2 } else { p2 > p1 }
+    });
+    let leader = if player1_leads { "Player 1" } else { "Player 2" };
+    Some(Achievement {
+        id: "visionDuel".to_string(),
+        title: "Lights in the Dark".to_string(),
+        description: format!(
+            "{} had the higher vision score in {} of {} game(s).",
+            leader,
+            match_ids.len(),
+            duel.games()
+        ),
+        match_ids,
+    })
+}
+
+/// Prints the achievements as a plain-text list.
+pub fn print_achievements(achievements: &[Achievement]) {
+    if achievements.is_empty() {
+        return;
+    }
+    println!("\n--- Highlights ---");
+    for achievement in achievements {
+        println!("{}: {}", achievement.title, achievement.description);
+    }
+}
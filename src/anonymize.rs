@@ -0,0 +1,106 @@
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::api_client::{OverallOutput, PlayerIdentity};
+use crate::riot_id::RiotId;
+
+/// Tag line given to every pseudonymized player.
+const PSEUDONYM_TAG_LINE: &str = "ANON";
+
+/// Set once by `--anonymize`, so log lines anywhere can redact identities without threading a flag through.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// First 8 hex digits of the SHA-256 of `value`: stable across runs, so posted results stay comparable.
+fn short_hash(value: &str) -> String {
+    Sha256::digest(value.as_bytes())
+        .iter()
+        .take(4)
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn pseudonym(game_name: &str, tag_line: &str) -> String {
+    format!("Player-{}", short_hash(&format!("{}#{}", game_name, tag_line).to_lowercase()))
+}
+
+/// The Riot ID as it may appear in logs.
+pub fn riot_id(riot_id: &RiotId) -> String {
+    if is_enabled() {
+        format!("{}#{}", pseudonym(&riot_id.game_name, &riot_id.tag_line), PSEUDONYM_TAG_LINE)
+    } else {
+        riot_id.to_string()
+    }
+}
+
+/// The PUUID as it may appear in logs.
+pub fn puuid(puuid: &str) -> String {
+    if is_enabled() {
+        format!("puuid-{}", short_hash(puuid))
+    } else {
+        puuid.to_string()
+    }
+}
+
+pub fn anonymize_identity(identity: &mut PlayerIdentity) {
+    identity.game_name = pseudonym(&identity.game_name, &identity.tag_line);
+    identity.tag_line = PSEUDONYM_TAG_LINE.to_string();
+    // A level and icon are enough to recognize an account.
+    identity.profile = None;
+    identity.formerly_known_as.clear();
+    identity.nickname = None;
+}
+
+/// Match IDs can be looked up on the API or on match history sites, which names every player.
+fn match_pseudonym(match_id: &str) -> String {
+    format!("match-{}", short_hash(match_id))
+}
+
+/// The match ID as it may appear in logs.
+pub fn match_id(match_id: &str) -> String {
+    if is_enabled() {
+        match_pseudonym(match_id)
+    } else {
+        match_id.to_string()
+    }
+}
+
+/// Replaces both players' Riot IDs in the results with their pseudonyms, hashes the match IDs and drops
+/// the leagueofgraphs links.
+pub fn anonymize_output(output: &mut OverallOutput) {
+    anonymize_identity(&mut output.query_summary.player1);
+    anonymize_identity(&mut output.query_summary.player2);
+    if let Some(venn) = &mut output.query_summary.venn {
+        anonymize_identity(&mut venn.player3);
+    }
+    for match_details in &mut output.found_matches {
+        match_details.match_id = match_pseudonym(&match_details.match_id);
+        match_details.league_of_graphs_link = None;
+    }
+    for session in &mut output.sessions {
+        anonymize_match_ids(&mut session.match_ids);
+    }
+    for achievement in &mut output.achievements {
+        anonymize_match_ids(&mut achievement.match_ids);
+    }
+    for warning in &mut output.warnings {
+        if let Some(match_id) = &warning.match_id {
+            let pseudonym = match_pseudonym(match_id);
+            warning.message = warning.message.replace(match_id.as_str(), &pseudonym);
+            warning.match_id = Some(pseudonym);
+        }
+    }
+}
+
+fn anonymize_match_ids(match_ids: &mut [String]) {
+    for match_id in match_ids {
+        *match_id = match_pseudonym(match_id);
+    }
+}
@@ -1,29 +1,132 @@
-use riven::consts::RegionalRoute;
-use riven::RiotApi;
+use riven::consts::{GameMode, PlatformRoute, Queue, RegionalRoute};
+use riven::{RiotApi, RiotApiConfig};
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::Method;
+use serde_json::Value;
+use std::io::{BufWriter, Write};
+use std::time::Duration;
+use riven::models::match_v5::{Info, Match, Participant};
 use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::error::Error;
-use std::time::SystemTime;
+use std::time::{Instant, SystemTime};
 use std::time::UNIX_EPOCH;
 use chrono::{TimeZone, Utc};
 
-use serde::{Serialize};
+use serde::{Deserialize, Serialize};
 
 use crate::riot_id::RiotId;
-use crate::cli::UserFacingRegion;
+use crate::cli::{MatchType, UserFacingRegion};
+use crate::config::NetworkConfig;
 use colored::Colorize;
 use crate::utils::print_in_box;
+use crate::ranked::{QueueBreakdown, RankedSummary};
+use crate::anonymize;
+use crate::cache::AccountCache;
+use crate::contacts::nickname_of;
+use crate::ddragon::{self, DataDragon};
+use crate::notes::{MatchNote, NoteBook};
+use crate::index::MatchIndex;
+use crate::key_lock::{KeyBudget, DEVELOPMENT_KEY_RATE_LIMITS};
+use crate::offline;
+use crate::rate_limit;
+use crate::stats::{classify_game_flow, likely_afk, patch_of, performance_score, CarryTally, GameFlow, MatchStats, SoloBaseline};
+use crate::timeline::{gold_graph, lane_gold_diff_at, lane_matchup, pair_kill_participation, team_gold_diff_at, GoldGraph, LaneMatchup, PairKillParticipation};
+use crate::achievements::{find_achievements, Achievement};
+use crate::date_range::DateRange;
+use crate::duo_timeline::DuoTimeline;
+use crate::sessions::{detect_sessions, PlaySession};
+use crate::venn::VennSummary;
 
 
 // --- JSON Output Structures ---
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")] // Convert struct field names to camelCase for JSON keys
 pub struct OverallOutput {
     pub query_summary: QuerySummary,
     pub found_matches: Vec<MatchDetails>,
+    pub stats: MatchStats,
+    pub duo_timeline: DuoTimeline,
+    /// Shared games grouped into play sessions, oldest first.
+    #[serde(default)]
+    pub sessions: Vec<PlaySession>,
+    pub achievements: Vec<Achievement>,
+    /// Problems that did not stop the query, e.g. skipped matches.
+    pub warnings: Vec<Warning>,
 }
 
-#[derive(Debug, Serialize)]
+/// A problem met during a query that did not stop it. Also printed to stderr, as prose, in verbose mode.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Warning {
+    pub code: WarningCode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub match_id: Option<String>,
+    pub message: String,
+}
+
+/// Stable identifiers of the warnings, for scripts to match on instead of the message.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WarningCode {
+    /// The API key may not read the match (401/403).
+    MatchForbidden,
+    /// The match is older than Riot's match retention.
+    MatchExpired,
+    /// The API returned no data for the match (404).
+    MatchMissing,
+    /// The match is in a game mode riven cannot model; only its result was counted.
+    OutlineOnly,
+    /// The match data lacks one of the players.
+    MissingParticipant,
+    /// An indexed match could not be read back from the local index.
+    UnreadableIndexedMatch,
+    /// Player1 plays on another region than the selected one.
+    RegionTransfer,
+    /// Player1 has never played League of Legends on the regional route, e.g. a Valorant-only account.
+    NoLeagueMatches,
+    /// Player1 has no matches in the window, even after `--auto-widen` if given.
+    NoMatchesInWindow,
+    /// `--auto-widen` found player1's matches only in a wider window than the default.
+    WindowWidened,
+}
+
+impl Warning {
+    pub fn new(code: WarningCode, match_id: Option<&str>, message: String) -> Self {
+        Warning { code, match_id: match_id.map(str::to_string), message }
+    }
+}
+
+/// Records `warning`, printing it to stderr first when `verbose`.
+pub fn push_warning(warnings: &mut Vec<Warning>, verbose: bool, warning: Warning) {
+    if verbose {
+        match &warning.match_id {
+            Some(match_id) => eprintln!("{}", warning.message.replace(match_id.as_str(), &anonymize::match_id(match_id))),
+            None => eprintln!("{}", warning.message),
+        }
+    }
+    warnings.push(warning);
+}
+
+/// Buffer size of the JSON writer; the output reaches the pipe or file in chunks of this size.
+const JSON_CHUNK_BYTES: usize = 64 * 1024;
+
+impl OverallOutput {
+    /// Writes the output as pretty-printed JSON and a trailing newline, serialized straight into `writer`
+    /// chunk by chunk instead of into one string first, so deep index-backed results are not held twice.
+    /// The matches themselves are still collected in full first, since the stats and every other
+    /// destination need all of them.
+    pub fn write_json<W: Write>(&self, writer: W) -> Result<(), Box<dyn Error>> {
+        let mut writer = BufWriter::with_capacity(JSON_CHUNK_BYTES, writer);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct QuerySummary {
     pub player1: PlayerIdentity,
@@ -34,126 +137,1543 @@ pub struct QuerySummary {
     pub player1_wins_together_count: u32,
     pub player1_puuid_found: bool,
     pub player2_puuid_found: bool,
+    pub api_calls: ApiCallStats,
+    pub ranked: RankedSummary,
+    pub carries: CarryTally,
+    /// Shared games per queue family; outline-only matches are not included.
+    pub per_queue: QueueBreakdown,
+    pub unavailable_matches: UnavailableMatches,
+    /// Shared matches riven could not model, counted from their outline only and missing from `foundMatches`.
+    pub outline_only_matches_count: u32,
+    /// Shared matches where either player likely went AFK; left out of every other count unless `--include-afk`.
+    pub likely_afk_matches_count: u32,
+    /// Set when a third player was given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub venn: Option<VennSummary>,
+    /// Days `--auto-widen` widened the window to, when the default one held none of player1's matches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub widened_to_days: Option<u32>,
+}
+
+/// Listed matches whose details could not be loaded, by cause. They are skipped, not counted as checked games.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnavailableMatches {
+    /// Older than Riot's match retention, judged from the previous match of the list.
+    pub expired: u32,
+    /// Refused for the API key (401/403), e.g. matches of restricted queues.
+    pub forbidden: u32,
+    /// No data returned for the match ID (404).
+    pub missing: u32,
+}
+
+impl UnavailableMatches {
+    pub fn total(&self) -> u32 {
+        self.expired + self.forbidden + self.missing
+    }
 }
 
-#[derive(Debug, Serialize)]
+/// Number of Riot API requests issued by a single query, split per endpoint.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiCallStats {
+    pub account_calls: u32,
+    pub match_list_calls: u32,
+    pub match_detail_calls: u32,
+    pub timeline_calls: u32,
+    pub summoner_calls: u32,
+    /// Rank lookups (league-v4).
+    #[serde(default)]
+    pub league_calls: u32,
+    /// Requests sent again after a 429 response, a server error or a failed connection; not part of
+    /// `total_calls`.
+    #[serde(default)]
+    pub retries: u32,
+    /// Lookups answered from the local cache instead of the API.
+    pub cache_hits: u32,
+    pub total_calls: u32,
+    pub wall_time_ms: u64,
+}
+
+impl ApiCallStats {
+    pub fn finish(&mut self, started_at: Instant) {
+        self.total_calls = self.account_calls
+            + self.match_list_calls
+            + self.match_detail_calls
+            + self.timeline_calls
+            + self.summoner_calls
+            + self.league_calls;
+        self.retries += rate_limit::take_retries();
+        self.wall_time_ms = started_at.elapsed().as_millis() as u64;
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PlayerIdentity {
     pub game_name: String,
     pub tag_line: String,
+    /// Only fetched with `--profile`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<SummonerProfile>,
+    /// Earlier Riot IDs of the player, known from `ptg contacts refresh`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub formerly_known_as: Vec<String>,
+    /// Display nickname from the contact book.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nickname: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+impl PlayerIdentity {
+    /// "Nickname (Name#TAG)" for contacts with a nickname, otherwise the Riot ID.
+    pub fn display_name(&self) -> String {
+        let riot_id = format!("{}#{}", self.game_name, self.tag_line);
+        match &self.nickname {
+            Some(nickname) => format!("{} ({})", nickname, riot_id),
+            None => riot_id,
+        }
+    }
+}
+
+/// Summoner level and profile icon of a player, from summoner-v4.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SummonerProfile {
+    pub summoner_level: i64,
+    pub profile_icon_id: i32,
+    pub profile_icon_url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MatchDetails {
     pub match_id: String,
     pub game_date_utc: String,
+    /// Unix timestamp in milliseconds.
+    pub game_start_timestamp: i64,
+    pub game_duration_secs: i64,
     pub game_mode: String,
     pub game_type: Option<String>,
+    pub queue_id: u16,
+    pub game_version: String,
+    pub surrendered: bool,
+    /// Ended in an early surrender, as reported by match-v5.
+    pub early_surrendered: bool,
+    /// "player1" or "player2", whoever had the higher performance score; `None` on a tie or when
+    /// they played against each other.
+    pub carried_by: Option<String>,
+    /// Player1's team gold minus the enemy team's at 15 minutes; requires the match timeline.
+    pub gold_diff_at15: Option<i32>,
+    pub game_flow: Option<GameFlow>,
+    /// In Arena, whether both players were in the same 2-player subteam.
+    pub same_team: bool,
+    /// Both players picked the same champion (possible in ARAM, arena or on opposite teams).
+    pub same_champion: bool,
+    /// Both players had the same position on opposite teams.
+    pub mirror_roles: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arena: Option<ArenaDetails>,
+    /// Set for direct lane opponents when the timeline was fetched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lane_matchup: Option<LaneMatchup>,
+    /// Set for teammates when the timeline was fetched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kill_participation: Option<PairKillParticipation>,
+    /// Set when the timeline was fetched; its `svg` is a sparkline for HTML templates.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gold_graph: Option<GoldGraph>,
+    /// Notes and tags added with `ptg note`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<MatchNote>,
     pub league_of_graphs_link: Option<String>,
     pub player1_details: ParticipantDetails,
     pub player2_details: ParticipantDetails,
 }
 
-#[derive(Debug, Serialize)]
+/// Arena splits the lobby into 2-player subteams, so the duo relationship is the subteam, not the team.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArenaDetails {
+    /// Whether the two players were queued as partners, as opposed to opponents in the same lobby.
+    pub duo_partners: bool,
+    pub player1_placement: Option<i32>,
+    pub player2_placement: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ParticipantDetails {
+    /// Canonical (English) champion key, e.g. "MonkeyKing".
     pub champion: String,
+    /// Champion name in the configured language, only set when a language is configured.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub champion_display_name: Option<String>,
     pub role: String,
     pub kills: i32,
     pub deaths: i32,
     pub assists: i32,
     pub outcome: String,
+    /// Data Dragon asset URLs, only filled in with `--assets`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub champion_icon_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub champion_splash_url: Option<String>,
+    /// In-game ping counts; missing for matches older than the ping fields of match-v5.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pings: Option<PingCounts>,
+    /// Got or assisted on first blood.
+    pub first_blood: bool,
+    /// See `stats::performance_score`.
+    pub performance_score: f64,
+    /// Gold lead over the lane opponent at 10 minutes; requires the match timeline and a lane opponent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lane_gold_diff_at10: Option<i32>,
+    /// See `stats::likely_afk`.
+    pub likely_afk: bool,
+    #[serde(default)]
+    pub vision_score: i32,
+    /// Final items, without the trinket.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub items: Vec<BuildEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub summoner_spells: Vec<BuildEntry>,
+    /// Missing for game modes without rune pages, e.g. Arena.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runes: Option<RunesSummary>,
+}
+
+/// The keystone and the two rune trees of a player's rune page.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunesSummary {
+    pub keystone: BuildEntry,
+    pub primary_tree: BuildEntry,
+    pub secondary_tree: BuildEntry,
+}
+
+impl RunesSummary {
+    pub fn of(participant: &Participant, data_dragon: Option<&DataDragon>) -> Option<Self> {
+        let [primary, secondary, ..] = participant.perks.styles.as_slice() else {
+            return None;
+        };
+        let entry = |id: i32| BuildEntry { id, name: data_dragon.and_then(|dd| dd.rune_name(id)), icon_url: None };
+        Some(RunesSummary {
+            keystone: entry(primary.selections.first()?.perk),
+            primary_tree: entry(primary.style),
+            secondary_tree: entry(secondary.style),
+        })
+    }
+}
+
+/// An item, summoner spell or rune of a player. The name is filled in when Data Dragon is loaded
+/// (`--language` or `--assets`), the icon URL with `--assets`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildEntry {
+    pub id: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon_url: Option<String>,
+}
+
+impl BuildEntry {
+    /// The name, or the ID as "#3078" when Data Dragon was not loaded.
+    pub fn label(&self) -> String {
+        self.name.clone().unwrap_or_else(|| format!("#{}", self.id))
+    }
+
+    /// Entries of the non-empty slots (ID 0) among `ids`, named by `name_of` if given.
+    fn from_ids(ids: &[i32], name_of: Option<impl Fn(i32) -> Option<String>>) -> Vec<Self> {
+        ids.iter()
+            .filter(|id| **id != 0)
+            .map(|&id| BuildEntry { id, name: name_of.as_ref().and_then(|name_of| name_of(id)), icon_url: None })
+            .collect()
+    }
+}
+
+/// How much a player pinged during a match, as a rough proxy for communication.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PingCounts {
+    /// Sum of every ping type, including the ones not listed separately.
+    pub total: i32,
+    pub enemy_missing: i32,
+    pub enemy_vision: i32,
+    pub need_vision: i32,
+    pub assist_me: i32,
+    pub on_my_way: i32,
+    pub get_back: i32,
+}
+
+impl PingCounts {
+    /// Reads the ping counters of a participant, `None` when the match predates them.
+    pub fn of(participant: &Participant) -> Option<Self> {
+        let all_pings = [
+            participant.all_in_pings,
+            participant.assist_me_pings,
+            participant.bait_pings,
+            participant.basic_pings,
+            participant.command_pings,
+            participant.danger_pings,
+            participant.enemy_missing_pings,
+            participant.enemy_vision_pings,
+            participant.get_back_pings,
+            participant.hold_pings,
+            participant.need_vision_pings,
+            participant.on_my_way_pings,
+            participant.push_pings,
+            participant.retreat_pings,
+            participant.vision_cleared_pings,
+        ];
+        if all_pings.iter().all(Option::is_none) {
+            return None;
+        }
+        Some(PingCounts {
+            total: all_pings.iter().flatten().sum(),
+            enemy_missing: participant.enemy_missing_pings.unwrap_or(0),
+            enemy_vision: participant.enemy_vision_pings.unwrap_or(0),
+            need_vision: participant.need_vision_pings.unwrap_or(0),
+            assist_me: participant.assist_me_pings.unwrap_or(0),
+            on_my_way: participant.on_my_way_pings.unwrap_or(0),
+            get_back: participant.get_back_pings.unwrap_or(0),
+        })
+    }
+}
+
+impl ParticipantDetails {
+    /// The champion name to show to the user: localized if available, the canonical key otherwise.
+    pub fn champion_label(&self) -> &str {
+        self.champion_display_name.as_deref().unwrap_or(&self.champion)
+    }
 }
 
 // --- End JSON Output Structures ---
 
+/// Round-robins requests over one `RiotApi` client per configured API key.
+/// Every client keeps its own rate limiter, so two keys roughly double the throughput.
+pub struct RiotApiPool {
+    clients: Vec<RiotApi>,
+    next_client: AtomicUsize,
+    /// The budget of each client's key, shared with other runs; empty unless `lock_api_key` is set.
+    key_budgets: Vec<KeyBudget>,
+}
+
+impl RiotApiPool {
+    pub fn new(api_keys: &[String], network: &NetworkConfig) -> Result<Self, Box<dyn Error>> {
+        if api_keys.is_empty() && !offline::is_enabled() {
+            return Err("At least one Riot API key is required.".into());
+        }
+        // Offline, no request is ever sent, so a missing or expired key does not matter.
+        let offline_key = [String::new()];
+        let api_keys = if api_keys.is_empty() { &offline_key[..] } else { api_keys };
+        let clients = api_keys
+            .iter()
+            .map(|api_key| Ok(RiotApi::new(riot_api_config(api_key, network)?)))
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+        let key_budgets = match network.lock_api_key {
+            Some(true) if !offline::is_enabled() => {
+                let rate_limits = network.api_key_rate_limits.as_deref().unwrap_or(DEVELOPMENT_KEY_RATE_LIMITS);
+                api_keys
+                    .iter()
+                    .map(|api_key| KeyBudget::open(api_key, rate_limits))
+                    .collect::<Result<Vec<_>, _>>()?
+            }
+            _ => Vec::new(),
+        };
+        Ok(RiotApiPool {
+            clients,
+            next_client: AtomicUsize::new(0),
+            key_budgets,
+        })
+    }
 
-pub async fn run_query(
-    riot_api: &RiotApi,
-    player1_riot_id: RiotId,
-    player2_riot_id: RiotId,
+    /// Returns the client whose turn it is to send the next request. Every call is taken as one
+    /// request, and waits for the shared budget of the client's key when there is one.
+    pub async fn client(&self) -> &RiotApi {
+        let index = self.next_client.fetch_add(1, Ordering::Relaxed) % self.clients.len();
+        if let Some(key_budget) = self.key_budgets.get(index)
+            && let Err(e) = key_budget.take_slot().await
+        {
+            eprintln!("Warning: {} Sending the request anyway.", e);
+        }
+        &self.clients[index]
+    }
+}
+
+/// Builds the client configuration of one API key with the HTTP tuning of the `[network]` config section.
+fn riot_api_config(api_key: &str, network: &NetworkConfig) -> Result<RiotApiConfig, Box<dyn Error>> {
+    let mut default_headers = HeaderMap::new();
+    default_headers.insert(
+        RiotApiConfig::RIOT_KEY_HEADER,
+        HeaderValue::from_str(api_key).map_err(|_| "The Riot API key contains invalid characters.")?,
+    );
+    let user_agent = network
+        .user_agent
+        .clone()
+        .unwrap_or_else(|| format!("ptg/{}", env!("CARGO_PKG_VERSION")));
+    let mut client_builder = reqwest::ClientBuilder::new()
+        .default_headers(default_headers)
+        .user_agent(user_agent);
+    if network.http2 == Some(false) {
+        client_builder = client_builder.http1_only();
+    }
+    if let Some(max_idle) = network.pool_max_idle_per_host {
+        client_builder = client_builder.pool_max_idle_per_host(max_idle);
+    }
+    if let Some(idle_timeout) = network.pool_idle_timeout_secs {
+        client_builder = client_builder.pool_idle_timeout(Duration::from_secs(idle_timeout));
+    }
+    if let Some(keepalive) = network.tcp_keepalive_secs {
+        client_builder = client_builder.tcp_keepalive(Duration::from_secs(keepalive));
+    }
+    let mut riot_api_config = RiotApiConfig::with_client_builder(client_builder);
+    if rate_limit::fail_fast() {
+        riot_api_config = riot_api_config.set_retries(0);
+    }
+    if let Some(base_url) = &network.base_url {
+        riot_api_config = riot_api_config.set_base_url(base_url);
+    }
+    Ok(riot_api_config)
+}
+
+/// Everything `run_query` needs to know about a single lookup besides the API client.
+pub struct QueryOptions {
+    pub player1_riot_id: RiotId,
+    pub player2_riot_id: RiotId,
+    /// A third player to break the shared matches down by, Venn-style.
+    pub player3_riot_id: Option<RiotId>,
+    /// `None` auto-detects the route from player1's match history.
+    pub regional_route: Option<RegionalRoute>,
+    pub user_selected_region: Option<UserFacingRegion>,
+    pub number_of_matches: Option<i32>,
+    /// Only list player1's matches of this type. Filtered lists are not recorded as index coverage.
+    pub match_type: Option<MatchType>,
+    /// Double the default 30-day window, up to this many days, while player1 has no matches in it.
+    pub auto_widen_max_days: Option<u32>,
+    /// Only list and count matches of this raw queue ID. Filtered lists are not recorded as index coverage.
+    pub queue_id: Option<u16>,
+    /// Only count shared matches played on this patch (e.g. "14.20").
+    pub patch: Option<String>,
+    /// Only list and count matches started in this span instead of the last 30 days.
+    pub date_range: Option<DateRange>,
+    /// Fetch the timeline of every shared match (one extra request each) for timeline-based stats.
+    pub fetch_timelines: bool,
+    pub fetch_profiles: bool,
+    /// Only keep games on opposite teams, fetching timelines for direct lane opponents.
+    pub opponents_only: bool,
+    /// Scan the region player1 transferred to instead of only warning about the transfer.
+    pub follow_region_transfer: bool,
+    /// Stop checking matches once the first shared one is found.
+    pub stop_at_first_shared: bool,
+    /// Count shared matches where either player likely went AFK instead of leaving them out.
+    pub include_afk: bool,
+    /// Also list player2's matches and compare both players' games without the other to the shared ones.
+    pub solo_baseline: bool,
+    /// Localizes champion names when set.
+    pub data_dragon: Option<DataDragon>,
+    pub verbose: bool,
+    /// Prints none of the shared matches while scanning, for JSON output or callers that only
+    /// summarize them.
+    pub quiet: bool,
+}
+
+/// Regional routes probed when no region was given.
+const AUTO_DETECT_ROUTES: [RegionalRoute; 4] = [
+    RegionalRoute::AMERICAS,
+    RegionalRoute::ASIA,
+    RegionalRoute::EUROPE,
+    RegionalRoute::SEA,
+];
+
+/// Accounts are shared by every cluster, so account-v1 answers on any route; this one is used when
+/// the player's route is not known.
+pub const ANY_ACCOUNT_ROUTE: RegionalRoute = RegionalRoute::EUROPE;
+
+/// account-v1 is not served on SEA, so those lookups go to ASIA.
+fn account_route(regional_route: RegionalRoute) -> RegionalRoute {
+    match regional_route {
+        RegionalRoute::SEA => RegionalRoute::ASIA,
+        route => route,
+    }
+}
+
+/// Result of looking a Riot ID up on a single regional route.
+struct RouteProbe {
+    route: RegionalRoute,
+    puuid: Option<String>,
+    has_recent_matches: bool,
+}
+
+async fn probe_route(riot_api: &RiotApiPool, riot_id: &RiotId, route: RegionalRoute) -> Result<RouteProbe, Box<dyn Error>> {
+    let account = riot_api
+        .client().await
+        .account_v1()
+        .get_by_riot_id(account_route(route), &riot_id.game_name, &riot_id.tag_line)
+        .await?;
+    let Some(account) = account else {
+        return Ok(RouteProbe { route, puuid: None, has_recent_matches: false });
+    };
+    let match_ids = riot_api
+        .client().await
+        .match_v5()
+        .get_match_ids_by_puuid(route, &account.puuid, Some(1), None, None, None, None, None)
+        .await?;
+    Ok(RouteProbe {
+        route,
+        puuid: Some(account.puuid),
+        has_recent_matches: !match_ids.is_empty(),
+    })
+}
+
+/// Finds the regional route where `riot_id` has match history by probing every route concurrently.
+/// The result is remembered in the account cache so the probe only ever runs once per player.
+pub async fn detect_regional_route(
+    riot_api: &RiotApiPool,
+    account_cache: &mut AccountCache,
+    riot_id: &RiotId,
+    verbose: bool,
+    api_calls: &mut ApiCallStats,
+) -> Result<RegionalRoute, Box<dyn Error>> {
+    if let Some(route) = account_cache.get(riot_id).and_then(|account| account.regional_route()) {
+        api_calls.cache_hits += 1;
+        if verbose {
+            println!("Using previously detected regional route {} for {}.", route, anonymize::riot_id(riot_id));
+        }
+        return Ok(route);
+    }
+
+    offline::ensure_online(&format!("the regional route of {}", anonymize::riot_id(riot_id)))?;
+    if verbose {
+        println!("No region given, detecting the regional route of {}...", anonymize::riot_id(riot_id));
+    }
+    let (americas, asia, europe, sea) = tokio::join!(
+        probe_route(riot_api, riot_id, AUTO_DETECT_ROUTES[0]),
+        probe_route(riot_api, riot_id, AUTO_DETECT_ROUTES[1]),
+        probe_route(riot_api, riot_id, AUTO_DETECT_ROUTES[2]),
+        probe_route(riot_api, riot_id, AUTO_DETECT_ROUTES[3]),
+    );
+
+    let mut probes = Vec::new();
+    let mut probe_error = None;
+    for probe in [americas, asia, europe, sea] {
+        api_calls.account_calls += 1;
+        // A route that cannot be probed, e.g. during an outage, counts as one the player is not on.
+        let probe = match probe {
+            Ok(probe) => probe,
+            Err(e) => {
+                probe_error.get_or_insert(e);
+                continue;
+            }
+        };
+        if probe.puuid.is_some() {
+            api_calls.match_list_calls += 1;
+        }
+        probes.push(probe);
+    }
+
+    let Some(puuid) = probes.iter().find_map(|probe| probe.puuid.clone()) else {
+        if let Some(e) = probe_error {
+            return Err(e);
+        }
+        return Err(format!(
+            "Error: Riot ID '{}' was not found on any regional route. Please check spelling and tag line.",
+            riot_id
+        ).into());
+    };
+    let route = probes
+        .iter()
+        .find(|probe| probe.has_recent_matches)
+        .map(|probe| probe.route)
+        .unwrap_or(RegionalRoute::EUROPE);
+
+    if verbose {
+        println!("Detected regional route {} for {}.", route, anonymize::riot_id(riot_id));
+    }
+    account_cache.insert(riot_id, &puuid, Some(route));
+    Ok(route)
+}
+
+/// Looks up the PUUID of a Riot ID, answering from the account cache when possible.
+pub async fn resolve_puuid(
+    riot_api: &RiotApiPool,
+    account_cache: &mut AccountCache,
+    riot_id: &RiotId,
+    regional_route: RegionalRoute,
+    api_calls: &mut ApiCallStats,
+) -> Result<Option<String>, Box<dyn Error>> {
+    if let Some(account) = account_cache.get(riot_id) {
+        api_calls.cache_hits += 1;
+        return Ok(Some(account.puuid.clone()));
+    }
+
+    offline::ensure_online(&format!("the PUUID of {}", anonymize::riot_id(riot_id)))?;
+    api_calls.account_calls += 1;
+    let account = riot_api
+        .client().await
+        .account_v1()
+        .get_by_riot_id(account_route(regional_route), &riot_id.game_name, &riot_id.tag_line)
+        .await?;
+    Ok(account.map(|account| {
+        account_cache.insert(riot_id, &account.puuid, None);
+        account.puuid
+    }))
+}
+
+/// Looks up the current Riot ID of a PUUID. `None` if the account has no Riot ID.
+pub async fn fetch_riot_id(
+    riot_api: &RiotApiPool,
+    regional_route: RegionalRoute,
+    puuid: &str,
+    api_calls: &mut ApiCallStats,
+) -> Result<Option<RiotId>, Box<dyn Error>> {
+    offline::ensure_online(&format!("the Riot ID of PUUID '{}'", anonymize::puuid(puuid)))?;
+    api_calls.account_calls += 1;
+    let account = riot_api.client().await.account_v1().get_by_puuid(account_route(regional_route), puuid).await?;
+    Ok(account.game_name.zip(account.tag_line).map(|(game_name, tag_line)| RiotId { game_name, tag_line, puuid: None }))
+}
+
+/// Riot keeps match-v5 details for about two years.
+const MATCH_RETENTION_MS: i64 = 2 * 365 * 24 * 60 * 60 * 1000;
+
+/// Outcome of loading a listed match.
+pub enum MatchFetch {
+    Found(Box<Match>),
+    /// The payload does not fit riven's match model, e.g. a new game mode, so only its outline was read.
+    Outline(MatchOutline),
+    /// The API returned no data for the match ID (404).
+    Missing,
+    /// The API key may not read the match (401/403).
+    Forbidden,
+}
+
+/// The parts of a match payload that are read without riven's model: who played, on which team, and who won.
+#[derive(Debug)]
+pub struct MatchOutline {
+    pub game_start_timestamp: i64,
+    pub game_version: String,
+    /// 0 when the payload has none.
+    pub queue_id: u16,
+    pub participants: Vec<OutlineParticipant>,
+}
+
+#[derive(Debug)]
+pub struct OutlineParticipant {
+    pub puuid: String,
+    pub team_id: i64,
+    pub win: bool,
+}
+
+impl MatchOutline {
+    pub fn from_json(match_json: &Value) -> Option<Self> {
+        let info = match_json.get("info")?;
+        let participants = info
+            .get("participants")?
+            .as_array()?
+            .iter()
+            .map(|participant| {
+                Some(OutlineParticipant {
+                    puuid: participant.get("puuid")?.as_str()?.to_string(),
+                    team_id: participant.get("teamId")?.as_i64()?,
+                    win: participant.get("win")?.as_bool()?,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(MatchOutline {
+            game_start_timestamp: info.get("gameStartTimestamp")?.as_i64()?,
+            game_version: info.get("gameVersion").and_then(Value::as_str).unwrap_or_default().to_string(),
+            queue_id: info.get("queueId").and_then(Value::as_u64).and_then(|id| u16::try_from(id).ok()).unwrap_or_default(),
+            participants,
+        })
+    }
+
+    pub fn participant(&self, puuid: &str) -> Option<&OutlineParticipant> {
+        self.participants.iter().find(|participant| participant.puuid == puuid)
+    }
+}
+
+/// Fetches a match again as plain JSON after riven failed to deserialize it, and reads its outline.
+async fn fetch_match_outline(
+    riot_api: &RiotApiPool,
+    regional_route: RegionalRoute,
+    match_id: &str,
+    api_calls: &mut ApiCallStats,
+) -> Result<MatchOutline, Box<dyn Error>> {
+    api_calls.match_detail_calls += 1;
+    let client = riot_api.client().await;
+    let route: &'static str = regional_route.into();
+    let request = client.request(Method::GET, route, &format!("/lol/match/v5/matches/{}", match_id));
+    let match_json: Value = client.execute_raw("match-v5.getMatch", route, request).await?.response.json().await?;
+    MatchOutline::from_json(&match_json)
+        .ok_or_else(|| format!("Match {} could not be read: no participants, teams or results found.", match_id).into())
+}
+
+/// Fetches a match, serving it from the local index when it was fetched before and indexing it otherwise.
+pub async fn fetch_match(
+    riot_api: &RiotApiPool,
+    match_index: &MatchIndex,
+    regional_route: RegionalRoute,
+    match_id: &str,
+    api_calls: &mut ApiCallStats,
+) -> Result<MatchFetch, Box<dyn Error>> {
+    if let Some(match_data) = match_index.get_match(match_id)? {
+        api_calls.cache_hits += 1;
+        return Ok(MatchFetch::Found(Box::new(match_data)));
+    }
+
+    offline::ensure_online(&format!("match {}", match_id))?;
+    api_calls.match_detail_calls += 1;
+    let match_data = match riot_api.client().await.match_v5().get_match(regional_route, match_id).await {
+        Ok(match_data) => match_data,
+        Err(e) if matches!(e.status_code().map(|s| s.as_u16()), Some(401 | 403)) => return Ok(MatchFetch::Forbidden),
+        Err(e) if e.de_error().is_some() => {
+            return Ok(MatchFetch::Outline(fetch_match_outline(riot_api, regional_route, match_id, api_calls).await?));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    match match_data {
+        Some(match_data) => {
+            match_index.insert_match(&match_data)?;
+            Ok(MatchFetch::Found(Box::new(match_data)))
+        }
+        None => Ok(MatchFetch::Missing),
+    }
+}
+
+/// Detects that a player left `selected_region`: their latest listed match, or their active shard
+/// when the list is empty, is on another platform. Returns the region they play on now.
+pub async fn detect_region_transfer(
+    riot_api: &RiotApiPool,
+    regional_route: RegionalRoute,
+    selected_region: &UserFacingRegion,
+    puuid: &str,
+    match_ids: &[String],
+    api_calls: &mut ApiCallStats,
+) -> Result<Option<UserFacingRegion>, Box<dyn Error>> {
+    // Needs account-v1, so offline the cached matches are taken as they are.
+    if offline::is_enabled() {
+        return Ok(None);
+    }
+    let selected_platform = selected_region.to_platform_route();
+    let latest_platform = match_ids
+        .first()
+        .and_then(|match_id| match_id.split_once('_'))
+        .and_then(|(platform, _)| platform.parse::<PlatformRoute>().ok());
+    if latest_platform == Some(selected_platform) {
+        return Ok(None);
+    }
+
+    // The active shard is authoritative; the match prefix is the fallback when it cannot be read.
+    let active_platform = active_platform(riot_api, regional_route, puuid, api_calls).await.ok();
+    Ok(active_platform
+        .or(latest_platform)
+        .filter(|platform| *platform != selected_platform)
+        .and_then(UserFacingRegion::from_platform_route))
+}
+
+/// The platform (shard) a player currently plays League of Legends on, from account-v1.
+pub async fn active_platform(
+    riot_api: &RiotApiPool,
+    regional_route: RegionalRoute,
+    puuid: &str,
+    api_calls: &mut ApiCallStats,
+) -> Result<PlatformRoute, Box<dyn Error>> {
+    offline::ensure_online(&format!("the active region of PUUID '{}'", anonymize::puuid(puuid)))?;
+    api_calls.account_calls += 1;
+    let account_region = riot_api
+        .client().await
+        .account_v1()
+        .get_active_region(account_route(regional_route), "lol", puuid)
+        .await?;
+    Ok(account_region
+        .region
+        .to_uppercase()
+        .parse()
+        .map_err(|_| format!("Unknown platform '{}' returned for the player's active region.", account_region.region))?)
+}
+
+/// Whether the player has played any League of Legends match on `regional_route`, regardless of when.
+async fn has_league_matches(
+    riot_api: &RiotApiPool,
+    regional_route: RegionalRoute,
+    puuid: &str,
+    api_calls: &mut ApiCallStats,
+) -> Result<bool, Box<dyn Error>> {
+    api_calls.match_list_calls += 1;
+    let match_ids = riot_api
+        .client().await
+        .match_v5()
+        .get_match_ids_by_puuid(regional_route, puuid, Some(1), None, None, None, None, None)
+        .await?;
+    Ok(!match_ids.is_empty())
+}
+
+/// Whether the player has a Valorant shard, i.e. has played Valorant with the account.
+async fn plays_valorant(
+    riot_api: &RiotApiPool,
+    regional_route: RegionalRoute,
+    puuid: &str,
+    api_calls: &mut ApiCallStats,
+) -> Result<bool, Box<dyn Error>> {
+    api_calls.account_calls += 1;
+    let shard = riot_api
+        .client().await
+        .account_v1()
+        .get_active_shard(account_route(regional_route), "val", puuid)
+        .await?;
+    Ok(shard.is_some())
+}
+
+/// Explains an empty match list of a player with no League of Legends matches at all, e.g. someone
+/// who only plays Valorant, instead of leaving it to look like a quiet month. `None` when the player
+/// does play League of Legends or when that cannot be checked; the checks are best-effort.
+async fn no_league_matches_warning(
+    riot_api: &RiotApiPool,
+    regional_route: RegionalRoute,
+    riot_id: &RiotId,
+    puuid: &str,
+    api_calls: &mut ApiCallStats,
+) -> Option<Warning> {
+    if offline::is_enabled() || has_league_matches(riot_api, regional_route, puuid, api_calls).await.unwrap_or(true) {
+        return None;
+    }
+    let message = if plays_valorant(riot_api, regional_route, puuid, api_calls).await.unwrap_or(false) {
+        format!(
+            "Warning: {} has no League of Legends matches but plays Valorant, which ptg cannot check. Make sure this is the account you meant.",
+            anonymize::riot_id(riot_id)
+        )
+    } else {
+        format!(
+            "Warning: {} has never played a League of Legends match on {:?}.",
+            anonymize::riot_id(riot_id),
+            regional_route
+        )
+    };
+    Some(Warning::new(WarningCode::NoLeagueMatches, None, message))
+}
+
+/// Looks up the summoner level and profile icon of a player. Without a known platform, the player's
+/// active League of Legends shard is looked up first. Returns `None` if the player has no summoner.
+pub async fn fetch_summoner_profile(
+    riot_api: &RiotApiPool,
+    regional_route: RegionalRoute,
+    platform_route: Option<PlatformRoute>,
+    puuid: &str,
+    ddragon_version: &str,
+    api_calls: &mut ApiCallStats,
+) -> Result<Option<SummonerProfile>, Box<dyn Error>> {
+    let platform_route = match platform_route {
+        Some(route) => route,
+        None => active_platform(riot_api, regional_route, puuid, api_calls).await?,
+    };
+    offline::ensure_online(&format!("the summoner profile of PUUID '{}'", anonymize::puuid(puuid)))?;
+    api_calls.summoner_calls += 1;
+    let summoner = riot_api
+        .client().await
+        .summoner_v4()
+        .get_by_puuid(platform_route, puuid)
+        .await;
+    match summoner {
+        Ok(summoner) => Ok(Some(SummonerProfile {
+            summoner_level: summoner.summoner_level,
+            profile_icon_id: summoner.profile_icon_id,
+            profile_icon_url: ddragon::profile_icon_url(ddragon_version, summoner.profile_icon_id),
+        })),
+        Err(e) if e.status_code().map(|s| s.as_u16()) == Some(404) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Whether a match is on the `--patch`, in the `--queue` and within the `--between` dates of a query.
+fn passes_match_filters(
+    game_version: &str,
+    match_queue_id: u16,
+    game_start_timestamp: i64,
+    patch: Option<&str>,
+    queue_id: Option<u16>,
+    date_range: Option<DateRange>,
+) -> bool {
+    patch.is_none_or(|patch| patch_of(game_version) == patch)
+        && queue_id.is_none_or(|queue_id| match_queue_id == queue_id)
+        && date_range.is_none_or(|range| range.contains(game_start_timestamp))
+}
+
+/// Whether both players fought on the same side: the same team, or in Arena the same 2-player subteam.
+pub fn on_same_side(info: &Info, p1_data: &Participant, p2_data: &Participant) -> bool {
+    if info.game_mode == GameMode::CHERRY {
+        p1_data.player_subteam_id.is_some() && p1_data.player_subteam_id == p2_data.player_subteam_id
+    } else {
+        p1_data.team_id == p2_data.team_id
+    }
+}
+
+fn final_items(participant: &Participant, data_dragon: Option<&DataDragon>) -> Vec<BuildEntry> {
+    let slots = [participant.item0, participant.item1, participant.item2, participant.item3, participant.item4, participant.item5];
+    BuildEntry::from_ids(&slots, data_dragon.map(|dd| |id| dd.item_name(id)))
+}
+
+fn summoner_spells(participant: &Participant, data_dragon: Option<&DataDragon>) -> Vec<BuildEntry> {
+    let spells = [participant.summoner1_id, participant.summoner2_id];
+    BuildEntry::from_ids(&spells, data_dragon.map(|dd| |id| dd.summoner_spell_name(id)))
+}
+
+/// Builds the output record of a shared match from the two players' participant data.
+#[allow(clippy::too_many_arguments)]
+pub fn build_match_details(
+    match_id: &str,
+    info: &Info,
+    p1_data: &Participant,
+    p2_data: &Participant,
+    log_region: &str,
+    gold_diff_at15: Option<i32>,
+    lane_matchup: Option<LaneMatchup>,
+    data_dragon: Option<&DataDragon>,
+) -> MatchDetails {
+    let game_start_datetime =
+        Utc.timestamp_millis_opt(info.game_start_timestamp)
+           .single()
+           .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+           .unwrap_or_else(|| "Unknown Date".to_string());
+
+    let league_of_graphs_link = match_id
+        .split_once('_')
+        .map(|(_region_id, stripped_match_id)| format!("https://www.leagueofgraphs.com/match/{}/{}", log_region, stripped_match_id));
+
+    let surrendered = p1_data.game_ended_in_surrender || p1_data.game_ended_in_early_surrender;
+    let game_flow = classify_game_flow(
+        p1_data.win,
+        gold_diff_at15,
+        p1_data.game_ended_in_early_surrender,
+        p1_data.game_ended_in_surrender,
+    );
+
+    let arena = (info.game_mode == GameMode::CHERRY).then(|| ArenaDetails {
+        duo_partners: on_same_side(info, p1_data, p2_data),
+        player1_placement: p1_data.subteam_placement.or(p1_data.placement),
+        player2_placement: p2_data.subteam_placement.or(p2_data.placement),
+    });
+    let same_team = on_same_side(info, p1_data, p2_data);
+    let same_champion = p1_data.champion_name == p2_data.champion_name;
+    let mirror_roles = !same_team
+        && !p1_data.team_position.is_empty()
+        && p1_data.team_position == p2_data.team_position;
+
+    let p1_score = performance_score(p1_data, &info.participants);
+    let p2_score = performance_score(p2_data, &info.participants);
+    let p1_outcome = if p1_data.win { "Victory" } else { "Defeat" }.to_string();
+    let p2_outcome = if p2_data.win { "Victory" } else { "Defeat" }.to_string();
+
+    MatchDetails {
+        match_id: match_id.to_string(),
+        game_date_utc: game_start_datetime,
+        game_start_timestamp: info.game_start_timestamp,
+        game_duration_secs: info.game_duration,
+        game_mode: format!("{:?}", info.game_mode),
+        game_type: info.game_type.map(|gt| format!("{:?}", gt)),
+        queue_id: info.queue_id.0,
+        game_version: info.game_version.clone(),
+        surrendered,
+        early_surrendered: p1_data.game_ended_in_early_surrender,
+        // Scores are shares of each player's own team, so they only compare on the same team.
+        carried_by: match p1_score.total_cmp(&p2_score) {
+            _ if !same_team => None,
+            std::cmp::Ordering::Greater => Some("player1".to_string()),
+            std::cmp::Ordering::Less => Some("player2".to_string()),
+            std::cmp::Ordering::Equal => None,
+        },
+        gold_diff_at15,
+        game_flow,
+        same_team,
+        same_champion,
+        mirror_roles,
+        arena,
+        lane_matchup,
+        kill_participation: None,
+        gold_graph: None,
+        note: None,
+        league_of_graphs_link,
+        player1_details: ParticipantDetails {
+            champion: p1_data.champion_name.clone(),
+            champion_display_name: data_dragon.map(|dd| dd.display_name(&p1_data.champion_name)),
+            role: p1_data.team_position.to_string(),
+            kills: p1_data.kills,
+            deaths: p1_data.deaths,
+            assists: p1_data.assists,
+            outcome: p1_outcome,
+            champion_icon_url: None,
+            champion_splash_url: None,
+            pings: PingCounts::of(p1_data),
+            first_blood: p1_data.first_blood_kill || p1_data.first_blood_assist,
+            performance_score: p1_score,
+            lane_gold_diff_at10: None,
+            likely_afk: likely_afk(p1_data, info),
+            vision_score: p1_data.vision_score,
+            items: final_items(p1_data, data_dragon),
+            summoner_spells: summoner_spells(p1_data, data_dragon),
+            runes: RunesSummary::of(p1_data, data_dragon),
+        },
+        player2_details: ParticipantDetails {
+            champion: p2_data.champion_name.clone(),
+            champion_display_name: data_dragon.map(|dd| dd.display_name(&p2_data.champion_name)),
+            role: p2_data.team_position.to_string(),
+            kills: p2_data.kills,
+            deaths: p2_data.deaths,
+            assists: p2_data.assists,
+            outcome: p2_outcome,
+            champion_icon_url: None,
+            champion_splash_url: None,
+            pings: PingCounts::of(p2_data),
+            first_blood: p2_data.first_blood_kill || p2_data.first_blood_assist,
+            performance_score: p2_score,
+            lane_gold_diff_at10: None,
+            likely_afk: likely_afk(p2_data, info),
+            vision_score: p2_data.vision_score,
+            items: final_items(p2_data, data_dragon),
+            summoner_spells: summoner_spells(p2_data, data_dragon),
+            runes: RunesSummary::of(p2_data, data_dragon),
+        },
+    }
+}
+
+/// Prints every match as one aligned line: date, mode, champions, both KDAs, player1's result and link.
+pub fn print_match_lines(found_matches: &[MatchDetails]) {
+    let rows: Vec<[String; 7]> = found_matches
+        .iter()
+        .map(|m| {
+            let kda = |d: &ParticipantDetails| format!("{}/{}/{}", d.kills, d.deaths, d.assists);
+            [
+                m.game_date_utc.clone(),
+                m.game_mode.clone(),
+                format!("{}/{}", m.player1_details.champion_label(), m.player2_details.champion_label()),
+                kda(&m.player1_details),
+                kda(&m.player2_details),
+                if m.player1_details.outcome == "Victory" { "W" } else { "L" }.to_string(),
+                m.league_of_graphs_link.clone().unwrap_or_else(|| m.match_id.clone()),
+            ]
+        })
+        .collect();
+    let mut widths = [0; 7];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for row in &rows {
+        let cells: Vec<String> = row.iter().zip(widths).map(|(cell, width)| format!("{:<width$}", cell, width = width)).collect();
+        println!("{}", cells.join("  ").trim_end());
+    }
+}
+
+/// Adds the summoner spells and items of one player to a match box, when the match has them.
+fn push_build_lines(lines_of_text: &mut Vec<String>, details: &ParticipantDetails) {
+    let list = |entries: &[BuildEntry]| entries.iter().map(BuildEntry::label).collect::<Vec<_>>().join(", ");
+    if !details.summoner_spells.is_empty() {
+        lines_of_text.push(format!("  Summoner Spells: {}", list(&details.summoner_spells)));
+    }
+    if !details.items.is_empty() {
+        lines_of_text.push(format!("  Items: {}", list(&details.items)));
+    }
+    if let Some(runes) = &details.runes {
+        lines_of_text.push(format!(
+            "  Runes: {} ({} / {})",
+            runes.keystone.label(),
+            runes.primary_tree.label(),
+            runes.secondary_tree.label()
+        ));
+    }
+}
+
+/// Prints the verbose per-match box.
+pub fn print_match_box(match_details: &MatchDetails, player1_riot_id: &RiotId, player2_riot_id: &RiotId) {
+    let mut lines_of_text: Vec<String> = Vec::new();
+    lines_of_text.push(format!(
+        "Players {} and {} played together in Match ID: {}",
+        anonymize::riot_id(player1_riot_id),
+        anonymize::riot_id(player2_riot_id),
+        anonymize::match_id(&match_details.match_id)
+    ));
+    lines_of_text.push(format!("Date: {}", match_details.game_date_utc));
+    lines_of_text.push(format!(
+        "Game Mode: {}, Game Type: {}",
+        match_details.game_mode,
+        match_details.game_type.as_deref().unwrap_or("N/A")
+    ));
+
+    if let Some(link) = &match_details.league_of_graphs_link
+        && !anonymize::is_enabled()
+    {
+        lines_of_text.push(link.clone());
+    }
+
+    lines_of_text.push("--- Participant Details ---".to_string());
+    lines_of_text.push(format!("{}:", anonymize::riot_id(player1_riot_id)));
+    lines_of_text.push(format!("  Champion: {}", match_details.player1_details.champion_label()));
+    lines_of_text.push(format!("  Role: {}", match_details.player1_details.role));
+    lines_of_text.push(format!(
+        "  KDA: {}/{}/{}",
+        match_details.player1_details.kills, match_details.player1_details.deaths, match_details.player1_details.assists
+    ));
+    push_build_lines(&mut lines_of_text, &match_details.player1_details);
+
+    lines_of_text.push(format!("{}:", anonymize::riot_id(player2_riot_id)));
+    lines_of_text.push(format!("  Champion: {}", match_details.player2_details.champion_label()));
+    lines_of_text.push(format!("  Role: {}", match_details.player2_details.role));
+    lines_of_text.push(format!(
+        "  KDA: {}/{}/{}",
+        match_details.player2_details.kills, match_details.player2_details.deaths, match_details.player2_details.assists
+    ));
+    push_build_lines(&mut lines_of_text, &match_details.player2_details);
+
+    lines_of_text.push("--- Match Outcome ---".to_string());
+    let outcome_text = if match_details.player1_details.outcome == "Victory" {
+        "Victory".green().to_string()
+    } else {
+        "Defeat".red().to_string()
+    };
+    lines_of_text.push(format!("  Outcome: {}", outcome_text));
+    if let Some(game_flow) = match_details.game_flow {
+        lines_of_text.push(format!("  Game Flow: {}", game_flow));
+    }
+    let scores = (match_details.player1_details.performance_score, match_details.player2_details.performance_score);
+    if match_details.same_team {
+        let carry = match match_details.carried_by.as_deref() {
+            Some("player1") => anonymize::riot_id(player1_riot_id),
+            Some(_) => anonymize::riot_id(player2_riot_id),
+            None => "nobody".to_string(),
+        };
+        lines_of_text.push(format!("  Carried by: {} (score {:.0} vs {:.0})", carry, scores.0, scores.1));
+    } else {
+        lines_of_text.push(format!("  Performance Scores: {:.0} vs {:.0} (each within their own team)", scores.0, scores.1));
+    }
+    if let (Some(p1_pings), Some(p2_pings)) = (&match_details.player1_details.pings, &match_details.player2_details.pings) {
+        let describe = |pings: &PingCounts| {
+            format!(
+                "{} (missing {}, vision {}, assist me {})",
+                pings.total,
+                pings.enemy_missing,
+                pings.enemy_vision + pings.need_vision,
+                pings.assist_me
+            )
+        };
+        lines_of_text.push(format!("  Pings: P1 {} vs P2 {}", describe(p1_pings), describe(p2_pings)));
+    }
+    if let Some(participation) = &match_details.kill_participation {
+        let together_rate = participation.together_rate.map_or("-".to_string(), |rate| format!("{:.0}%", rate));
+        lines_of_text.push(format!(
+            "  Kills Together: {} ({}/{} assisted by P2, {}/{} assisted by P1)",
+            together_rate,
+            participation.player1_kills_assisted_by_player2,
+            participation.player1_kills,
+            participation.player2_kills_assisted_by_player1,
+            participation.player2_kills
+        ));
+    }
+    if let Some(lane_matchup) = &match_details.lane_matchup {
+        let cs_diff = lane_matchup.cs_diff_at10.map_or("?".to_string(), |diff| format!("{:+}", diff));
+        lines_of_text.push(format!(
+            "  Lane: CS diff @10 {}, solo kills {}-{}",
+            cs_diff, lane_matchup.player1_solo_kills, lane_matchup.player2_solo_kills
+        ));
+    }
+    if let Some(arena) = &match_details.arena {
+        let placement = |placement: Option<i32>| placement.map_or("?".to_string(), |p| format!("#{}", p));
+        if arena.duo_partners {
+            lines_of_text.push(format!("  Arena: duo partners, placed {}", placement(arena.player1_placement)));
+        } else {
+            lines_of_text.push(format!(
+                "  Arena: same lobby, not partners ({} vs {})",
+                placement(arena.player1_placement),
+                placement(arena.player2_placement)
+            ));
+        }
+    }
+    if match_details.same_champion {
+        lines_of_text.push("  Highlight: both players picked the same champion!".to_string());
+    }
+    if match_details.mirror_roles {
+        lines_of_text.push("  Highlight: mirror match in the same role!".to_string());
+    }
+    if let Some(note) = &match_details.note {
+        lines_of_text.push(format!("  Notes: {}", note.summary()));
+    }
+
+    print_in_box(
+        &lines_of_text
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<&str>>(),
+    );
+    println!(); // Add a newline after each box for spacing
+}
+
+/// Number of match IDs the match list endpoint returns when no count is given.
+const DEFAULT_MATCH_COUNT: i32 = 20;
+/// Largest count the match list endpoint accepts.
+const MAX_MATCH_LIST_COUNT: i32 = 100;
+/// Days the match list reaches back without --on or --between.
+const DEFAULT_WINDOW_DAYS: u32 = 30;
+/// Furthest back `--auto-widen` looks when neither `--max-window-days` nor max_window_days is set.
+pub const DEFAULT_MAX_WINDOW_DAYS: u32 = 365;
+
+/// Unix timestamp in seconds of `days` days ago.
+fn days_ago(days: u32) -> Option<i64> {
+    SystemTime::now()
+        .checked_sub(std::time::Duration::from_secs(u64::from(days) * 24 * 60 * 60))
+        .map(|t| t.duration_since(UNIX_EPOCH).unwrap().as_secs() as i64)
+}
+
+/// Loads `match_ids` like `fetch_match`, leaving out the matches that cannot be read in full.
+async fn load_matches(
+    riot_api: &RiotApiPool,
+    match_index: &MatchIndex,
+    regional_route: RegionalRoute,
+    match_ids: &[String],
+    api_calls: &mut ApiCallStats,
+) -> Result<Vec<Match>, Box<dyn Error>> {
+    let mut matches = Vec::new();
+    for match_id in match_ids {
+        if let MatchFetch::Found(match_data) = fetch_match(riot_api, match_index, regional_route, match_id, api_calls).await? {
+            matches.push(*match_data);
+        }
+    }
+    Ok(matches)
+}
+
+/// Lists up to `count` of the player's match IDs since `start_time` (Unix seconds), newest first.
+/// When the index already covers that window for the player, only matches newer than the latest
+/// indexed one are requested and the rest are read from the index, so repeated scans cost one cheap call.
+#[allow(clippy::too_many_arguments)]
+async fn list_match_ids(
+    riot_api: &RiotApiPool,
+    match_index: &MatchIndex,
     regional_route: RegionalRoute,
-    user_selected_region: Option<UserFacingRegion>,
-    number_of_matches: Option<i32>,
+    puuid: &str,
+    count: i32,
+    start_time: Option<i64>,
+    end_time: Option<i64>,
+    match_type: Option<MatchType>,
+    queue_id: Option<u16>,
     verbose: bool,
-    _silent: bool,
-    json_output_enabled: bool,
+    api_calls: &mut ApiCallStats,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let window_start = start_time.unwrap_or(0) * 1000;
+    // The index does not know match types or queues, and its coverage always reaches the present,
+    // so filtered and past windows always come from the API.
+    let coverage = match_index
+        .player_coverage(puuid)?
+        .filter(|coverage| coverage.covered_since <= window_start && match_type.is_none() && queue_id.is_none() && end_time.is_none());
+
+    if offline::is_enabled() {
+        return offline_match_ids(match_index, puuid, count, window_start, end_time, match_type);
+    }
+
+    api_calls.match_list_calls += 1;
+    let Some(coverage) = coverage else {
+        return Ok(riot_api
+            .client().await
+            .match_v5()
+            .get_match_ids_by_puuid(
+                regional_route,
+                puuid,
+                Some(count),
+                end_time,
+                queue_id.map(Queue),
+                start_time,
+                None,
+                match_type.map(|match_type| match_type.as_api_str()),
+            )
+            .await?);
+    };
+
+    let mut match_ids = riot_api
+        .client().await
+        .match_v5()
+        .get_match_ids_by_puuid(
+            regional_route,
+            puuid,
+            Some(MAX_MATCH_LIST_COUNT),
+            None,
+            None,
+            Some(coverage.latest_game_start / 1000),
+            None,
+            None,
+        )
+        .await?;
+    if verbose {
+        println!("Local index is up to date except for {} newer matches.", match_ids.len());
+    }
+    for match_id in match_index.match_ids_for_player_since(puuid, window_start)? {
+        if !match_ids.contains(&match_id) {
+            match_ids.push(match_id);
+        }
+    }
+    match_ids.truncate(count as usize);
+    Ok(match_ids)
+}
+
+/// The match list of `list_match_ids` read from the index alone. Past windows are fine offline, since
+/// the index keeps every match it covers, but match types are not indexed.
+fn offline_match_ids(
+    match_index: &MatchIndex,
+    puuid: &str,
+    count: i32,
+    window_start: i64,
+    end_time: Option<i64>,
+    match_type: Option<MatchType>,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    if match_type.is_some() {
+        return Err("--offline: the local index does not know match types; drop --match-type (or default_match_type) to query it.".into());
+    }
+    let covered = match_index
+        .player_coverage(puuid)?
+        .is_some_and(|coverage| coverage.covered_since <= window_start);
+    if !covered {
+        offline::ensure_online(&format!("the match list of PUUID '{}' for this window", anonymize::puuid(puuid)))?;
+    }
+    let mut match_ids = Vec::new();
+    for match_id in match_index.match_ids_for_player_since(puuid, window_start)? {
+        let in_window = match end_time {
+            Some(end_time) => match_index.game_start_of(&match_id)?.is_some_and(|start| start <= end_time * 1000),
+            None => true,
+        };
+        if in_window {
+            match_ids.push(match_id);
+        }
+    }
+    match_ids.truncate(count as usize);
+    Ok(match_ids)
+}
+
+pub async fn run_query(
+    riot_api: &RiotApiPool,
+    account_cache: &mut AccountCache,
+    match_index: &MatchIndex,
+    options: QueryOptions,
 ) -> Result<OverallOutput, Box<dyn Error>> {
+    let QueryOptions {
+        player1_riot_id,
+        player2_riot_id,
+        player3_riot_id,
+        regional_route,
+        mut user_selected_region,
+        number_of_matches,
+        match_type,
+        auto_widen_max_days,
+        queue_id,
+        patch,
+        date_range,
+        fetch_timelines,
+        fetch_profiles,
+        opponents_only,
+        follow_region_transfer,
+        stop_at_first_shared,
+        include_afk,
+        solo_baseline,
+        data_dragon,
+        verbose,
+        quiet,
+    } = options;
+
+    let started_at = Instant::now();
+    let mut api_calls = ApiCallStats::default();
+    let note_book = NoteBook::load()?;
+
     let player1_game_name = player1_riot_id.game_name.clone();
     let player1_tag_line = player1_riot_id.tag_line.clone();
 
     let player2_game_name = player2_riot_id.game_name.clone();
     let player2_tag_line = player2_riot_id.tag_line.clone();
 
+    let mut regional_route = match regional_route {
+        Some(route) => route,
+        None => detect_regional_route(riot_api, account_cache, &player1_riot_id, verbose, &mut api_calls).await?,
+    };
+
     if verbose {
-        println!("Fetching PUUID for {}#{}", player1_game_name, player1_tag_line);
+        println!("Fetching PUUID for {}", anonymize::riot_id(&player1_riot_id));
     }
     let player1_puuid_found = true;
-    let account1 = riot_api
-        .account_v1()
-        .get_by_riot_id(regional_route, &player1_game_name, &player1_tag_line)
-        .await?;
-
-    let puuid1 = match account1 {
-        Some(acc) => {
+    let puuid1 = match resolve_puuid(riot_api, account_cache, &player1_riot_id, regional_route, &mut api_calls).await? {
+        Some(puuid) => {
             if verbose {
-                println!("Player 1 PUUID: {}", acc.puuid);
+                println!("Player 1 PUUID: {}", anonymize::puuid(&puuid));
             }
-            acc.puuid
+            puuid
         },
-        _none => {
+        None => {
             return Err(format!(
                 "Error: Player 1 Riot ID '{}' not found on regional route '{:?}'. Please check spelling, tag line, and ensure the account exists and is active in this region.",
-                player1_riot_id, regional_route
+                anonymize::riot_id(&player1_riot_id), regional_route
             ).into());
         }
     };
 
     if verbose {
-        println!("Fetching PUUID for {}#{}", player2_game_name, player2_tag_line);
+        println!("Fetching PUUID for {}", anonymize::riot_id(&player2_riot_id));
     }
     let player2_puuid_found = true;
-    let account2 = riot_api
-        .account_v1()
-        .get_by_riot_id(regional_route, &player2_game_name, &player2_tag_line)
-        .await?;
-    
-    let puuid2 = match account2 {
-        Some(acc) => {
+    let puuid2 = match resolve_puuid(riot_api, account_cache, &player2_riot_id, regional_route, &mut api_calls).await? {
+        Some(puuid) => {
             if verbose {
-                println!("Player 2 PUUID: {}", acc.puuid);
+                println!("Player 2 PUUID: {}", anonymize::puuid(&puuid));
             }
-            acc.puuid
+            puuid
         },
-        _none => {
+        None => {
             return Err(format!(
                 "Error: Player 2 Riot ID '{}' not found on regional route '{:?}'. Please check spelling, tag line, and ensure the account exists and is active in this region.",
-                player2_riot_id, regional_route
+                anonymize::riot_id(&player2_riot_id), regional_route
             ).into());
         }
     };
 
+    let player3 = match &player3_riot_id {
+        Some(player3_riot_id) => match resolve_puuid(riot_api, account_cache, player3_riot_id, regional_route, &mut api_calls).await? {
+            Some(puuid) => Some((puuid, player3_riot_id)),
+            None => {
+                return Err(format!(
+                    "Error: Player 3 Riot ID '{}' not found on regional route '{:?}'.",
+                    anonymize::riot_id(player3_riot_id), regional_route
+                ).into());
+            }
+        },
+        None => None,
+    };
+    let mut venn = player3.as_ref().map(|(_, riot_id)| VennSummary::new(PlayerIdentity {
+        game_name: riot_id.game_name.clone(),
+        tag_line: riot_id.tag_line.clone(),
+        profile: None,
+        formerly_known_as: Vec::new(),
+        nickname: nickname_of(riot_id),
+    }));
+    let puuid3 = player3.map(|(puuid, _)| puuid);
+
+    let (player1_profile, player2_profile) = if fetch_profiles {
+        let platform_route = user_selected_region.as_ref().map(|r| r.to_platform_route());
+        let ddragon_version = ddragon::latest_version().await?;
+        (
+            fetch_summoner_profile(riot_api, regional_route, platform_route, &puuid1, &ddragon_version, &mut api_calls).await?,
+            fetch_summoner_profile(riot_api, regional_route, platform_route, &puuid2, &ddragon_version, &mut api_calls).await?,
+        )
+    } else {
+        (None, None)
+    };
+
     if verbose {
-        println!("Fetching match IDs for Player 1 (last {} matches, roughly last 30 days if available)...", number_of_matches.unwrap_or(100));
+        println!("Fetching match IDs for Player 1 (last {} matches, roughly last 30 days if available)...", number_of_matches.unwrap_or(DEFAULT_MATCH_COUNT));
     }
-    let one_month_ago = SystemTime::now()
-        .checked_sub(std::time::Duration::from_secs(30 * 24 * 60 * 60))
-        .map(|t| t.duration_since(UNIX_EPOCH).unwrap().as_secs() as i64);
+    let (mut start_time, end_time) = match &date_range {
+        Some(range) => (Some(range.start_ms / 1000), Some(range.end_ms / 1000)),
+        None => (days_ago(DEFAULT_WINDOW_DAYS), None),
+    };
 
-    let match_ids = riot_api
-        .match_v5()
-        .get_match_ids_by_puuid(
-            regional_route,
-            &puuid1,
-            number_of_matches,
-            None,
-            None,
-            one_month_ago,
-            None,
+    let mut match_ids = list_match_ids(
+        riot_api,
+        match_index,
+        regional_route,
+        &puuid1,
+        number_of_matches.unwrap_or(DEFAULT_MATCH_COUNT),
+        start_time,
+        end_time,
+        match_type,
+        queue_id,
+        verbose,
+        &mut api_calls,
+    ).await?;
+
+    let mut warnings: Vec<Warning> = Vec::new();
+    if let Some(selected_region) = &user_selected_region
+        && let Some(new_region) = detect_region_transfer(riot_api, regional_route, selected_region, &puuid1, &match_ids, &mut api_calls).await?
+    {
+        // Printed even without --verbose, since it likely explains an empty result.
+        push_warning(&mut warnings, true, Warning::new(
+            WarningCode::RegionTransfer,
             None,
-        )
-        .await?;
+            format!(
+                "Warning: {} plays on {} now, not {}; the account was likely transferred.",
+                anonymize::riot_id(&player1_riot_id),
+                new_region.to_log_string().to_uppercase(),
+                selected_region.to_log_string().to_uppercase()
+            ),
+        ));
+        if follow_region_transfer {
+            eprintln!("Scanning {} instead.", new_region.to_log_string().to_uppercase());
+            regional_route = new_region.to_regional_route();
+            user_selected_region = Some(new_region);
+            match_ids = list_match_ids(
+                riot_api,
+                match_index,
+                regional_route,
+                &puuid1,
+                number_of_matches.unwrap_or(DEFAULT_MATCH_COUNT),
+                start_time,
+                end_time,
+                match_type,
+                queue_id,
+                verbose,
+                &mut api_calls,
+            ).await?;
+        } else {
+            eprintln!(
+                "Pass --region {} or --follow-transfer to scan the new region.",
+                new_region.to_log_string().to_uppercase()
+            );
+        }
+    }
+
+    let mut widened_to_days = None;
+    if match_ids.is_empty() {
+        // Both warnings are printed even without --verbose, since they are the whole answer.
+        if let Some(warning) = no_league_matches_warning(riot_api, regional_route, &player1_riot_id, &puuid1, &mut api_calls).await {
+            push_warning(&mut warnings, true, warning);
+        } else if date_range.is_none() {
+            let mut days = DEFAULT_WINDOW_DAYS;
+            while let Some(max_days) = auto_widen_max_days
+                && match_ids.is_empty()
+                && days < max_days
+            {
+                days = (days * 2).min(max_days);
+                if verbose {
+                    println!("No matches found; widening the window to the last {} days...", days);
+                }
+                start_time = days_ago(days);
+                match_ids = list_match_ids(
+                    riot_api,
+                    match_index,
+                    regional_route,
+                    &puuid1,
+                    number_of_matches.unwrap_or(DEFAULT_MATCH_COUNT),
+                    start_time,
+                    end_time,
+                    match_type,
+                    queue_id,
+                    verbose,
+                    &mut api_calls,
+                ).await?;
+            }
+            let riot_id = anonymize::riot_id(&player1_riot_id);
+            if !match_ids.is_empty() {
+                widened_to_days = Some(days);
+                push_warning(&mut warnings, true, Warning::new(
+                    WarningCode::WindowWidened,
+                    None,
+                    format!("{} has no matches in the last {} days; widened the window to the last {} days.", riot_id, DEFAULT_WINDOW_DAYS, days),
+                ));
+            } else if auto_widen_max_days.is_some() {
+                push_warning(&mut warnings, true, Warning::new(
+                    WarningCode::NoMatchesInWindow,
+                    None,
+                    format!("Warning: {} has no matches in the last {} days either.", riot_id, days),
+                ));
+            } else {
+                push_warning(&mut warnings, true, Warning::new(
+                    WarningCode::NoMatchesInWindow,
+                    None,
+                    format!("Warning: {} has no matches in the last {} days. Pass --auto-widen to look further back.", riot_id, DEFAULT_WINDOW_DAYS),
+                ));
+            }
+        }
+    }
 
     if verbose {
         println!("Found {} recent matches for Player 1.", match_ids.len());
@@ -163,44 +1683,140 @@ pub async fn run_query(
     let mut player1_games_won_count = 0;
     let mut checked_matches_count = 0;
     let mut found_matches_details: Vec<MatchDetails> = Vec::new();
+    let mut ranked_summary = RankedSummary::default();
+    let mut per_queue = QueueBreakdown::default();
+    let mut unavailable_matches = UnavailableMatches::default();
+    let mut previous_game_start: Option<i64> = None;
+    let mut outline_only_matches_count = 0;
+    let mut likely_afk_matches_count = 0;
 
     let total_match_ids = match_ids.len();
 
+    let match_ids_in_window = match_ids.clone();
     for match_id_str in match_ids {
+        if stop_at_first_shared && found_together_count > 0 {
+            break;
+        }
         checked_matches_count += 1;
         if verbose {
             // Updated to use match_ids.len() for total count
-            println!("Checking match {} ({} of {})...", match_id_str, checked_matches_count, total_match_ids);
+            println!("Checking match {} ({} of {})...", anonymize::match_id(&match_id_str), checked_matches_count, total_match_ids);
         }
 
-        let match_data_option = riot_api
-            .match_v5()
-            .get_match(regional_route, &match_id_str)
-            .await?;
+        // Indexed matches without player2 are skipped before their payload is loaded and deserialized,
+        // unless the Venn counts need them.
+        if venn.is_none() && match_index.contains_match(&match_id_str)? && !match_index.has_participant(&match_id_str, &puuid2)? {
+            api_calls.cache_hits += 1;
+            previous_game_start = match_index.game_start_of(&match_id_str)?;
+            continue;
+        }
+        let match_data_option = match fetch_match(riot_api, match_index, regional_route, &match_id_str, &mut api_calls).await? {
+            MatchFetch::Found(match_data) => Some(match_data),
+            MatchFetch::Outline(outline) => {
+                previous_game_start = Some(outline.game_start_timestamp);
+                let passes_filters = passes_match_filters(
+                    &outline.game_version,
+                    outline.queue_id,
+                    outline.game_start_timestamp,
+                    patch.as_deref(),
+                    queue_id,
+                    date_range,
+                );
+                if let (Some(venn), Some(puuid3)) = (&mut venn, &puuid3)
+                    && passes_filters
+                {
+                    let player1_team = outline.participant(&puuid1).map(|p| p.team_id);
+                    let counted = |puuid: &str| {
+                        outline
+                            .participant(puuid)
+                            .is_some_and(|p| !opponents_only || player1_team != Some(p.team_id))
+                    };
+                    venn.record(counted(&puuid2), counted(puuid3));
+                }
+                let (Some(p1_data), Some(p2_data)) = (outline.participant(&puuid1), outline.participant(&puuid2)) else {
+                    continue;
+                };
+                if !passes_filters || (opponents_only && p1_data.team_id == p2_data.team_id) {
+                    continue;
+                }
+                push_warning(&mut warnings, verbose, Warning::new(
+                    WarningCode::OutlineOnly,
+                    Some(&match_id_str),
+                    format!("Match {} is in a game mode ptg cannot fully read yet; only its result is counted.", match_id_str),
+                ));
+                found_together_count += 1;
+                outline_only_matches_count += 1;
+                if p1_data.win {
+                    player1_games_won_count += 1;
+                }
+                None
+            }
+            MatchFetch::Forbidden => {
+                unavailable_matches.forbidden += 1;
+                push_warning(&mut warnings, verbose, Warning::new(
+                    WarningCode::MatchForbidden,
+                    Some(&match_id_str),
+                    format!("Match {} is not accessible with this API key. Skipping.", match_id_str),
+                ));
+                None
+            }
+            MatchFetch::Missing => {
+                // The list is newest first, so a match after one past the retention is past it too.
+                let now_ms = Utc::now().timestamp_millis();
+                if previous_game_start.is_some_and(|game_start| now_ms - game_start > MATCH_RETENTION_MS) {
+                    unavailable_matches.expired += 1;
+                    push_warning(&mut warnings, verbose, Warning::new(
+                        WarningCode::MatchExpired,
+                        Some(&match_id_str),
+                        format!("Match {} is older than Riot's match retention. Skipping.", match_id_str),
+                    ));
+                } else {
+                    unavailable_matches.missing += 1;
+                    push_warning(&mut warnings, verbose, Warning::new(
+                        WarningCode::MatchMissing,
+                        Some(&match_id_str),
+                        format!("Match {} returned no data. Skipping.", match_id_str),
+                    ));
+                }
+                None
+            }
+        };
 
         if let Some(match_data) = match_data_option {
             let info = match_data.info;
+            previous_game_start = Some(info.game_start_timestamp);
             let participants_puuids: HashSet<&str> =
                 info.participants.iter().map(|p| p.puuid.as_str()).collect();
+            if let (Some(venn), Some(puuid3)) = (&mut venn, &puuid3)
+                && passes_match_filters(&info.game_version, info.queue_id.0, info.game_start_timestamp, patch.as_deref(), queue_id, date_range)
+            {
+                let player1_participant = info.participants.iter().find(|p| p.puuid == puuid1);
+                let counted = |puuid: &str| {
+                    info.participants.iter().find(|p| p.puuid == puuid).is_some_and(|p| {
+                        !opponents_only || player1_participant.is_none_or(|p1| !on_same_side(&info, p1, p))
+                    })
+                };
+                venn.record(counted(&puuid2), counted(puuid3));
+            }
 
             if participants_puuids.contains(&puuid2.as_str()) {
-                found_together_count += 1;
-
-                let game_start_datetime =
-                    Utc.timestamp_millis_opt(info.game_start_timestamp)
-                       .single()
-                       .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
-                       .unwrap_or_else(|| "Unknown Date".to_string());
-
-                let mut league_of_graphs_link = None; 
-                if let Some((_region_id, stripped_match_id)) = match_id_str.split_once('_') {
-                    let log_region = user_selected_region
-                        .as_ref()
-                        .map(|r| r.to_log_string())
-                        .unwrap_or("eune");
-                    league_of_graphs_link = Some(format!("https://www.leagueofgraphs.com/match/{}/{}", log_region, stripped_match_id));
+                if let Some(patch) = &patch
+                    && &patch_of(&info.game_version) != patch
+                {
+                    if verbose {
+                        println!("Skipping match {} from patch {}.", anonymize::match_id(&match_id_str), patch_of(&info.game_version));
+                    }
+                    continue;
+                }
+                if let Some(range) = &date_range
+                    && !range.contains(info.game_start_timestamp)
+                {
+                    continue;
+                }
+                // The API filters by queue already; matches from the local index were not.
+                if queue_id.is_some_and(|queue_id| info.queue_id.0 != queue_id) {
+                    continue;
                 }
-                
                 let player1_participant = info
                     .participants
                     .iter()
@@ -212,117 +1828,176 @@ pub async fn run_query(
                     .find(|p| p.puuid == puuid2);
 
                 if let (Some(p1_data), Some(p2_data)) = (player1_participant, player2_participant) {
+                    if opponents_only && on_same_side(&info, p1_data, p2_data) {
+                        if verbose {
+                            println!("Skipping match {} where both players were on the same team.", anonymize::match_id(&match_id_str));
+                        }
+                        continue;
+                    }
+                    if likely_afk(p1_data, &info) || likely_afk(p2_data, &info) {
+                        likely_afk_matches_count += 1;
+                        if !include_afk {
+                            if verbose {
+                                println!("Leaving out match {} where one of the players likely went AFK.", anonymize::match_id(&match_id_str));
+                            }
+                            continue;
+                        }
+                    }
+                    found_together_count += 1;
                     if p1_data.win {
                         player1_games_won_count += 1;
                     }
+                    ranked_summary.record(info.queue_id, p1_data.win);
+                    per_queue.record(info.queue_id, p1_data.win);
 
-                    let p1_outcome = if p1_data.win { "Victory" } else { "Defeat" }.to_string();
-                    let p2_outcome = if p2_data.win { "Victory" } else { "Defeat" }.to_string();
-
-                    // Create MatchDetails struct
-                    let current_match_details = MatchDetails {
-                        match_id: match_id_str.clone(),
-                        game_date_utc: game_start_datetime,
-                        game_mode: format!("{:?}", info.game_mode),
-                        game_type: info.game_type.map(|gt| format!("{:?}", gt)),
-                        league_of_graphs_link,
-                        player1_details: ParticipantDetails {
-                            champion: p1_data.champion_name.clone(),
-                            role: p1_data.team_position.to_string(),
-                            kills: p1_data.kills,
-                            deaths: p1_data.deaths,
-                            assists: p1_data.assists,
-                            outcome: p1_outcome,
-                        },
-                        player2_details: ParticipantDetails {
-                            champion: p2_data.champion_name.clone(),
-                            role: p2_data.team_position.to_string(),
-                            kills: p2_data.kills,
-                            deaths: p2_data.deaths,
-                            assists: p2_data.assists,
-                            outcome: p2_outcome,
-                        },
+                    let lane_opponents = p1_data.team_id != p2_data.team_id
+                        && !p1_data.team_position.is_empty()
+                        && p1_data.team_position == p2_data.team_position;
+                    let timeline = if fetch_timelines || (opponents_only && lane_opponents) {
+                        // Timelines are not indexed.
+                        offline::ensure_online(&format!("the timeline of match {}", match_id_str))?;
+                        api_calls.timeline_calls += 1;
+                        riot_api
+                            .client().await
+                            .match_v5()
+                            .get_timeline(regional_route, &match_id_str)
+                            .await?
+                    } else {
+                        None
                     };
-
-                    if verbose && !json_output_enabled {
-                        let mut lines_of_text: Vec<String> = Vec::new();
-                        lines_of_text.push(format!(
-                            "Players {}#{} and {}#{} played together in Match ID: {}",
-                            player1_game_name, player1_tag_line,
-                            player2_game_name, player2_tag_line,
-                            current_match_details.match_id
-                        ));
-                        lines_of_text.push(format!("Date: {}", current_match_details.game_date_utc));
-                        lines_of_text.push(format!(
-                            "Game Mode: {}, Game Type: {}",
-                            current_match_details.game_mode,
-                            current_match_details.game_type.as_deref().unwrap_or("N/A")
-                        ));
-
-                        if let Some(link) = &current_match_details.league_of_graphs_link {
-                            lines_of_text.push(link.clone());
-                        }
-
-                        lines_of_text.push("--- Participant Details ---".to_string());
-                        lines_of_text.push(format!("{}:", player1_game_name));
-                        lines_of_text.push(format!("  Champion: {}", current_match_details.player1_details.champion));
-                        lines_of_text.push(format!("  Role: {}", current_match_details.player1_details.role));
-                        lines_of_text.push(format!(
-                            "  KDA: {}/{}/{}",
-                            current_match_details.player1_details.kills, current_match_details.player1_details.deaths, current_match_details.player1_details.assists
-                        ));
-
-                        lines_of_text.push(format!("{}:", player2_game_name));
-                        lines_of_text.push(format!("  Champion: {}", current_match_details.player2_details.champion));
-                        lines_of_text.push(format!("  Role: {}", current_match_details.player2_details.role));
-                        lines_of_text.push(format!(
-                            "  KDA: {}/{}/{}",
-                            current_match_details.player2_details.kills, current_match_details.player2_details.deaths, current_match_details.player2_details.assists
-                        ));
-
-                        lines_of_text.push("--- Match Outcome ---".to_string());
-                        let outcome_text = if current_match_details.player1_details.outcome == "Victory" {
-                            "Victory".green().to_string()
-                        } else {
-                            "Defeat".red().to_string()
-                        };
-                        lines_of_text.push(format!("  Outcome: {}", outcome_text));
-
-                        print_in_box(
-                            &lines_of_text
-                                .iter()
-                                .map(String::as_str)
-                                .collect::<Vec<&str>>(),
-                        );
-                        println!(); // Add a newline after each box for spacing
+                    let gold_diff_at15 = timeline
+                        .as_ref()
+                        .and_then(|timeline| team_gold_diff_at(timeline, &info.participants, p1_data.team_id, 15));
+                    let lane_matchup = timeline
+                        .as_ref()
+                        .filter(|_| lane_opponents)
+                        .map(|timeline| lane_matchup(timeline, p1_data.participant_id, p2_data.participant_id));
+                    let log_region = user_selected_region
+                        .as_ref()
+                        .map(|r| r.to_log_string())
+                        .unwrap_or("eune");
+                    let mut current_match_details = build_match_details(
+                        &match_id_str,
+                        &info,
+                        p1_data,
+                        p2_data,
+                        log_region,
+                        gold_diff_at15,
+                        lane_matchup,
+                        data_dragon.as_ref(),
+                    );
+                    if current_match_details.same_team {
+                        current_match_details.kill_participation = timeline
+                            .as_ref()
+                            .map(|timeline| pair_kill_participation(timeline, p1_data.participant_id, p2_data.participant_id));
+                    }
+                    current_match_details.gold_graph = timeline.as_ref().and_then(|timeline| {
+                        gold_graph(timeline, &info.participants, p1_data.team_id, p1_data.participant_id, p2_data.participant_id)
+                    });
+                    if let Some(timeline) = &timeline {
+                        current_match_details.player1_details.lane_gold_diff_at10 = lane_gold_diff_at(timeline, &info.participants, p1_data, 10);
+                        current_match_details.player2_details.lane_gold_diff_at10 = lane_gold_diff_at(timeline, &info.participants, p2_data, 10);
                     }
 
+                    // Attached now already, so the match box shows them.
+                    current_match_details.note = note_book.get(&match_id_str).cloned();
+                    if verbose && !quiet {
+                        print_match_box(&current_match_details, &player1_riot_id, &player2_riot_id);
+                    }
 
                     found_matches_details.push(current_match_details); // Still collect for JSON output
                 } else {
-                    if verbose {
-                        // Changed from `eprintln!("Warning: Participant data incomplete for match ID '{}'. Skipping this match.", match_id_str);`
-                        // to the current message for clarity based on original output.
-                        eprintln!("Warning: Could not find participant data for player 1 or player 2 in match '{}'. Skipping this match.", match_id_str);
-                    }
+                    push_warning(&mut warnings, verbose, Warning::new(
+                        WarningCode::MissingParticipant,
+                        Some(&match_id_str),
+                        format!("Warning: Could not find participant data for player 1 or player 2 in match '{}'. Skipping this match.", match_id_str),
+                    ));
                     continue;
                 }
             }
-        } else {
-            if verbose {
-                eprintln!("Warning: Match {} not found or accessible. Skipping.", match_id_str);
+        }
+    }
+
+    if let (Some(venn), Some(puuid3)) = (&mut venn, &puuid3) {
+        let since = previous_game_start.unwrap_or(0);
+        for match_id in match_index.shared_match_ids_since(&puuid2, puuid3, since)? {
+            if match_index.has_participant(&match_id, &puuid1)? {
+                continue;
+            }
+            let Some(match_data) = match_index.get_match(&match_id)? else {
+                continue;
+            };
+            let info = &match_data.info;
+            if !passes_match_filters(&info.game_version, info.queue_id.0, info.game_start_timestamp, patch.as_deref(), queue_id, date_range) {
+                continue;
+            }
+            let participant = |puuid: &str| info.participants.iter().find(|p| p.puuid == puuid);
+            if let (Some(p2_data), Some(p3_data)) = (participant(&puuid2), participant(puuid3))
+                && !(opponents_only && on_same_side(info, p2_data, p3_data))
+            {
+                venn.player2_and_player3_only += 1;
             }
         }
     }
 
+    // A scan stopped at the first shared match left the rest of the list unindexed.
+    let scanned_whole_list = checked_matches_count == total_match_ids;
+    if match_type.is_none() && queue_id.is_none() && date_range.is_none() && scanned_whole_list {
+        match_index.record_match_list(
+            &puuid1,
+            &match_ids_in_window,
+            number_of_matches.unwrap_or(DEFAULT_MATCH_COUNT) as usize,
+            start_time.unwrap_or(0) * 1000,
+        )?;
+    }
+
+    let solo_baseline = if solo_baseline {
+        if verbose {
+            println!("Fetching match IDs for Player 2 for the solo baseline...");
+        }
+        let player2_match_ids = list_match_ids(
+            riot_api,
+            match_index,
+            regional_route,
+            &puuid2,
+            number_of_matches.unwrap_or(DEFAULT_MATCH_COUNT),
+            start_time,
+            end_time,
+            match_type,
+            queue_id,
+            verbose,
+            &mut api_calls,
+        ).await?;
+        let shared_match_ids: Vec<String> = found_matches_details.iter().map(|m| m.match_id.clone()).collect();
+        let shared = load_matches(riot_api, match_index, regional_route, &shared_match_ids, &mut api_calls).await?;
+        let player1_games = load_matches(riot_api, match_index, regional_route, &match_ids_in_window, &mut api_calls).await?;
+        let player2_games = load_matches(riot_api, match_index, regional_route, &player2_match_ids, &mut api_calls).await?;
+        Some(SoloBaseline::from_matches(
+            (&puuid1, &player1_games),
+            (&puuid2, &player2_games),
+            &shared,
+            patch.as_deref(),
+        ))
+    } else {
+        None
+    };
+
+    api_calls.finish(started_at);
+
     let query_summary = QuerySummary {
         player1: PlayerIdentity {
             game_name: player1_game_name,
             tag_line: player1_tag_line,
+            profile: player1_profile,
+            formerly_known_as: Vec::new(),
+            nickname: nickname_of(&player1_riot_id),
         },
         player2: PlayerIdentity {
             game_name: player2_game_name,
             tag_line: player2_tag_line,
+            profile: player2_profile,
+            formerly_known_as: Vec::new(),
+            nickname: nickname_of(&player2_riot_id),
         },
         regional_route: format!("{:?}", regional_route),
         checked_matches_count: checked_matches_count as u32,
@@ -330,10 +2005,26 @@ pub async fn run_query(
         player1_wins_together_count: player1_games_won_count as u32,
         player1_puuid_found,
         player2_puuid_found,
+        api_calls,
+        ranked: ranked_summary,
+        carries: CarryTally::from_matches(&found_matches_details),
+        per_queue,
+        unavailable_matches,
+        outline_only_matches_count,
+        likely_afk_matches_count,
+        venn,
+        widened_to_days,
     };
 
+    let mut stats = MatchStats::from_matches(&found_matches_details);
+    stats.solo_baseline = solo_baseline;
     Ok(OverallOutput {
         query_summary,
+        stats,
+        duo_timeline: DuoTimeline::from_matches(&found_matches_details),
+        sessions: detect_sessions(&found_matches_details),
+        achievements: find_achievements(&found_matches_details),
         found_matches: found_matches_details,
+        warnings,
     })
 }
\ No newline at end of file
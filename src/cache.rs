@@ -0,0 +1,111 @@
+use chrono::Utc;
+use riven::consts::RegionalRoute;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config::Config;
+use crate::riot_id::RiotId;
+
+const ACCOUNT_CACHE_FILE_NAME: &str = "accounts.json";
+
+/// A resolved account, remembered so later runs can skip the account_v1 lookup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedAccount {
+    pub puuid: String,
+    pub game_name: String,
+    pub tag_line: String,
+    /// Regional route found by auto-detection (e.g. "EUROPE"), if it ever ran for this account.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regional_route: Option<String>,
+    /// Unix timestamp (seconds) of when the entry was written.
+    pub cached_at: i64,
+}
+
+impl CachedAccount {
+    pub fn regional_route(&self) -> Option<RegionalRoute> {
+        self.regional_route.as_deref().and_then(|route| route.parse().ok())
+    }
+}
+
+/// Riot ID to PUUID cache, keyed by the lowercased `GameName#TagLine`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AccountCache {
+    accounts: HashMap<String, CachedAccount>,
+}
+
+impl AccountCache {
+    fn cache_file_path() -> Result<PathBuf, Box<dyn Error>> {
+        Ok(Config::cache_dir()?.join(ACCOUNT_CACHE_FILE_NAME))
+    }
+
+    fn key(riot_id: &RiotId) -> String {
+        riot_id.to_string().to_lowercase()
+    }
+
+    /// Loads the cache, starting over with an empty one if the file is missing or unreadable.
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        let path = Self::cache_file_path()?;
+        if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            Ok(serde_json::from_str(&content).unwrap_or_default())
+        } else {
+            Ok(AccountCache::default())
+        }
+    }
+
+    /// Parses the cache file strictly, returning the number of entries, for diagnostics.
+    pub fn verify() -> Result<usize, Box<dyn Error>> {
+        let path = Self::cache_file_path()?;
+        if !path.exists() {
+            return Ok(0);
+        }
+        let cache: AccountCache = serde_json::from_str(&fs::read_to_string(&path)?)?;
+        Ok(cache.accounts.len())
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = Self::cache_file_path()?;
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, riot_id: &RiotId) -> Option<&CachedAccount> {
+        self.accounts.get(&Self::key(riot_id))
+    }
+
+    /// Reverse lookup of the current Riot ID of a PUUID, as far as the cache knows.
+    pub fn find_by_puuid(&self, puuid: &str) -> Option<&CachedAccount> {
+        self.accounts.values().find(|account| account.puuid == puuid)
+    }
+
+    /// Stores the PUUID of a Riot ID, keeping a previously detected route unless a new one is given.
+    /// An account has one Riot ID at a time, so entries of earlier Riot IDs of the PUUID are dropped.
+    pub fn insert(&mut self, riot_id: &RiotId, puuid: &str, regional_route: Option<RegionalRoute>) {
+        let key = Self::key(riot_id);
+        self.accounts.retain(|other_key, account| account.puuid != puuid || *other_key == key);
+        let regional_route = regional_route
+            .map(|route| route.to_string())
+            .or_else(|| self.accounts.get(&key).and_then(|a| a.regional_route.clone()));
+        self.accounts.insert(
+            key,
+            CachedAccount {
+                puuid: puuid.to_string(),
+                game_name: riot_id.game_name.clone(),
+                tag_line: riot_id.tag_line.clone(),
+                regional_route,
+                cached_at: Utc::now().timestamp(),
+            },
+        );
+    }
+
+    /// Drops every entry written before `cutoff` (Unix seconds) and returns how many were dropped.
+    pub fn prune_older_than(&mut self, cutoff: i64) -> usize {
+        let before = self.accounts.len();
+        self.accounts.retain(|_, account| account.cached_at >= cutoff);
+        before - self.accounts.len()
+    }
+}
@@ -0,0 +1,190 @@
+use riven::consts::{Division, PlatformRoute, QueueType, RegionalRoute, Tier};
+use serde::Serialize;
+use std::error::Error;
+use std::time::Instant;
+
+use crate::anonymize;
+use crate::api_client::{active_platform, detect_regional_route, resolve_puuid, ApiCallStats, PlayerIdentity, RiotApiPool};
+use crate::cache::AccountCache;
+use crate::contacts::nickname_of;
+use crate::offline;
+use crate::riot_id::RiotId;
+
+/// Tiers with four divisions, lowest first. Apex tiers (Master and above) cannot duo in Solo/Duo.
+const DIVISION_TIERS: [Tier; 7] = [
+    Tier::IRON,
+    Tier::BRONZE,
+    Tier::SILVER,
+    Tier::GOLD,
+    Tier::PLATINUM,
+    Tier::EMERALD,
+    Tier::DIAMOND,
+];
+/// From Emerald up, partners may be at most this many divisions apart.
+const HIGH_ELO_MAX_DIVISIONS_APART: u32 = 2;
+
+pub struct CanDuoOptions {
+    pub player1_riot_id: RiotId,
+    pub player2_riot_id: RiotId,
+    /// `None` auto-detects the route from player1.
+    pub regional_route: Option<RegionalRoute>,
+    /// `None` looks up player1's active shard.
+    pub platform_route: Option<PlatformRoute>,
+    pub verbose: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SoloRank {
+    pub player: PlayerIdentity,
+    /// `None` while unranked in Solo/Duo this split.
+    pub tier: Option<Tier>,
+    pub division: Option<Division>,
+    pub league_points: Option<i32>,
+}
+
+impl SoloRank {
+    fn label(&self) -> String {
+        match (self.tier, self.division) {
+            (Some(tier), _) if tier.is_apex() => format!("{:?} {} LP", tier, self.league_points.unwrap_or(0)),
+            (Some(tier), Some(division)) => format!("{:?} {:?} {} LP", tier, division, self.league_points.unwrap_or(0)),
+            _ => "Unranked".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DuoEligibility {
+    Allowed,
+    NotAllowed,
+    /// Unranked players are matched by hidden MMR, so their restriction cannot be checked.
+    Unknown,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CanDuoOutput {
+    pub player1: SoloRank,
+    pub player2: SoloRank,
+    /// Divisions between the two ranks; `None` if either player is unranked or in an apex tier.
+    pub divisions_apart: Option<u32>,
+    pub eligibility: DuoEligibility,
+    pub reason: String,
+    pub api_calls: ApiCallStats,
+}
+
+/// Position of a rank on the ladder in divisions, from Iron IV (0) to Diamond I (27).
+fn division_index(tier: Tier, division: Division) -> Option<u32> {
+    let tier_index = DIVISION_TIERS.iter().position(|t| *t == tier)? as u32;
+    let division_offset = match division {
+        Division::IV => 0,
+        Division::III => 1,
+        Division::II => 2,
+        Division::I => 3,
+        #[allow(deprecated)]
+        Division::V => return None,
+    };
+    Some(tier_index * 4 + division_offset)
+}
+
+/// Applies Riot's Solo/Duo restrictions, as published at the time of writing: Iron to Platinum may duo
+/// within one tier, Emerald and Diamond within two divisions, Master and above not at all. Each player's
+/// own restriction has to allow the partner.
+pub fn duo_eligibility(player1: &SoloRank, player2: &SoloRank) -> (DuoEligibility, Option<u32>, String) {
+    let (Some(tier1), Some(tier2)) = (player1.tier, player2.tier) else {
+        return (DuoEligibility::Unknown, None, "Unranked players are restricted by hidden MMR, which the API does not expose.".to_string());
+    };
+    if tier1.is_apex() || tier2.is_apex() {
+        return (DuoEligibility::NotAllowed, None, "Master and above cannot duo in Solo/Duo.".to_string());
+    }
+    let index_of = |tier, division: Option<Division>| division.and_then(|division| division_index(tier, division));
+    let (Some(index1), Some(index2)) = (index_of(tier1, player1.division), index_of(tier2, player2.division)) else {
+        return (DuoEligibility::Unknown, None, "A rank could not be read.".to_string());
+    };
+    let divisions_apart = index1.abs_diff(index2);
+    let tiers_apart = (index1 / 4).abs_diff(index2 / 4);
+    let high_elo = |tier| matches!(tier, Tier::EMERALD | Tier::DIAMOND);
+    let (eligibility, reason) = if (high_elo(tier1) || high_elo(tier2)) && divisions_apart > HIGH_ELO_MAX_DIVISIONS_APART {
+        (DuoEligibility::NotAllowed, format!("From Emerald up, partners must be within {} divisions.", HIGH_ELO_MAX_DIVISIONS_APART))
+    } else if tiers_apart > 1 {
+        (DuoEligibility::NotAllowed, "Partners must be at most one tier apart.".to_string())
+    } else {
+        (DuoEligibility::Allowed, "The ranks are close enough to duo.".to_string())
+    };
+    (eligibility, Some(divisions_apart), reason)
+}
+
+async fn fetch_solo_rank(
+    riot_api: &RiotApiPool,
+    platform_route: PlatformRoute,
+    riot_id: &RiotId,
+    puuid: &str,
+    api_calls: &mut ApiCallStats,
+) -> Result<SoloRank, Box<dyn Error>> {
+    offline::ensure_online(&format!("the solo queue rank of {}", anonymize::riot_id(riot_id)))?;
+    api_calls.league_calls += 1;
+    let entries = riot_api.client().await.league_v4().get_league_entries_by_puuid(platform_route, puuid).await?;
+    let solo_entry = entries.into_iter().find(|entry| entry.queue_type == QueueType::RANKED_SOLO_5x5);
+    Ok(SoloRank {
+        player: PlayerIdentity {
+            game_name: riot_id.game_name.clone(),
+            tag_line: riot_id.tag_line.clone(),
+            profile: None,
+            formerly_known_as: Vec::new(),
+            nickname: nickname_of(riot_id),
+        },
+        tier: solo_entry.as_ref().and_then(|entry| entry.tier).filter(|tier| *tier != Tier::UNRANKED),
+        division: solo_entry.as_ref().and_then(|entry| entry.rank),
+        league_points: solo_entry.map(|entry| entry.league_points),
+    })
+}
+
+/// Fetches both players' Solo/Duo ranks and checks them against the duo restrictions.
+pub async fn run_can_duo(
+    riot_api: &RiotApiPool,
+    account_cache: &mut AccountCache,
+    options: CanDuoOptions,
+) -> Result<CanDuoOutput, Box<dyn Error>> {
+    let CanDuoOptions { player1_riot_id, player2_riot_id, regional_route, platform_route, verbose } = options;
+    let started_at = Instant::now();
+    let mut api_calls = ApiCallStats::default();
+
+    let regional_route = match regional_route {
+        Some(route) => route,
+        None => detect_regional_route(riot_api, account_cache, &player1_riot_id, verbose, &mut api_calls).await?,
+    };
+    let puuid1 = resolve_puuid(riot_api, account_cache, &player1_riot_id, regional_route, &mut api_calls)
+        .await?
+        .ok_or_else(|| format!("Riot ID '{}' not found on regional route '{:?}'.", anonymize::riot_id(&player1_riot_id), regional_route))?;
+    let puuid2 = resolve_puuid(riot_api, account_cache, &player2_riot_id, regional_route, &mut api_calls)
+        .await?
+        .ok_or_else(|| format!("Riot ID '{}' not found on regional route '{:?}'.", anonymize::riot_id(&player2_riot_id), regional_route))?;
+    let platform_route = match platform_route {
+        Some(route) => route,
+        None => active_platform(riot_api, regional_route, &puuid1, &mut api_calls).await?,
+    };
+
+    let player1 = fetch_solo_rank(riot_api, platform_route, &player1_riot_id, &puuid1, &mut api_calls).await?;
+    let player2 = fetch_solo_rank(riot_api, platform_route, &player2_riot_id, &puuid2, &mut api_calls).await?;
+    let (eligibility, divisions_apart, reason) = duo_eligibility(&player1, &player2);
+    api_calls.finish(started_at);
+    Ok(CanDuoOutput { player1, player2, divisions_apart, eligibility, reason, api_calls })
+}
+
+impl CanDuoOutput {
+    pub fn print(&self) {
+        for player in [&self.player1, &self.player2] {
+            println!("{}: {}", player.player.display_name(), player.label());
+        }
+        if let Some(divisions_apart) = self.divisions_apart {
+            println!("{} divisions apart.", divisions_apart);
+        }
+        let verdict = match self.eligibility {
+            DuoEligibility::Allowed => "Can duo in Solo/Duo.",
+            DuoEligibility::NotAllowed => "Cannot duo in Solo/Duo.",
+            DuoEligibility::Unknown => "Cannot tell whether they can duo in Solo/Duo.",
+        };
+        println!("{} {}", verdict, self.reason);
+    }
+}
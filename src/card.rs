@@ -0,0 +1,203 @@
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::Write;
+
+use crate::api_client::OverallOutput;
+use crate::stats::win_rate;
+
+const CARD_WIDTH: usize = 600;
+const CARD_HEIGHT: usize = 315;
+const MARGIN: usize = 30;
+
+const BACKGROUND: Rgb = [30, 35, 40];
+const GOLD: Rgb = [200, 155, 60];
+const WHITE: Rgb = [240, 230, 210];
+const GREEN: Rgb = [46, 158, 68];
+const RED: Rgb = [209, 54, 57];
+
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+
+type Rgb = [u8; 3];
+
+/// A 5x7 bitmap font, one row per byte with the leftmost pixel in bit 4. Lowercase letters are
+/// drawn as capitals and characters without a glyph as `?`.
+fn glyph(character: char) -> [u8; GLYPH_HEIGHT] {
+    match character.to_ascii_uppercase() {
+        'A' => [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'B' => [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E],
+        'C' => [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E],
+        'D' => [0x1E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x1E],
+        'E' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F],
+        'F' => [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10],
+        'G' => [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F],
+        'H' => [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11],
+        'I' => [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        'J' => [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C],
+        'K' => [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11],
+        'L' => [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F],
+        'M' => [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11],
+        'N' => [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11],
+        'O' => [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'P' => [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10],
+        'Q' => [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D],
+        'R' => [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11],
+        'S' => [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E],
+        'T' => [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04],
+        'U' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E],
+        'V' => [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04],
+        'W' => [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A],
+        'X' => [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11],
+        'Y' => [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04],
+        'Z' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F],
+        '0' => [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E],
+        '1' => [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E],
+        '2' => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F],
+        '3' => [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E],
+        '4' => [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02],
+        '5' => [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E],
+        '6' => [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E],
+        '7' => [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08],
+        '8' => [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E],
+        '9' => [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C],
+        ' ' => [0x00; GLYPH_HEIGHT],
+        '#' => [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A],
+        '%' => [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03],
+        ':' => [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00],
+        '.' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C],
+        ',' => [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08],
+        '-' => [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00],
+        '_' => [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F],
+        '&' => [0x0C, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0D],
+        '+' => [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00],
+        '/' => [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00],
+        '(' => [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02],
+        ')' => [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08],
+        '!' => [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04],
+        '\'' => [0x0C, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00],
+        _ => [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04],
+    }
+}
+
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<Rgb>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize, background: Rgb) -> Self {
+        Canvas { width, height, pixels: vec![background; width * height] }
+    }
+
+    fn fill_rect(&mut self, x: usize, y: usize, width: usize, height: usize, color: Rgb) {
+        for row in y..(y + height).min(self.height) {
+            for column in x..(x + width).min(self.width) {
+                self.pixels[row * self.width + column] = color;
+            }
+        }
+    }
+
+    /// Draws `text` with its top-left corner at (`x`, `y`), each font pixel `scale` pixels wide,
+    /// cut off with `...` where it would pass `max_width`.
+    fn draw_text(&mut self, x: usize, y: usize, text: &str, scale: usize, color: Rgb, max_width: usize) {
+        let advance = (GLYPH_WIDTH + 1) * scale;
+        let max_chars = max_width / advance;
+        let mut characters: Vec<char> = text.chars().collect();
+        if characters.len() > max_chars {
+            characters.truncate(max_chars.saturating_sub(3));
+            characters.extend("...".chars());
+        }
+        for (position, character) in characters.into_iter().enumerate() {
+            for (row, bits) in glyph(character).iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (0x10 >> column) != 0 {
+                        self.fill_rect(x + position * advance + column * scale, y + row * scale, scale, scale, color);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Encodes the canvas as an 8-bit RGB PNG without filtering.
+    fn to_png(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut raw = Vec::with_capacity((self.width * 3 + 1) * self.height);
+        for row in self.pixels.chunks(self.width) {
+            raw.push(0);
+            raw.extend(row.iter().flatten());
+        }
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&raw)?;
+
+        let mut header = Vec::with_capacity(13);
+        header.extend((self.width as u32).to_be_bytes());
+        header.extend((self.height as u32).to_be_bytes());
+        header.extend([8, 2, 0, 0, 0]);
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        for (kind, data) in [(b"IHDR", header), (b"IDAT", encoder.finish()?), (b"IEND", Vec::new())] {
+            png.extend((data.len() as u32).to_be_bytes());
+            let mut hasher = crc32fast::Hasher::new();
+            hasher.update(kind);
+            hasher.update(&data);
+            png.extend(kind);
+            png.extend(&data);
+            png.extend(hasher.finalize().to_be_bytes());
+        }
+        Ok(png)
+    }
+}
+
+/// The champion pair the two players picked together most often, with its game count.
+fn top_champion_pair(output: &OverallOutput) -> Option<(String, u32)> {
+    let mut pairs: HashMap<String, u32> = HashMap::new();
+    for m in &output.found_matches {
+        let pair = format!("{} + {}", m.player1_details.champion_label(), m.player2_details.champion_label());
+        *pairs.entry(pair).or_default() += 1;
+    }
+    pairs.into_iter().max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+}
+
+/// Renders a shareable summary card of the query as PNG bytes: both players, games together,
+/// win rate with a bar, and the most played champion pair.
+pub fn render_summary_card(output: &OverallOutput) -> Result<Vec<u8>, Box<dyn Error>> {
+    let summary = &output.query_summary;
+    let games = summary.matches_played_together_count;
+    let wins = summary.player1_wins_together_count;
+    let text_width = CARD_WIDTH - 2 * MARGIN;
+
+    let mut canvas = Canvas::new(CARD_WIDTH, CARD_HEIGHT, BACKGROUND);
+    canvas.fill_rect(0, 0, CARD_WIDTH, 6, GOLD);
+    canvas.draw_text(MARGIN, 28, "Played Together", 3, GOLD, text_width);
+    canvas.draw_text(
+        MARGIN,
+        70,
+        &summary.player1.display_name(),
+        2,
+        WHITE,
+        text_width,
+    );
+    canvas.draw_text(
+        MARGIN,
+        92,
+        &format!("& {}", summary.player2.display_name()),
+        2,
+        WHITE,
+        text_width,
+    );
+    canvas.draw_text(MARGIN, 132, &format!("{} {} together", games, if games == 1 { "game" } else { "games" }), 4, WHITE, text_width);
+
+    if games > 0 {
+        let rate = win_rate(wins, games);
+        canvas.draw_text(MARGIN, 180, &format!("{:.0}% win rate ({}W {}L)", rate, wins, games - wins), 3, WHITE, text_width);
+        let won_width = (text_width as f64 * rate / 100.0).round() as usize;
+        canvas.fill_rect(MARGIN, 214, text_width, 12, RED);
+        canvas.fill_rect(MARGIN, 214, won_width, 12, GREEN);
+    }
+    if let Some((pair, pair_games)) = top_champion_pair(output) {
+        canvas.draw_text(MARGIN, 250, &format!("Top pair: {} ({})", pair, pair_games), 2, GOLD, text_width);
+    }
+    canvas.to_png()
+}
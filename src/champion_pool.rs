@@ -0,0 +1,250 @@
+use riven::consts::PlatformRoute;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error::Error;
+
+use crate::anonymize;
+use crate::api_client::{active_platform, ApiCallStats, PlayerIdentity, RiotApiPool};
+use crate::cache::AccountCache;
+use crate::contacts::nickname_of;
+use crate::index::MatchIndex;
+use crate::offline;
+use crate::pair_history::cached_puuid;
+use crate::riot_id::RiotId;
+
+/// Summoner's Rift positions in lane order, as reported in `teamPosition`.
+pub const ROLES: [&str; 5] = ["TOP", "JUNGLE", "MIDDLE", "BOTTOM", "UTILITY"];
+/// Champions listed per player and role in the text output.
+const TOP_CHAMPIONS_SHOWN: usize = 3;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChampionPlays {
+    /// Canonical champion key, e.g. "MonkeyKing".
+    pub champion: String,
+    pub games: u32,
+    pub wins: u32,
+    /// Only fetched with `--mastery`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mastery_points: Option<i32>,
+}
+
+/// Both players' champions in one role, most played first.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RolePool {
+    pub role: String,
+    pub player1_games: u32,
+    pub player2_games: u32,
+    pub player1: Vec<ChampionPlays>,
+    pub player2: Vec<ChampionPlays>,
+    /// Champions both players play in this role, i.e. possible draft conflicts.
+    pub shared: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChampionPoolOutput {
+    pub player1: PlayerIdentity,
+    pub player2: PlayerIdentity,
+    /// Indexed Summoner's Rift games with a position, per player.
+    pub player1_games: u32,
+    pub player2_games: u32,
+    pub roles: Vec<RolePool>,
+    /// Champions both players have played in any role.
+    pub shared_champions: Vec<String>,
+    pub player1_main_role: Option<String>,
+    pub player2_main_role: Option<String>,
+}
+
+pub struct ChampionPoolOptions {
+    pub player1_riot_id: RiotId,
+    pub player2_riot_id: RiotId,
+    /// Look up champion mastery points, which costs two API calls.
+    pub fetch_mastery: bool,
+    /// `None` looks up player1's active shard when mastery is fetched.
+    pub platform_route: Option<PlatformRoute>,
+}
+
+/// Games and wins per champion key.
+type ChampionTally = HashMap<String, (u32, u32)>;
+
+/// Games and wins per role and champion of one player, over every indexed match.
+fn plays_by_role(match_index: &MatchIndex, puuid: &str) -> Result<BTreeMap<String, ChampionTally>, Box<dyn Error>> {
+    let mut by_role: BTreeMap<String, ChampionTally> = BTreeMap::new();
+    for match_id in match_index.match_ids_for_player_since(puuid, 0)? {
+        let Some(match_data) = match_index.get_match(&match_id)? else {
+            continue;
+        };
+        let Some(participant) = match_data.info.participants.iter().find(|p| p.puuid == puuid) else {
+            continue;
+        };
+        if !ROLES.contains(&participant.team_position.as_str()) {
+            continue;
+        }
+        let plays = by_role
+            .entry(participant.team_position.clone())
+            .or_default()
+            .entry(participant.champion_name.clone())
+            .or_default();
+        plays.0 += 1;
+        plays.1 += participant.win as u32;
+    }
+    Ok(by_role)
+}
+
+async fn mastery_points(
+    riot_api: &RiotApiPool,
+    platform_route: PlatformRoute,
+    puuid: &str,
+) -> Result<HashMap<String, i32>, Box<dyn Error>> {
+    offline::ensure_online(&format!("the champion mastery of PUUID '{}'", anonymize::puuid(puuid)))?;
+    let masteries = riot_api
+        .client().await
+        .champion_mastery_v4()
+        .get_all_champion_masteries_by_puuid(platform_route, puuid)
+        .await?;
+    Ok(masteries
+        .into_iter()
+        .filter_map(|mastery| Some((mastery.champion_id.identifier()?.to_string(), mastery.champion_points)))
+        .collect())
+}
+
+fn sorted_plays(plays: Option<&ChampionTally>, mastery: Option<&HashMap<String, i32>>) -> Vec<ChampionPlays> {
+    let mut sorted: Vec<ChampionPlays> = plays
+        .into_iter()
+        .flatten()
+        .map(|(champion, (games, wins))| ChampionPlays {
+            champion: champion.clone(),
+            games: *games,
+            wins: *wins,
+            mastery_points: mastery.map(|mastery| mastery.get(champion).copied().unwrap_or(0)),
+        })
+        .collect();
+    sorted.sort_by(|a, b| b.games.cmp(&a.games).then_with(|| a.champion.cmp(&b.champion)));
+    sorted
+}
+
+fn main_role(roles: &[RolePool], games_of: impl Fn(&RolePool) -> u32) -> Option<String> {
+    roles
+        .iter()
+        .filter(|role| games_of(role) > 0)
+        .max_by_key(|role| games_of(role))
+        .map(|role| role.role.clone())
+}
+
+/// Compares the champions both players played per role, from the local match index. Only the
+/// optional mastery lookup uses the API.
+pub async fn run_champion_pool(
+    riot_api: Option<&RiotApiPool>,
+    account_cache: &AccountCache,
+    match_index: &MatchIndex,
+    options: ChampionPoolOptions,
+) -> Result<ChampionPoolOutput, Box<dyn Error>> {
+    let ChampionPoolOptions { player1_riot_id, player2_riot_id, fetch_mastery, platform_route } = options;
+    let puuid1 = cached_puuid(account_cache, &player1_riot_id)?;
+    let puuid2 = cached_puuid(account_cache, &player2_riot_id)?;
+    let plays1 = plays_by_role(match_index, &puuid1)?;
+    let plays2 = plays_by_role(match_index, &puuid2)?;
+
+    let (mastery1, mastery2) = match riot_api.filter(|_| fetch_mastery) {
+        Some(riot_api) => {
+            let platform_route = match platform_route {
+                Some(route) => route,
+                None => {
+                    let regional_route = account_cache
+                        .get(&player1_riot_id)
+                        .and_then(|account| account.regional_route())
+                        .ok_or("The region of player 1 is unknown. Pass --region to fetch mastery points.")?;
+                    active_platform(riot_api, regional_route, &puuid1, &mut ApiCallStats::default()).await?
+                }
+            };
+            (
+                Some(mastery_points(riot_api, platform_route, &puuid1).await?),
+                Some(mastery_points(riot_api, platform_route, &puuid2).await?),
+            )
+        }
+        None => (None, None),
+    };
+
+    let roles: Vec<RolePool> = ROLES
+        .iter()
+        .map(|role| {
+            let player1 = sorted_plays(plays1.get(*role), mastery1.as_ref());
+            let player2 = sorted_plays(plays2.get(*role), mastery2.as_ref());
+            let mut shared: Vec<String> = player1
+                .iter()
+                .filter(|plays| player2.iter().any(|other| other.champion == plays.champion))
+                .map(|plays| plays.champion.clone())
+                .collect();
+            shared.sort();
+            RolePool {
+                role: role.to_string(),
+                player1_games: player1.iter().map(|plays| plays.games).sum(),
+                player2_games: player2.iter().map(|plays| plays.games).sum(),
+                player1,
+                player2,
+                shared,
+            }
+        })
+        .collect();
+
+    let champions_of = |plays: &BTreeMap<String, ChampionTally>| {
+        plays.values().flat_map(|champions| champions.keys().cloned()).collect::<BTreeSet<_>>()
+    };
+    let shared_champions = champions_of(&plays1).intersection(&champions_of(&plays2)).cloned().collect();
+    let identity = |riot_id: RiotId| PlayerIdentity {
+        nickname: nickname_of(&riot_id),
+        game_name: riot_id.game_name,
+        tag_line: riot_id.tag_line,
+        profile: None,
+        formerly_known_as: Vec::new(),
+    };
+
+    Ok(ChampionPoolOutput {
+        player1: identity(player1_riot_id),
+        player2: identity(player2_riot_id),
+        player1_games: roles.iter().map(|role| role.player1_games).sum(),
+        player2_games: roles.iter().map(|role| role.player2_games).sum(),
+        player1_main_role: main_role(&roles, |role| role.player1_games),
+        player2_main_role: main_role(&roles, |role| role.player2_games),
+        shared_champions,
+        roles,
+    })
+}
+
+impl ChampionPoolOutput {
+    pub fn print(&self) {
+        let name1 = self.player1.display_name();
+        let name2 = self.player2.display_name();
+        println!("\n--- Champion Pools ---");
+        println!("From {} indexed games of {} and {} of {}.", self.player1_games, name1, self.player2_games, name2);
+        let top = |plays: &[ChampionPlays]| {
+            let shown: Vec<String> = plays
+                .iter()
+                .take(TOP_CHAMPIONS_SHOWN)
+                .map(|plays| match plays.mastery_points {
+                    Some(points) => format!("{} {} ({} pts)", plays.champion, plays.games, points),
+                    None => format!("{} {}", plays.champion, plays.games),
+                })
+                .collect();
+            if shown.is_empty() { "-".to_string() } else { shown.join(", ") }
+        };
+        for role in &self.roles {
+            println!("{:<8} {}: {} | {}: {}", role.role, name1, top(&role.player1), name2, top(&role.player2));
+            if !role.shared.is_empty() {
+                println!("{:<8} Both play: {}", "", role.shared.join(", "));
+            }
+        }
+        if !self.shared_champions.is_empty() {
+            println!("Shared champions: {}", self.shared_champions.join(", "));
+        }
+        match (&self.player1_main_role, &self.player2_main_role) {
+            (Some(role1), Some(role2)) if role1 == role2 => {
+                println!("Both mainly play {}; one of you has to flex.", role1)
+            }
+            (Some(role1), Some(role2)) => println!("Complementary mains: {} plays {}, {} plays {}.", name1, role1, name2, role2),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use crate::api_client::MatchDetails;
+
+/// Games the rolling win rate is averaged over.
+pub const ROLLING_WINDOW: usize = 5;
+/// Weeks covered by `games_per_week`, counting back from the latest game.
+pub const MAX_WEEKS: usize = 12;
+const WEEK_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+
+const WIDTH: f64 = 640.0;
+const HEIGHT: f64 = 240.0;
+/// Room for the axis labels left of and below the plot.
+const LEFT: f64 = 40.0;
+const BOTTOM: f64 = 24.0;
+const TOP: f64 = 10.0;
+const RIGHT: f64 = 10.0;
+
+/// Player1's rolling win rate over the last `ROLLING_WINDOW` shared games, oldest game first,
+/// with the date of each game.
+pub fn rolling_win_rates(found_matches: &[MatchDetails]) -> Vec<(String, f64)> {
+    let mut games: Vec<&MatchDetails> = found_matches.iter().collect();
+    games.sort_by_key(|m| m.game_start_timestamp);
+    let outcomes: Vec<bool> = games.iter().map(|m| m.player1_details.outcome == "Victory").collect();
+    games
+        .iter()
+        .enumerate()
+        .map(|(i, m)| {
+            let window = &outcomes[(i + 1).saturating_sub(ROLLING_WINDOW)..=i];
+            let wins = window.iter().filter(|won| **won).count();
+            let date = m.game_date_utc.split(' ').next().unwrap_or_default().to_string();
+            (date, wins as f64 / window.len() as f64 * 100.0)
+        })
+        .collect()
+}
+
+/// Shared games per seven-day period, oldest first, ending with the week of the latest game and
+/// reaching back to the earliest game or `MAX_WEEKS` weeks.
+pub fn games_per_week(found_matches: &[MatchDetails]) -> Vec<u32> {
+    let Some(latest) = found_matches.iter().map(|m| m.game_start_timestamp).max() else {
+        return Vec::new();
+    };
+    let mut weeks = vec![0; MAX_WEEKS];
+    let mut oldest_week = 0;
+    for m in found_matches {
+        let weeks_ago = ((latest - m.game_start_timestamp) / WEEK_MS) as usize;
+        if weeks_ago < MAX_WEEKS {
+            weeks[MAX_WEEKS - 1 - weeks_ago] += 1;
+            oldest_week = oldest_week.max(weeks_ago);
+        }
+    }
+    weeks.split_off(MAX_WEEKS - 1 - oldest_week)
+}
+
+/// A self-contained SVG line chart of the rolling win rate, with 0/50/100% grid lines and the
+/// first and last game dates. `None` below two shared games.
+pub fn win_rate_svg(found_matches: &[MatchDetails]) -> Option<String> {
+    let rates = rolling_win_rates(found_matches);
+    if rates.len() < 2 {
+        return None;
+    }
+
+    let plot_width = WIDTH - LEFT - RIGHT;
+    let plot_height = HEIGHT - TOP - BOTTOM;
+    let x_of = |game: usize| LEFT + game as f64 / (rates.len() - 1) as f64 * plot_width;
+    let y_of = |rate: f64| TOP + (100.0 - rate) / 100.0 * plot_height;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" \
+         font-family=\"sans-serif\" font-size=\"11\">",
+        w = WIDTH,
+        h = HEIGHT
+    );
+    for rate in [0.0, 50.0, 100.0] {
+        svg.push_str(&format!(
+            "<line x1=\"{x1}\" y1=\"{y:.1}\" x2=\"{x2}\" y2=\"{y:.1}\" stroke=\"#ccc\" stroke-width=\"0.5\"/>\
+             <text x=\"{tx}\" y=\"{ty:.1}\" text-anchor=\"end\">{rate:.0}%</text>",
+            x1 = LEFT,
+            x2 = WIDTH - RIGHT,
+            y = y_of(rate),
+            tx = LEFT - 4.0,
+            ty = y_of(rate) + 4.0,
+            rate = rate
+        ));
+    }
+    let points: Vec<String> = rates
+        .iter()
+        .enumerate()
+        .map(|(game, (_, rate))| format!("{:.1},{:.1}", x_of(game), y_of(*rate)))
+        .collect();
+    svg.push_str(&format!(
+        "<polyline points=\"{}\" fill=\"none\" stroke=\"#c89b3c\" stroke-width=\"2\"/>",
+        points.join(" ")
+    ));
+    let label_y = HEIGHT - 6.0;
+    svg.push_str(&format!(
+        "<text x=\"{:.1}\" y=\"{:.1}\">{}</text><text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text></svg>",
+        LEFT,
+        label_y,
+        rates[0].0,
+        WIDTH - RIGHT,
+        label_y,
+        rates[rates.len() - 1].0
+    ));
+    Some(svg)
+}
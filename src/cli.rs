@@ -1,9 +1,11 @@
-use clap::Parser;
-use crate::riot_id::RiotId;
-use riven::consts::RegionalRoute;
+use clap::{CommandFactory, Parser, Subcommand};
+use crate::help::HelpTopic;
+use crate::riot_id::{looks_like_puuid, RiotId};
+use riven::consts::{PlatformRoute, RegionalRoute};
 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum UserFacingRegion {
     BR,
     EUNE,
@@ -48,6 +50,25 @@ impl std::str::FromStr for UserFacingRegion {
 }
 
 impl UserFacingRegion {
+    pub const ALL: [UserFacingRegion; 15] = [
+        UserFacingRegion::BR,
+        UserFacingRegion::EUNE,
+        UserFacingRegion::EUW,
+        UserFacingRegion::JP,
+        UserFacingRegion::KR,
+        UserFacingRegion::LAN,
+        UserFacingRegion::LAS,
+        UserFacingRegion::ME,
+        UserFacingRegion::NA,
+        UserFacingRegion::OCE,
+        UserFacingRegion::RU,
+        UserFacingRegion::SEA,
+        UserFacingRegion::TR,
+        UserFacingRegion::TW,
+        UserFacingRegion::VN,
+    ];
+
+    /// The regional cluster holding the region's match history. Oceania and Southeast Asia moved to SEA.
     pub fn to_regional_route(&self) -> RegionalRoute {
         match self {
             UserFacingRegion::BR => RegionalRoute::AMERICAS,
@@ -59,12 +80,37 @@ impl UserFacingRegion {
             UserFacingRegion::LAS => RegionalRoute::AMERICAS,
             UserFacingRegion::ME => RegionalRoute::EUROPE,
             UserFacingRegion::NA => RegionalRoute::AMERICAS,
-            UserFacingRegion::OCE => RegionalRoute::AMERICAS,
+            UserFacingRegion::OCE => RegionalRoute::SEA,
             UserFacingRegion::RU => RegionalRoute::EUROPE,
-            UserFacingRegion::SEA => RegionalRoute::ASIA,
+            UserFacingRegion::SEA => RegionalRoute::SEA,
             UserFacingRegion::TR => RegionalRoute::EUROPE,
-            UserFacingRegion::TW => RegionalRoute::ASIA,
-            UserFacingRegion::VN => RegionalRoute::ASIA,
+            UserFacingRegion::TW => RegionalRoute::SEA,
+            UserFacingRegion::VN => RegionalRoute::SEA,
+        }
+    }
+
+    /// The region of a platform, `None` for platforms the tool does not offer (e.g. PBE).
+    pub fn from_platform_route(platform: PlatformRoute) -> Option<Self> {
+        Self::ALL.into_iter().find(|region| region.to_platform_route() == platform)
+    }
+
+    pub fn to_platform_route(&self) -> PlatformRoute {
+        match self {
+            UserFacingRegion::BR => PlatformRoute::BR1,
+            UserFacingRegion::EUNE => PlatformRoute::EUN1,
+            UserFacingRegion::EUW => PlatformRoute::EUW1,
+            UserFacingRegion::JP => PlatformRoute::JP1,
+            UserFacingRegion::KR => PlatformRoute::KR,
+            UserFacingRegion::LAN => PlatformRoute::LA1,
+            UserFacingRegion::LAS => PlatformRoute::LA2,
+            UserFacingRegion::ME => PlatformRoute::ME1,
+            UserFacingRegion::NA => PlatformRoute::NA1,
+            UserFacingRegion::OCE => PlatformRoute::OC1,
+            UserFacingRegion::RU => PlatformRoute::RU,
+            UserFacingRegion::SEA => PlatformRoute::SG2,
+            UserFacingRegion::TR => PlatformRoute::TR1,
+            UserFacingRegion::TW => PlatformRoute::TW2,
+            UserFacingRegion::VN => PlatformRoute::VN2,
         }
     }
 
@@ -89,6 +135,89 @@ impl UserFacingRegion {
     }
 }
 
+/// Match types the match list can be limited to, as named by match-v5's `type` filter.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchType {
+    Ranked,
+    Normal,
+    Tourney,
+    Tutorial,
+}
+
+impl MatchType {
+    pub fn as_api_str(&self) -> &'static str {
+        match self {
+            MatchType::Ranked => "ranked",
+            MatchType::Normal => "normal",
+            MatchType::Tourney => "tourney",
+            MatchType::Tutorial => "tutorial",
+        }
+    }
+}
+
+impl std::fmt::Display for MatchType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_api_str())
+    }
+}
+
+impl std::str::FromStr for MatchType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ranked" => Ok(MatchType::Ranked),
+            "normal" => Ok(MatchType::Normal),
+            "tourney" => Ok(MatchType::Tourney),
+            "tutorial" => Ok(MatchType::Tutorial),
+            _ => Err(format!("Invalid match type: {}. Supported types are ranked, normal, tourney, tutorial", s)),
+        }
+    }
+}
+
+/// Destinations the post-scan summary can be sent to.
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyTarget {
+    Stdout,
+    Desktop,
+    Discord,
+    Slack,
+    Telegram,
+    Webhook,
+}
+
+impl std::fmt::Display for NotifyTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            NotifyTarget::Stdout => "stdout",
+            NotifyTarget::Desktop => "desktop",
+            NotifyTarget::Discord => "discord",
+            NotifyTarget::Slack => "slack",
+            NotifyTarget::Telegram => "telegram",
+            NotifyTarget::Webhook => "webhook",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl std::str::FromStr for NotifyTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "stdout" => Ok(NotifyTarget::Stdout),
+            "desktop" => Ok(NotifyTarget::Desktop),
+            "discord" => Ok(NotifyTarget::Discord),
+            "slack" => Ok(NotifyTarget::Slack),
+            "telegram" => Ok(NotifyTarget::Telegram),
+            "webhook" => Ok(NotifyTarget::Webhook),
+            _ => Err(format!("Invalid notification target: {}. Supported targets are stdout, desktop, discord, slack, telegram, webhook", s)),
+        }
+    }
+}
+
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
@@ -99,13 +228,26 @@ impl UserFacingRegion {
                   You can specify Riot IDs directly, set a default 'self' ID, and control output verbosity."
 )]
 pub struct Cli {
-    /// Sets the "self" Riot ID for subsequent calls (e.g., InGameName#GamerTag)
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
+    /// Print the help of every subcommand, the config keys, environment variables and exit codes.
+    #[clap(long)]
+    pub help_all: bool,
+
+    /// Sets the "self" Riot ID for subsequent calls (e.g., InGameName#GamerTag).
+    /// Together with --region, also stores the region it plays on.
     #[clap(long = "self", value_name = "RIOT_ID")]
     pub set_self: Option<RiotId>,
 
     /// Set and store your Riot API key locally. This key will be used if the RGAPI_KEY environment variable is not found.
-    #[clap(long, value_name = "KEY")]
-    pub api_key: Option<String>,
+    /// Pass several keys to rotate requests between them, each with its own rate limit.
+    #[clap(long, value_name = "KEY", num_args = 1..)]
+    pub api_key: Option<Vec<String>>,
+
+    /// Store the path of the Google service-account key file used by --sheets.
+    #[clap(long, value_name = "PATH")]
+    pub sheets_credentials: Option<std::path::PathBuf>,
 
     /// Check if two Riot IDs played together.
     /// player1: The Riot ID whose match history will be checked.
@@ -113,9 +255,20 @@ pub struct Cli {
     #[clap(value_parser, num_args = 0..=2)]
     pub riot_ids: Vec<RiotId>,
 
+    /// The Riot ID whose match history will be checked; an alternative to the first positional
+    /// Riot ID that needs no care with spaces in the game name.
+    #[clap(long, value_name = "RIOT_ID", conflicts_with = "riot_ids", requires = "player2")]
+    pub player1: Option<RiotId>,
+
+    /// The Riot ID to search for in player1's match history. Without --player1, the "self" Riot ID
+    /// is player1.
+    #[clap(long, value_name = "RIOT_ID", conflicts_with = "riot_ids")]
+    pub player2: Option<RiotId>,
+
     #[clap(
         long,
         value_name = "REGION_OVERRIDE",
+        global = true,
         help = "Specify the Regional Route (e.g., EUW, NA, KR).\n\
                 Overrides --default-region if both are specified.\n\
                 Supported:\n    BR, EUNE, EUW, JP, KR, LAN, LAS, ME, NA, OCE, RU, SEA, TR, TW, VN"
@@ -126,7 +279,9 @@ pub struct Cli {
         long,
         value_name = "DEFAULT_REGION",
         help = "Set a default Regional Route to use if --region is not specified.\n\
-                Defaults to EUROPE if neither --region nor --default-region are specified.\n\
+                If neither --region nor --default-region are specified, default_region from the\n\
+                config file is used, and otherwise the route is detected from player1's match\n\
+                history and remembered for later runs.\n\
                 Supported:\n    BR, EUNE, EUW, JP, KR, LAN, LAS, ME, NA, OCE, RU, SEA, TR, TW, VN"
     )]
     pub default_region: Option<UserFacingRegion>,
@@ -136,15 +291,677 @@ pub struct Cli {
     #[clap(short, long, value_name = "COUNT")]
     pub number: Option<i32>,
 
+    /// Only check games played on this day (e.g. 2024-12-31) in your local timezone,
+    /// instead of the last 30 days.
+    #[clap(long, value_name = "DATE", conflicts_with = "between")]
+    pub on: Option<chrono::NaiveDate>,
+
+    /// Only check games played between these two days (e.g. 2024-12-24 2024-12-31), both included,
+    /// in your local timezone.
+    #[clap(long, num_args = 2, value_names = ["FROM", "TO"])]
+    pub between: Option<Vec<chrono::NaiveDate>>,
+
+    /// Only list player1's matches of this type: ranked, normal, tourney or tutorial.
+    /// Overrides default_match_type from the config.
+    #[clap(long, value_name = "TYPE", global = true)]
+    pub match_type: Option<MatchType>,
+
+    /// Only list and count matches of this numeric queue ID (e.g. 1700 for Arena), passed straight
+    /// to the API, so new or rotating modes work before ptg knows them.
+    #[clap(long, value_name = "ID", global = true)]
+    pub queue_id: Option<u16>,
+
+    /// Only count shared games played on this patch (e.g. 14.20).
+    #[clap(long, value_name = "PATCH", global = true)]
+    pub patch: Option<String>,
+
+    /// Print extended statistics, such as the per-patch win rate table.
+    #[clap(long, global = true)]
+    pub stats: bool,
+
+    /// Print a month-by-month chart of the games together. Combine with `pair-history --all-time`
+    /// to cover the whole indexed history.
+    #[clap(long, global = true)]
+    pub duo_timeline: bool,
+
+    /// Group the games together into play sessions (less than an hour apart) and print the record
+    /// and length of each. Sessions are always part of the JSON output.
+    #[clap(long, global = true)]
+    pub sessions: bool,
+
+    /// Only count games where the players were on opposite teams. For direct lane opponents
+    /// (same position), the timeline is fetched to report CS difference at 10 minutes and solo kills.
+    #[clap(long)]
+    pub opponents: bool,
+
+    /// Break the matches down by a third player as well: games of player1 with only player2, with
+    /// only this player, with both, and indexed games of the other two without player1.
+    #[clap(long, value_name = "RIOT_ID")]
+    pub venn: Option<RiotId>,
+
+    /// Count shared games where either player likely went AFK or disconnected, which are left out
+    /// of every statistic by default.
+    #[clap(long, global = true)]
+    pub include_afk: bool,
+
+    /// When player1's account was transferred away from the selected region, scan the region
+    /// it moved to instead of only warning about it.
+    #[clap(long)]
+    pub follow_transfer: bool,
+
+    /// Check Riot's service status for the selected region before scanning, and warn about
+    /// ongoing maintenances or incidents.
+    #[clap(long)]
+    pub status_check: bool,
+
+    /// Look up both players' summoner level and profile icon (one or two extra requests per player).
+    #[clap(long)]
+    pub profile: bool,
+
+    /// Fetch match timelines for shared games (one extra request per game) to enable
+    /// timeline-based stats such as stomp/comeback classification and how often the pair
+    /// assisted on each other's kills.
+    #[clap(long)]
+    pub timeline: bool,
+
+    /// Compare each player's shared games with their other games in the window (KDA, damage,
+    /// win rate). Lists player2's matches too, so it costs a match list call plus one call per
+    /// match not indexed yet.
+    #[clap(long)]
+    pub solo_baseline: bool,
+
+    /// When player1 has no matches in the last 30 days, double the window until matches turn up,
+    /// up to --max-window-days, and report the window that had them.
+    #[clap(long)]
+    pub auto_widen: bool,
+
+    /// Furthest back --auto-widen looks, in days. Overrides max_window_days from the config; 365
+    /// by default.
+    #[clap(long, value_name = "DAYS", requires = "auto_widen")]
+    pub max_window_days: Option<u32>,
+
+    /// Write an SVG line chart of the rolling win rate over the shared games to FILE.
+    #[clap(long, value_name = "FILE", global = true)]
+    pub chart: Option<std::path::PathBuf>,
+
+    /// Export the results to a file, e.g. `--export sqlite shared_games.db`.
+    /// Supported formats: sqlite (appends to an existing database across runs),
+    /// parquet (writes matches.parquet and participants.parquet into the PATH directory),
+    /// ics (one calendar event per shared game), png (a summary card image to share).
+    #[clap(long, num_args = 2, value_names = ["FORMAT", "PATH"], global = true)]
+    pub export: Option<Vec<String>>,
+
+    /// Also write the results in this format into --output-dir (can be repeated): json, html (a
+    /// standalone page) or markdown, e.g. `--format json --format html --output-dir out/`.
+    #[clap(long = "format", value_name = "FORMAT", requires = "output_dir")]
+    pub formats: Vec<crate::render::RenderFormat>,
+
+    /// Directory the --format files are written to, as results.json, results.html and results.md.
+    #[clap(long, value_name = "DIR", requires = "formats")]
+    pub output_dir: Option<std::path::PathBuf>,
+
+    /// Save the results and the raw data of the found matches to FILE as a compressed bundle,
+    /// which `--load-run` renders later without an API key. Holds real Riot IDs and PUUIDs.
+    #[clap(long, value_name = "FILE", conflicts_with = "anonymize", global = true)]
+    pub save_run: Option<std::path::PathBuf>,
+
+    /// Render the results saved with `--save-run` to FILE instead of querying, in any output
+    /// format (--json, --template, --export, ...). Its matches are added to the local index.
+    #[clap(long, value_name = "FILE", conflicts_with_all = ["save_run", "exists"])]
+    pub load_run: Option<std::path::PathBuf>,
+
+    /// Read JSON instead of querying, `-` for stdin: results printed by `--json` (shown in the
+    /// requested output format), or a list of raw match-v5 matches, which are checked for the
+    /// two given players without touching the local index.
+    #[clap(long, value_name = "FILE", conflicts_with_all = ["load_run", "exists"])]
+    pub from_json: Option<std::path::PathBuf>,
+
+    /// Append every found match as a row to this Google Sheet (requires --sheets-credentials).
+    #[clap(long, value_name = "SPREADSHEET_ID", global = true)]
+    pub sheets: Option<String>,
+
+    /// Send the post-scan summary to a notification target (can be repeated), configured in the
+    /// integrations section of the config: stdout, desktop, discord (discord_webhook_url),
+    /// slack (slack_webhook_url), telegram (telegram_bot_token, telegram_chat_id), webhook (webhook_url).
+    #[clap(long, value_name = "TARGET", global = true)]
+    pub notify_target: Vec<NotifyTarget>,
+
+    /// Send the post-scan summary to the notify_targets listed in the config.
+    #[clap(long, global = true)]
+    pub notify: bool,
+
+    /// POST the JSON results to this URL.
+    #[clap(long, value_name = "URL", global = true)]
+    pub webhook: Option<String>,
+
+    /// Render the --webhook request body with this Tera template instead of sending raw JSON.
+    /// The template context is the JSON output (querySummary, foundMatches, ...).
+    #[clap(long, value_name = "FILE", requires = "webhook", global = true)]
+    pub webhook_template: Option<std::path::PathBuf>,
+
+    /// Content-Type header of --webhook requests. Defaults to application/json.
+    #[clap(long, value_name = "MIME", requires = "webhook", global = true)]
+    pub webhook_content_type: Option<String>,
+
+    /// Also send one alert per shared match found, not just the summary.
+    #[clap(long, global = true)]
+    pub notify_matches: bool,
+
+    /// Replace Riot IDs and PUUIDs with stable pseudonyms in every output and log line,
+    /// so results can be shared publicly.
+    #[clap(long, global = true)]
+    pub anonymize: bool,
+
     /// Enable verbose output, showing search progression and full match details.
-    #[clap(short, long)]
+    #[clap(short, long, global = true)]
     pub verbose: bool,
 
     /// Enable silent output, only a summary.
-    #[clap(short, long, conflicts_with = "verbose")]
+    #[clap(short, long, conflicts_with = "verbose", global = true)]
     pub silent: bool,
 
     /// Output results in JSON format.
-    #[arg(short, long, conflicts_with_all = &["silent", "verbose"])]
+    #[arg(short, long, conflicts_with_all = &["silent", "verbose"], global = true)]
     pub json: bool,
-}
\ No newline at end of file
+
+    /// Only answer whether the players played together, stopping at the first shared match.
+    /// Exits with 0 if they did, 1 if not and 2 if the query failed.
+    #[clap(long, conflicts_with_all = ["json", "verbose", "template", "oneline"])]
+    pub exists: bool,
+
+    /// Print each shared match as one aligned line instead of the summary.
+    #[clap(long, conflicts_with_all = ["json", "verbose", "template"], global = true)]
+    pub oneline: bool,
+
+    /// Stop with an error when Riot answers 429 Too Many Requests instead of waiting out its
+    /// Retry-After delay. Server errors are not retried either.
+    #[clap(long, global = true)]
+    pub fail_fast_on_429: bool,
+
+    /// Answer only from the local account cache and match index, without any network request.
+    /// Fails when something the query needs was never fetched.
+    #[clap(long, global = true)]
+    pub offline: bool,
+
+    /// Draw charts and trends with plain ASCII characters, e.g. for a console or log viewer that shows
+    /// unicode blocks as garbage. Legacy Windows consoles get this automatically.
+    #[clap(long, global = true)]
+    pub ascii: bool,
+
+    /// Print match results directly instead of through $PTG_PAGER, $PAGER or less.
+    #[clap(long, global = true)]
+    pub no_pager: bool,
+
+    /// Show champion names in this Data Dragon locale (e.g. ko_KR, ja_JP, de_DE).
+    /// Overrides the language in the config. JSON output keeps the canonical English key in `champion`.
+    #[clap(long, value_name = "LOCALE", global = true)]
+    pub language: Option<String>,
+
+    /// Add champion icon and splash art URLs and item and summoner spell names and icons from Data
+    /// Dragon to every match (downloaded once per patch).
+    #[clap(long, global = true)]
+    pub assets: bool,
+
+    /// Print the results rendered with this Tera template instead of the built-in summary.
+    /// The context is the JSON output (querySummary, foundMatches, stats, achievements);
+    /// .html templates are HTML-escaped. With --timeline, `goldGraph.svg` of each match is a gold
+    /// sparkline to embed with `| safe`, and `winRateChart` is the --chart SVG.
+    #[clap(long, value_name = "FILE", conflicts_with = "json", global = true)]
+    pub template: Option<std::path::PathBuf>,
+}
+
+impl Cli {
+    /// Parses the command line like `Cli::parse`, after joining Riot IDs split at their spaces by a
+    /// missing pair of quotes (see `join_split_riot_ids`). `--player1`/`--player2` are folded into
+    /// the positional Riot IDs.
+    pub fn parse_args() -> Self {
+        let mut cli = Cli::parse_from(join_split_riot_ids(std::env::args().collect()));
+        if let Some(player2) = cli.player2.take() {
+            cli.riot_ids = cli.player1.take().into_iter().chain([player2]).collect();
+        }
+        cli
+    }
+
+    /// Every Riot ID argument, including those of the subcommand, so PUUIDs can be resolved in place.
+    pub fn riot_ids_mut(&mut self) -> Vec<&mut RiotId> {
+        let mut riot_ids: Vec<&mut RiotId> = self.riot_ids.iter_mut().collect();
+        riot_ids.extend(self.set_self.as_mut());
+        riot_ids.extend(self.venn.as_mut());
+        match &mut self.command {
+            Some(
+                Command::Prefetch { riot_ids: group, .. }
+                | Command::Lineup { riot_ids: group }
+                | Command::Scout { riot_ids: group, .. },
+            ) => {
+                riot_ids.extend(group.iter_mut())
+            }
+            Some(Command::Rivalry { first_pair, second_pair }) => riot_ids.extend(first_pair.iter_mut().chain(second_pair.iter_mut())),
+            Some(
+                Command::PairHistory { player1, player2, .. }
+                | Command::Tft { player1, player2, .. }
+                | Command::ChampionPool { player1, player2, .. }
+                | Command::CanDuo { player1, player2 },
+            ) => riot_ids.extend([player1, player2]),
+            Some(Command::Fav { command: FavCommand::Add { riot_id } | FavCommand::Remove { riot_id } })
+            | Some(Command::Contacts { command: ContactsCommand::Nick { riot_id, .. } | ContactsCommand::Region { riot_id, .. } }) => {
+                riot_ids.push(riot_id)
+            }
+            _ => {}
+        }
+        riot_ids
+    }
+}
+
+/// Rejoins Riot IDs the shell split at spaces, e.g. `ptg Hide on bush #KR1 Faker#KR1` for
+/// `ptg "Hide on bush#KR1" Faker#KR1`: a token starting with `#` is the tag of the words before it,
+/// and bare words right before a `Name#Tag` token belong to its game name. Only top-level
+/// positional arguments are joined; values of options, PUUIDs, subcommands and everything after
+/// `--` are left alone.
+pub fn join_split_riot_ids(args: Vec<String>) -> Vec<String> {
+    let mut command = Cli::command();
+    command.build();
+    // How many values the option `token` takes, at least and at most.
+    let values_of = |token: &str| {
+        let arg = match token.strip_prefix("--") {
+            Some(long) if !long.contains('=') => command.get_arguments().find(|arg| arg.get_long() == Some(long)),
+            Some(_) => None,
+            None => {
+                let mut shorts = token.chars().skip(1);
+                match (shorts.next(), shorts.next()) {
+                    (Some(short), None) => command.get_arguments().find(|arg| arg.get_short() == Some(short)),
+                    _ => None,
+                }
+            }
+        };
+        match arg.and_then(|arg| arg.get_num_args()) {
+            Some(range) if arg.is_some_and(|arg| arg.get_action().takes_values()) => (range.min_values(), range.max_values()),
+            _ => (0, 0),
+        }
+    };
+
+    let mut joined = Vec::with_capacity(args.len());
+    let mut words: Vec<String> = Vec::new();
+    let mut positional_seen = false;
+    let mut tokens = args.into_iter().peekable();
+    joined.extend(tokens.next());
+    while let Some(token) = tokens.next() {
+        if token == "--" || (!positional_seen && command.find_subcommand(&token).is_some()) {
+            joined.append(&mut words);
+            joined.push(token);
+            joined.extend(tokens.by_ref());
+            break;
+        }
+        if token.starts_with('-') && token.len() > 1 {
+            joined.append(&mut words);
+            let (min_values, max_values) = values_of(&token);
+            joined.push(token);
+            for taken in 0..max_values {
+                match tokens.next_if(|value| taken < min_values || !value.starts_with('-')) {
+                    Some(value) => joined.push(value),
+                    None => break,
+                }
+            }
+            continue;
+        }
+        positional_seen = true;
+        if token.starts_with('#') && !words.is_empty() {
+            joined.push(format!("{}{}", std::mem::take(&mut words).join(" "), token));
+        } else if token.contains('#') {
+            words.push(token);
+            joined.push(std::mem::take(&mut words).join(" "));
+        } else if looks_like_puuid(&token) {
+            joined.append(&mut words);
+            joined.push(token);
+        } else {
+            words.push(token);
+        }
+    }
+    joined.extend(words);
+    joined
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Populate the local match index for a list of players.
+    ///
+    /// Run it e.g. overnight so later checks are answered without API calls.
+    /// Already indexed matches are skipped, so an interrupted run can simply be restarted.
+    Prefetch {
+        /// Riot IDs to index (e.g., InGameName#GamerTag).
+        #[clap(required = true, value_name = "RIOT_ID")]
+        riot_ids: Vec<RiotId>,
+
+        /// Number of most recent matches to index per player.
+        #[clap(long, value_name = "COUNT", default_value_t = 100)]
+        max_matches: i32,
+    },
+
+    /// Look up the shared history of two players from the local match index only.
+    ///
+    /// No API calls are made: both players must already be in the account cache,
+    /// and only matches indexed by earlier checks or `prefetch` are considered.
+    PairHistory {
+        /// Riot ID of player 1 (e.g., InGameName#GamerTag).
+        player1: RiotId,
+
+        /// Riot ID of player 2 (e.g., InGameName#GamerTag).
+        player2: RiotId,
+
+        /// Consider every indexed match instead of only the last 30 days.
+        #[clap(long)]
+        all_time: bool,
+    },
+
+    /// Present results saved earlier in another format, without any API access.
+    ///
+    /// Reads a `--save-run` file or the output of `--json`, e.g.
+    /// `ptg --json A#EUW B#EUW | ptg render - --format html > duo.html`.
+    Render {
+        /// The saved results, or `-` to read them from stdin.
+        #[clap(value_name = "RUN_FILE")]
+        source: std::path::PathBuf,
+
+        /// Output format: text, oneline, json, html (a standalone page) or markdown.
+        #[clap(long, value_name = "FORMAT", default_value = "text")]
+        format: crate::render::RenderFormat,
+    },
+
+    /// Manage favorite partners and check them all at once.
+    Fav {
+        #[clap(subcommand)]
+        command: FavCommand,
+    },
+
+    /// Build a static website of the indexed shared history with every contact.
+    ///
+    /// Writes an index page, a page per contact with a win-rate chart and a page per shared match,
+    /// ready to publish e.g. on GitHub Pages. Only the local match index is read.
+    Site {
+        #[clap(subcommand)]
+        command: SiteCommand,
+    },
+
+    /// Summarize the past period across all favorite pairs, e.g. from a cron job.
+    ///
+    /// Prints games, win rates and highlights per favorite partner. With --notify or --notify-target
+    /// the digest is also posted to those targets.
+    Digest {
+        /// Cover the last seven days, today included.
+        #[clap(long, required = true)]
+        weekly: bool,
+
+        /// Output format: markdown or html.
+        #[clap(long, value_name = "FORMAT", default_value = "markdown")]
+        format: crate::digest::DigestFormat,
+    },
+
+    /// List contacts or look up their current Riot IDs.
+    Contacts {
+        #[clap(subcommand)]
+        command: ContactsCommand,
+    },
+
+    /// Attach a note or tags to a match, shown whenever the match appears in later results.
+    ///
+    /// Without a note or tags, prints what is stored for the match.
+    Note {
+        /// Match ID, e.g. EUN1_3612345678.
+        match_id: String,
+
+        /// Note text, e.g. "the baron throw game".
+        note: Option<String>,
+
+        /// Tag to add (can be repeated).
+        #[clap(long, value_name = "TAG")]
+        tag: Vec<String>,
+
+        /// Remove every note and tag of the match.
+        #[clap(long, conflicts_with_all = ["note", "tag"])]
+        clear: bool,
+    },
+
+    /// Check whether two players played Teamfight Tactics together.
+    ///
+    /// For Double Up games, queue partners are told apart from opponents in the same lobby.
+    Tft {
+        /// Riot ID of player 1, whose TFT match history is checked.
+        player1: RiotId,
+
+        /// Riot ID of player 2.
+        player2: RiotId,
+
+        /// Number of most recent TFT matches to check for player1.
+        #[clap(short, long, value_name = "COUNT", default_value_t = crate::tft::DEFAULT_TFT_MATCH_COUNT)]
+        number: i32,
+    },
+
+    /// Rank every contact by how the self ID does with them, from the local match index only.
+    ///
+    /// Both the self ID and the contacts must be in the account cache; run a query or `prefetch` first.
+    Leaderboard {
+        /// Order by win-rate, games, kda (the contact's average KDA in the shared games), assists
+        /// (the contact's average assists as a teammate) or vision (how many more shared games the
+        /// contact had the higher vision score in than the self ID).
+        #[clap(long, value_name = "ORDER", default_value = "win-rate")]
+        by: crate::leaderboard::LeaderboardOrder,
+    },
+
+    /// Compare the champions two players play per role, from the local match index.
+    ///
+    /// Shows shared champions, draft conflicts within a role and whether the main roles complement each other.
+    ChampionPool {
+        /// Riot ID of player 1 (e.g., InGameName#GamerTag).
+        player1: RiotId,
+
+        /// Riot ID of player 2 (e.g., InGameName#GamerTag).
+        player2: RiotId,
+
+        /// Add champion mastery points from the API (two extra requests).
+        #[clap(long)]
+        mastery: bool,
+    },
+
+    /// Show who plays which role when a group of up to five plays together, from the local match index.
+    ///
+    /// For Clash planning: flags players who flex or share a main role and suggests the lineup with the
+    /// best record.
+    Lineup {
+        /// Riot IDs of the group (e.g., InGameName#GamerTag), the first one's indexed games are searched.
+        #[clap(required = true, num_args = 2..=5, value_name = "RIOT_ID")]
+        riot_ids: Vec<RiotId>,
+    },
+
+    /// Scout an enemy Clash roster from its players' recent matches.
+    ///
+    /// Reports how often the players queued together and how those games went, plus each player's
+    /// likely role and most played champions.
+    Scout {
+        /// Riot IDs of the roster (e.g., InGameName#GamerTag).
+        #[clap(required = true, num_args = 2..=5, value_name = "RIOT_ID")]
+        riot_ids: Vec<RiotId>,
+
+        /// Number of most recent matches to check per player.
+        #[clap(long, value_name = "COUNT", default_value_t = 20)]
+        max_matches: i32,
+
+        /// Print only the ranked ban suggestions, as text, json or markdown.
+        #[clap(long, value_name = "FORMAT")]
+        bans: Option<crate::scout::BanListFormat>,
+    },
+
+    /// Check whether Riot's Solo/Duo rank restrictions let two players queue together.
+    ///
+    /// Fetches both players' Solo/Duo ranks and reports how many divisions apart they are.
+    CanDuo {
+        /// Riot ID of player 1 (e.g., InGameName#GamerTag).
+        player1: RiotId,
+
+        /// Riot ID of player 2 (e.g., InGameName#GamerTag).
+        player2: RiotId,
+    },
+
+    /// Compare two duos' shared games over the same window, to settle which pair carries the group.
+    ///
+    /// Give the second duo after `--`, e.g. `ptg rivalry A#EUW B#EUW -- C#EUW D#EUW`. Shows games,
+    /// win rate, combined KDA and a synergy score side by side: the win rate together minus the
+    /// average win rate of both players without each other, which lists both players' matches.
+    Rivalry {
+        /// Riot IDs of the first duo (e.g., InGameName#GamerTag).
+        #[clap(required = true, num_args = 2, value_name = "RIOT_ID")]
+        first_pair: Vec<RiotId>,
+
+        /// Riot IDs of the second duo, after `--`.
+        #[clap(required = true, num_args = 2, last = true, value_name = "RIOT_ID")]
+        second_pair: Vec<RiotId>,
+    },
+
+    /// Diagnose common setup problems and print how to fix them.
+    ///
+    /// Checks the config file, API keys, network access to the Riot API, the local caches and the system clock.
+    Doctor,
+
+    /// Show extended help: every subcommand, the config keys, environment variables, exit codes or a man page.
+    ///
+    /// Without a topic, lists the topics. `ptg help-topics man > ptg.1` installs as a man page.
+    HelpTopics {
+        /// One of all, man, config, env, exit-codes.
+        topic: Option<HelpTopic>,
+    },
+
+    /// Update ptg to the latest GitHub release.
+    ///
+    /// Downloads the prebuilt binary for this platform, verifies its checksum and replaces the running executable.
+    /// Installs made with cargo should be updated with `cargo install --locked ptg` instead.
+    SelfUpdate {
+        /// Do not ask for confirmation before installing.
+        #[clap(short, long)]
+        yes: bool,
+    },
+
+    /// Manage how the configuration file is stored, or move the setup to another machine.
+    Config {
+        #[clap(subcommand)]
+        command: ConfigCommand,
+    },
+
+    /// Manage the local account cache and match index.
+    Cache {
+        #[clap(subcommand)]
+        command: CacheCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SiteCommand {
+    /// Render the site into DIR, creating it if needed and overwriting earlier builds.
+    Build {
+        /// Output directory, e.g. the `docs` folder published by GitHub Pages.
+        dir: std::path::PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ContactsCommand {
+    /// List every contact with its former Riot IDs.
+    List,
+
+    /// Look up every contact's current Riot ID by PUUID and record name changes.
+    Refresh,
+
+    /// Give a contact a nickname, shown next to their Riot ID in every output.
+    ///
+    /// Without a nickname, removes the stored one.
+    Nick {
+        #[clap(value_name = "RIOT_ID")]
+        riot_id: RiotId,
+
+        /// Nickname, e.g. "Bob".
+        nickname: Option<String>,
+    },
+
+    /// Store the region a contact plays on, used when a query with them as player1 gives no --region.
+    ///
+    /// Without a region, removes the stored one.
+    Region {
+        #[clap(value_name = "RIOT_ID")]
+        riot_id: RiotId,
+
+        /// Region, e.g. EUW.
+        #[clap(value_name = "REGION")]
+        home_region: Option<UserFacingRegion>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum FavCommand {
+    /// Add a player to the favorites.
+    Add {
+        #[clap(value_name = "RIOT_ID")]
+        riot_id: RiotId,
+    },
+
+    /// Remove a player from the favorites.
+    Remove {
+        #[clap(value_name = "RIOT_ID")]
+        riot_id: RiotId,
+    },
+
+    /// List the favorites.
+    List,
+
+    /// Check the self Riot ID against every favorite and print a compact summary per partner.
+    Check {
+        /// Number of most recent games to check.
+        #[clap(short, long, value_name = "COUNT")]
+        number: Option<i32>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Encrypt the stored API keys with a passphrase.
+    ///
+    /// The passphrase is asked for on every later run, or read from PTG_CONFIG_PASSPHRASE.
+    Encrypt,
+
+    /// Store the API keys in plaintext again.
+    Decrypt,
+
+    /// Write the config, contacts, favorites and match notes to one file, to move them to another machine.
+    ///
+    /// The API keys are left out unless --include-api-keys is given.
+    Export {
+        /// File to write, e.g. ptg-setup.json.
+        path: std::path::PathBuf,
+
+        /// Include the API keys, in plaintext.
+        #[clap(long)]
+        include_api_keys: bool,
+    },
+
+    /// Merge a file written by `config export` into the local setup.
+    ///
+    /// Settings in the file replace the local ones, everything it leaves unset is kept. Contacts and
+    /// notes are added to the local ones; contacts already known keep their local nickname.
+    Import {
+        /// File written by `config export`.
+        path: std::path::PathBuf,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CacheCommand {
+    /// Shrink the local caches to the retention limits.
+    ///
+    /// Limits come from the retention section of the config and can be overridden per run.
+    /// The oldest matches are dropped from the index first.
+    Prune {
+        /// Maximum size of the match index in megabytes.
+        #[clap(long, value_name = "MB")]
+        max_size_mb: Option<u64>,
+
+        /// Drop account cache entries older than this many days.
+        #[clap(long, value_name = "DAYS")]
+        max_age_days: Option<i64>,
+    },
+}
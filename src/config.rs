@@ -1,12 +1,43 @@
 use serde::{Deserialize, Serialize};
+use std::env;
 use std::error::Error;
 use std::fs;
-use std::path::PathBuf;
-use dirs; // For finding config directory
+use std::path::{Path, PathBuf};
 
 use crate::riot_id::{RiotId}; // Import RiotId from our new module
+use crate::cli::{MatchType, NotifyTarget, UserFacingRegion};
+use crate::secrets::{read_passphrase, EncryptedSecret};
 
-const CONFIG_FILE_NAME: &str = "config.json";
+const CONFIG_FILE_NAME: &str = "config.toml";
+/// Config file of earlier versions, still read and migrated to TOML on first load.
+const LEGACY_CONFIG_FILE_NAME: &str = "config.json";
+
+/// Written on top of every saved config file, since serializing drops hand-written comments.
+/// Its key table is also shown by `ptg help-topics config`.
+pub(crate) const CONFIG_FILE_HEADER: &str = "\
+# PlayedTogether configuration. Every key is optional.
+#
+# self_riot_id         Riot ID checked when only one Riot ID is given (set with --self).
+# self_region          Region of the self Riot ID, stored when --self is given together with --region.
+# api_key              Riot API key, used when RGAPI_KEY is not set (set with --api-key).
+# additional_api_keys  Further keys that requests are rotated between.
+# default_region       Region used when --region and --default-region are not given,
+#                      e.g. default_region = \"EUW\". Without it the region is auto-detected.
+# language             Data Dragon locale for champion names, e.g. language = \"ko_KR\".
+# max_window_days      Furthest back --auto-widen looks for matches, in days (365 by default).
+# [integrations]       Notification targets, webhooks and Google Sheets settings.
+# [retention]          max_cache_size_mb and max_account_age_days, enforced by `ptg cache prune`.
+# [network]            HTTP client tuning: http2, pool_max_idle_per_host, pool_idle_timeout_secs,
+#                      tcp_keepalive_secs, user_agent, base_url, lock_api_key and
+#                      api_key_rate_limits (per key, \"20:1,100:120\" by default).
+#
+# This header is rewritten whenever ptg saves the file; comments added elsewhere are not kept.
+
+";
+/// Subdirectory of the platform config and cache directories used by the tool.
+const APP_DIR_NAME: &str = "ptg";
+/// Directory under the platform config directory that held both config and caches in earlier versions.
+const LEGACY_APP_DIR_NAME: &str = "rito";
 
 /// Helper struct for `RiotId` to be `Serializable` and `Deserializable`
 /// because `FromStr` and `Display` traits aren't directly compatible with Serde.
@@ -30,43 +61,316 @@ impl From<RiotIdSerializable> for RiotId {
         RiotId {
             game_name: riot_id_s.game_name,
             tag_line: riot_id_s.tag_line,
+            puuid: None,
         }
     }
 }
 
 /// Configuration structure to store the "self" Riot ID.
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Config {
     pub self_riot_id: Option<RiotIdSerializable>,
+    /// Region the self Riot ID plays on, used when a query with it as player1 gives no region.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub self_region: Option<UserFacingRegion>,
     pub api_key: Option<String>,
+    /// Extra keys rotated together with `api_key` for heavy batch jobs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub additional_api_keys: Vec<String>,
+    /// Data Dragon locale champion names are shown in, e.g. "ko_KR". Canonical English keys are shown when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Region used when neither `--region` nor `--default-region` is given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_region: Option<UserFacingRegion>,
+    /// Match type the match list is limited to when `--match-type` is not given, e.g. "ranked".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_match_type: Option<MatchType>,
+    /// Furthest back `--auto-widen` looks when `--max-window-days` is not given, in days.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_window_days: Option<u32>,
+    /// The API keys encrypted by `config encrypt`; the plaintext fields stay empty on disk while it is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encrypted_api_keys: Option<EncryptedSecret>,
+    /// Passphrase the keys were decrypted with at load, used to encrypt them again on save.
+    #[serde(skip)]
+    passphrase: Option<String>,
+    #[serde(default)]
+    pub integrations: IntegrationsConfig,
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    #[serde(default)]
+    pub network: NetworkConfig,
+}
+
+/// HTTP client settings for the Riot API clients. Unset values keep the client's defaults.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct NetworkConfig {
+    /// Negotiate HTTP/2 when the server offers it (the default); `false` forces HTTP/1.1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http2: Option<bool>,
+    /// Maximum number of idle connections kept open per host.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool_max_idle_per_host: Option<usize>,
+    /// Seconds an idle pooled connection is kept before it is closed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool_idle_timeout_secs: Option<u64>,
+    /// Interval of TCP keep-alive probes in seconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tcp_keepalive_secs: Option<u64>,
+    /// User-Agent header sent with every request, `ptg/<version>` by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// Riot API base URL with `{}` standing for the route, e.g. for a proxy or a mock server.
+    /// Defaults to `https://{}.api.riotgames.com`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Share the rate limit of every API key between concurrent runs on one machine, so together
+    /// they stay within it. Off by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lock_api_key: Option<bool>,
+    /// Rate limits of each API key as Riot's `X-App-Rate-Limit` header lists them, e.g. "500:10,30000:600"
+    /// for a production key. Defaults to the limits of a development key, "20:1,100:120".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_rate_limits: Option<String>,
+}
+
+/// Limits applied by `cache prune`. Unset limits are not enforced.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RetentionConfig {
+    /// Maximum size of the match index in megabytes; the oldest matches are dropped first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_cache_size_mb: Option<u64>,
+    /// Account cache entries older than this many days are dropped and resolved again on next use.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_account_age_days: Option<i64>,
+}
+
+/// Settings for the optional third-party integrations.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct IntegrationsConfig {
+    /// Targets used by `--notify` when no `--notify-target` is given.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notify_targets: Vec<NotifyTarget>,
+    /// Path to a Google service-account key file, used by `--sheets`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub google_service_account_path: Option<PathBuf>,
+    /// A1-notation range rows are appended to. Defaults to the first sheet.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub google_sheets_range: Option<String>,
+    /// Bot token from @BotFather, used by `--notify-target telegram`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub telegram_bot_token: Option<String>,
+    /// Chat the Telegram bot posts into.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub telegram_chat_id: Option<String>,
+    /// Base URL of a self-hosted Telegram Bot API server, `https://api.telegram.org` by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub telegram_api_url: Option<String>,
+    /// Slack incoming-webhook URL, used by `--notify-target slack`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slack_webhook_url: Option<String>,
+    /// Discord channel webhook URL, used by `--notify-target discord`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub discord_webhook_url: Option<String>,
+    /// Endpoint that receives the raw JSON results, used by `--notify-target webhook`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+    /// Tera template rendering the webhook body; the raw JSON is sent when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_template: Option<PathBuf>,
+    /// Content-Type header of webhook requests, `application/json` by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_content_type: Option<String>,
 }
 
 impl Config {
-    /// Gets the path to the configuration file.
-    fn config_file_path() -> Result<PathBuf, Box<dyn Error>> {
+    /// Gets the directory holding the configuration file, creating it if needed.
+    /// Follows the platform convention, e.g. `$XDG_CONFIG_HOME/ptg` on Linux.
+    pub fn config_dir() -> Result<PathBuf, Box<dyn Error>> {
         let config_dir = dirs::config_dir()
             .ok_or("Could not find configuration directory.")?
-            .join("rito"); // Use your tool's name as a subdirectory
-        fs::create_dir_all(&config_dir)?; // Create the directory if it doesn't exist
-        Ok(config_dir.join(CONFIG_FILE_NAME))
+            .join(APP_DIR_NAME);
+        fs::create_dir_all(&config_dir)?;
+        Ok(config_dir)
+    }
+
+    /// Gets the directory holding the local caches (account cache, match index), creating it if needed.
+    /// Follows the platform convention, e.g. `$XDG_CACHE_HOME/ptg` on Linux.
+    pub fn cache_dir() -> Result<PathBuf, Box<dyn Error>> {
+        let cache_dir = dirs::cache_dir()
+            .ok_or("Could not find cache directory.")?
+            .join(APP_DIR_NAME);
+        fs::create_dir_all(&cache_dir)?;
+        Ok(cache_dir)
     }
 
-    /// Loads the configuration from the file.
+    /// Moves the files of the legacy `rito` directory to the config and cache directories.
+    /// Files already present at the new location are left alone.
+    fn migrate_legacy_dir() -> Result<(), Box<dyn Error>> {
+        let Some(legacy_dir) = dirs::config_dir().map(|dir| dir.join(LEGACY_APP_DIR_NAME)) else {
+            return Ok(());
+        };
+        if !legacy_dir.is_dir() {
+            return Ok(());
+        }
+        for entry in fs::read_dir(&legacy_dir)? {
+            let entry = entry?;
+            let target_dir = if entry.file_name() == LEGACY_CONFIG_FILE_NAME {
+                Self::config_dir()?
+            } else {
+                Self::cache_dir()?
+            };
+            let target = target_dir.join(entry.file_name());
+            if target.exists() {
+                continue;
+            }
+            if fs::rename(entry.path(), &target).is_err() {
+                // Renaming fails across file systems, e.g. when the cache directory is on another mount.
+                fs::copy(entry.path(), &target)?;
+                fs::remove_file(entry.path())?;
+            }
+        }
+        // Only succeeds once every file has been moved.
+        let _ = fs::remove_dir(&legacy_dir);
+        Ok(())
+    }
+
+    /// Gets the path to the configuration file.
+    fn config_file_path() -> Result<PathBuf, Box<dyn Error>> {
+        Ok(Self::config_dir()?.join(CONFIG_FILE_NAME))
+    }
+
+    /// Loads the configuration from the file, decrypting the API keys if they are encrypted.
+    /// A legacy `config.json` is converted to `config.toml` and kept as `config.json.bak`.
     pub fn load() -> Result<Self, Box<dyn Error>> {
+        Self::migrate_legacy_dir()?;
         let path = Self::config_file_path()?;
+        let legacy_path = Self::config_dir()?.join(LEGACY_CONFIG_FILE_NAME);
+        if !path.exists() && legacy_path.exists() {
+            let content = fs::read_to_string(&legacy_path)?;
+            let config: Config = serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to read {}: {}", legacy_path.display(), e))?;
+            config.write_to(&path)?;
+            fs::rename(&legacy_path, legacy_path.with_extension("json.bak"))?;
+        }
         if path.exists() {
             let content = fs::read_to_string(&path)?;
-            Ok(serde_json::from_str(&content)?)
+            let mut config: Config = toml::from_str(&content)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            if let Some(encrypted_api_keys) = &config.encrypted_api_keys {
+                let passphrase = read_passphrase(false)?;
+                let api_keys: Vec<String> = serde_json::from_slice(&encrypted_api_keys.decrypt(&passphrase)?)?;
+                let mut api_keys = api_keys.into_iter();
+                config.api_key = api_keys.next();
+                config.additional_api_keys = api_keys.collect();
+                config.passphrase = Some(passphrase);
+            }
+            Ok(config)
         } else {
             Ok(Config::default())
         }
     }
 
+    pub fn is_encrypted(&self) -> bool {
+        self.passphrase.is_some()
+    }
+
+    /// Encrypts the API keys with `passphrase` on every following save.
+    pub fn encrypt_with(&mut self, passphrase: String) {
+        self.passphrase = Some(passphrase);
+    }
+
+    /// Stores the API keys in plaintext again on the next save.
+    pub fn decrypt(&mut self) {
+        self.passphrase = None;
+        self.encrypted_api_keys = None;
+    }
+
+    /// Returns every stored API key, the primary one first.
+    pub fn api_keys(&self) -> Vec<String> {
+        self.api_key
+            .iter()
+            .chain(self.additional_api_keys.iter())
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the stored API keys, or the comma-separated keys of the RGAPI_KEY environment variable if none are stored.
+    pub fn effective_api_keys(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        if self.api_key.is_some() {
+            return Ok(self.api_keys());
+        }
+        Ok(env::var("RGAPI_KEY")
+            .map_err(|_| "Riot API key not found. Please set RGAPI_KEY environment variable, or store it using --api-key <YOUR_KEY>.")?
+            .split(',')
+            .map(|key| key.trim().to_string())
+            .filter(|key| !key.is_empty())
+            .collect())
+    }
+
+    /// A copy to move to another machine: the API keys are only kept in plaintext when `include_api_keys`,
+    /// and never in their encrypted form, since the passphrase does not travel with the file.
+    pub fn portable(&self, include_api_keys: bool) -> Config {
+        let mut portable = self.clone();
+        portable.encrypted_api_keys = None;
+        portable.passphrase = None;
+        if !include_api_keys {
+            portable.api_key = None;
+            portable.additional_api_keys.clear();
+        }
+        portable
+    }
+
+    /// Takes over every setting `imported` sets, section by section, and keeps the local value of
+    /// everything it leaves unset, so the API keys are only replaced when `imported` carries some.
+    pub fn merge(&mut self, imported: Config) -> Result<(), Box<dyn Error>> {
+        let mut merged = serde_json::to_value(&*self)?;
+        merge_json(&mut merged, serde_json::to_value(&imported)?);
+        let mut merged: Config = serde_json::from_value(merged)?;
+        merged.passphrase = self.passphrase.take();
+        *self = merged;
+        Ok(())
+    }
+
     /// Saves the configuration to the file.
     pub fn save(&self) -> Result<(), Box<dyn Error>> {
-        let path = Self::config_file_path()?;
-        let content = serde_json::to_string_pretty(self)?;
-        fs::write(&path, content)?;
+        self.write_to(&Self::config_file_path()?)
+    }
+
+    fn write_to(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let content = match &self.passphrase {
+            Some(passphrase) => {
+                let mut on_disk = self.clone();
+                let api_keys = serde_json::to_vec(&self.api_keys())?;
+                on_disk.encrypted_api_keys = Some(EncryptedSecret::encrypt(&api_keys, passphrase)?);
+                on_disk.api_key = None;
+                on_disk.additional_api_keys.clear();
+                toml::to_string_pretty(&on_disk)?
+            }
+            None => toml::to_string_pretty(self)?,
+        };
+        fs::write(path, format!("{}{}", CONFIG_FILE_HEADER, content))?;
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Copies every key of `imported` into `local`, descending into tables present on both sides.
+fn merge_json(local: &mut serde_json::Value, imported: serde_json::Value) {
+    match (local, imported) {
+        (serde_json::Value::Object(local), serde_json::Value::Object(imported)) => {
+            for (key, value) in imported {
+                if value.is_null() {
+                    continue;
+                }
+                match local.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        local.insert(key, value);
+                    }
+                }
+            }
+        }
+        (local, imported) => *local = imported,
+    }
+}
@@ -0,0 +1,51 @@
+//! Terminal compatibility: legacy Windows consoles show ANSI colors and the block characters of
+//! the charts as garbage. `setup` enables what the console supports and falls back to plain ASCII
+//! output without colors where it cannot, which `--ascii` also forces anywhere.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set once at startup, so every chart and box can pick its characters without an extra parameter.
+static ASCII: AtomicBool = AtomicBool::new(false);
+
+pub fn enable_ascii() {
+    ASCII.store(true, Ordering::Relaxed);
+}
+
+/// Whether output is limited to ASCII characters.
+pub fn is_ascii() -> bool {
+    ASCII.load(Ordering::Relaxed)
+}
+
+/// Prepares the console for ptg's output. On Windows, turns on ANSI escape sequences and the UTF-8
+/// code page; a console that refuses either gets ASCII output without colors.
+pub fn setup(force_ascii: bool) {
+    if force_ascii {
+        enable_ascii();
+    }
+    #[cfg(windows)]
+    windows::setup();
+}
+
+#[cfg(windows)]
+mod windows {
+    use windows_sys::Win32::Globalization::CP_UTF8;
+    use windows_sys::Win32::System::Console::SetConsoleOutputCP;
+
+    pub fn setup() {
+        // Fails on consoles older than Windows 10, which only understand plain text.
+        if colored::control::set_virtual_terminal(true).is_err() {
+            colored::control::set_override(false);
+            super::enable_ascii();
+        }
+        // Without the UTF-8 code page, non-ASCII output (and Riot IDs) is shown as mojibake.
+        // SAFETY: takes a plain code page number and only changes this console's output encoding.
+        if unsafe { SetConsoleOutputCP(CP_UTF8) } == 0 {
+            super::enable_ascii();
+        }
+    }
+}
+
+/// `unicode`, or `ascii` when output is limited to ASCII.
+pub fn pick<'a>(unicode: &'a str, ascii: &'a str) -> &'a str {
+    if is_ascii() { ascii } else { unicode }
+}
@@ -0,0 +1,309 @@
+use riven::consts::RegionalRoute;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::anonymize;
+use crate::api_client::{fetch_riot_id, resolve_puuid, ApiCallStats, OverallOutput, PlayerIdentity, RiotApiPool, ANY_ACCOUNT_ROUTE};
+use crate::cache::AccountCache;
+use crate::cli::UserFacingRegion;
+use crate::config::{Config, RiotIdSerializable};
+use crate::riot_id::RiotId;
+
+const CONTACTS_FILE_NAME: &str = "contacts.json";
+
+/// Nicknames by lowercase Riot ID, registered once at startup so every output can show them
+/// without threading the contact book through.
+static NICKNAMES: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+/// Makes the nicknames of `book` available to `nickname_of` and `display_name`.
+pub fn register_nicknames(book: &ContactBook) {
+    let nicknames = book
+        .contacts
+        .iter()
+        .filter_map(|contact| Some((contact.riot_id().to_string().to_lowercase(), contact.nickname.clone()?)))
+        .collect();
+    let _ = NICKNAMES.set(nicknames);
+}
+
+/// The contact nickname of `riot_id`; never set with `--anonymize`, since it could identify the player.
+pub fn nickname_of(riot_id: &RiotId) -> Option<String> {
+    if anonymize::is_enabled() {
+        return None;
+    }
+    NICKNAMES.get()?.get(&riot_id.to_string().to_lowercase()).cloned()
+}
+
+/// The Riot ID as shown in reports: "Nickname (Name#TAG)" for contacts with a nickname.
+pub fn display_name(riot_id: &RiotId) -> String {
+    match nickname_of(riot_id) {
+        Some(nickname) => format!("{} ({})", nickname, riot_id),
+        None => anonymize::riot_id(riot_id),
+    }
+}
+
+/// A player the user regularly checks against.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Contact {
+    /// The Riot ID the contact had at the last refresh.
+    pub riot_id: RiotIdSerializable,
+    /// Stays the same across Riot ID changes, so renames can be detected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub puuid: Option<String>,
+    /// Earlier Riot IDs, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub former_riot_ids: Vec<RiotIdSerializable>,
+    /// Included in `fav check`.
+    #[serde(default)]
+    pub favorite: bool,
+    /// Shown next to the Riot ID in every output.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nickname: Option<String>,
+    /// Region the contact plays on, used when a query with them as player1 gives no region.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<UserFacingRegion>,
+}
+
+impl Contact {
+    pub fn riot_id(&self) -> RiotId {
+        self.riot_id.clone().into()
+    }
+
+    /// Makes `current` the contact's Riot ID, remembering the previous one. Returns the previous
+    /// Riot ID if it changed.
+    fn rename(&mut self, current: RiotId) -> Option<RiotId> {
+        let previous = self.riot_id();
+        if previous.to_string().to_lowercase() == current.to_string().to_lowercase() {
+            return None;
+        }
+        self.former_riot_ids.retain(|former| RiotId::from(former.clone()).to_string().to_lowercase() != current.to_string().to_lowercase());
+        self.former_riot_ids.push(self.riot_id.clone());
+        self.riot_id = current.into();
+        Some(previous)
+    }
+}
+
+/// A contact whose Riot ID changed since the last refresh.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RiotIdChange {
+    pub previous: String,
+    pub current: String,
+}
+
+/// The user's contacts, stored in the config directory.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ContactBook {
+    contacts: Vec<Contact>,
+}
+
+impl ContactBook {
+    fn contacts_file_path() -> Result<PathBuf, Box<dyn Error>> {
+        Ok(Config::config_dir()?.join(CONTACTS_FILE_NAME))
+    }
+
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        let path = Self::contacts_file_path()?;
+        if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            Ok(serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?)
+        } else {
+            Ok(ContactBook::default())
+        }
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        fs::write(Self::contacts_file_path()?, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Finds a contact by Riot ID, ignoring case like the game does.
+    pub fn find(&self, riot_id: &RiotId) -> Option<&Contact> {
+        let wanted = riot_id.to_string().to_lowercase();
+        self.contacts
+            .iter()
+            .find(|contact| contact.riot_id().to_string().to_lowercase() == wanted)
+    }
+
+    /// Finds a contact by Riot ID, ignoring case like the game does.
+    pub fn find_mut(&mut self, riot_id: &RiotId) -> Option<&mut Contact> {
+        let wanted = riot_id.to_string().to_lowercase();
+        self.contacts
+            .iter_mut()
+            .find(|contact| contact.riot_id().to_string().to_lowercase() == wanted)
+    }
+
+    /// Returns the contact of `riot_id`, adding it first if it is not in the book yet. Given the
+    /// account's `puuid`, a contact stored under an earlier Riot ID is found and renamed instead.
+    pub fn get_or_insert(&mut self, riot_id: &RiotId, puuid: Option<&str>) -> &mut Contact {
+        let wanted = riot_id.to_string().to_lowercase();
+        let position = self
+            .contacts
+            .iter()
+            .position(|contact| puuid.is_some() && contact.puuid.as_deref() == puuid)
+            .or_else(|| self.contacts.iter().position(|contact| contact.riot_id().to_string().to_lowercase() == wanted));
+        let contact = match position {
+            Some(position) => &mut self.contacts[position],
+            None => {
+                self.contacts.push(Contact {
+                    riot_id: riot_id.clone().into(),
+                    puuid: None,
+                    former_riot_ids: Vec::new(),
+                    favorite: false,
+                    nickname: None,
+                    region: None,
+                });
+                self.contacts.last_mut().expect("contact was just inserted")
+            }
+        };
+        contact.rename(riot_id.clone());
+        if contact.puuid.is_none() {
+            contact.puuid = puuid.map(str::to_string);
+        }
+        contact
+    }
+
+    /// Brings the contacts up to date with the account cache: contacts without a PUUID get the one
+    /// cached for their Riot ID, and contacts whose PUUID is cached under another Riot ID are
+    /// renamed. This way a rename is picked up by any query that resolved the new Riot ID, not only
+    /// by `contacts refresh`. Returns the renames; the book changed if any PUUID was stored, too.
+    pub fn follow_account_cache(&mut self, account_cache: &AccountCache) -> (Vec<RiotIdChange>, bool) {
+        let mut changes = Vec::new();
+        let mut changed = false;
+        for contact in &mut self.contacts {
+            if contact.puuid.is_none() {
+                contact.puuid = account_cache.get(&contact.riot_id()).map(|account| account.puuid.clone());
+                changed |= contact.puuid.is_some();
+            }
+            let Some(account) = contact.puuid.as_deref().and_then(|puuid| account_cache.find_by_puuid(puuid)) else {
+                continue;
+            };
+            let current = RiotId { game_name: account.game_name.clone(), tag_line: account.tag_line.clone(), puuid: None };
+            if let Some(previous) = contact.rename(current.clone()) {
+                changes.push(RiotIdChange {
+                    previous: anonymize::riot_id(&previous),
+                    current: anonymize::riot_id(&current),
+                });
+            }
+        }
+        changed |= !changes.is_empty();
+        (changes, changed)
+    }
+
+    /// The stored region of `riot_id`, if it is a contact with one.
+    pub fn region_of(&self, riot_id: &RiotId) -> Option<UserFacingRegion> {
+        self.find(riot_id)?.region.clone()
+    }
+
+    pub fn all(&self) -> impl Iterator<Item = &Contact> {
+        self.contacts.iter()
+    }
+
+    pub fn favorites(&self) -> impl Iterator<Item = &Contact> {
+        self.contacts.iter().filter(|contact| contact.favorite)
+    }
+
+    /// Looks up every contact's current Riot ID by PUUID and records renames. Contacts without a
+    /// PUUID get one from their stored Riot ID first; the account cache is kept in sync. A contact
+    /// that cannot be looked up is warned about and skipped, so one failure does not hold up the rest.
+    pub async fn refresh_riot_ids(
+        &mut self,
+        riot_api: &RiotApiPool,
+        account_cache: &mut AccountCache,
+        regional_route: Option<RegionalRoute>,
+    ) -> Vec<RiotIdChange> {
+        let mut api_calls = ApiCallStats::default();
+        let mut changes = Vec::new();
+        for contact in &mut self.contacts {
+            let stored = contact.riot_id();
+            let route = regional_route
+                .or_else(|| account_cache.get(&stored).and_then(|account| account.regional_route()))
+                .unwrap_or(ANY_ACCOUNT_ROUTE);
+            if contact.puuid.is_none() {
+                match resolve_puuid(riot_api, account_cache, &stored, route, &mut api_calls).await {
+                    Ok(puuid) => contact.puuid = puuid,
+                    Err(e) => {
+                        eprintln!("Warning: Could not look up '{}': {}", anonymize::riot_id(&stored), e);
+                        continue;
+                    }
+                }
+            }
+            let Some(puuid) = contact.puuid.clone() else {
+                eprintln!("Warning: '{}' was not found. It may have been renamed before its PUUID was stored.", anonymize::riot_id(&stored));
+                continue;
+            };
+            let current = match fetch_riot_id(riot_api, route, &puuid, &mut api_calls).await {
+                Ok(Some(current)) => current,
+                Ok(None) => continue,
+                Err(e) => {
+                    eprintln!("Warning: Could not look up the current Riot ID of '{}': {}", anonymize::riot_id(&stored), e);
+                    continue;
+                }
+            };
+            if let Some(previous) = contact.rename(current.clone()) {
+                account_cache.insert(&current, &puuid, account_cache.get(&previous).and_then(|account| account.regional_route()));
+                changes.push(RiotIdChange {
+                    previous: anonymize::riot_id(&previous),
+                    current: anonymize::riot_id(&current),
+                });
+            }
+        }
+        changes
+    }
+
+    /// Adds the contacts of `imported`, merging those already in the book (same PUUID or Riot ID):
+    /// they stay favorites if either side marks them so, and keep their local nickname and Riot ID if set.
+    /// Returns how many contacts were added.
+    pub fn merge(&mut self, imported: ContactBook) -> usize {
+        let mut added = 0;
+        for contact in imported.contacts {
+            let existing = self.contacts.iter_mut().find(|existing| match (&existing.puuid, &contact.puuid) {
+                (Some(local), Some(imported)) => local == imported,
+                _ => existing.riot_id().to_string().to_lowercase() == contact.riot_id().to_string().to_lowercase(),
+            });
+            let Some(existing) = existing else {
+                self.contacts.push(contact);
+                added += 1;
+                continue;
+            };
+            existing.favorite |= contact.favorite;
+            if existing.nickname.is_none() {
+                existing.nickname = contact.nickname;
+            }
+            if existing.region.is_none() {
+                existing.region = contact.region;
+            }
+            if existing.puuid.is_none() {
+                existing.puuid = contact.puuid;
+            }
+            for former in contact.former_riot_ids {
+                let former_lowercase = RiotId::from(former.clone()).to_string().to_lowercase();
+                let known = existing.riot_id().to_string().to_lowercase() == former_lowercase
+                    || existing.former_riot_ids.iter().any(|f| RiotId::from(f.clone()).to_string().to_lowercase() == former_lowercase);
+                if !known {
+                    existing.former_riot_ids.push(former);
+                }
+            }
+        }
+        added
+    }
+
+    /// Adds the former Riot IDs of contacts to both players of a query result.
+    pub fn annotate(&self, output: &mut OverallOutput) {
+        let summary = &mut output.query_summary;
+        for player in [&mut summary.player1, &mut summary.player2] {
+            player.formerly_known_as = self.former_riot_ids_of(player);
+        }
+    }
+
+    fn former_riot_ids_of(&self, player: &PlayerIdentity) -> Vec<String> {
+        let riot_id = RiotId { game_name: player.game_name.clone(), tag_line: player.tag_line.clone(), puuid: None };
+        self.find(&riot_id)
+            .map(|contact| contact.former_riot_ids.iter().map(|former| RiotId::from(former.clone()).to_string()).collect())
+            .unwrap_or_default()
+    }
+}
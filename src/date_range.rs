@@ -0,0 +1,41 @@
+use chrono::{Days, Local, NaiveDate, TimeZone};
+use std::error::Error;
+
+/// A span of whole days in the user's local timezone, as Unix milliseconds (end exclusive).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DateRange {
+    pub start_ms: i64,
+    pub end_ms: i64,
+}
+
+fn local_midnight_ms(date: NaiveDate) -> Result<i64, Box<dyn Error>> {
+    let midnight = date.and_hms_opt(0, 0, 0).ok_or("Invalid date.")?;
+    // On days where a DST change skips midnight, `earliest` picks the first valid instant.
+    let local = Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .ok_or_else(|| format!("{} has no midnight in the local timezone.", date))?;
+    Ok(local.timestamp_millis())
+}
+
+impl DateRange {
+    /// From the start of `first` to the end of `last`, both included.
+    pub fn between(first: NaiveDate, last: NaiveDate) -> Result<Self, Box<dyn Error>> {
+        if last < first {
+            return Err(format!("The date range ends ({}) before it starts ({}).", last, first).into());
+        }
+        let day_after_last = last.checked_add_days(Days::new(1)).ok_or("Date out of range.")?;
+        Ok(DateRange {
+            start_ms: local_midnight_ms(first)?,
+            end_ms: local_midnight_ms(day_after_last)?,
+        })
+    }
+
+    pub fn on(day: NaiveDate) -> Result<Self, Box<dyn Error>> {
+        Self::between(day, day)
+    }
+
+    pub fn contains(&self, timestamp_ms: i64) -> bool {
+        (self.start_ms..self.end_ms).contains(&timestamp_ms)
+    }
+}
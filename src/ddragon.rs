@@ -0,0 +1,290 @@
+use chrono::Utc;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::api_client::{BuildEntry, OverallOutput, ParticipantDetails};
+use crate::config::Config;
+use crate::offline;
+
+const DDRAGON_BASE_URL: &str = "https://ddragon.leagueoflegends.com";
+const DDRAGON_CACHE_DIR_NAME: &str = "ddragon";
+const VERSIONS_FILE_NAME: &str = "versions.json";
+/// A new patch ships every two weeks, so the version list only needs an occasional refresh.
+const VERSIONS_MAX_AGE_SECS: i64 = 24 * 60 * 60;
+pub const DEFAULT_LOCALE: &str = "en_US";
+
+#[derive(Serialize, Deserialize)]
+struct CachedVersions {
+    fetched_at: i64,
+    versions: Vec<String>,
+}
+
+/// A champion as listed in Data Dragon's champion.json.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Champion {
+    /// Asset name, e.g. "MonkeyKing". Match-v5's champion_name uses the same value.
+    pub id: String,
+    /// Numeric champion ID as a string, e.g. "62".
+    pub key: String,
+    /// Display name in the loaded locale, e.g. "Wukong".
+    pub name: String,
+}
+
+#[derive(Deserialize)]
+struct ChampionFile {
+    data: HashMap<String, Champion>,
+}
+
+#[derive(Deserialize)]
+struct NamedEntry {
+    name: String,
+}
+
+/// Data Dragon's item.json, keyed by item ID.
+#[derive(Deserialize)]
+struct ItemFile {
+    data: HashMap<String, NamedEntry>,
+}
+
+/// A summoner spell as listed in Data Dragon's summoner.json.
+#[derive(Deserialize)]
+struct SummonerSpell {
+    /// Asset name, e.g. "SummonerFlash".
+    id: String,
+    /// Numeric spell ID as a string, e.g. "4"; match-v5's summoner1Id/summoner2Id.
+    key: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct SummonerSpellFile {
+    data: HashMap<String, SummonerSpell>,
+}
+
+/// A rune or rune tree as listed in Data Dragon's runesReforged.json.
+#[derive(Deserialize)]
+struct Rune {
+    id: i32,
+    name: String,
+    /// Image path below `/cdn/img/`, e.g. "perk-images/Styles/Precision/Conqueror/Conqueror.png".
+    icon: String,
+}
+
+#[derive(Deserialize)]
+struct RuneSlot {
+    runes: Vec<Rune>,
+}
+
+#[derive(Deserialize)]
+struct RuneTree {
+    #[serde(flatten)]
+    tree: Rune,
+    slots: Vec<RuneSlot>,
+}
+
+/// Static game data of one patch and locale, downloaded once and then served from the cache directory.
+pub struct DataDragon {
+    version: String,
+    champions: HashMap<String, Champion>,
+    champions_by_key: HashMap<String, String>,
+    item_names: HashMap<i32, String>,
+    /// Asset name and display name by numeric spell ID.
+    summoner_spells: HashMap<i32, (String, String)>,
+    /// Display name and icon path of every rune and rune tree by ID.
+    runes: HashMap<i32, (String, String)>,
+}
+
+fn cache_dir() -> Result<PathBuf, Box<dyn Error>> {
+    let dir = Config::cache_dir()?.join(DDRAGON_CACHE_DIR_NAME);
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+async fn fetch_json<T: serde::de::DeserializeOwned>(url: &str) -> Result<T, Box<dyn Error>> {
+    Ok(reqwest::get(url).await?.error_for_status()?.json().await?)
+}
+
+/// Returns the newest Data Dragon version (e.g. "14.20.1"), refreshing the cached version list once a day.
+/// A stale list is still used if Data Dragon cannot be reached.
+pub async fn latest_version() -> Result<String, Box<dyn Error>> {
+    let path = cache_dir()?.join(VERSIONS_FILE_NAME);
+    let cached: Option<CachedVersions> = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok());
+    if cached.is_none() {
+        offline::ensure_online("the Data Dragon version list")?;
+    }
+    let now = Utc::now().timestamp();
+    let versions = match cached {
+        Some(cached) if now - cached.fetched_at < VERSIONS_MAX_AGE_SECS || offline::is_enabled() => cached.versions,
+        cached => match fetch_json::<Vec<String>>(&format!("{}/api/versions.json", DDRAGON_BASE_URL)).await {
+            Ok(versions) => {
+                let fresh = CachedVersions { fetched_at: now, versions };
+                fs::write(&path, serde_json::to_string(&fresh)?)?;
+                fresh.versions
+            }
+            Err(e) => cached.map(|cached| cached.versions).ok_or(e)?,
+        },
+    };
+    versions
+        .into_iter()
+        .next()
+        .ok_or_else(|| "Data Dragon returned no versions.".into())
+}
+
+pub fn profile_icon_url(version: &str, profile_icon_id: i32) -> String {
+    format!("{}/cdn/{}/img/profileicon/{}.png", DDRAGON_BASE_URL, version, profile_icon_id)
+}
+
+/// The content of the Data Dragon data file `file_name` (e.g. "champion.json"), downloaded once per
+/// patch and locale.
+async fn data_file(version: &str, locale: &str, file_name: &str) -> Result<String, Box<dyn Error>> {
+    let dir = cache_dir()?.join(version).join(locale);
+    let path = dir.join(file_name);
+    if let Ok(content) = fs::read_to_string(&path) {
+        return Ok(content);
+    }
+    offline::ensure_online(&format!("the Data Dragon {} for locale '{}'", file_name, locale))?;
+    let url = format!("{}/cdn/{}/data/{}/{}", DDRAGON_BASE_URL, version, locale, file_name);
+    let content = reqwest::get(&url).await?.error_for_status()?.text().await
+        .map_err(|e| format!("Could not download Data Dragon data for locale '{}': {}", locale, e))?;
+    fs::create_dir_all(&dir)?;
+    fs::write(&path, &content)?;
+    Ok(content)
+}
+
+/// A data file that only names build entries. One that cannot be loaded leaves its entries
+/// unnamed, shown by ID, instead of failing the whole query.
+async fn optional_data_file<T: DeserializeOwned>(version: &str, locale: &str, file_name: &str) -> Option<T> {
+    let parsed: Result<T, Box<dyn Error>> = match data_file(version, locale, file_name).await {
+        Ok(content) => serde_json::from_str(&content).map_err(Into::into),
+        Err(e) => Err(e),
+    };
+    match parsed {
+        Ok(parsed) => Some(parsed),
+        Err(e) => {
+            eprintln!("Warning: Could not load the Data Dragon {} ({}); showing IDs instead.", file_name, e);
+            None
+        }
+    }
+}
+
+impl DataDragon {
+    /// Loads the champion, item and summoner spell data of the latest patch in `locale` (e.g. "en_US", "ko_KR").
+    pub async fn load(locale: &str) -> Result<Self, Box<dyn Error>> {
+        let version = latest_version().await?;
+        let champion_file: ChampionFile = serde_json::from_str(&data_file(&version, locale, "champion.json").await?)?;
+        let item_file: Option<ItemFile> = optional_data_file(&version, locale, "item.json").await;
+        let spell_file: Option<SummonerSpellFile> = optional_data_file(&version, locale, "summoner.json").await;
+        let rune_trees: Vec<RuneTree> = serde_json::from_str(&data_file(&version, locale, "runesReforged.json").await?)?;
+        let champions: HashMap<String, Champion> = champion_file
+            .data
+            .into_values()
+            .map(|champion| (champion.id.to_lowercase(), champion))
+            .collect();
+        let champions_by_key = champions
+            .values()
+            .map(|champion| (champion.key.clone(), champion.id.to_lowercase()))
+            .collect();
+        let item_names = item_file
+            .into_iter()
+            .flat_map(|item_file| item_file.data)
+            .filter_map(|(id, item)| Some((id.parse().ok()?, item.name)))
+            .collect();
+        let summoner_spells = spell_file
+            .into_iter()
+            .flat_map(|spell_file| spell_file.data.into_values())
+            .filter_map(|spell| Some((spell.key.parse().ok()?, (spell.id, spell.name))))
+            .collect();
+        let runes = rune_trees
+            .into_iter()
+            .flat_map(|tree| std::iter::once(tree.tree).chain(tree.slots.into_iter().flat_map(|slot| slot.runes)))
+            .map(|rune| (rune.id, (rune.name, rune.icon)))
+            .collect();
+        Ok(DataDragon { version, champions, champions_by_key, item_names, summoner_spells, runes })
+    }
+
+    /// Finds a champion by asset name (case-insensitive, as in match-v5's champion_name) or numeric ID.
+    pub fn champion(&self, name_or_key: &str) -> Option<&Champion> {
+        self.champions.get(&name_or_key.to_lowercase()).or_else(|| {
+            self.champions_by_key
+                .get(name_or_key)
+                .and_then(|id| self.champions.get(id))
+        })
+    }
+
+    /// The localized display name, or the input itself for champions newer than the loaded data.
+    pub fn display_name(&self, name_or_key: &str) -> String {
+        self.champion(name_or_key)
+            .map(|champion| champion.name.clone())
+            .unwrap_or_else(|| name_or_key.to_string())
+    }
+
+    pub fn champion_icon_url(&self, champion: &Champion) -> String {
+        format!("{}/cdn/{}/img/champion/{}.png", DDRAGON_BASE_URL, self.version, champion.id)
+    }
+
+    pub fn champion_splash_url(&self, champion: &Champion) -> String {
+        format!("{}/cdn/img/champion/splash/{}_0.jpg", DDRAGON_BASE_URL, champion.id)
+    }
+
+    /// The item's name, `None` for items newer than the loaded data.
+    pub fn item_name(&self, item_id: i32) -> Option<String> {
+        self.item_names.get(&item_id).cloned()
+    }
+
+    pub fn summoner_spell_name(&self, spell_id: i32) -> Option<String> {
+        self.summoner_spells.get(&spell_id).map(|(_, name)| name.clone())
+    }
+
+    /// The name of a rune or rune tree, e.g. "Conqueror" or "Precision".
+    pub fn rune_name(&self, rune_id: i32) -> Option<String> {
+        self.runes.get(&rune_id).map(|(name, _)| name.clone())
+    }
+
+    fn add_rune_assets(&self, rune: &mut BuildEntry) {
+        if let Some((name, icon)) = self.runes.get(&rune.id) {
+            rune.name = rune.name.take().or_else(|| Some(name.clone()));
+            rune.icon_url = Some(format!("{}/cdn/img/{}", DDRAGON_BASE_URL, icon));
+        }
+    }
+
+    fn add_participant_assets(&self, participant: &mut ParticipantDetails) {
+        if let Some(champion) = self.champion(&participant.champion) {
+            participant.champion_icon_url = Some(self.champion_icon_url(champion));
+            participant.champion_splash_url = Some(self.champion_splash_url(champion));
+        }
+        for item in &mut participant.items {
+            item.name = item.name.take().or_else(|| self.item_name(item.id));
+            item.icon_url = Some(format!("{}/cdn/{}/img/item/{}.png", DDRAGON_BASE_URL, self.version, item.id));
+        }
+        for spell in &mut participant.summoner_spells {
+            if let Some((asset, name)) = self.summoner_spells.get(&spell.id) {
+                spell.name = spell.name.take().or_else(|| Some(name.clone()));
+                spell.icon_url = Some(format!("{}/cdn/{}/img/spell/{}.png", DDRAGON_BASE_URL, self.version, asset));
+            }
+        }
+        if let Some(runes) = &mut participant.runes {
+            self.add_rune_assets(&mut runes.keystone);
+            self.add_rune_assets(&mut runes.primary_tree);
+            self.add_rune_assets(&mut runes.secondary_tree);
+        }
+    }
+
+    /// Fills in the champion icon and splash art URLs and the item, summoner spell and rune names and
+    /// icons of every found match.
+    pub fn add_assets(&self, output: &mut OverallOutput) {
+        for match_details in &mut output.found_matches {
+            self.add_participant_assets(&mut match_details.player1_details);
+            self.add_participant_assets(&mut match_details.player2_details);
+        }
+        for pair in &mut output.stats.keystones_together {
+            self.add_rune_assets(&mut pair.player1_keystone);
+            self.add_rune_assets(&mut pair.player2_keystone);
+        }
+    }
+}
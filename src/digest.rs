@@ -0,0 +1,215 @@
+use chrono::{Days, Local};
+use serde::Serialize;
+use std::error::Error;
+use tera::{Context, Tera};
+
+use crate::contacts;
+use crate::api_client::{run_query, QueryOptions, RiotApiPool};
+use crate::cache::AccountCache;
+use crate::cli::UserFacingRegion;
+use crate::date_range::DateRange;
+use crate::index::MatchIndex;
+use crate::riot_id::RiotId;
+use crate::stats::win_rate;
+
+/// Days covered by `digest --weekly`, today included.
+const WEEK_DAYS: u64 = 7;
+/// A week rarely has more games than this; the date range cuts the list down anyway.
+const DIGEST_MATCH_COUNT: i32 = 100;
+/// Fewer games than this cannot make a partner the duo of the week.
+const MIN_BEST_DUO_GAMES: u32 = 3;
+
+const MARKDOWN_TEMPLATE: &str = "\
+# Weekly digest for {{ player }}
+
+{{ periodStart }} to {{ periodEnd }}: {{ games }} games with favorites, {{ wins }} won.
+{% if bestDuo %}Duo of the week: **{{ bestDuo }}**
+{% endif %}
+| Partner | Games | Wins | Win rate |
+|---|---|---|---|
+{% for pair in pairs %}| {{ pair.partner }} | {{ pair.games }} | {{ pair.wins }} | {% if pair.games > 0 %}{{ pair.winRate | round }}%{% else %}-{% endif %} |
+{% endfor %}{% for pair in pairs %}{% for highlight in pair.highlights %}
+- {{ pair.partner }}: {{ highlight }}{% endfor %}{% endfor %}{% for partner in unchecked %}
+- Could not check {{ partner.partner }}: {{ partner.error }}{% endfor %}
+";
+
+const HTML_TEMPLATE: &str = "\
+<!DOCTYPE html>
+<html>
+<head><meta charset=\"utf-8\"><title>Weekly digest for {{ player }}</title></head>
+<body>
+<h1>Weekly digest for {{ player }}</h1>
+<p>{{ periodStart }} to {{ periodEnd }}: {{ games }} games with favorites, {{ wins }} won.</p>
+{% if bestDuo %}<p>Duo of the week: <strong>{{ bestDuo }}</strong></p>
+{% endif %}<table>
+<tr><th>Partner</th><th>Games</th><th>Wins</th><th>Win rate</th></tr>
+{% for pair in pairs %}<tr><td>{{ pair.partner }}</td><td>{{ pair.games }}</td><td>{{ pair.wins }}</td><td>{% if pair.games > 0 %}{{ pair.winRate | round }}%{% else %}-{% endif %}</td></tr>
+{% endfor %}</table>
+<ul>
+{% for pair in pairs %}{% for highlight in pair.highlights %}<li>{{ pair.partner }}: {{ highlight }}</li>
+{% endfor %}{% endfor %}{% for partner in unchecked %}<li>Could not check {{ partner.partner }}: {{ partner.error }}</li>
+{% endfor %}</ul>
+</body>
+</html>
+";
+
+/// Output formats of `digest`.
+#[derive(Debug, Clone, Copy)]
+pub enum DigestFormat {
+    Markdown,
+    Html,
+}
+
+impl std::str::FromStr for DigestFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "markdown" | "md" => Ok(DigestFormat::Markdown),
+            "html" => Ok(DigestFormat::Html),
+            _ => Err(format!("Invalid digest format: {}. Supported formats are markdown, html", s)),
+        }
+    }
+}
+
+pub struct DigestOptions {
+    pub self_riot_id: RiotId,
+    pub favorites: Vec<RiotId>,
+    pub user_selected_region: Option<UserFacingRegion>,
+}
+
+/// The week with one favorite partner.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DigestPair {
+    pub partner: String,
+    pub games: u32,
+    pub wins: u32,
+    /// In percent.
+    pub win_rate: f64,
+    /// Achievement descriptions of the week's shared games.
+    pub highlights: Vec<String>,
+}
+
+/// A favorite partner whose week could not be checked.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UncheckedPartner {
+    pub partner: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Digest {
+    pub player: String,
+    /// First and last local day covered, as YYYY-MM-DD.
+    pub period_start: String,
+    pub period_end: String,
+    /// Summed over the partners, so a game with two favorites counts twice.
+    pub games: u32,
+    pub wins: u32,
+    /// Best win rate among the partners with enough games.
+    pub best_duo: Option<String>,
+    /// Most games first.
+    pub pairs: Vec<DigestPair>,
+    #[serde(default)]
+    pub unchecked: Vec<UncheckedPartner>,
+}
+
+/// Summarizes the past week with every favorite partner. After the first partner, the self ID's
+/// matches come from the local index, so the digest costs little more than one regular query.
+/// A partner that cannot be checked is listed as unchecked; only when none can be is it an error.
+pub async fn run_weekly_digest(
+    riot_api: &RiotApiPool,
+    account_cache: &mut AccountCache,
+    match_index: &MatchIndex,
+    options: DigestOptions,
+) -> Result<Digest, Box<dyn Error>> {
+    let today = Local::now().date_naive();
+    let first_day = today.checked_sub_days(Days::new(WEEK_DAYS - 1)).ok_or("Date out of range.")?;
+    let date_range = DateRange::between(first_day, today)?;
+
+    let mut pairs = Vec::new();
+    let mut unchecked = Vec::new();
+    let mut first_error = None;
+    for favorite in options.favorites {
+        let result = run_query(
+            riot_api,
+            account_cache,
+            match_index,
+            QueryOptions {
+                player1_riot_id: options.self_riot_id.clone(),
+                player2_riot_id: favorite.clone(),
+                player3_riot_id: None,
+                match_type: None,
+                auto_widen_max_days: None,
+                queue_id: None,
+                date_range: Some(date_range),
+                regional_route: options.user_selected_region.as_ref().map(|r| r.to_regional_route()),
+                user_selected_region: options.user_selected_region.clone(),
+                number_of_matches: Some(DIGEST_MATCH_COUNT),
+                patch: None,
+                fetch_timelines: false,
+                fetch_profiles: false,
+                opponents_only: false,
+                follow_region_transfer: false,
+                stop_at_first_shared: false,
+                include_afk: false,
+                solo_baseline: false,
+                data_dragon: None,
+                verbose: false,
+                quiet: true,
+            },
+        ).await;
+        let partner = contacts::display_name(&favorite);
+        let output = match result {
+            Ok(output) => output,
+            Err(e) => {
+                unchecked.push(UncheckedPartner { partner, error: e.to_string() });
+                first_error.get_or_insert(e);
+                continue;
+            }
+        };
+        let summary = &output.query_summary;
+        pairs.push(DigestPair {
+            partner,
+            games: summary.matches_played_together_count,
+            wins: summary.player1_wins_together_count,
+            win_rate: win_rate(summary.player1_wins_together_count, summary.matches_played_together_count),
+            highlights: output.achievements.iter().map(|achievement| achievement.description.clone()).collect(),
+        });
+    }
+    if pairs.is_empty()
+        && let Some(e) = first_error
+    {
+        return Err(e);
+    }
+    pairs.sort_by(|a, b| b.games.cmp(&a.games).then_with(|| a.partner.cmp(&b.partner)));
+
+    let best_duo = pairs
+        .iter()
+        .filter(|pair| pair.games >= MIN_BEST_DUO_GAMES)
+        .max_by(|a, b| a.win_rate.total_cmp(&b.win_rate).then(a.games.cmp(&b.games)))
+        .map(|pair| pair.partner.clone());
+    Ok(Digest {
+        player: contacts::display_name(&options.self_riot_id),
+        period_start: first_day.to_string(),
+        period_end: today.to_string(),
+        games: pairs.iter().map(|pair| pair.games).sum(),
+        wins: pairs.iter().map(|pair| pair.wins).sum(),
+        best_duo,
+        pairs,
+        unchecked,
+    })
+}
+
+impl Digest {
+    pub fn render(&self, format: DigestFormat) -> Result<String, Box<dyn Error>> {
+        let context = Context::from_serialize(self)?;
+        Ok(match format {
+            DigestFormat::Markdown => Tera::one_off(MARKDOWN_TEMPLATE, &context, false)?,
+            DigestFormat::Html => Tera::one_off(HTML_TEMPLATE, &context, true)?,
+        })
+    }
+}
@@ -0,0 +1,145 @@
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+use riven::consts::PlatformRoute;
+use riven::RiotApi;
+use std::error::Error;
+use std::time::Duration;
+
+use crate::cache::AccountCache;
+use crate::config::Config;
+use crate::index::MatchIndex;
+
+/// Regional hosts whose reachability is checked.
+const RIOT_API_HOSTS: [&str; 3] = [
+    "https://americas.api.riotgames.com",
+    "https://europe.api.riotgames.com",
+    "https://asia.api.riotgames.com",
+];
+
+/// Clock differences above this break the "last 30 days" window and signed Google requests.
+const MAX_CLOCK_SKEW_SECS: i64 = 60;
+
+/// Prints the outcome of one check, with a suggested fix when it failed.
+fn report(ok: bool, check: &str, fix: &str) {
+    if ok {
+        println!("[{}] {}", " OK ".green(), check);
+    } else {
+        println!("[{}] {}", "FAIL".red(), check);
+        println!("       Fix: {}", fix);
+    }
+}
+
+fn mask_key(api_key: &str) -> String {
+    let visible: String = api_key.chars().rev().take(4).collect::<Vec<_>>().into_iter().rev().collect();
+    format!("...{}", visible)
+}
+
+/// Checks the configuration, API keys, network, local caches and clock, printing a fix for every problem.
+/// Returns an error if any check failed, so scripts can rely on the exit code.
+pub async fn run_doctor() -> Result<(), Box<dyn Error>> {
+    let mut failures = 0;
+
+    let config = match Config::load() {
+        Ok(config) => {
+            report(true, "Config file is valid.", "");
+            Some(config)
+        }
+        Err(e) => {
+            failures += 1;
+            report(false, &format!("Config file could not be loaded: {}", e), "Correct or delete config.toml in the ptg config directory.");
+            None
+        }
+    };
+
+    if let Some(config) = &config {
+        if config.self_riot_id.is_none() {
+            println!("[{}] No self Riot ID stored; every check needs two Riot IDs. Set one with `ptg --self <RIOT_ID>`.", "INFO".yellow());
+        }
+        match config.effective_api_keys() {
+            Ok(api_keys) if !api_keys.is_empty() => {
+                for api_key in api_keys {
+                    let result = RiotApi::new(api_key.as_str())
+                        .lol_status_v4()
+                        .get_platform_data(PlatformRoute::EUW1)
+                        .await;
+                    let status = result.as_ref().err().and_then(|e| e.status_code()).map(|s| s.as_u16());
+                    match (&result, status) {
+                        (Ok(_), _) => report(true, &format!("API key {} is valid.", mask_key(&api_key)), ""),
+                        (Err(_), Some(401 | 403)) => {
+                            failures += 1;
+                            report(
+                                false,
+                                &format!("API key {} was rejected by Riot.", mask_key(&api_key)),
+                                "Development keys expire every 24 hours. Get a new one at https://developer.riotgames.com and store it with `ptg --api-key <KEY>`.",
+                            );
+                        }
+                        (Err(e), _) => {
+                            failures += 1;
+                            report(false, &format!("API key {} could not be tested: {}", mask_key(&api_key), e), "Check the network checks below and try again.");
+                        }
+                    }
+                }
+            }
+            Ok(_) | Err(_) => {
+                failures += 1;
+                report(false, "No Riot API key found.", "Store one with `ptg --api-key <KEY>` or set the RGAPI_KEY environment variable.");
+            }
+        }
+    }
+
+    let http_client = reqwest::Client::builder().timeout(Duration::from_secs(10)).build()?;
+    let mut server_time: Option<DateTime<Utc>> = None;
+    for host in RIOT_API_HOSTS {
+        match http_client.get(host).send().await {
+            Ok(response) => {
+                server_time = server_time.or_else(|| {
+                    response
+                        .headers()
+                        .get(reqwest::header::DATE)
+                        .and_then(|date| date.to_str().ok())
+                        .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+                        .map(|date| date.with_timezone(&Utc))
+                });
+                report(true, &format!("{} is reachable.", host), "");
+            }
+            Err(e) => {
+                failures += 1;
+                report(false, &format!("{} is not reachable: {}", host, e), "Check your internet connection, proxy and firewall settings.");
+            }
+        }
+    }
+
+    match AccountCache::verify() {
+        Ok(accounts) => report(true, &format!("Account cache is readable ({} accounts).", accounts), ""),
+        Err(e) => {
+            failures += 1;
+            report(false, &format!("Account cache is corrupted: {}", e), "Delete accounts.json in the ptg cache directory; accounts are resolved again on the next run.");
+        }
+    }
+    match MatchIndex::open().and_then(|index| index.integrity_check()) {
+        Ok(()) => report(true, "Match index passed the integrity check.", ""),
+        Err(e) => {
+            failures += 1;
+            report(false, &format!("Match index is damaged: {}", e), "Delete index.sqlite in the ptg cache directory; matches are fetched again when needed.");
+        }
+    }
+
+    match server_time {
+        Some(server_time) => {
+            let skew = (Utc::now() - server_time).num_seconds();
+            if skew.abs() <= MAX_CLOCK_SKEW_SECS {
+                report(true, &format!("System clock is in sync ({:+}s).", skew), "");
+            } else {
+                failures += 1;
+                report(false, &format!("System clock is off by {:+}s.", skew), "Enable automatic time synchronization in your system settings.");
+            }
+        }
+        None => println!("[{}] Clock skew could not be checked without a server response.", "INFO".yellow()),
+    }
+
+    if failures > 0 {
+        return Err(format!("{} checks failed.", failures).into());
+    }
+    println!("Everything looks good.");
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use chrono::{Datelike, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::api_client::MatchDetails;
+use crate::console;
+
+const MILLIS_PER_DAY: i64 = 24 * 60 * 60 * 1000;
+/// Width of the longest bar in the terminal chart, in characters.
+const CHART_WIDTH: u32 = 40;
+
+/// Games together per calendar month (UTC), from the first shared game to the latest one.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuoTimeline {
+    /// Unix timestamp in milliseconds of the earliest shared game found.
+    pub first_game_timestamp: Option<i64>,
+    /// Every month of the span, including months without games.
+    pub months: Vec<MonthActivity>,
+    /// The month with the most games; the earliest one on ties.
+    pub busiest_month: Option<String>,
+    pub longest_gap: Option<GameGap>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonthActivity {
+    /// "YYYY-MM".
+    pub month: String,
+    pub games: u32,
+    pub player1_wins: u32,
+}
+
+/// The longest stretch between two consecutive shared games.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameGap {
+    pub days: i64,
+    pub from_timestamp: i64,
+    pub to_timestamp: i64,
+}
+
+fn year_month(timestamp_ms: i64) -> Option<(i32, u32)> {
+    let date = Utc.timestamp_millis_opt(timestamp_ms).single()?;
+    Some((date.year(), date.month()))
+}
+
+impl DuoTimeline {
+    pub fn from_matches(matches: &[MatchDetails]) -> Self {
+        let mut game_starts: Vec<(i64, bool)> = matches
+            .iter()
+            .map(|m| (m.game_start_timestamp, m.player1_details.outcome == "Victory"))
+            .collect();
+        game_starts.sort_by_key(|(timestamp, _)| *timestamp);
+        let (Some(first), Some(last)) = (game_starts.first(), game_starts.last()) else {
+            return DuoTimeline::default();
+        };
+        let (Some(mut current), Some(end)) = (year_month(first.0), year_month(last.0)) else {
+            return DuoTimeline::default();
+        };
+
+        let mut months = Vec::new();
+        loop {
+            let in_month = game_starts.iter().filter(|(timestamp, _)| year_month(*timestamp) == Some(current));
+            let (games, player1_wins) = in_month.fold((0, 0), |(games, wins), (_, won)| (games + 1, wins + *won as u32));
+            months.push(MonthActivity { month: format!("{:04}-{:02}", current.0, current.1), games, player1_wins });
+            if current == end {
+                break;
+            }
+            current = if current.1 == 12 { (current.0 + 1, 1) } else { (current.0, current.1 + 1) };
+        }
+
+        let busiest_month = months
+            .iter()
+            .fold(None::<&MonthActivity>, |busiest, month| match busiest {
+                Some(busiest) if busiest.games >= month.games => Some(busiest),
+                _ => Some(month),
+            })
+            .map(|month| month.month.clone());
+        let longest_gap = game_starts
+            .windows(2)
+            .map(|pair| (pair[0].0, pair[1].0))
+            .max_by_key(|(from, to)| to - from)
+            .map(|(from_timestamp, to_timestamp)| GameGap {
+                days: (to_timestamp - from_timestamp) / MILLIS_PER_DAY,
+                from_timestamp,
+                to_timestamp,
+            });
+
+        DuoTimeline { first_game_timestamp: Some(first.0), months, busiest_month, longest_gap }
+    }
+
+    /// Prints one bar per month, scaled to the busiest month.
+    pub fn print_chart(&self) {
+        println!("\n--- Duo Timeline ---");
+        let Some(first_game_timestamp) = self.first_game_timestamp else {
+            println!("No games found together.");
+            return;
+        };
+        let max_games = self.months.iter().map(|month| month.games).max().unwrap_or(0).max(1);
+        for month in &self.months {
+            let bar_length = (month.games * CHART_WIDTH).div_ceil(max_games);
+            println!("{} {:<width$} {}", month.month, console::pick("█", "#").repeat(bar_length as usize), month.games, width = CHART_WIDTH as usize);
+        }
+        if let Some(first_game) = Utc.timestamp_millis_opt(first_game_timestamp).single() {
+            println!("First game together: {}", first_game.format("%Y-%m-%d"));
+        }
+        if let Some(busiest_month) = &self.busiest_month {
+            println!("Busiest month: {}", busiest_month);
+        }
+        if let Some(gap) = &self.longest_gap {
+            println!("Longest gap between games: {} days", gap.days);
+        }
+    }
+}
@@ -0,0 +1,386 @@
+use chrono::{DateTime, Utc};
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, Int32Type, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use rusqlite::{params, Connection};
+use std::error::Error;
+use std::fs::{self, File};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::api_client::{MatchDetails, OverallOutput, ParticipantDetails, PlayerIdentity};
+use crate::card::render_summary_card;
+
+/// File formats accepted by `--export <FORMAT> <PATH>`.
+#[derive(Debug, Clone, Copy)]
+pub enum ExportFormat {
+    Sqlite,
+    Parquet,
+    Ics,
+    Png,
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sqlite" => Ok(ExportFormat::Sqlite),
+            "parquet" => Ok(ExportFormat::Parquet),
+            "ics" | "ical" => Ok(ExportFormat::Ics),
+            "png" => Ok(ExportFormat::Png),
+            _ => Err(format!("Invalid export format: {}. Supported formats are sqlite, parquet, ics, png", s)),
+        }
+    }
+}
+
+/// Writes the query results to `path` in the given format.
+pub fn export(output: &OverallOutput, format: ExportFormat, path: &Path) -> Result<(), Box<dyn Error>> {
+    match format {
+        ExportFormat::Sqlite => export_sqlite(output, path),
+        ExportFormat::Parquet => export_parquet(output, path),
+        ExportFormat::Ics => export_ics(output, path),
+        ExportFormat::Png => Ok(fs::write(path, render_summary_card(output)?)?),
+    }
+}
+
+const SQLITE_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS query_runs (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        run_at TEXT NOT NULL,
+        player1 TEXT NOT NULL,
+        player2 TEXT NOT NULL,
+        regional_route TEXT NOT NULL,
+        checked_matches INTEGER NOT NULL,
+        matches_together INTEGER NOT NULL,
+        player1_wins INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS matches (
+        match_id TEXT PRIMARY KEY,
+        game_date_utc TEXT NOT NULL,
+        game_mode TEXT NOT NULL,
+        game_type TEXT,
+        queue_id INTEGER NOT NULL,
+        game_version TEXT NOT NULL,
+        surrendered INTEGER NOT NULL,
+        gold_diff_at15 INTEGER,
+        game_flow TEXT,
+        same_team INTEGER NOT NULL,
+        league_of_graphs_link TEXT
+    );
+    CREATE TABLE IF NOT EXISTS participants (
+        match_id TEXT NOT NULL REFERENCES matches(match_id),
+        riot_id TEXT NOT NULL,
+        champion TEXT NOT NULL,
+        role TEXT NOT NULL,
+        kills INTEGER NOT NULL,
+        deaths INTEGER NOT NULL,
+        assists INTEGER NOT NULL,
+        outcome TEXT NOT NULL,
+        PRIMARY KEY (match_id, riot_id)
+    );
+    CREATE TABLE IF NOT EXISTS query_run_matches (
+        run_id INTEGER NOT NULL REFERENCES query_runs(id),
+        match_id TEXT NOT NULL REFERENCES matches(match_id),
+        PRIMARY KEY (run_id, match_id)
+    );
+";
+
+fn riot_id_of(player: &PlayerIdentity) -> String {
+    format!("{}#{}", player.game_name, player.tag_line)
+}
+
+/// Appends the run to a SQLite database, creating the tables on first use.
+/// Matches and participants are upserted, so repeated runs accumulate without duplicates.
+fn export_sqlite(output: &OverallOutput, path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut connection = Connection::open(path)?;
+    connection.execute_batch(SQLITE_SCHEMA)?;
+
+    let transaction = connection.transaction()?;
+    let summary = &output.query_summary;
+    transaction.execute(
+        "INSERT INTO query_runs (run_at, player1, player2, regional_route, checked_matches, matches_together, player1_wins)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            Utc::now().to_rfc3339(),
+            riot_id_of(&summary.player1),
+            riot_id_of(&summary.player2),
+            summary.regional_route,
+            summary.checked_matches_count,
+            summary.matches_played_together_count,
+            summary.player1_wins_together_count,
+        ],
+    )?;
+    let run_id = transaction.last_insert_rowid();
+
+    for match_details in &output.found_matches {
+        insert_match(&transaction, match_details)?;
+        for (player, details) in [
+            (&summary.player1, &match_details.player1_details),
+            (&summary.player2, &match_details.player2_details),
+        ] {
+            transaction.execute(
+                "INSERT OR REPLACE INTO participants (match_id, riot_id, champion, role, kills, deaths, assists, outcome)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    match_details.match_id,
+                    riot_id_of(player),
+                    details.champion,
+                    details.role,
+                    details.kills,
+                    details.deaths,
+                    details.assists,
+                    details.outcome,
+                ],
+            )?;
+        }
+        transaction.execute(
+            "INSERT OR IGNORE INTO query_run_matches (run_id, match_id) VALUES (?1, ?2)",
+            params![run_id, match_details.match_id],
+        )?;
+    }
+    transaction.commit()?;
+    Ok(())
+}
+
+fn insert_match(connection: &Connection, match_details: &MatchDetails) -> rusqlite::Result<usize> {
+    connection.execute(
+        "INSERT OR REPLACE INTO matches (match_id, game_date_utc, game_mode, game_type, queue_id, game_version,
+             surrendered, gold_diff_at15, game_flow, same_team, league_of_graphs_link)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            match_details.match_id,
+            match_details.game_date_utc,
+            match_details.game_mode,
+            match_details.game_type,
+            match_details.queue_id,
+            match_details.game_version,
+            match_details.surrendered,
+            match_details.gold_diff_at15,
+            match_details.game_flow.map(|flow| flow.to_string()),
+            match_details.same_team,
+            match_details.league_of_graphs_link,
+        ],
+    )
+}
+
+const PARQUET_MATCHES_SCHEMA: &str = "
+    message matches {
+        REQUIRED BYTE_ARRAY match_id (UTF8);
+        REQUIRED INT64 game_start (TIMESTAMP(MILLIS, true));
+        REQUIRED INT64 game_duration_secs;
+        REQUIRED BYTE_ARRAY game_mode (UTF8);
+        OPTIONAL BYTE_ARRAY game_type (UTF8);
+        REQUIRED INT32 queue_id;
+        REQUIRED BYTE_ARRAY game_version (UTF8);
+        REQUIRED BOOLEAN surrendered;
+        OPTIONAL INT32 gold_diff_at15;
+        OPTIONAL BYTE_ARRAY game_flow (UTF8);
+        REQUIRED BOOLEAN same_team;
+        REQUIRED BOOLEAN same_champion;
+        REQUIRED BOOLEAN mirror_roles;
+        OPTIONAL BYTE_ARRAY league_of_graphs_link (UTF8);
+    }
+";
+
+const PARQUET_PARTICIPANTS_SCHEMA: &str = "
+    message participants {
+        REQUIRED BYTE_ARRAY match_id (UTF8);
+        REQUIRED INT32 player;
+        REQUIRED BYTE_ARRAY riot_id (UTF8);
+        REQUIRED BYTE_ARRAY champion (UTF8);
+        REQUIRED BYTE_ARRAY role (UTF8);
+        REQUIRED INT32 kills;
+        REQUIRED INT32 deaths;
+        REQUIRED INT32 assists;
+        REQUIRED BOOLEAN win;
+    }
+";
+
+/// One column of a Parquet table, in schema order.
+enum ParquetColumn {
+    Utf8(Vec<String>),
+    OptionalUtf8(Vec<Option<String>>),
+    Int32(Vec<i32>),
+    OptionalInt32(Vec<Option<i32>>),
+    Int64(Vec<i64>),
+    Boolean(Vec<bool>),
+}
+
+/// Definition levels for an optional column: 1 for present values, 0 for nulls.
+fn definition_levels<T>(values: &[Option<T>]) -> Vec<i16> {
+    values.iter().map(|value| value.is_some() as i16).collect()
+}
+
+fn write_parquet_file(path: &Path, schema: &str, columns: Vec<ParquetColumn>) -> Result<(), Box<dyn Error>> {
+    let schema = Arc::new(parse_message_type(schema)?);
+    let properties = Arc::new(WriterProperties::builder().build());
+    let mut writer = SerializedFileWriter::new(File::create(path)?, schema, properties)?;
+    let mut row_group = writer.next_row_group()?;
+
+    for column in columns {
+        let mut column_writer = row_group.next_column()?.ok_or("Parquet schema has fewer columns than the data.")?;
+        match column {
+            ParquetColumn::Utf8(values) => {
+                let values: Vec<ByteArray> = values.iter().map(|v| ByteArray::from(v.as_str())).collect();
+                column_writer.typed::<ByteArrayType>().write_batch(&values, None, None)?;
+            }
+            ParquetColumn::OptionalUtf8(values) => {
+                let levels = definition_levels(&values);
+                let present: Vec<ByteArray> = values.iter().flatten().map(|v| ByteArray::from(v.as_str())).collect();
+                column_writer.typed::<ByteArrayType>().write_batch(&present, Some(&levels), None)?;
+            }
+            ParquetColumn::Int32(values) => {
+                column_writer.typed::<Int32Type>().write_batch(&values, None, None)?;
+            }
+            ParquetColumn::OptionalInt32(values) => {
+                let levels = definition_levels(&values);
+                let present: Vec<i32> = values.iter().flatten().copied().collect();
+                column_writer.typed::<Int32Type>().write_batch(&present, Some(&levels), None)?;
+            }
+            ParquetColumn::Int64(values) => {
+                column_writer.typed::<Int64Type>().write_batch(&values, None, None)?;
+            }
+            ParquetColumn::Boolean(values) => {
+                column_writer.typed::<BoolType>().write_batch(&values, None, None)?;
+            }
+        }
+        column_writer.close()?;
+    }
+
+    row_group.close()?;
+    writer.close()?;
+    Ok(())
+}
+
+/// Writes `matches.parquet` and `participants.parquet` into the directory at `path`.
+fn export_parquet(output: &OverallOutput, path: &Path) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(path)?;
+    let matches = &output.found_matches;
+
+    write_parquet_file(
+        &path.join("matches.parquet"),
+        PARQUET_MATCHES_SCHEMA,
+        vec![
+            ParquetColumn::Utf8(matches.iter().map(|m| m.match_id.clone()).collect()),
+            ParquetColumn::Int64(matches.iter().map(|m| m.game_start_timestamp).collect()),
+            ParquetColumn::Int64(matches.iter().map(|m| m.game_duration_secs).collect()),
+            ParquetColumn::Utf8(matches.iter().map(|m| m.game_mode.clone()).collect()),
+            ParquetColumn::OptionalUtf8(matches.iter().map(|m| m.game_type.clone()).collect()),
+            ParquetColumn::Int32(matches.iter().map(|m| m.queue_id as i32).collect()),
+            ParquetColumn::Utf8(matches.iter().map(|m| m.game_version.clone()).collect()),
+            ParquetColumn::Boolean(matches.iter().map(|m| m.surrendered).collect()),
+            ParquetColumn::OptionalInt32(matches.iter().map(|m| m.gold_diff_at15).collect()),
+            ParquetColumn::OptionalUtf8(matches.iter().map(|m| m.game_flow.map(|flow| flow.to_string())).collect()),
+            ParquetColumn::Boolean(matches.iter().map(|m| m.same_team).collect()),
+            ParquetColumn::Boolean(matches.iter().map(|m| m.same_champion).collect()),
+            ParquetColumn::Boolean(matches.iter().map(|m| m.mirror_roles).collect()),
+            ParquetColumn::OptionalUtf8(matches.iter().map(|m| m.league_of_graphs_link.clone()).collect()),
+        ],
+    )?;
+
+    let summary = &output.query_summary;
+    let participants: Vec<(&MatchDetails, i32, String, &ParticipantDetails)> = matches
+        .iter()
+        .flat_map(|m| {
+            [
+                (m, 1, riot_id_of(&summary.player1), &m.player1_details),
+                (m, 2, riot_id_of(&summary.player2), &m.player2_details),
+            ]
+        })
+        .collect();
+
+    write_parquet_file(
+        &path.join("participants.parquet"),
+        PARQUET_PARTICIPANTS_SCHEMA,
+        vec![
+            ParquetColumn::Utf8(participants.iter().map(|(m, ..)| m.match_id.clone()).collect()),
+            ParquetColumn::Int32(participants.iter().map(|(_, player, ..)| *player).collect()),
+            ParquetColumn::Utf8(participants.iter().map(|(_, _, riot_id, _)| riot_id.clone()).collect()),
+            ParquetColumn::Utf8(participants.iter().map(|(.., p)| p.champion.clone()).collect()),
+            ParquetColumn::Utf8(participants.iter().map(|(.., p)| p.role.clone()).collect()),
+            ParquetColumn::Int32(participants.iter().map(|(.., p)| p.kills).collect()),
+            ParquetColumn::Int32(participants.iter().map(|(.., p)| p.deaths).collect()),
+            ParquetColumn::Int32(participants.iter().map(|(.., p)| p.assists).collect()),
+            ParquetColumn::Boolean(participants.iter().map(|(.., p)| p.outcome == "Victory").collect()),
+        ],
+    )
+}
+
+/// RFC 5545 lines longer than this many octets must be folded.
+const ICS_LINE_LIMIT: usize = 75;
+
+fn ics_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn ics_time(timestamp_ms: i64) -> String {
+    DateTime::from_timestamp_millis(timestamp_ms)
+        .unwrap_or_default()
+        .format("%Y%m%dT%H%M%SZ")
+        .to_string()
+}
+
+/// Appends `line` with CRLF, folded into continuation lines without splitting a character.
+fn push_ics_line(calendar: &mut String, line: &str) {
+    let mut octets = 0;
+    for character in line.chars() {
+        if octets + character.len_utf8() > ICS_LINE_LIMIT {
+            calendar.push_str("\r\n ");
+            octets = 1;
+        }
+        calendar.push(character);
+        octets += character.len_utf8();
+    }
+    calendar.push_str("\r\n");
+}
+
+/// Writes one calendar event per shared match, spanning the game, to an iCalendar file.
+fn export_ics(output: &OverallOutput, path: &Path) -> Result<(), Box<dyn Error>> {
+    let summary = &output.query_summary;
+    let mut calendar = String::new();
+    push_ics_line(&mut calendar, "BEGIN:VCALENDAR");
+    push_ics_line(&mut calendar, "VERSION:2.0");
+    push_ics_line(&mut calendar, "PRODID:-//PlayedTogether//ptg//EN");
+    let stamp = ics_time(Utc::now().timestamp_millis());
+    for match_details in &output.found_matches {
+        let (p1, p2) = (&match_details.player1_details, &match_details.player2_details);
+        let start = match_details.game_start_timestamp;
+        let description = format!(
+            "{} ({}) {}/{}/{}\n{} ({}) {}/{}/{}\nDuration: {}:{:02}",
+            riot_id_of(&summary.player1),
+            p1.champion_label(),
+            p1.kills,
+            p1.deaths,
+            p1.assists,
+            riot_id_of(&summary.player2),
+            p2.champion_label(),
+            p2.kills,
+            p2.deaths,
+            p2.assists,
+            match_details.game_duration_secs / 60,
+            match_details.game_duration_secs % 60,
+        );
+        push_ics_line(&mut calendar, "BEGIN:VEVENT");
+        push_ics_line(&mut calendar, &format!("UID:{}@playedtogether", match_details.match_id));
+        push_ics_line(&mut calendar, &format!("DTSTAMP:{}", stamp));
+        push_ics_line(&mut calendar, &format!("DTSTART:{}", ics_time(start)));
+        push_ics_line(&mut calendar, &format!("DTEND:{}", ics_time(start + match_details.game_duration_secs * 1000)));
+        push_ics_line(&mut calendar, &format!(
+            "SUMMARY:{}",
+            ics_text(&format!("{}: {} & {} ({})", p1.outcome, p1.champion_label(), p2.champion_label(), match_details.game_mode))
+        ));
+        push_ics_line(&mut calendar, &format!("DESCRIPTION:{}", ics_text(&description)));
+        if let Some(link) = &match_details.league_of_graphs_link {
+            push_ics_line(&mut calendar, &format!("URL:{}", link));
+        }
+        push_ics_line(&mut calendar, "END:VEVENT");
+    }
+    push_ics_line(&mut calendar, "END:VCALENDAR");
+    fs::write(path, calendar)?;
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use serde::Serialize;
+use std::error::Error;
+
+use crate::contacts;
+use crate::api_client::{run_query, QueryOptions, RiotApiPool};
+use crate::cache::AccountCache;
+use crate::cli::UserFacingRegion;
+use crate::index::MatchIndex;
+use crate::riot_id::RiotId;
+use crate::stats::win_rate;
+
+pub struct FavCheckOptions {
+    pub self_riot_id: RiotId,
+    pub favorites: Vec<RiotId>,
+    pub user_selected_region: Option<UserFacingRegion>,
+    pub number_of_matches: Option<i32>,
+}
+
+/// Compact result of one favorite partner check.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FavoriteSummary {
+    pub partner: String,
+    pub checked_matches_count: u32,
+    pub matches_played_together_count: u32,
+    pub wins_together_count: u32,
+    /// In percent.
+    pub win_rate: f64,
+    /// Why this partner could not be checked; the counts are zero then.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Checks the self ID against every favorite in turn. Player1's match history is the same for every
+/// partner, so after the first check the matches come from the local index instead of the API.
+/// A partner that cannot be checked gets a summary with the error instead of ending the check.
+pub async fn run_fav_check(
+    riot_api: &RiotApiPool,
+    account_cache: &mut AccountCache,
+    match_index: &MatchIndex,
+    options: FavCheckOptions,
+) -> Result<Vec<FavoriteSummary>, Box<dyn Error>> {
+    let mut summaries = Vec::new();
+    for favorite in options.favorites {
+        let result = run_query(
+            riot_api,
+            account_cache,
+            match_index,
+            QueryOptions {
+                player1_riot_id: options.self_riot_id.clone(),
+                player2_riot_id: favorite.clone(),
+                player3_riot_id: None,
+                match_type: None,
+                auto_widen_max_days: None,
+                queue_id: None,
+                date_range: None,
+                regional_route: options.user_selected_region.as_ref().map(|r| r.to_regional_route()),
+                user_selected_region: options.user_selected_region.clone(),
+                number_of_matches: options.number_of_matches,
+                patch: None,
+                fetch_timelines: false,
+                fetch_profiles: false,
+                opponents_only: false,
+                follow_region_transfer: false,
+                stop_at_first_shared: false,
+                include_afk: false,
+                solo_baseline: false,
+                data_dragon: None,
+                verbose: false,
+                quiet: true,
+            },
+        ).await;
+        let partner = contacts::display_name(&favorite);
+        summaries.push(match result {
+            Ok(output) => {
+                let summary = &output.query_summary;
+                FavoriteSummary {
+                    partner,
+                    checked_matches_count: summary.checked_matches_count,
+                    matches_played_together_count: summary.matches_played_together_count,
+                    wins_together_count: summary.player1_wins_together_count,
+                    win_rate: win_rate(summary.player1_wins_together_count, summary.matches_played_together_count),
+                    error: None,
+                }
+            }
+            Err(e) => FavoriteSummary {
+                partner,
+                checked_matches_count: 0,
+                matches_played_together_count: 0,
+                wins_together_count: 0,
+                win_rate: 0.0,
+                error: Some(e.to_string()),
+            },
+        });
+    }
+    Ok(summaries)
+}
+
+pub fn print_fav_summaries(summaries: &[FavoriteSummary]) {
+    println!("\n--- Favorites ---");
+    let width = summaries.iter().map(|s| s.partner.chars().count()).max().unwrap_or(0);
+    for summary in summaries {
+        if let Some(error) = &summary.error {
+            println!("{:<width$}  could not be checked: {}", summary.partner, error, width = width);
+            continue;
+        }
+        let win_rate = if summary.matches_played_together_count == 0 {
+            "-".to_string()
+        } else {
+            format!("{:.0}%", summary.win_rate)
+        };
+        println!(
+            "{:<width$}  {:>3} of {:>3} games together, {:>3} wins ({})",
+            summary.partner,
+            summary.matches_played_together_count,
+            summary.checked_matches_count,
+            summary.wins_together_count,
+            win_rate,
+            width = width
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! Extended help: `--help-all` and `ptg help-topics`, including a man page generated with
+//! clap_mangen, so the subcommands, config keys, environment variables and exit codes are
+//! documented in one place.
+
+use clap::{Command, CommandFactory};
+use clap_mangen::Man;
+use roff::{bold, roman, Roff};
+
+use crate::cli::Cli;
+use crate::config::CONFIG_FILE_HEADER;
+use crate::secrets::PASSPHRASE_ENV_VAR;
+
+/// Topics `ptg help-topics` prints.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HelpTopic {
+    /// The same as `--help-all`.
+    All,
+    /// A man page in roff, e.g. for `ptg help-topics man > ptg.1`.
+    Man,
+    Config,
+    Env,
+    ExitCodes,
+}
+
+impl std::str::FromStr for HelpTopic {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "all" => Ok(HelpTopic::All),
+            "man" => Ok(HelpTopic::Man),
+            "config" => Ok(HelpTopic::Config),
+            "env" => Ok(HelpTopic::Env),
+            "exit-codes" => Ok(HelpTopic::ExitCodes),
+            _ => Err(format!("Invalid help topic: {}. Supported topics are all, man, config, env, exit-codes", s)),
+        }
+    }
+}
+
+/// Topic names with a one-line description, listed by `ptg help-topics` without a topic.
+const TOPICS: [(&str, &str); 5] = [
+    ("all", "every option and subcommand, the config keys, environment variables and exit codes"),
+    ("man", "a man page, e.g. `ptg help-topics man > ptg.1`"),
+    ("config", "the keys of the configuration file"),
+    ("env", "environment variables ptg reads"),
+    ("exit-codes", "what the exit status of ptg means"),
+];
+
+/// Environment variables ptg reads, with what they do.
+const ENVIRONMENT: [(&str, &str); 6] = [
+    ("RGAPI_KEY", "Riot API key, used when no key is stored in the config. Several keys can be separated by commas."),
+    (PASSPHRASE_ENV_VAR, "Passphrase of API keys encrypted with `ptg config encrypt`, instead of prompting for it."),
+    ("PTG_PAGER", "Pager long results are shown through; takes precedence over PAGER, and less is used without either."),
+    ("LESS", "Options passed to less, \"FRX\" by default."),
+    ("NO_COLOR", "Disables colored output when set."),
+    ("TZ", "Time zone sessions and their labels (e.g. \"Friday night\") are computed in."),
+];
+
+/// Exit statuses of ptg, with when they are returned.
+const EXIT_CODES: [(i32, &str); 3] = [
+    (0, "Success; with --exists, the players played together."),
+    (1, "An error occurred; with --exists, no shared match was found."),
+    (2, "Invalid command line arguments; with --exists, the query itself failed."),
+];
+
+/// Prints `topic`, or the list of topics without one.
+pub fn print_topic(topic: Option<HelpTopic>) {
+    match topic {
+        None => {
+            println!("Help topics, shown with `ptg help-topics <TOPIC>`:");
+            for (name, description) in TOPICS {
+                println!("  {:<12}{}", name, description);
+            }
+        }
+        Some(HelpTopic::All) => print!("{}", help_all()),
+        Some(HelpTopic::Man) => print!("{}", man_page()),
+        Some(HelpTopic::Config) => print!("{}", config_keys()),
+        Some(HelpTopic::Env) => print!("{}", two_columns(ENVIRONMENT.iter().map(|(name, text)| (name.to_string(), *text)))),
+        Some(HelpTopic::ExitCodes) => print!("{}", two_columns(EXIT_CODES.iter().map(|(code, text)| (code.to_string(), *text)))),
+    }
+}
+
+/// The key table of the config file header, without the comment markers.
+fn config_keys() -> String {
+    CONFIG_FILE_HEADER
+        .lines()
+        .skip(2)
+        .take_while(|line| *line != "#")
+        .map(|line| format!("{}\n", line.trim_start_matches("# ")))
+        .collect()
+}
+
+fn two_columns<'a>(rows: impl Iterator<Item = (String, &'a str)>) -> String {
+    rows.map(|(name, text)| format!("{:<22}{}\n", name, text)).collect()
+}
+
+/// Every subcommand below `command`, with its full name such as "ptg config encrypt".
+fn subcommands(command: &Command, prefix: &str) -> Vec<(String, Command)> {
+    let mut all = Vec::new();
+    for subcommand in command.get_subcommands().filter(|subcommand| !subcommand.is_hide_set()) {
+        let name = format!("{} {}", prefix, subcommand.get_name());
+        all.push((name.clone(), subcommand.clone()));
+        all.extend(subcommands(subcommand, &name));
+    }
+    all
+}
+
+/// `--help` of ptg followed by the help of every subcommand, the config keys, environment
+/// variables and exit codes.
+pub fn help_all() -> String {
+    let mut command = Cli::command();
+    let mut help = command.render_long_help().to_string();
+    // Taken before the command is built, so the global options are only shown once, at the top.
+    for (name, subcommand) in subcommands(&Cli::command(), command.get_name()) {
+        help.push_str(&format!("\n\n{}\n", name));
+        help.push_str(&subcommand.bin_name(name).render_long_help().to_string());
+    }
+    help.push_str("\n\nConfiguration (config.toml in the ptg config directory):\n");
+    help.push_str(&config_keys());
+    help.push_str("\nEnvironment:\n");
+    help.push_str(&two_columns(ENVIRONMENT.iter().map(|(name, text)| (name.to_string(), *text))));
+    help.push_str("\nExit status:\n");
+    help.push_str(&two_columns(EXIT_CODES.iter().map(|(code, text)| (code.to_string(), *text))));
+    help
+}
+
+/// The CONFIGURATION, ENVIRONMENT and EXIT STATUS sections clap does not know about.
+fn extra_man_sections() -> Roff {
+    let mut roff = Roff::new();
+    roff.control("SH", ["CONFIGURATION"])
+        .text([roman("config.toml in the ptg config directory; every key is optional.")])
+        .control("PP", [])
+        .control("nf", []);
+    for line in config_keys().lines() {
+        roff.text([roman(line)]);
+    }
+    roff.control("fi", []).control("SH", ["ENVIRONMENT"]);
+    for (variable, text) in ENVIRONMENT {
+        roff.control("TP", []).text([bold(variable)]).text([roman(text)]);
+    }
+    roff.control("SH", ["EXIT STATUS"]);
+    for (code, text) in EXIT_CODES {
+        roff.control("TP", []).text([bold(code.to_string())]).text([roman(text)]);
+    }
+    roff
+}
+
+/// The man page of ptg in roff, section 1.
+pub fn man_page() -> String {
+    let man = Man::new(Cli::command());
+    let mut page = Vec::new();
+    let rendered = man
+        .render_title(&mut page)
+        .and_then(|_| man.render_name_section(&mut page))
+        .and_then(|_| man.render_synopsis_section(&mut page))
+        .and_then(|_| man.render_description_section(&mut page))
+        .and_then(|_| man.render_options_section(&mut page))
+        .and_then(|_| man.render_subcommands_section(&mut page))
+        .and_then(|_| extra_man_sections().to_writer(&mut page))
+        .and_then(|_| man.render_version_section(&mut page));
+    rendered.expect("writing to a Vec cannot fail");
+    String::from_utf8_lossy(&page).into_owned()
+}
@@ -0,0 +1,241 @@
+use chrono::Utc;
+use riven::models::match_v5::Match;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::error::Error;
+use std::path::Path;
+
+use crate::config::Config;
+
+const INDEX_FILE_NAME: &str = "index.sqlite";
+
+const INDEX_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS matches (
+        match_id TEXT PRIMARY KEY,
+        game_start_timestamp INTEGER NOT NULL,
+        indexed_at INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS player_matches (
+        puuid TEXT NOT NULL,
+        match_id TEXT NOT NULL REFERENCES matches(match_id),
+        PRIMARY KEY (puuid, match_id)
+    );
+    CREATE INDEX IF NOT EXISTS player_matches_by_match ON player_matches(match_id);
+    CREATE TABLE IF NOT EXISTS indexed_players (
+        puuid TEXT PRIMARY KEY,
+        covered_since INTEGER NOT NULL,
+        latest_game_start INTEGER NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+";
+
+/// The time span (Unix milliseconds) in which every match of a player is known to be indexed.
+#[derive(Debug, Clone, Copy)]
+pub struct PlayerCoverage {
+    pub covered_since: i64,
+    pub latest_game_start: i64,
+}
+
+/// Local SQLite store of full match payloads, indexed by every participant's PUUID.
+/// Matches never change once played, so anything in here can be served without an API call.
+pub struct MatchIndex {
+    connection: Connection,
+}
+
+impl MatchIndex {
+    /// Opens the index in the cache directory, creating it on first use.
+    pub fn open() -> Result<Self, Box<dyn Error>> {
+        Self::open_at(&Config::cache_dir()?.join(INDEX_FILE_NAME))
+    }
+
+    /// A throwaway index in memory, for matches that should not end up in the cache directory.
+    pub fn in_memory() -> Result<Self, Box<dyn Error>> {
+        Self::open_at(Path::new(":memory:"))
+    }
+
+    pub fn open_at(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(INDEX_SCHEMA)?;
+        Ok(MatchIndex { connection })
+    }
+
+    /// Runs SQLite's integrity check, returning its findings as the error.
+    pub fn integrity_check(&self) -> Result<(), Box<dyn Error>> {
+        let result: String = self.connection.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+        if result == "ok" {
+            Ok(())
+        } else {
+            Err(result.into())
+        }
+    }
+
+    pub fn contains_match(&self, match_id: &str) -> Result<bool, Box<dyn Error>> {
+        let found = self
+            .connection
+            .query_row("SELECT 1 FROM matches WHERE match_id = ?1", params![match_id], |_| Ok(()))
+            .optional()?;
+        Ok(found.is_some())
+    }
+
+    /// Whether `puuid` took part in an indexed match, answered from the participant table
+    /// without loading the payload. Unknown matches count as not including the player.
+    pub fn has_participant(&self, match_id: &str, puuid: &str) -> Result<bool, Box<dyn Error>> {
+        let found = self
+            .connection
+            .query_row(
+                "SELECT 1 FROM player_matches WHERE match_id = ?1 AND puuid = ?2",
+                params![match_id, puuid],
+                |_| Ok(()),
+            )
+            .optional()?;
+        Ok(found.is_some())
+    }
+
+    /// Returns the stored match, or `None` if it was never indexed or its payload no longer parses.
+    pub fn get_match(&self, match_id: &str) -> Result<Option<Match>, Box<dyn Error>> {
+        let data: Option<String> = self
+            .connection
+            .query_row("SELECT data FROM matches WHERE match_id = ?1", params![match_id], |row| row.get(0))
+            .optional()?;
+        Ok(data.and_then(|data| serde_json::from_str(&data).ok()))
+    }
+
+    pub fn game_start_of(&self, match_id: &str) -> Result<Option<i64>, Box<dyn Error>> {
+        Ok(self
+            .connection
+            .query_row("SELECT game_start_timestamp FROM matches WHERE match_id = ?1", params![match_id], |row| row.get(0))
+            .optional()?)
+    }
+
+    /// IDs of the indexed matches of `puuid` that started at or after `since` (Unix ms), newest first.
+    pub fn match_ids_for_player_since(&self, puuid: &str, since: i64) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut statement = self.connection.prepare(
+            "SELECT m.match_id FROM matches m
+             JOIN player_matches pm ON pm.match_id = m.match_id
+             WHERE pm.puuid = ?1 AND m.game_start_timestamp >= ?2
+             ORDER BY m.game_start_timestamp DESC",
+        )?;
+        let match_ids = statement
+            .query_map(params![puuid, since], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(match_ids)
+    }
+
+    /// IDs of the indexed matches both players took part in that started at or after `since` (Unix ms), newest first.
+    pub fn shared_match_ids_since(&self, puuid1: &str, puuid2: &str, since: i64) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut statement = self.connection.prepare(
+            "SELECT m.match_id FROM matches m
+             JOIN player_matches p1 ON p1.match_id = m.match_id
+             JOIN player_matches p2 ON p2.match_id = m.match_id
+             WHERE p1.puuid = ?1 AND p2.puuid = ?2 AND m.game_start_timestamp >= ?3
+             ORDER BY m.game_start_timestamp DESC",
+        )?;
+        let match_ids = statement
+            .query_map(params![puuid1, puuid2, since], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(match_ids)
+    }
+
+    pub fn player_coverage(&self, puuid: &str) -> Result<Option<PlayerCoverage>, Box<dyn Error>> {
+        Ok(self
+            .connection
+            .query_row(
+                "SELECT covered_since, latest_game_start FROM indexed_players WHERE puuid = ?1",
+                params![puuid],
+                |row| Ok(PlayerCoverage { covered_since: row.get(0)?, latest_game_start: row.get(1)? }),
+            )
+            .optional()?)
+    }
+
+    /// Records that every match of `puuid` in the given span is indexed. Spans overlapping the
+    /// existing coverage are merged; a disjoint span replaces it, since the gap between them is unknown.
+    pub fn record_player_coverage(&self, puuid: &str, coverage: PlayerCoverage) -> Result<(), Box<dyn Error>> {
+        let merged = match self.player_coverage(puuid)? {
+            Some(existing) if existing.latest_game_start >= coverage.covered_since => PlayerCoverage {
+                covered_since: existing.covered_since.min(coverage.covered_since),
+                latest_game_start: existing.latest_game_start.max(coverage.latest_game_start),
+            },
+            _ => coverage,
+        };
+        self.connection.execute(
+            "INSERT OR REPLACE INTO indexed_players (puuid, covered_since, latest_game_start, updated_at) VALUES (?1, ?2, ?3, ?4)",
+            params![puuid, merged.covered_since, merged.latest_game_start, Utc::now().timestamp()],
+        )?;
+        Ok(())
+    }
+
+    /// Records the coverage of a freshly fetched match list. When the list came back shorter than
+    /// requested it reached `window_start` (Unix ms), otherwise it only reaches its oldest match.
+    pub fn record_match_list(&self, puuid: &str, match_ids: &[String], requested: usize, window_start: i64) -> Result<(), Box<dyn Error>> {
+        let mut game_starts = Vec::new();
+        for match_id in match_ids {
+            if let Some(game_start) = self.game_start_of(match_id)? {
+                game_starts.push(game_start);
+            }
+        }
+        let oldest = game_starts.iter().copied().min();
+        let covered_since = match oldest {
+            Some(oldest) if match_ids.len() >= requested => oldest,
+            _ => window_start,
+        };
+        let latest_game_start = game_starts.iter().copied().max().unwrap_or(window_start);
+        self.record_player_coverage(puuid, PlayerCoverage { covered_since, latest_game_start })
+    }
+
+    /// Size of the index database on disk, in bytes.
+    pub fn size_bytes(&self) -> Result<u64, Box<dyn Error>> {
+        let page_count: i64 = self.connection.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+        let page_size: i64 = self.connection.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+        Ok((page_count * page_size) as u64)
+    }
+
+    /// Drops the oldest matches until the database fits in `max_bytes`, returning how many were dropped.
+    /// Player coverage is shortened accordingly so dropped matches are fetched again when needed.
+    pub fn prune_to_size(&self, max_bytes: u64) -> Result<usize, Box<dyn Error>> {
+        let size = self.size_bytes()?;
+        let match_count: i64 = self.connection.query_row("SELECT COUNT(*) FROM matches", [], |row| row.get(0))?;
+        if size <= max_bytes || match_count == 0 {
+            return Ok(0);
+        }
+        let bytes_per_match = (size / match_count as u64).max(1);
+        let to_remove = (size - max_bytes).div_ceil(bytes_per_match).min(match_count as u64) as i64;
+        let cutoff: i64 = self.connection.query_row(
+            "SELECT game_start_timestamp FROM matches ORDER BY game_start_timestamp ASC LIMIT 1 OFFSET ?1",
+            params![to_remove - 1],
+            |row| row.get(0),
+        )?;
+
+        self.connection.execute(
+            "DELETE FROM player_matches WHERE match_id IN (SELECT match_id FROM matches WHERE game_start_timestamp <= ?1)",
+            params![cutoff],
+        )?;
+        let removed = self.connection.execute("DELETE FROM matches WHERE game_start_timestamp <= ?1", params![cutoff])?;
+        self.connection.execute(
+            "UPDATE indexed_players SET covered_since = MAX(covered_since, ?1)",
+            params![cutoff + 1],
+        )?;
+        self.connection.execute("DELETE FROM indexed_players WHERE covered_since > latest_game_start", [])?;
+        self.connection.execute_batch("VACUUM")?;
+        Ok(removed)
+    }
+
+    pub fn insert_match(&self, match_data: &Match) -> Result<(), Box<dyn Error>> {
+        let match_id = &match_data.metadata.match_id;
+        self.connection.execute(
+            "INSERT OR REPLACE INTO matches (match_id, game_start_timestamp, indexed_at, data) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                match_id,
+                match_data.info.game_start_timestamp,
+                Utc::now().timestamp(),
+                serde_json::to_string(match_data)?,
+            ],
+        )?;
+        for puuid in &match_data.metadata.participants {
+            self.connection.execute(
+                "INSERT OR IGNORE INTO player_matches (puuid, match_id) VALUES (?1, ?2)",
+                params![puuid, match_id],
+            )?;
+        }
+        Ok(())
+    }
+}
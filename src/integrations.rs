@@ -0,0 +1,126 @@
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::api_client::OverallOutput;
+
+const SHEETS_SCOPE: &str = "https://www.googleapis.com/auth/spreadsheets";
+const DEFAULT_SHEETS_RANGE: &str = "A1";
+const SHEETS_API_URL: &str = "https://sheets.googleapis.com/v4/spreadsheets";
+
+/// The fields of a Google service-account key file needed to obtain an access token.
+#[derive(Debug, Deserialize)]
+struct ServiceAccountKey {
+    client_email: String,
+    private_key: String,
+    token_uri: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ServiceAccountClaims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: i64,
+    exp: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct AccessTokenResponse {
+    access_token: String,
+}
+
+/// Exchanges a signed service-account JWT for a short-lived OAuth access token.
+async fn fetch_access_token(client: &reqwest::Client, key: &ServiceAccountKey) -> Result<String, Box<dyn Error>> {
+    let issued_at = chrono::Utc::now().timestamp();
+    let claims = ServiceAccountClaims {
+        iss: &key.client_email,
+        scope: SHEETS_SCOPE,
+        aud: &key.token_uri,
+        iat: issued_at,
+        exp: issued_at + 3600,
+    };
+    let assertion = encode(
+        &Header::new(Algorithm::RS256),
+        &claims,
+        &EncodingKey::from_rsa_pem(key.private_key.as_bytes())?,
+    )?;
+
+    let response: AccessTokenResponse = client
+        .post(&key.token_uri)
+        .form(&[
+            ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+            ("assertion", assertion.as_str()),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(response.access_token)
+}
+
+/// One spreadsheet row per shared match.
+fn sheet_rows(output: &OverallOutput) -> Vec<Vec<String>> {
+    let summary = &output.query_summary;
+    output
+        .found_matches
+        .iter()
+        .map(|m| {
+            vec![
+                m.game_date_utc.clone(),
+                m.match_id.clone(),
+                m.game_mode.clone(),
+                format!("{}#{}", summary.player1.game_name, summary.player1.tag_line),
+                m.player1_details.champion_label().to_string(),
+                format!("{}/{}/{}", m.player1_details.kills, m.player1_details.deaths, m.player1_details.assists),
+                format!("{}#{}", summary.player2.game_name, summary.player2.tag_line),
+                m.player2_details.champion_label().to_string(),
+                format!("{}/{}/{}", m.player2_details.kills, m.player2_details.deaths, m.player2_details.assists),
+                m.player1_details.outcome.clone(),
+                m.league_of_graphs_link.clone().unwrap_or_default(),
+            ]
+        })
+        .collect()
+}
+
+/// The values:append endpoint of a sheet range. The range is an A1 reference like `Duo Log!A1`, whose
+/// sheet name may need percent-encoding.
+pub fn sheets_append_url(spreadsheet_id: &str, range: &str) -> Result<reqwest::Url, Box<dyn Error>> {
+    let mut url = reqwest::Url::parse(SHEETS_API_URL)?;
+    url.path_segments_mut()
+        .map_err(|_| "The Sheets API URL cannot have a path.")?
+        .extend([spreadsheet_id, "values", &format!("{}:append", range)]);
+    Ok(url)
+}
+
+/// Appends every found match as a row to a Google Sheet, authenticating with a service-account key file.
+/// The spreadsheet must be shared with the service account's email address.
+pub async fn append_to_google_sheet(
+    output: &OverallOutput,
+    spreadsheet_id: &str,
+    service_account_path: &Path,
+    range: Option<&str>,
+) -> Result<usize, Box<dyn Error>> {
+    let rows = sheet_rows(output);
+    if rows.is_empty() {
+        return Ok(0);
+    }
+
+    let key: ServiceAccountKey = serde_json::from_str(&fs::read_to_string(service_account_path)?)?;
+    let client = reqwest::Client::new();
+    let access_token = fetch_access_token(&client, &key).await?;
+
+    client
+        .post(sheets_append_url(spreadsheet_id, range.unwrap_or(DEFAULT_SHEETS_RANGE))?)
+        .query(&[("valueInputOption", "USER_ENTERED"), ("insertDataOption", "INSERT_ROWS")])
+        .bearer_auth(access_token)
+        .json(&json!({ "values": rows }))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(rows.len())
+}
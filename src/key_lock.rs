@@ -0,0 +1,161 @@
+//! Shares the rate limit of an API key between concurrent ptg runs, so two runs started at once (e.g.
+//! a cron job and a manual check) take turns within the key's budget instead of blowing through it
+//! together. Every request is recorded in the budget file of its key, which is locked only while it
+//! is updated, so runs sharing any key are coordinated on it.
+
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::config::Config;
+
+/// Subdirectory of the cache directory holding one budget file per API key.
+const LOCKS_DIR_NAME: &str = "locks";
+
+/// The limits of a development key, in the `requests:seconds` format of Riot's `X-App-Rate-Limit` header.
+pub const DEVELOPMENT_KEY_RATE_LIMITS: &str = "20:1,100:120";
+
+/// How often a run retries to lock a budget file another run is updating, in milliseconds.
+const LOCK_RETRY_MS: u64 = 1;
+
+/// Parses rate limits like `20:1,100:120` into (requests, window in milliseconds) pairs.
+pub fn parse_rate_limits(limits: &str) -> Result<Vec<(usize, i64)>, Box<dyn Error>> {
+    limits
+        .split(',')
+        .map(|limit| {
+            let invalid = || format!("Invalid rate limit '{}'. Expected requests:seconds, e.g. 20:1.", limit.trim());
+            let (requests, seconds) = limit.trim().split_once(':').ok_or_else(invalid)?;
+            let requests: usize = requests.parse().map_err(|_| invalid())?;
+            let seconds: i64 = seconds.parse().map_err(|_| invalid())?;
+            if requests == 0 || seconds <= 0 {
+                return Err(invalid().into());
+            }
+            Ok((requests, seconds * 1000))
+        })
+        .collect()
+}
+
+/// The requests recently sent with one API key by every run on this machine.
+pub struct KeyBudget {
+    path: PathBuf,
+    /// Requests allowed per window, in milliseconds.
+    limits: Vec<(usize, i64)>,
+}
+
+/// Budget file name for `api_key`, hashed so the key itself never ends up on disk.
+fn budget_file_name(api_key: &str) -> String {
+    let hash: String = Sha256::digest(api_key.as_bytes())
+        .iter()
+        .take(8)
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("{}.budget", hash)
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |now| now.as_millis() as i64)
+}
+
+/// How long to wait until one more request fits into every limit, given the send times of the
+/// requests before it; `None` when it fits now.
+fn wait_for_slot(limits: &[(usize, i64)], sent: &[i64], now: i64) -> Option<i64> {
+    limits
+        .iter()
+        .filter_map(|&(limit, window)| {
+            let in_window: Vec<i64> = sent.iter().copied().filter(|&sent_at| sent_at > now - window).collect();
+            // The oldest requests have to leave the window until fewer than `limit` remain.
+            (in_window.len() >= limit).then(|| in_window[in_window.len() - limit] + window - now)
+        })
+        .max()
+}
+
+/// Outcome of one attempt at taking a slot.
+enum SlotAttempt {
+    Taken,
+    /// Another run is updating the budget file right now.
+    Locked,
+    /// The budget is used up for this many milliseconds.
+    Full(i64),
+}
+
+impl KeyBudget {
+    /// Opens the budget of `api_key`, with the key's `rate_limits` as Riot lists them (`20:1,100:120`).
+    pub fn open(api_key: &str, rate_limits: &str) -> Result<Self, Box<dyn Error>> {
+        let limits = parse_rate_limits(rate_limits)?;
+        let locks_dir = Config::cache_dir()?.join(LOCKS_DIR_NAME);
+        fs::create_dir_all(&locks_dir)?;
+        Ok(KeyBudget { path: locks_dir.join(budget_file_name(api_key)), limits })
+    }
+
+    /// Waits until the shared budget has room for one more request, then records it. Waiting sleeps
+    /// the task, not the thread, so concurrent requests of the same run go on meanwhile.
+    pub async fn take_slot(&self) -> Result<(), Box<dyn Error>> {
+        let mut announced = false;
+        loop {
+            match self.try_take_slot()? {
+                SlotAttempt::Taken => return Ok(()),
+                SlotAttempt::Locked => tokio::time::sleep(Duration::from_millis(LOCK_RETRY_MS)).await,
+                SlotAttempt::Full(wait) => {
+                    if !announced {
+                        eprintln!("Waiting for the API key's rate limit, shared with another ptg run...");
+                        announced = true;
+                    }
+                    tokio::time::sleep(Duration::from_millis(wait.max(1) as u64)).await;
+                }
+            }
+        }
+    }
+
+    fn try_take_slot(&self) -> Result<SlotAttempt, Box<dyn Error>> {
+        let mut file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&self.path)?;
+        if !try_lock_exclusive(&file)? {
+            return Ok(SlotAttempt::Locked);
+        }
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+        let now = now_ms();
+        let longest_window = self.limits.iter().map(|&(_, window)| window).max().unwrap_or(0);
+        let mut sent: Vec<i64> = contents
+            .lines()
+            .filter_map(|line| line.parse().ok())
+            .filter(|&sent_at| sent_at > now - longest_window)
+            .collect();
+        sent.sort_unstable();
+        // Returning closes the file and so releases the lock, so other runs can go on while this one waits.
+        if let Some(wait) = wait_for_slot(&self.limits, &sent, now) {
+            return Ok(SlotAttempt::Full(wait));
+        }
+        sent.push(now);
+        let contents: String = sent.iter().map(|sent_at| format!("{}\n", sent_at)).collect();
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(contents.as_bytes())?;
+        Ok(SlotAttempt::Taken)
+    }
+}
+
+/// Locks the budget against other runs updating it, without waiting for them; `false` when another
+/// run holds the lock. Only supported on Unix, elsewhere runs are not coordinated.
+#[cfg(unix)]
+fn try_lock_exclusive(file: &File) -> Result<bool, Box<dyn Error>> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: the descriptor belongs to `file`, which outlives the call; the lock is held only
+    // until the file is closed.
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+        let error = std::io::Error::last_os_error();
+        if error.kind() == std::io::ErrorKind::WouldBlock {
+            return Ok(false);
+        }
+        return Err(format!("Could not lock the API key budget: {}", error).into());
+    }
+    Ok(true)
+}
+
+#[cfg(not(unix))]
+fn try_lock_exclusive(_file: &File) -> Result<bool, Box<dyn Error>> {
+    Ok(true)
+}
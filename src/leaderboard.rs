@@ -0,0 +1,168 @@
+use serde::Serialize;
+use std::error::Error;
+
+use crate::anonymize;
+use crate::contacts;
+use crate::cache::AccountCache;
+use crate::index::MatchIndex;
+use crate::pair_history::{run_pair_history, PairHistoryOptions};
+use crate::riot_id::RiotId;
+use crate::stats::win_rate;
+
+/// Column the leaderboard is ranked by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LeaderboardOrder {
+    WinRate,
+    Games,
+    Kda,
+    Assists,
+    Vision,
+}
+
+impl std::str::FromStr for LeaderboardOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "win-rate" | "winrate" => Ok(LeaderboardOrder::WinRate),
+            "games" => Ok(LeaderboardOrder::Games),
+            "kda" => Ok(LeaderboardOrder::Kda),
+            "assists" => Ok(LeaderboardOrder::Assists),
+            "vision" => Ok(LeaderboardOrder::Vision),
+            _ => Err(format!("Invalid leaderboard order: {}. Supported orders are win-rate, games, kda, assists, vision", s)),
+        }
+    }
+}
+
+/// How the self ID fares with one contact, over every shared match in the local index.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaderboardEntry {
+    pub partner: String,
+    pub games: u32,
+    pub wins: u32,
+    /// In percent.
+    pub win_rate: f64,
+    /// The contact's average (kills + assists) / deaths per shared game, deaths counted as at least 1.
+    pub average_kda: Option<f64>,
+    /// The contact's average assists per shared game on the same team. Indexed matches have no
+    /// timeline, so this counts all their assists, not only those on the self ID's kills.
+    pub average_teammate_assists: Option<f64>,
+    /// Shared games where the contact had the higher vision score than the self ID, and the lower one.
+    pub vision_duels_won: u32,
+    pub vision_duels_lost: u32,
+}
+
+fn average(values: &[f64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+/// Ranks every contact from the local index only, without API calls. Contacts missing from the
+/// account cache are skipped with a warning, since their PUUID is unknown.
+pub fn run_leaderboard(
+    account_cache: &AccountCache,
+    match_index: &MatchIndex,
+    self_riot_id: &RiotId,
+    contacts: Vec<RiotId>,
+    order: LeaderboardOrder,
+) -> Result<Vec<LeaderboardEntry>, Box<dyn Error>> {
+    let mut entries = Vec::new();
+    for contact in contacts {
+        if account_cache.get(&contact).is_none() {
+            eprintln!("Warning: '{}' is not in the local cache yet. Skipping.", anonymize::riot_id(&contact));
+            continue;
+        }
+        let output = run_pair_history(
+            account_cache,
+            match_index,
+            PairHistoryOptions {
+                player1_riot_id: self_riot_id.clone(),
+                player2_riot_id: contact.clone(),
+                all_time: true,
+                user_selected_region: None,
+                patch: None,
+                include_afk: false,
+                data_dragon: None,
+                verbose: false,
+                quiet: true,
+            },
+        )?;
+        let summary = &output.query_summary;
+        let games = summary.matches_played_together_count;
+        let kdas: Vec<f64> = output
+            .found_matches
+            .iter()
+            .map(|m| {
+                let details = &m.player2_details;
+                (details.kills + details.assists) as f64 / details.deaths.max(1) as f64
+            })
+            .collect();
+        let teammate_assists: Vec<f64> = output
+            .found_matches
+            .iter()
+            .filter(|m| m.same_team)
+            .map(|m| m.player2_details.assists as f64)
+            .collect();
+        entries.push(LeaderboardEntry {
+            partner: contacts::display_name(&contact),
+            games,
+            wins: summary.player1_wins_together_count,
+            win_rate: win_rate(summary.player1_wins_together_count, games),
+            average_kda: average(&kdas),
+            average_teammate_assists: average(&teammate_assists),
+            vision_duels_won: output.stats.vision_duel.player2_wins,
+            vision_duels_lost: output.stats.vision_duel.player1_wins,
+        });
+    }
+
+    // Contacts without shared games go last whatever the order.
+    entries.sort_by(|a, b| {
+        let key = |entry: &LeaderboardEntry| match order {
+            LeaderboardOrder::WinRate => entry.win_rate,
+            LeaderboardOrder::Games => entry.games as f64,
+            LeaderboardOrder::Kda => entry.average_kda.unwrap_or(0.0),
+            LeaderboardOrder::Assists => entry.average_teammate_assists.unwrap_or(0.0),
+            LeaderboardOrder::Vision => entry.vision_duels_won as f64 - entry.vision_duels_lost as f64,
+        };
+        (b.games > 0).cmp(&(a.games > 0)).then(key(b).total_cmp(&key(a))).then(b.games.cmp(&a.games))
+    });
+    Ok(entries)
+}
+
+pub fn print_leaderboard(entries: &[LeaderboardEntry]) {
+    println!("\n--- Leaderboard ---");
+    if entries.is_empty() {
+        println!("No contacts with cached accounts yet.");
+        return;
+    }
+    let width = entries.iter().map(|e| e.partner.chars().count()).max().unwrap_or(0).max("Partner".len());
+    println!(
+        "{:>3}  {:<width$}  {:>5}  {:>4}  {:>8}  {:>5}  {:>7}  {:>6}",
+        "#", "Partner", "Games", "Wins", "Win rate", "KDA", "Assists", "Vision",
+        width = width
+    );
+    for (rank, entry) in entries.iter().enumerate() {
+        let (win_rate, kda) = match entry.average_kda {
+            Some(kda) => (format!("{:.0}%", entry.win_rate), format!("{:.2}", kda)),
+            None => ("-".to_string(), "-".to_string()),
+        };
+        let assists = entry.average_teammate_assists.map_or("-".to_string(), |assists| format!("{:.1}", assists));
+        let vision = if entry.games > 0 {
+            format!("{}-{}", entry.vision_duels_won, entry.vision_duels_lost)
+        } else {
+            "-".to_string()
+        };
+        println!(
+            "{:>3}  {:<width$}  {:>5}  {:>4}  {:>8}  {:>5}  {:>7}  {:>6}",
+            rank + 1,
+            entry.partner,
+            entry.games,
+            entry.wins,
+            win_rate,
+            kda,
+            assists,
+            vision,
+            width = width
+        );
+    }
+}
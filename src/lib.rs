@@ -0,0 +1,54 @@
+//! Library side of `ptg`: the Riot API queries, local caches and report builders the binary is made of,
+//! exposed so benchmarks and integration tests can drive them directly.
+
+pub mod cli;
+pub mod config;
+pub mod riot_id;
+pub mod api_client;
+pub mod utils;
+pub mod ranked;
+pub mod cache;
+pub mod stats;
+pub mod timeline;
+pub mod achievements;
+pub mod export;
+pub mod integrations;
+pub mod notify;
+pub mod templates;
+pub mod index;
+pub mod prefetch;
+pub mod pair_history;
+pub mod retention;
+pub mod secrets;
+pub mod doctor;
+pub mod updater;
+pub mod anonymize;
+pub mod ddragon;
+pub mod tft;
+pub mod notes;
+pub mod contacts;
+pub mod favorites;
+pub mod status;
+pub mod duo_timeline;
+pub mod venn;
+pub mod date_range;
+pub mod can_duo;
+pub mod leaderboard;
+pub mod champion_pool;
+pub mod pager;
+pub mod lineup;
+pub mod scout;
+pub mod digest;
+pub mod site;
+pub mod card;
+pub mod chart;
+pub mod portable;
+pub mod key_lock;
+pub mod rate_limit;
+pub mod offline;
+pub mod run_bundle;
+pub mod render;
+pub mod sessions;
+pub mod console;
+pub mod help;
+pub mod rivalry;
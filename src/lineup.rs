@@ -0,0 +1,187 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error::Error;
+
+use crate::contacts;
+use crate::cache::AccountCache;
+use crate::champion_pool::ROLES;
+use crate::index::MatchIndex;
+use crate::pair_history::cached_puuid;
+use crate::riot_id::RiotId;
+use crate::stats::win_rate;
+
+/// Below this share of games in the main role, a player is flagged as flexing.
+const CONSISTENT_ROLE_SHARE: f64 = 60.0;
+/// A role mapping needs this many games before its win rate can pick the suggested lineup.
+const MIN_MAPPING_GAMES: u32 = 2;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RolePlays {
+    pub role: String,
+    pub games: u32,
+    pub wins: u32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerRoles {
+    pub player: String,
+    /// Most played first.
+    pub roles: Vec<RolePlays>,
+    pub main_role: Option<String>,
+    /// Share of the group's games played in the main role, in percent.
+    pub consistency: f64,
+}
+
+/// One assignment of roles to the group, in the order the players were given.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoleMapping {
+    pub roles: Vec<String>,
+    pub games: u32,
+    pub wins: u32,
+    pub win_rate: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LineupOutput {
+    /// Indexed games with the whole group on one team and every player in a position.
+    pub games: u32,
+    pub players: Vec<PlayerRoles>,
+    /// Best win rate first.
+    pub mappings: Vec<RoleMapping>,
+    /// Role per player, in the order the players were given.
+    pub suggested_lineup: Option<Vec<String>>,
+    pub warnings: Vec<String>,
+}
+
+/// Tallies who played which role in the indexed Summoner's Rift games the whole group played on one
+/// team, from the local match index only.
+pub fn run_lineup(account_cache: &AccountCache, match_index: &MatchIndex, riot_ids: &[RiotId]) -> Result<LineupOutput, Box<dyn Error>> {
+    let puuids = riot_ids
+        .iter()
+        .map(|riot_id| cached_puuid(account_cache, riot_id))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut games = 0;
+    let mut role_plays: Vec<HashMap<String, (u32, u32)>> = vec![HashMap::new(); puuids.len()];
+    let mut mappings: HashMap<Vec<String>, (u32, u32)> = HashMap::new();
+    for match_id in match_index.match_ids_for_player_since(&puuids[0], 0)? {
+        let Some(match_data) = match_index.get_match(&match_id)? else {
+            continue;
+        };
+        let participants = &match_data.info.participants;
+        let Some(group) = puuids
+            .iter()
+            .map(|puuid| participants.iter().find(|p| &p.puuid == puuid))
+            .collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+        if group.iter().any(|p| p.team_id != group[0].team_id || !ROLES.contains(&p.team_position.as_str())) {
+            continue;
+        }
+        let won = group[0].win;
+        games += 1;
+        for (plays, participant) in role_plays.iter_mut().zip(&group) {
+            let entry = plays.entry(participant.team_position.clone()).or_default();
+            entry.0 += 1;
+            entry.1 += won as u32;
+        }
+        let entry = mappings.entry(group.iter().map(|p| p.team_position.clone()).collect()).or_default();
+        entry.0 += 1;
+        entry.1 += won as u32;
+    }
+
+    let players: Vec<PlayerRoles> = riot_ids
+        .iter()
+        .zip(role_plays)
+        .map(|(riot_id, plays)| {
+            let mut roles: Vec<RolePlays> = plays
+                .into_iter()
+                .map(|(role, (games, wins))| RolePlays { role, games, wins })
+                .collect();
+            roles.sort_by(|a, b| b.games.cmp(&a.games).then_with(|| a.role.cmp(&b.role)));
+            PlayerRoles {
+                player: contacts::display_name(riot_id),
+                main_role: roles.first().map(|role| role.role.clone()),
+                consistency: win_rate(roles.first().map_or(0, |role| role.games), games),
+                roles,
+            }
+        })
+        .collect();
+
+    let mut mappings: Vec<RoleMapping> = mappings
+        .into_iter()
+        .map(|(roles, (games, wins))| RoleMapping { roles, games, wins, win_rate: win_rate(wins, games) })
+        .collect();
+    mappings.sort_by(|a, b| {
+        let established = |m: &RoleMapping| m.games >= MIN_MAPPING_GAMES;
+        established(b)
+            .cmp(&established(a))
+            .then(b.win_rate.total_cmp(&a.win_rate))
+            .then(b.games.cmp(&a.games))
+    });
+
+    let mut warnings = Vec::new();
+    for player in &players {
+        if games > 0 && player.consistency < CONSISTENT_ROLE_SHARE {
+            warnings.push(format!(
+                "{} flexes: only {:.0}% of the group's games in {}.",
+                player.player,
+                player.consistency,
+                player.main_role.as_deref().unwrap_or("any role")
+            ));
+        }
+    }
+    for (i, player) in players.iter().enumerate() {
+        for other in &players[i + 1..] {
+            if let (Some(role), Some(other_role)) = (&player.main_role, &other.main_role)
+                && role == other_role
+            {
+                warnings.push(format!("{} and {} both mainly play {}.", player.player, other.player, role));
+            }
+        }
+    }
+
+    Ok(LineupOutput {
+        games,
+        suggested_lineup: mappings.first().map(|mapping| mapping.roles.clone()),
+        players,
+        mappings,
+        warnings,
+    })
+}
+
+impl LineupOutput {
+    pub fn print(&self) {
+        println!("\n--- Lineup ---");
+        if self.games == 0 {
+            println!("No indexed games with the whole group on one team.");
+            return;
+        }
+        println!("From {} indexed games with the whole group on one team.", self.games);
+        for player in &self.players {
+            let roles: Vec<String> = player.roles.iter().map(|role| format!("{} {}", role.role, role.games)).collect();
+            println!("{}: {} ({:.0}% in main role)", player.player, roles.join(", "), player.consistency);
+        }
+        println!("Role mappings:");
+        for mapping in &self.mappings {
+            println!("  {}: {}W-{}L ({:.0}%)", mapping.roles.join("/"), mapping.wins, mapping.games - mapping.wins, mapping.win_rate);
+        }
+        if let Some(lineup) = &self.suggested_lineup {
+            let assignments: Vec<String> = self
+                .players
+                .iter()
+                .zip(lineup)
+                .map(|(player, role)| format!("{} {}", player.player, role))
+                .collect();
+            println!("Suggested lineup: {}", assignments.join(", "));
+        }
+        for warning in &self.warnings {
+            println!("Warning: {}", warning);
+        }
+    }
+}
@@ -1,42 +1,646 @@
-use clap::{CommandFactory, Parser};
-use riven::RiotApi;
+use clap::CommandFactory;
 use std::error::Error;
-use std::env;
-use riven::consts::RegionalRoute;
+use std::path::Path;
 
-mod cli;
-mod config;
-mod riot_id;
-mod api_client;
-mod utils;
+use ptg::{achievements, anonymize, can_duo, champion_pool, chart, console, contacts, ddragon, digest, doctor, export, favorites, help, integrations, leaderboard, lineup, notes, notify, offline, pager, portable, rate_limit, render, rivalry, secrets, sessions, site, status, templates, tft, updater, utils};
+use ptg::cli::{CacheCommand, Cli, Command, ConfigCommand, ContactsCommand, FavCommand, SiteCommand, UserFacingRegion};
+use ptg::config::{Config, RetentionConfig};
+use ptg::date_range::DateRange;
+use ptg::riot_id::RiotId;
+use ptg::cache::AccountCache;
+use ptg::export::ExportFormat;
+use ptg::index::MatchIndex;
+use ptg::prefetch::{run_prefetch, PrefetchOptions};
+use ptg::pair_history::{pair_history_of_matches, run_pair_history, PairHistoryOptions};
+use ptg::scout::{run_scout, ScoutOptions};
+use ptg::retention::run_cache_prune;
+use ptg::render::{JsonInput, RenderFormat};
+use ptg::run_bundle::RunBundle;
+use ptg::api_client::{fetch_riot_id, print_match_lines, run_query, ApiCallStats, OverallOutput, QueryOptions, RiotApiPool, ANY_ACCOUNT_ROUTE, DEFAULT_MAX_WINDOW_DAYS};
 
-use cli::{Cli, UserFacingRegion};
-use config::Config;
-use riot_id::RiotId;
-use api_client::run_query;
 
-use serde_json;
+/// Builds the API client pool from the stored keys, falling back to the RGAPI_KEY environment variable.
+/// With --offline no key is needed, since no request is sent.
+fn riot_api_pool(config: &Config) -> Result<RiotApiPool, Box<dyn Error>> {
+    let api_keys = match config.effective_api_keys() {
+        Err(_) if offline::is_enabled() => Vec::new(),
+        api_keys => api_keys?,
+    };
+    RiotApiPool::new(&api_keys, &config.network)
+}
+
+/// Loads the Data Dragon champion data for the language picked with `--language` or in the config, if any.
+async fn localization(cli: &Cli, config: &Config) -> Result<Option<ddragon::DataDragon>, Box<dyn Error>> {
+    match cli.language.as_ref().or(config.language.as_ref()) {
+        Some(locale) => Ok(Some(ddragon::DataDragon::load(locale).await?)),
+        None => Ok(None),
+    }
+}
+
+/// The region picked by the user: `--region`, then `--default-region`, then the config's default_region.
+fn user_selected_region(cli: &Cli, config: &Config) -> Option<UserFacingRegion> {
+    cli.region.clone()
+        .or(cli.default_region.clone())
+        .or(config.default_region.clone())
+}
+
+/// The region stored for a player: the self region for the self Riot ID, the contact's region otherwise.
+fn stored_region(config: &Config, contact_book: &contacts::ContactBook, riot_id: &RiotId) -> Option<UserFacingRegion> {
+    let is_self = config.self_riot_id.clone().map(RiotId::from)
+        .is_some_and(|self_riot_id| self_riot_id.to_string().to_lowercase() == riot_id.to_string().to_lowercase());
+    if is_self {
+        config.self_region.clone()
+    } else {
+        contact_book.region_of(riot_id)
+    }
+}
 
+/// Takes the renames and PUUIDs that earlier runs stored in the account cache over into the contact
+/// book, so contacts follow Riot ID changes without a `contacts refresh`.
+fn follow_contact_renames(contact_book: &mut contacts::ContactBook) {
+    let Ok(account_cache) = AccountCache::load() else {
+        return;
+    };
+    let (changes, changed) = contact_book.follow_account_cache(&account_cache);
+    for change in &changes {
+        eprintln!("{} is now {}; updated the contact.", change.previous, change.current);
+    }
+    if changed && let Err(e) = contact_book.save() {
+        eprintln!("Warning: Could not save the contact book: {}", e);
+    }
+}
+
+/// The contact book as far as queries use it, for nicknames, stored regions and former Riot IDs.
+/// These only refine the results, so a contact book that cannot be read (warned about once at
+/// startup) counts as empty instead of failing the query.
+fn queried_contact_book() -> contacts::ContactBook {
+    contacts::ContactBook::load().unwrap_or_default()
+}
+
+/// The region of a query between two players: `--region`, then `--default-region`, then the region stored
+/// for player1, then the config's default_region. Fails when both players have stored regions on
+/// different platforms, since they cannot have played together.
+fn query_region(cli: &Cli, config: &Config, player1: &RiotId, player2: &RiotId) -> Result<Option<UserFacingRegion>, Box<dyn Error>> {
+    let contact_book = queried_contact_book();
+    let player1_region = stored_region(config, &contact_book, player1);
+    if let (Some(region1), Some(region2)) = (&player1_region, stored_region(config, &contact_book, player2))
+        && region1.to_platform_route() != region2.to_platform_route()
+    {
+        return Err(format!(
+            "{} plays on {} and {} on {}, so they cannot have played together. \
+             Fix a stale region with `ptg contacts region <RIOT_ID> <REGION>` (or `--self <RIOT_ID> --region <REGION>`).",
+            contacts::display_name(player1),
+            region1.to_log_string().to_uppercase(),
+            contacts::display_name(player2),
+            region2.to_log_string().to_uppercase()
+        ).into());
+    }
+    Ok(cli.region.clone()
+        .or(cli.default_region.clone())
+        .or(player1_region)
+        .or(config.default_region.clone()))
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
+    let mut cli = Cli::parse_args();
+    console::setup(cli.ascii);
+    if cli.help_all {
+        print!("{}", help::help_all());
+        return Ok(());
+    }
+    if let Some(Command::HelpTopics { topic }) = cli.command {
+        help::print_topic(topic);
+        return Ok(());
+    }
+    if let Some(Command::Doctor) = cli.command {
+        // Runs before loading the config, since a broken config is one of the things it diagnoses.
+        return doctor::run_doctor().await;
+    }
+    if cli.formats.iter().any(|format| format.file_name().is_none()) {
+        // Checked up front, so a long scan does not end in this error.
+        return Err("--format text and oneline only print to the terminal; --output-dir takes json, html or markdown.".into());
+    }
     let mut config = Config::load()?;
-    let cli = Cli::parse();
+    rate_limit::install(cli.fail_fast_on_429);
+    if cli.anonymize {
+        anonymize::enable();
+    }
+    if cli.offline {
+        offline::enable();
+    }
+    match contacts::ContactBook::load() {
+        Ok(mut contact_book) => {
+            follow_contact_renames(&mut contact_book);
+            contacts::register_nicknames(&contact_book);
+        }
+        Err(e) => eprintln!("Warning: Could not read the contact book ({}); going on without nicknames and stored regions.", e),
+    }
+
+    resolve_puuid_arguments(&mut cli, &config).await?;
 
     if let Some(riot_id_to_store) = cli.set_self {
         config.self_riot_id = Some(riot_id_to_store.clone().into());
+        match &cli.region {
+            Some(region) => {
+                config.self_region = Some(region.clone());
+                println!("Stored '{}' on {} as your self Riot ID.", riot_id_to_store, region.to_log_string().to_uppercase());
+            }
+            None => println!("Stored '{}' as your self Riot ID.", riot_id_to_store),
+        }
         config.save()?;
-        println!("Stored '{}' as your self Riot ID.", riot_id_to_store);
         return Ok(());
     }
 
-    if let Some(api_key_to_store) = cli.api_key {
-        config.api_key = Some(api_key_to_store.clone());
+    if let Some(api_keys_to_store) = cli.api_key {
+        let mut api_keys_to_store = api_keys_to_store.into_iter();
+        config.api_key = api_keys_to_store.next();
+        config.additional_api_keys = api_keys_to_store.collect();
         config.save()?;
-        println!("Stored API key locally.");
+        if config.additional_api_keys.is_empty() {
+            println!("Stored API key locally.");
+        } else {
+            println!("Stored {} API keys locally.", config.api_keys().len());
+        }
+        return Ok(());
+    }
+
+    if let Some(credentials_path) = cli.sheets_credentials {
+        println!("Stored Google service-account key path '{}'.", credentials_path.display());
+        config.integrations.google_service_account_path = Some(credentials_path);
+        config.save()?;
+        return Ok(());
+    }
+
+    if let Some(command) = cli.command.take() {
+        let regional_route = user_selected_region(&cli, &config).map(|r| r.to_regional_route());
+        match command {
+            Command::Prefetch { riot_ids, max_matches } => {
+                let riot_api = riot_api_pool(&config)?;
+                let mut account_cache = AccountCache::load()?;
+                let match_index = MatchIndex::open()?;
+                run_prefetch(
+                    &riot_api,
+                    &mut account_cache,
+                    &match_index,
+                    PrefetchOptions { riot_ids, regional_route, max_matches },
+                ).await?;
+                account_cache.save()?;
+            }
+            Command::PairHistory { player1, player2, all_time } => {
+                let account_cache = AccountCache::load()?;
+                let match_index = MatchIndex::open()?;
+                let region = query_region(&cli, &config, &player1, &player2)?;
+                let _pager = start_pager(&cli);
+                let output = run_pair_history(
+                    &account_cache,
+                    &match_index,
+                    PairHistoryOptions {
+                        player1_riot_id: player1,
+                        player2_riot_id: player2,
+                        all_time,
+                        user_selected_region: region,
+                        patch: cli.patch.clone(),
+                        include_afk: cli.include_afk,
+                        data_dragon: localization(&cli, &config).await?,
+                        verbose: cli.verbose,
+                        quiet: cli.json,
+                    },
+                )?;
+                let output = save_run(&cli, &match_index, output)?;
+                report_output(&cli, &config, output).await?;
+            }
+            Command::Render { source, format } => {
+                let output = render::read_results(&source)?;
+                if format == RenderFormat::Html {
+                    print!("{}", render::report_html(&output)?);
+                } else if format == RenderFormat::Markdown {
+                    print!("{}", render::report_markdown(&output));
+                } else {
+                    cli.json = format == RenderFormat::Json;
+                    cli.oneline = format == RenderFormat::Oneline;
+                    let _pager = start_pager(&cli);
+                    report_output(&cli, &config, output).await?;
+                }
+            }
+            Command::Fav { command: FavCommand::Add { riot_id } } => {
+                let mut contact_book = contacts::ContactBook::load()?;
+                let puuid = AccountCache::load()?.get(&riot_id).map(|account| account.puuid.clone());
+                contact_book.get_or_insert(&riot_id, puuid.as_deref()).favorite = true;
+                contact_book.save()?;
+                println!("Added '{}' to the favorites.", riot_id);
+            }
+            Command::Fav { command: FavCommand::Remove { riot_id } } => {
+                let mut contact_book = contacts::ContactBook::load()?;
+                match contact_book.find_mut(&riot_id) {
+                    Some(contact) if contact.favorite => {
+                        contact.favorite = false;
+                        contact_book.save()?;
+                        println!("Removed '{}' from the favorites.", riot_id);
+                    }
+                    _ => println!("'{}' is not a favorite.", riot_id),
+                }
+            }
+            Command::Fav { command: FavCommand::List } => {
+                let contact_book = contacts::ContactBook::load()?;
+                let favorites: Vec<_> = contact_book.favorites().collect();
+                if favorites.is_empty() {
+                    println!("No favorites yet. Add one with `ptg fav add <RIOT_ID>`.");
+                }
+                for favorite in favorites {
+                    println!("{}", favorite.riot_id());
+                }
+            }
+            Command::Fav { command: FavCommand::Check { number } } => {
+                let self_riot_id = config.self_riot_id.clone().map(RiotId::from)
+                    .ok_or("No 'self' Riot ID stored. Please set it using `--self <RIOT_ID>`.")?;
+                let favorites: Vec<RiotId> = contacts::ContactBook::load()?.favorites().map(|c| c.riot_id()).collect();
+                if favorites.is_empty() {
+                    return Err("No favorites yet. Add one with `ptg fav add <RIOT_ID>`.".into());
+                }
+                let riot_api = riot_api_pool(&config)?;
+                let mut account_cache = AccountCache::load()?;
+                let match_index = MatchIndex::open()?;
+                let summaries = favorites::run_fav_check(
+                    &riot_api,
+                    &mut account_cache,
+                    &match_index,
+                    favorites::FavCheckOptions {
+                        self_riot_id,
+                        favorites,
+                        user_selected_region: user_selected_region(&cli, &config).or(config.self_region.clone()),
+                        number_of_matches: number,
+                    },
+                ).await?;
+                account_cache.save()?;
+                if cli.json {
+                    println!("{}", serde_json::to_string_pretty(&summaries)?);
+                } else {
+                    favorites::print_fav_summaries(&summaries);
+                }
+            }
+            Command::Digest { weekly: _, format } => {
+                let self_riot_id = config.self_riot_id.clone().map(RiotId::from)
+                    .ok_or("No 'self' Riot ID stored. Please set it using `--self <RIOT_ID>`.")?;
+                let favorites: Vec<RiotId> = contacts::ContactBook::load()?.favorites().map(|c| c.riot_id()).collect();
+                if favorites.is_empty() {
+                    return Err("No favorites yet. Add one with `ptg fav add <RIOT_ID>`.".into());
+                }
+                let riot_api = riot_api_pool(&config)?;
+                let mut account_cache = AccountCache::load()?;
+                let match_index = MatchIndex::open()?;
+                let digest = digest::run_weekly_digest(
+                    &riot_api,
+                    &mut account_cache,
+                    &match_index,
+                    digest::DigestOptions {
+                        self_riot_id,
+                        favorites,
+                        user_selected_region: user_selected_region(&cli, &config).or(config.self_region.clone()),
+                    },
+                ).await?;
+                account_cache.save()?;
+                for notifier in build_notifiers(&cli, &config)? {
+                    notifier.send_digest(&digest).await?;
+                }
+                if cli.json {
+                    println!("{}", serde_json::to_string_pretty(&digest)?);
+                } else {
+                    print!("{}", digest.render(format)?);
+                }
+            }
+            Command::Contacts { command: ContactsCommand::List } => {
+                let contact_book = contacts::ContactBook::load()?;
+                let mut any = false;
+                for contact in contact_book.all() {
+                    any = true;
+                    let former: Vec<String> = contact.former_riot_ids.iter().map(|f| RiotId::from(f.clone()).to_string()).collect();
+                    let name = contacts::display_name(&contact.riot_id());
+                    if former.is_empty() {
+                        println!("{}", name);
+                    } else {
+                        println!("{} (formerly {})", name, former.join(", "));
+                    }
+                }
+                if !any {
+                    println!("No contacts yet. Add one with `ptg fav add <RIOT_ID>`.");
+                }
+            }
+            Command::Contacts { command: ContactsCommand::Nick { riot_id, nickname } } => {
+                let mut contact_book = contacts::ContactBook::load()?;
+                let puuid = AccountCache::load()?.get(&riot_id).map(|account| account.puuid.clone());
+                let contact = contact_book.get_or_insert(&riot_id, puuid.as_deref());
+                match &nickname {
+                    Some(nickname) => println!("'{}' is now shown as '{}'.", riot_id, nickname),
+                    None => println!("Removed the nickname of '{}'.", riot_id),
+                }
+                contact.nickname = nickname;
+                contact_book.save()?;
+            }
+            Command::Contacts { command: ContactsCommand::Region { riot_id, home_region } } => {
+                let mut contact_book = contacts::ContactBook::load()?;
+                let puuid = AccountCache::load()?.get(&riot_id).map(|account| account.puuid.clone());
+                let contact = contact_book.get_or_insert(&riot_id, puuid.as_deref());
+                match &home_region {
+                    Some(region) => println!("'{}' now plays on {}.", riot_id, region.to_log_string().to_uppercase()),
+                    None => println!("Removed the region of '{}'.", riot_id),
+                }
+                contact.region = home_region;
+                contact_book.save()?;
+            }
+            Command::Contacts { command: ContactsCommand::Refresh } => {
+                let riot_api = riot_api_pool(&config)?;
+                let mut account_cache = AccountCache::load()?;
+                let mut contact_book = contacts::ContactBook::load()?;
+                let changes = contact_book.refresh_riot_ids(&riot_api, &mut account_cache, regional_route).await;
+                contact_book.save()?;
+                account_cache.save()?;
+                if cli.json {
+                    println!("{}", serde_json::to_string_pretty(&changes)?);
+                } else if changes.is_empty() {
+                    println!("No contact changed their Riot ID.");
+                } else {
+                    for change in changes {
+                        println!("{} is now {}.", change.previous, change.current);
+                    }
+                }
+            }
+            Command::Note { match_id, note, tag, clear } => {
+                let mut note_book = notes::NoteBook::load()?;
+                if clear {
+                    if note_book.remove(&match_id) {
+                        println!("Removed the notes of {}.", match_id);
+                    } else {
+                        println!("{} has no notes.", match_id);
+                    }
+                } else if note.is_none() && tag.is_empty() {
+                    match note_book.get(&match_id) {
+                        Some(match_note) => println!("{}: {}", match_id, match_note.summary()),
+                        None => println!("{} has no notes.", match_id),
+                    }
+                    return Ok(());
+                } else {
+                    note_book.add(&match_id, note, tag);
+                    println!("Saved note for {}.", match_id);
+                }
+                note_book.save()?;
+            }
+            Command::Tft { player1, player2, number } => {
+                let riot_api = riot_api_pool(&config)?;
+                let mut account_cache = AccountCache::load()?;
+                let mut output = tft::run_tft_query(
+                    &riot_api,
+                    &mut account_cache,
+                    tft::TftOptions {
+                        player1_riot_id: player1,
+                        player2_riot_id: player2,
+                        regional_route,
+                        number_of_matches: number,
+                        verbose: cli.verbose,
+                    },
+                ).await?;
+                account_cache.save()?;
+                if cli.anonymize {
+                    anonymize::anonymize_identity(&mut output.player1);
+                    anonymize::anonymize_identity(&mut output.player2);
+                }
+                if cli.json {
+                    println!("{}", serde_json::to_string_pretty(&output)?);
+                } else {
+                    output.print();
+                }
+            }
+            Command::Leaderboard { by } => {
+                let self_riot_id = config.self_riot_id.clone().map(RiotId::from)
+                    .ok_or("No 'self' Riot ID stored. Please set it using `--self <RIOT_ID>`.")?;
+                let contacts: Vec<RiotId> = contacts::ContactBook::load()?.all().map(|c| c.riot_id()).collect();
+                if contacts.is_empty() {
+                    return Err("No contacts yet. Add one with `ptg fav add <RIOT_ID>`.".into());
+                }
+                let account_cache = AccountCache::load()?;
+                let match_index = MatchIndex::open()?;
+                let entries = leaderboard::run_leaderboard(&account_cache, &match_index, &self_riot_id, contacts, by)?;
+                if cli.json {
+                    println!("{}", serde_json::to_string_pretty(&entries)?);
+                } else {
+                    leaderboard::print_leaderboard(&entries);
+                }
+            }
+            Command::Site { command: SiteCommand::Build { dir } } => {
+                let self_riot_id = config.self_riot_id.clone().map(RiotId::from)
+                    .ok_or("No 'self' Riot ID stored. Please set it using `--self <RIOT_ID>`.")?;
+                let contacts: Vec<RiotId> = contacts::ContactBook::load()?.all().map(|c| c.riot_id()).collect();
+                if contacts.is_empty() {
+                    return Err("No contacts yet. Add one with `ptg fav add <RIOT_ID>`.".into());
+                }
+                let account_cache = AccountCache::load()?;
+                let match_index = MatchIndex::open()?;
+                let report = site::build_site(&account_cache, &match_index, &self_riot_id, contacts, &dir)?;
+                if cli.json {
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                } else {
+                    println!(
+                        "Built {} partner pages and {} match pages in {}.",
+                        report.pair_pages,
+                        report.match_pages,
+                        dir.display()
+                    );
+                }
+            }
+            Command::ChampionPool { player1, player2, mastery } => {
+                let riot_api = if mastery { Some(riot_api_pool(&config)?) } else { None };
+                let account_cache = AccountCache::load()?;
+                let match_index = MatchIndex::open()?;
+                let mut output = champion_pool::run_champion_pool(
+                    riot_api.as_ref(),
+                    &account_cache,
+                    &match_index,
+                    champion_pool::ChampionPoolOptions {
+                        player1_riot_id: player1,
+                        player2_riot_id: player2,
+                        fetch_mastery: mastery,
+                        platform_route: user_selected_region(&cli, &config).map(|r| r.to_platform_route()),
+                    },
+                ).await?;
+                if cli.anonymize {
+                    anonymize::anonymize_identity(&mut output.player1);
+                    anonymize::anonymize_identity(&mut output.player2);
+                }
+                if cli.json {
+                    println!("{}", serde_json::to_string_pretty(&output)?);
+                } else {
+                    output.print();
+                }
+            }
+            Command::Lineup { riot_ids } => {
+                let account_cache = AccountCache::load()?;
+                let match_index = MatchIndex::open()?;
+                let output = lineup::run_lineup(&account_cache, &match_index, &riot_ids)?;
+                if cli.json {
+                    println!("{}", serde_json::to_string_pretty(&output)?);
+                } else {
+                    output.print();
+                }
+            }
+            Command::Scout { riot_ids, max_matches, bans } => {
+                let riot_api = riot_api_pool(&config)?;
+                let mut account_cache = AccountCache::load()?;
+                let match_index = MatchIndex::open()?;
+                let output = run_scout(
+                    &riot_api,
+                    &mut account_cache,
+                    &match_index,
+                    ScoutOptions { riot_ids, regional_route, number_of_matches: max_matches },
+                ).await?;
+                account_cache.save()?;
+                if let Some(format) = bans {
+                    output.print_ban_list(format)?;
+                } else if cli.json {
+                    println!("{}", serde_json::to_string_pretty(&output)?);
+                } else {
+                    output.print();
+                }
+            }
+            Command::CanDuo { player1, player2 } => {
+                let riot_api = riot_api_pool(&config)?;
+                let mut account_cache = AccountCache::load()?;
+                let mut output = can_duo::run_can_duo(
+                    &riot_api,
+                    &mut account_cache,
+                    can_duo::CanDuoOptions {
+                        player1_riot_id: player1,
+                        player2_riot_id: player2,
+                        regional_route,
+                        platform_route: user_selected_region(&cli, &config).map(|r| r.to_platform_route()),
+                        verbose: cli.verbose,
+                    },
+                ).await?;
+                account_cache.save()?;
+                if cli.anonymize {
+                    anonymize::anonymize_identity(&mut output.player1.player);
+                    anonymize::anonymize_identity(&mut output.player2.player);
+                }
+                if cli.json {
+                    println!("{}", serde_json::to_string_pretty(&output)?);
+                } else {
+                    output.print();
+                }
+            }
+            Command::Rivalry { first_pair, second_pair } => {
+                let (Ok([a, b]), Ok([c, d])) = (<[RiotId; 2]>::try_from(first_pair), <[RiotId; 2]>::try_from(second_pair)) else {
+                    unreachable!("clap takes exactly two Riot IDs per duo");
+                };
+                let riot_api = riot_api_pool(&config)?;
+                let mut account_cache = AccountCache::load()?;
+                let match_index = MatchIndex::open()?;
+                let mut output = rivalry::run_rivalry(
+                    &riot_api,
+                    &mut account_cache,
+                    &match_index,
+                    rivalry::RivalryOptions {
+                        user_selected_region: query_region(&cli, &config, &a, &b)?,
+                        first_pair: (a, b),
+                        second_pair: (c, d),
+                        regional_route,
+                        number_of_matches: cli.number,
+                        match_type: cli.match_type.or(config.default_match_type),
+                        queue_id: cli.queue_id,
+                        patch: cli.patch.clone(),
+                        date_range: query_date_range(&cli)?,
+                        include_afk: cli.include_afk,
+                        verbose: cli.verbose,
+                    },
+                ).await?;
+                account_cache.save()?;
+                if cli.anonymize {
+                    for record in [&mut output.first, &mut output.second] {
+                        anonymize::anonymize_identity(&mut record.player1);
+                        anonymize::anonymize_identity(&mut record.player2);
+                    }
+                }
+                if cli.json {
+                    println!("{}", serde_json::to_string_pretty(&output)?);
+                } else {
+                    output.print();
+                }
+            }
+            Command::Doctor => unreachable!("handled before loading the config"),
+            Command::HelpTopics { .. } => unreachable!("handled before loading the config"),
+            Command::SelfUpdate { yes } => {
+                updater::run_self_update(yes).await?;
+            }
+            Command::Config { command: ConfigCommand::Encrypt } => {
+                if config.is_encrypted() {
+                    return Err("The API keys are already encrypted. Run `config decrypt` first to change the passphrase.".into());
+                }
+                config.encrypt_with(secrets::read_passphrase(true)?);
+                config.save()?;
+                println!("Encrypted the stored API keys.");
+            }
+            Command::Config { command: ConfigCommand::Decrypt } => {
+                if !config.is_encrypted() {
+                    return Err("The API keys are not encrypted.".into());
+                }
+                config.decrypt();
+                config.save()?;
+                println!("Stored the API keys in plaintext.");
+            }
+            Command::Config { command: ConfigCommand::Export { path, include_api_keys } } => {
+                portable::export_setup(&config, &path, include_api_keys)?;
+                if include_api_keys {
+                    println!("Exported the setup, API keys included, to {}. Keep the file private.", path.display());
+                } else {
+                    println!("Exported the setup to {} (without API keys).", path.display());
+                }
+            }
+            Command::Config { command: ConfigCommand::Import { path } } => {
+                let report = portable::import_setup(&mut config, &path)?;
+                if cli.json {
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                } else {
+                    println!(
+                        "Imported {}: {} new contacts, new notes on {} matches{}.",
+                        path.display(),
+                        report.added_contacts,
+                        report.updated_notes,
+                        if report.imported_api_keys { ", API keys replaced" } else { "" }
+                    );
+                }
+            }
+            Command::Cache { command: CacheCommand::Prune { max_size_mb, max_age_days } } => {
+                let mut account_cache = AccountCache::load()?;
+                let match_index = MatchIndex::open()?;
+                let retention = RetentionConfig {
+                    max_cache_size_mb: max_size_mb.or(config.retention.max_cache_size_mb),
+                    max_account_age_days: max_age_days.or(config.retention.max_account_age_days),
+                };
+                run_cache_prune(&mut account_cache, &match_index, &retention)?;
+            }
+        }
         return Ok(());
     }
 
+    if let Some(run_path) = &cli.load_run {
+        let bundle = RunBundle::load(run_path)?;
+        let newly_indexed = bundle.index_matches(&MatchIndex::open()?)?;
+        if !cli.silent {
+            eprintln!("Loaded {} ({} matches added to the local index).", run_path.display(), newly_indexed);
+        }
+        let _pager = start_pager(&cli);
+        return report_output(&cli, &config, bundle.output).await;
+    }
+
+    let mut piped_matches = None;
+    if let Some(source) = &cli.from_json {
+        match render::read_input(source)? {
+            JsonInput::Results(output) => {
+                let _pager = start_pager(&cli);
+                return report_output(&cli, &config, *output).await;
+            }
+            JsonInput::Matches(matches) => piped_matches = Some(matches),
+        }
+    }
+
     let player1_riot_id: RiotId;
     let player2_riot_id: RiotId;
 
@@ -46,7 +650,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
             return Ok(());
         }
         1 => {
-            if let Some(self_id_stored) = config.self_riot_id.map(RiotId::from) {
+            if let Some(self_id_stored) = config.self_riot_id.clone().map(RiotId::from) {
                 player1_riot_id = self_id_stored;
                 player2_riot_id = cli.riot_ids[0].clone();
             } else {
@@ -65,70 +669,440 @@ async fn main() -> Result<(), Box<dyn Error>> {
         }
     }
 
-    let user_selected_region: Option<UserFacingRegion> = cli.region.or(cli.default_region.clone());
+    let user_selected_region: Option<UserFacingRegion> = query_region(&cli, &config, &player1_riot_id, &player2_riot_id)?;
+
+    if let Some(matches) = piped_matches {
+        let output = pair_history_of_matches(
+            &matches,
+            PairHistoryOptions {
+                player1_riot_id,
+                player2_riot_id,
+                all_time: true,
+                user_selected_region,
+                patch: cli.patch.clone(),
+                include_afk: cli.include_afk,
+                data_dragon: localization(&cli, &config).await?,
+                verbose: cli.verbose,
+                quiet: cli.json,
+            },
+        )?;
+        let _pager = start_pager(&cli);
+        return report_output(&cli, &config, output).await;
+    }
 
     let regional_route = user_selected_region.as_ref()
-                                               .map(|r| r.to_regional_route())
-                                               .unwrap_or(RegionalRoute::EUROPE);
+                                               .map(|r| r.to_regional_route());
 
-    let api_key = if let Some(key_from_config) = config.api_key {
-        key_from_config
-    } else {
-        env::var("RGAPI_KEY")
-            .expect("Riot API key not found. Please set RGAPI_KEY environment variable, or store it using --api-key <YOUR_KEY>.")
-    };
-    let riot_api = RiotApi::new(api_key);
+    let riot_api = riot_api_pool(&config)?;
+
+    let mut riot_degraded = false;
+    if cli.status_check && cli.offline {
+        eprintln!("Warning: --status-check needs the network; skipping it with --offline.");
+    } else if cli.status_check {
+        match user_selected_region.as_ref().map(|r| r.to_platform_route()) {
+            Some(platform) => match status::check_platform_status(&riot_api, platform).await {
+                Ok(platform_status) => {
+                    platform_status.print_warning();
+                    riot_degraded = platform_status.is_degraded();
+                }
+                Err(e) => {
+                    eprintln!("Warning: Riot's status service did not answer ({}); Riot may be having an outage.", e);
+                    riot_degraded = true;
+                }
+            },
+            None => eprintln!("Warning: --status-check needs a region (--region or default_region); skipping it."),
+        }
+    }
 
+    let mut account_cache = AccountCache::load()?;
+    let match_index = MatchIndex::open()?;
+    let _pager = start_pager(&cli);
     let output = run_query(
         &riot_api,
-        player1_riot_id,
-        player2_riot_id,
-        regional_route,
-        user_selected_region,
-        cli.number,
-        cli.verbose,
-        cli.silent,
-        cli.json,
-    ).await?;
+        &mut account_cache,
+        &match_index,
+        QueryOptions {
+            player1_riot_id,
+            player2_riot_id,
+            player3_riot_id: cli.venn.clone(),
+            match_type: cli.match_type.or(config.default_match_type),
+            auto_widen_max_days: cli.auto_widen.then(|| cli.max_window_days.or(config.max_window_days).unwrap_or(DEFAULT_MAX_WINDOW_DAYS)),
+            queue_id: cli.queue_id,
+            regional_route,
+            user_selected_region,
+            number_of_matches: cli.number,
+            patch: cli.patch.clone(),
+            date_range: query_date_range(&cli)?,
+            fetch_timelines: cli.timeline,
+            fetch_profiles: cli.profile,
+            opponents_only: cli.opponents,
+            follow_region_transfer: cli.follow_transfer,
+            stop_at_first_shared: cli.exists,
+            include_afk: cli.include_afk,
+            solo_baseline: cli.solo_baseline,
+            data_dragon: localization(&cli, &config).await?,
+            verbose: cli.verbose,
+            quiet: cli.json,
+        },
+    ).await;
+    let output = match output {
+        Ok(output) => output,
+        Err(e) if cli.exists => {
+            eprintln!("Error: {}", e);
+            std::process::exit(EXISTS_QUERY_FAILED);
+        }
+        Err(e) if riot_degraded => {
+            return Err(format!("{} (Riot is reporting problems for this region, so this is likely not an issue with your query; try again later.)", e).into());
+        }
+        Err(e) => return Err(e),
+    };
+    account_cache.save()?;
+    let mut output = save_run(&cli, &match_index, output)?;
+
+    if cli.exists {
+        if cli.anonymize {
+            anonymize::anonymize_output(&mut output);
+        }
+        let summary = &output.query_summary;
+        let played_together = summary.matches_played_together_count > 0;
+        if !cli.silent {
+            let (name1, name2) = (
+                summary.player1.display_name(),
+                summary.player2.display_name(),
+            );
+            match output.found_matches.first() {
+                Some(found) => println!("Yes: {} and {} played together in {}.", name1, name2, found.match_id),
+                None if played_together => println!("Yes: {} and {} played together.", name1, name2),
+                None => println!("No: {} and {} did not play together in {} checked matches.", name1, name2, summary.checked_matches_count),
+            }
+        }
+        std::process::exit(if played_together { 0 } else { EXISTS_NOT_FOUND });
+    }
+
+    report_output(&cli, &config, output).await
+}
+
+/// The span given with --on or --between, if any.
+fn query_date_range(cli: &Cli) -> Result<Option<DateRange>, Box<dyn Error>> {
+    Ok(match (cli.on, cli.between.as_deref()) {
+        (Some(day), _) => Some(DateRange::on(day)?),
+        (None, Some([first, last])) => Some(DateRange::between(*first, *last)?),
+        _ => None,
+    })
+}
+
+/// Exit status of `--exists` when no shared match was found, like `grep -q`.
+const EXISTS_NOT_FOUND: i32 = 1;
+/// Exit status of `--exists` when the query itself failed.
+const EXISTS_QUERY_FAILED: i32 = 2;
+
+/// Writes the --save-run bundle of `output`, if asked for, and hands the output back for reporting.
+fn save_run(cli: &Cli, match_index: &MatchIndex, output: OverallOutput) -> Result<OverallOutput, Box<dyn Error>> {
+    let Some(run_path) = &cli.save_run else {
+        return Ok(output);
+    };
+    let bundle = RunBundle::capture(output, match_index)?;
+    bundle.save(run_path)?;
+    if !cli.silent {
+        eprintln!("Saved the run to {}; render it anywhere with `ptg --load-run {}`.", run_path.display(), run_path.display());
+    }
+    Ok(bundle.output)
+}
+
+/// Pages the human-readable match results, which run long with --verbose.
+fn start_pager(cli: &Cli) -> Option<pager::Pager> {
+    if cli.no_pager || cli.exists || cli.json || cli.template.is_some() {
+        return None;
+    }
+    pager::start()
+}
+
+/// Replaces PUUIDs given instead of Riot IDs with the Riot ID they belong to, from the account cache
+/// when possible and from account-v1 otherwise.
+async fn resolve_puuid_arguments(cli: &mut Cli, config: &Config) -> Result<(), Box<dyn Error>> {
+    let regional_route = user_selected_region(cli, config)
+        .map(|r| r.to_regional_route())
+        .unwrap_or(ANY_ACCOUNT_ROUTE);
+    let mut account_cache: Option<AccountCache> = None;
+    let mut riot_api = None;
+    for riot_id in cli.riot_ids_mut() {
+        let Some(puuid) = riot_id.unresolved_puuid().map(str::to_string) else {
+            continue;
+        };
+        let account_cache = match &mut account_cache {
+            Some(account_cache) => account_cache,
+            None => account_cache.insert(AccountCache::load()?),
+        };
+        if let Some(account) = account_cache.find_by_puuid(&puuid) {
+            *riot_id = RiotId { game_name: account.game_name.clone(), tag_line: account.tag_line.clone(), puuid: None };
+            continue;
+        }
+        let riot_api = match &riot_api {
+            Some(riot_api) => riot_api,
+            None => riot_api.insert(riot_api_pool(config)?),
+        };
+        let resolved = fetch_riot_id(riot_api, regional_route, &puuid, &mut ApiCallStats::default())
+            .await?
+            .ok_or_else(|| format!("The account with PUUID '{}' has no Riot ID.", anonymize::puuid(&puuid)))?;
+        account_cache.insert(&resolved, &puuid, None);
+        *riot_id = resolved;
+    }
+    if let Some(account_cache) = account_cache {
+        account_cache.save()?;
+    }
+    Ok(())
+}
+
+/// The notifiers selected by --notify-target, --notify and --webhook.
+fn build_notifiers(cli: &Cli, config: &Config) -> Result<Vec<Box<dyn notify::Notifier>>, Box<dyn Error>> {
+    let notify_targets = if !cli.notify_target.is_empty() {
+        cli.notify_target.clone()
+    } else if cli.notify {
+        config.integrations.notify_targets.clone()
+    } else {
+        Vec::new()
+    };
+    let mut notifiers = notify_targets
+        .into_iter()
+        .map(|target| notify::build_notifier(target, &config.integrations))
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Some(url) = &cli.webhook {
+        notifiers.push(Box::new(notify::WebhookNotifier {
+            url: url.clone(),
+            template: cli.webhook_template.clone(),
+            content_type: cli.webhook_content_type.clone()
+                .unwrap_or_else(|| notify::DEFAULT_WEBHOOK_CONTENT_TYPE.to_string()),
+        }));
+    }
+    Ok(notifiers)
+}
+
+/// Delivers the results of a query to every requested destination and prints them.
+async fn report_output(cli: &Cli, config: &Config, mut output: OverallOutput) -> Result<(), Box<dyn Error>> {
+    queried_contact_book().annotate(&mut output);
+    // Notes are looked up by match ID, so they go in before the IDs are hashed.
+    notes::NoteBook::load()?.annotate(&mut output);
+    if cli.anonymize {
+        anonymize::anonymize_output(&mut output);
+    }
+    if cli.assets {
+        ddragon::DataDragon::load(ddragon::DEFAULT_LOCALE).await?.add_assets(&mut output);
+    }
+    let export_target = match cli.export.as_deref() {
+        Some([format, path]) => Some((format.parse::<ExportFormat>()?, path.clone())),
+        _ => None,
+    };
+
+    if let Some(spreadsheet_id) = &cli.sheets {
+        let credentials_path = config.integrations.google_service_account_path.as_deref()
+            .ok_or("No Google service-account key stored. Please set it using `--sheets-credentials <PATH>`.")?;
+        let appended_rows = integrations::append_to_google_sheet(
+            &output,
+            spreadsheet_id,
+            credentials_path,
+            config.integrations.google_sheets_range.as_deref(),
+        ).await?;
+        if !cli.json {
+            println!("Appended {} rows to Google Sheet {}.", appended_rows, spreadsheet_id);
+        }
+    }
+
+    for notifier in build_notifiers(cli, config)? {
+        if cli.notify_matches {
+            for match_details in &output.found_matches {
+                notifier.send_new_match(&output.query_summary, match_details).await?;
+            }
+        }
+        notifier.send_summary(&output).await?;
+    }
+
+    if let Some(chart_path) = &cli.chart {
+        match chart::win_rate_svg(&output.found_matches) {
+            Some(svg) => {
+                std::fs::write(chart_path, svg)?;
+                if !cli.json {
+                    println!("Wrote the win rate chart to {}.", chart_path.display());
+                }
+            }
+            None => eprintln!("Warning: The win rate chart needs at least two shared games. Skipping it."),
+        }
+    }
+
+    if let Some((format, path)) = &export_target {
+        export::export(&output, *format, Path::new(path))?;
+        if !cli.json {
+            println!("Exported results to {}.", path);
+        }
+    }
+
+    if let Some(output_dir) = &cli.output_dir {
+        let written = render::write_formats(&output, &cli.formats, output_dir)?;
+        if !cli.json {
+            let names: Vec<String> = written.iter().map(|path| path.display().to_string()).collect();
+            println!("Wrote {}.", names.join(", "));
+        }
+    }
 
     if cli.json {
-        let json_output = serde_json::to_string_pretty(&output)?;
-        println!("{}", json_output);
+        output.write_json(std::io::stdout().lock())?;
+    } else if let Some(template_path) = &cli.template {
+        let mut context = serde_json::to_value(&output)?;
+        context["winRateChart"] = chart::win_rate_svg(&output.found_matches).into();
+        print!("{}", templates::render_template_file(template_path, &context)?);
     } else {
 
         let summary = &output.query_summary;
         let found_matches = &output.found_matches;
 
-        if cli.silent || !cli.verbose {
+        if cli.oneline {
+            print_match_lines(found_matches);
+        } else if cli.silent || !cli.verbose {
             println!("\n--- Query Summary ---");
             println!(
-                "Checked {} matches for {}#{}.",
-                summary.checked_matches_count, summary.player1.game_name, summary.player1.tag_line
+                "Checked {} matches for {}.",
+                summary.checked_matches_count, summary.player1.display_name()
             );
             println!(
-                "Found {} matches where {}#{} and {}#{} played together.",
+                "Found {} matches where {} and {} played together.",
                 summary.matches_played_together_count,
-                summary.player1.game_name, summary.player1.tag_line,
-                summary.player2.game_name, summary.player2.tag_line
+                summary.player1.display_name(),
+                summary.player2.display_name()
             );
             println!(
-                "Of those, {} games were won by {}#{}.",
-                summary.player1_wins_together_count, summary.player1.game_name, summary.player1.tag_line
+                "Of those, {} games were won by {}.",
+                summary.player1_wins_together_count, summary.player1.display_name()
             );
+            if summary.carries.player1 + summary.carries.player2 > 0 {
+                println!(
+                    "Carried: {} {} games, {} {} games.",
+                    summary.player1.display_name(), summary.carries.player1,
+                    summary.player2.display_name(), summary.carries.player2
+                );
+            }
+            if let Some(form) = &output.stats.recent_form {
+                println!("Recent form: {}.", form.label());
+            }
+            if found_matches.len() >= 2 {
+                let rates: Vec<f64> = chart::rolling_win_rates(found_matches).into_iter().map(|(_, rate)| rate).collect();
+                println!(
+                    "Win rate trend: {} (last {} games each)",
+                    utils::sparkline_in_range(&rates, 0.0, 100.0),
+                    chart::ROLLING_WINDOW
+                );
+                let weeks: Vec<f64> = chart::games_per_week(found_matches).into_iter().map(f64::from).collect();
+                let period = if weeks.len() == 1 { "1 week".to_string() } else { format!("{} weeks", weeks.len()) };
+                println!("Games per week: {} (over {})", utils::sparkline(&weeks), period);
+            }
+            for player in [&summary.player1, &summary.player2] {
+                if !player.formerly_known_as.is_empty() {
+                    println!(
+                        "{} was formerly known as {}.",
+                        player.display_name(), player.formerly_known_as.join(", ")
+                    );
+                }
+                if let Some(profile) = &player.profile {
+                    println!(
+                        "{} is level {} (icon: {}).",
+                        player.display_name(), profile.summoner_level, profile.profile_icon_url
+                    );
+                }
+            }
+            let lane_matchups: Vec<_> = found_matches.iter().filter_map(|m| m.lane_matchup.as_ref()).collect();
+            if !lane_matchups.is_empty() {
+                println!(
+                    "Faced each other in lane {} times: solo kills {}-{}, {} of those games won by {}.",
+                    lane_matchups.len(),
+                    lane_matchups.iter().map(|l| l.player1_solo_kills).sum::<u32>(),
+                    lane_matchups.iter().map(|l| l.player2_solo_kills).sum::<u32>(),
+                    found_matches.iter().filter(|m| m.lane_matchup.is_some() && m.player1_details.outcome == "Victory").count(),
+                    summary.player1.display_name()
+                );
+            }
+            let unavailable = &summary.unavailable_matches;
+            if unavailable.total() > 0 {
+                println!(
+                    "Skipped {} matches without details: {} expired, {} restricted, {} missing.",
+                    unavailable.total(), unavailable.expired, unavailable.forbidden, unavailable.missing
+                );
+            }
+            if summary.outline_only_matches_count > 0 {
+                println!(
+                    "{} of the shared matches are in a game mode ptg cannot fully read yet; only their results are counted.",
+                    summary.outline_only_matches_count
+                );
+            }
+            if summary.likely_afk_matches_count > 0 {
+                if cli.include_afk {
+                    println!("{} of the shared games had a player who likely went AFK.", summary.likely_afk_matches_count);
+                } else {
+                    println!(
+                        "Left out {} shared games where one of the players likely went AFK (count them with --include-afk).",
+                        summary.likely_afk_matches_count
+                    );
+                }
+            }
+            if summary.per_queue.0.len() > 1 {
+                println!("Per queue: {}.", summary.per_queue.summary_line());
+            }
+            if summary.ranked.has_games() {
+                println!(
+                    "Ranked together: Solo/Duo {}W-{}L, Flex {}W-{}L (net {:+} games, estimated {:+} LP).",
+                    summary.ranked.solo.wins, summary.ranked.solo.losses,
+                    summary.ranked.flex.wins, summary.ranked.flex.losses,
+                    summary.ranked.net_wins, summary.ranked.estimated_net_lp
+                );
+            }
+            if !cli.sessions {
+                sessions::print_tilt(&output.sessions);
+            }
+            println!(
+                "Used {} API calls ({} account, {} match list, {} match details, {} timelines, {} cache hits) in {:.1}s.",
+                summary.api_calls.total_calls,
+                summary.api_calls.account_calls,
+                summary.api_calls.match_list_calls,
+                summary.api_calls.match_detail_calls,
+                summary.api_calls.timeline_calls,
+                summary.api_calls.cache_hits,
+                summary.api_calls.wall_time_ms as f64 / 1000.0
+            );
+
+            if !cli.silent {
+                achievements::print_achievements(&output.achievements);
+            }
 
-            if !cli.silent && !cli.verbose {
+            // Anonymized results have no links, since they would name the players.
+            if !cli.silent && !cli.verbose && !cli.anonymize {
                 println!("\n--- Found Game Links ---");
                 if found_matches.is_empty() {
                     println!("No games found together.");
                 } else {
                     for match_detail in found_matches {
                         if let Some(link) = &match_detail.league_of_graphs_link {
-                            println!("{}", link);
+                            match &match_detail.note {
+                                Some(note) => println!("{}  {}", link, note.summary()),
+                                None => println!("{}", link),
+                            }
                         }
                     }
                 }
             }
         }
+
+        if cli.stats {
+            output.stats.print();
+        }
+        if let Some(solo_baseline) = &output.stats.solo_baseline {
+            solo_baseline.print(&output.query_summary.player1.display_name(), &output.query_summary.player2.display_name());
+        }
+        if cli.duo_timeline {
+            output.duo_timeline.print_chart();
+        }
+        if cli.sessions {
+            sessions::print_sessions(&output.sessions);
+        }
+        if let Some(venn) = &output.query_summary.venn {
+            venn.print(&output.query_summary);
+        }
     }
     Ok(())
 }
\ No newline at end of file
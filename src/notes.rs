@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::api_client::OverallOutput;
+use crate::config::Config;
+
+const NOTES_FILE_NAME: &str = "notes.json";
+
+/// User notes and tags attached to a match.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchNote {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl MatchNote {
+    /// One-line rendering, e.g. `[throw, pentakill] the baron throw game`.
+    pub fn summary(&self) -> String {
+        let tags = if self.tags.is_empty() {
+            String::new()
+        } else {
+            format!("[{}] ", self.tags.join(", "))
+        };
+        format!("{}{}", tags, self.notes.join("; "))
+    }
+}
+
+/// Notes keyed by match ID. Kept in the config directory rather than the cache,
+/// since they are user data that `cache prune` must never drop.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct NoteBook {
+    matches: HashMap<String, MatchNote>,
+}
+
+impl NoteBook {
+    fn notes_file_path() -> Result<PathBuf, Box<dyn Error>> {
+        Ok(Config::config_dir()?.join(NOTES_FILE_NAME))
+    }
+
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        let path = Self::notes_file_path()?;
+        if path.exists() {
+            let content = fs::read_to_string(&path)?;
+            Ok(serde_json::from_str(&content)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?)
+        } else {
+            Ok(NoteBook::default())
+        }
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        fs::write(Self::notes_file_path()?, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, match_id: &str) -> Option<&MatchNote> {
+        self.matches.get(match_id)
+    }
+
+    /// Adds a note text and tags to a match; tags already present are not repeated.
+    pub fn add(&mut self, match_id: &str, note: Option<String>, tags: Vec<String>) {
+        let entry = self.matches.entry(match_id.to_string()).or_default();
+        entry.notes.extend(note);
+        for tag in tags {
+            if !entry.tags.contains(&tag) {
+                entry.tags.push(tag);
+            }
+        }
+    }
+
+    /// Adds the notes and tags of `imported` that are not stored yet. Returns how many matches got new ones.
+    pub fn merge(&mut self, imported: NoteBook) -> usize {
+        let mut updated = 0;
+        for (match_id, imported_note) in imported.matches {
+            let entry = self.matches.entry(match_id).or_default();
+            let (notes_before, tags_before) = (entry.notes.len(), entry.tags.len());
+            for note in imported_note.notes {
+                if !entry.notes.contains(&note) {
+                    entry.notes.push(note);
+                }
+            }
+            for tag in imported_note.tags {
+                if !entry.tags.contains(&tag) {
+                    entry.tags.push(tag);
+                }
+            }
+            if entry.notes.len() > notes_before || entry.tags.len() > tags_before {
+                updated += 1;
+            }
+        }
+        updated
+    }
+
+    pub fn remove(&mut self, match_id: &str) -> bool {
+        self.matches.remove(match_id).is_some()
+    }
+
+    /// Attaches the stored notes to every found match they belong to.
+    pub fn annotate(&self, output: &mut OverallOutput) {
+        for match_details in &mut output.found_matches {
+            match_details.note = self.get(&match_details.match_id).cloned();
+        }
+    }
+}
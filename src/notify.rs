@@ -0,0 +1,374 @@
+use async_trait::async_trait;
+use serde_json::json;
+use std::error::Error;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::api_client::{MatchDetails, OverallOutput, QuerySummary};
+use crate::cli::NotifyTarget;
+use crate::digest::{Digest, DigestFormat};
+use crate::config::IntegrationsConfig;
+use crate::templates::render_template_file;
+
+pub const DEFAULT_WEBHOOK_CONTENT_TYPE: &str = "application/json";
+
+/// Discord rejects webhook messages longer than this many characters.
+const DISCORD_MESSAGE_LIMIT: usize = 2000;
+/// Telegram rejects `sendMessage` texts longer than this many characters.
+const TELEGRAM_MESSAGE_LIMIT: usize = 4096;
+pub const DEFAULT_TELEGRAM_API_URL: &str = "https://api.telegram.org";
+
+/// A destination for scan results. Every chat service or output channel implements this,
+/// so adding a new target only means adding an implementation and a `NotifyTarget` variant.
+#[async_trait(?Send)]
+pub trait Notifier {
+    /// Sends the summary of a finished scan.
+    async fn send_summary(&self, output: &OverallOutput) -> Result<(), Box<dyn Error>>;
+
+    /// Announces a single newly found shared match.
+    async fn send_new_match(&self, summary: &QuerySummary, match_details: &MatchDetails) -> Result<(), Box<dyn Error>>;
+
+    /// Posts a periodic digest of the favorite pairs.
+    async fn send_digest(&self, digest: &Digest) -> Result<(), Box<dyn Error>>;
+}
+
+/// Short plain-text summary of a query, used by the chat notification targets.
+fn summary_text(output: &OverallOutput) -> String {
+    let summary = &output.query_summary;
+    let mut text = format!(
+        "{} and {} played {} of {} checked games together, {} won.",
+        summary.player1.display_name(),
+        summary.player2.display_name(),
+        summary.matches_played_together_count,
+        summary.checked_matches_count,
+        summary.player1_wins_together_count,
+    );
+    for link in output.found_matches.iter().filter_map(|m| m.league_of_graphs_link.as_ref()) {
+        text.push('\n');
+        text.push_str(link);
+    }
+    text
+}
+
+/// Renders the duo summary as Slack Block Kit blocks.
+fn slack_blocks(output: &OverallOutput) -> serde_json::Value {
+    let summary = &output.query_summary;
+    let win_rate = if summary.matches_played_together_count == 0 {
+        0.0
+    } else {
+        summary.player1_wins_together_count as f64 / summary.matches_played_together_count as f64 * 100.0
+    };
+    let mut blocks = vec![
+        json!({
+            "type": "header",
+            "text": {
+                "type": "plain_text",
+                "text": format!("{} + {}", summary.player1.display_name(), summary.player2.display_name()),
+            },
+        }),
+        json!({
+            "type": "section",
+            "fields": [
+                { "type": "mrkdwn", "text": format!("*Games together*\n{} of {}", summary.matches_played_together_count, summary.checked_matches_count) },
+                { "type": "mrkdwn", "text": format!("*Wins*\n{} ({:.0}%)", summary.player1_wins_together_count, win_rate) },
+            ],
+        }),
+    ];
+
+    let match_lines: Vec<String> = output
+        .found_matches
+        .iter()
+        .map(|m| {
+            let result = if m.player1_details.outcome == "Victory" { ":white_check_mark:" } else { ":x:" };
+            let title = format!("{} {} – {} & {}", m.game_date_utc, m.game_mode, m.player1_details.champion_label(), m.player2_details.champion_label());
+            match &m.league_of_graphs_link {
+                Some(link) => format!("{} <{}|{}>", result, link, title),
+                None => format!("{} {}", result, title),
+            }
+        })
+        .collect();
+    if !match_lines.is_empty() {
+        blocks.push(json!({ "type": "divider" }));
+        blocks.push(json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": match_lines.join("\n") },
+        }));
+    }
+
+    json!({ "text": summary_text(output), "blocks": blocks })
+}
+
+/// One-line description of a shared match, used for new-match alerts.
+fn new_match_text(summary: &QuerySummary, match_details: &MatchDetails) -> String {
+    let mut text = format!(
+        "New game together: {} ({}) and {} ({}) – {} {} on {}.",
+        summary.player1.display_name(),
+        match_details.player1_details.champion_label(),
+        summary.player2.display_name(),
+        match_details.player2_details.champion_label(),
+        match_details.game_mode,
+        match_details.player1_details.outcome,
+        match_details.game_date_utc,
+    );
+    if let Some(link) = &match_details.league_of_graphs_link {
+        text.push('\n');
+        text.push_str(link);
+    }
+    text
+}
+
+async fn post_json(url: &str, body: &serde_json::Value) -> Result<(), Box<dyn Error>> {
+    reqwest::Client::new()
+        .post(url)
+        .json(body)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Prints notifications to standard output.
+pub struct StdoutNotifier;
+
+#[async_trait(?Send)]
+impl Notifier for StdoutNotifier {
+    async fn send_summary(&self, output: &OverallOutput) -> Result<(), Box<dyn Error>> {
+        println!("{}", summary_text(output));
+        Ok(())
+    }
+
+    async fn send_new_match(&self, summary: &QuerySummary, match_details: &MatchDetails) -> Result<(), Box<dyn Error>> {
+        println!("{}", new_match_text(summary, match_details));
+        Ok(())
+    }
+
+    async fn send_digest(&self, digest: &Digest) -> Result<(), Box<dyn Error>> {
+        print!("{}", digest.render(DigestFormat::Markdown)?);
+        Ok(())
+    }
+}
+
+/// Quotes `text` as an AppleScript string literal, where only backslashes and double quotes need escaping.
+pub fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Shows notifications through the operating system's notification center,
+/// using the notifier command every desktop ships with.
+pub struct DesktopNotifier;
+
+impl DesktopNotifier {
+    fn show(body: &str) -> Result<(), Box<dyn Error>> {
+        let mut command = if cfg!(target_os = "macos") {
+            let mut command = Command::new("osascript");
+            command.arg("-e").arg(format!(
+                "display notification {} with title \"PlayedTogether\"",
+                applescript_string(body)
+            ));
+            command
+        } else if cfg!(target_os = "windows") {
+            let mut command = Command::new("powershell");
+            command.arg("-NoProfile").arg("-Command").arg(format!(
+                "[reflection.assembly]::loadwithpartialname('System.Windows.Forms') | Out-Null; \
+                 $n = New-Object System.Windows.Forms.NotifyIcon; \
+                 $n.Icon = [System.Drawing.SystemIcons]::Information; $n.Visible = $true; \
+                 $n.ShowBalloonTip(5000, 'PlayedTogether', '{}', 'Info'); Start-Sleep -Seconds 5",
+                body.replace('\'', "''")
+            ));
+            command
+        } else {
+            let mut command = Command::new("notify-send");
+            command.arg("PlayedTogether").arg(body);
+            command
+        };
+
+        let status = command.status()?;
+        if !status.success() {
+            return Err(format!("The desktop notification command exited with {}.", status).into());
+        }
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl Notifier for DesktopNotifier {
+    async fn send_summary(&self, output: &OverallOutput) -> Result<(), Box<dyn Error>> {
+        // Links are not clickable in most notification centers, so only the first line is shown.
+        let text = summary_text(output);
+        Self::show(text.lines().next().unwrap_or_default())
+    }
+
+    async fn send_new_match(&self, summary: &QuerySummary, match_details: &MatchDetails) -> Result<(), Box<dyn Error>> {
+        let text = new_match_text(summary, match_details);
+        Self::show(text.lines().next().unwrap_or_default())
+    }
+
+    async fn send_digest(&self, digest: &Digest) -> Result<(), Box<dyn Error>> {
+        Self::show(&format!(
+            "This week: {} games with favorites, {} won.",
+            digest.games, digest.wins
+        ))
+    }
+}
+
+/// Posts notifications to a Discord channel webhook.
+pub struct DiscordNotifier {
+    pub webhook_url: String,
+}
+
+impl DiscordNotifier {
+    async fn send(&self, text: &str) -> Result<(), Box<dyn Error>> {
+        let content: String = text.chars().take(DISCORD_MESSAGE_LIMIT).collect();
+        post_json(&self.webhook_url, &json!({ "content": content })).await
+    }
+}
+
+#[async_trait(?Send)]
+impl Notifier for DiscordNotifier {
+    async fn send_summary(&self, output: &OverallOutput) -> Result<(), Box<dyn Error>> {
+        self.send(&summary_text(output)).await
+    }
+
+    async fn send_new_match(&self, summary: &QuerySummary, match_details: &MatchDetails) -> Result<(), Box<dyn Error>> {
+        self.send(&new_match_text(summary, match_details)).await
+    }
+
+    async fn send_digest(&self, digest: &Digest) -> Result<(), Box<dyn Error>> {
+        self.send(&digest.render(DigestFormat::Markdown)?).await
+    }
+}
+
+/// Posts notifications to a Slack incoming webhook, formatted with Block Kit.
+pub struct SlackNotifier {
+    pub webhook_url: String,
+}
+
+#[async_trait(?Send)]
+impl Notifier for SlackNotifier {
+    async fn send_summary(&self, output: &OverallOutput) -> Result<(), Box<dyn Error>> {
+        post_json(&self.webhook_url, &slack_blocks(output)).await
+    }
+
+    async fn send_new_match(&self, summary: &QuerySummary, match_details: &MatchDetails) -> Result<(), Box<dyn Error>> {
+        post_json(&self.webhook_url, &json!({ "text": new_match_text(summary, match_details) })).await
+    }
+
+    async fn send_digest(&self, digest: &Digest) -> Result<(), Box<dyn Error>> {
+        post_json(&self.webhook_url, &json!({ "text": digest.render(DigestFormat::Markdown)? })).await
+    }
+}
+
+/// Sends notifications through a Telegram bot to a single chat.
+pub struct TelegramNotifier {
+    /// Bot API base URL, `https://api.telegram.org` unless a local Bot API server is used.
+    pub api_url: String,
+    pub bot_token: String,
+    pub chat_id: String,
+}
+
+impl TelegramNotifier {
+    async fn send(&self, text: &str) -> Result<(), Box<dyn Error>> {
+        let text: String = text.chars().take(TELEGRAM_MESSAGE_LIMIT).collect();
+        post_json(
+            &format!("{}/bot{}/sendMessage", self.api_url.trim_end_matches('/'), self.bot_token),
+            &json!({
+                "chat_id": self.chat_id,
+                "text": text,
+                "disable_web_page_preview": true,
+            }),
+        )
+        .await
+    }
+}
+
+#[async_trait(?Send)]
+impl Notifier for TelegramNotifier {
+    async fn send_summary(&self, output: &OverallOutput) -> Result<(), Box<dyn Error>> {
+        self.send(&summary_text(output)).await
+    }
+
+    async fn send_new_match(&self, summary: &QuerySummary, match_details: &MatchDetails) -> Result<(), Box<dyn Error>> {
+        self.send(&new_match_text(summary, match_details)).await
+    }
+
+    async fn send_digest(&self, digest: &Digest) -> Result<(), Box<dyn Error>> {
+        self.send(&digest.render(DigestFormat::Markdown)?).await
+    }
+}
+
+/// POSTs the results to an arbitrary endpoint, either as raw JSON or rendered through a Tera template.
+pub struct WebhookNotifier {
+    pub url: String,
+    /// Template rendered with the JSON payload as context; the rendering becomes the request body.
+    pub template: Option<PathBuf>,
+    pub content_type: String,
+}
+
+impl WebhookNotifier {
+    async fn send(&self, payload: serde_json::Value) -> Result<(), Box<dyn Error>> {
+        let body = match &self.template {
+            Some(template) => render_template_file(template, &payload)?,
+            None => serde_json::to_string(&payload)?,
+        };
+        reqwest::Client::new()
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, &self.content_type)
+            .body(body)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl Notifier for WebhookNotifier {
+    async fn send_summary(&self, output: &OverallOutput) -> Result<(), Box<dyn Error>> {
+        self.send(serde_json::to_value(output)?).await
+    }
+
+    async fn send_new_match(&self, summary: &QuerySummary, match_details: &MatchDetails) -> Result<(), Box<dyn Error>> {
+        self.send(json!({ "event": "newMatch", "querySummary": summary, "match": match_details })).await
+    }
+
+    async fn send_digest(&self, digest: &Digest) -> Result<(), Box<dyn Error>> {
+        self.send(json!({ "event": "digest", "digest": digest })).await
+    }
+}
+
+/// Builds the notifier for `target` from the integrations config, failing if its settings are missing.
+pub fn build_notifier(target: NotifyTarget, integrations: &IntegrationsConfig) -> Result<Box<dyn Notifier>, Box<dyn Error>> {
+    let missing = |keys: &str| -> Box<dyn Error> {
+        format!(
+            "The {} notification target is not configured. Please set {} in the integrations section of the config.",
+            target, keys
+        )
+        .into()
+    };
+
+    Ok(match target {
+        NotifyTarget::Stdout => Box::new(StdoutNotifier),
+        NotifyTarget::Desktop => Box::new(DesktopNotifier),
+        NotifyTarget::Discord => Box::new(DiscordNotifier {
+            webhook_url: integrations.discord_webhook_url.clone().ok_or_else(|| missing("discord_webhook_url"))?,
+        }),
+        NotifyTarget::Slack => Box::new(SlackNotifier {
+            webhook_url: integrations.slack_webhook_url.clone().ok_or_else(|| missing("slack_webhook_url"))?,
+        }),
+        NotifyTarget::Telegram => {
+            let (Some(bot_token), Some(chat_id)) = (&integrations.telegram_bot_token, &integrations.telegram_chat_id) else {
+                return Err(missing("telegram_bot_token and telegram_chat_id"));
+            };
+            Box::new(TelegramNotifier {
+                api_url: integrations.telegram_api_url.clone().unwrap_or_else(|| DEFAULT_TELEGRAM_API_URL.to_string()),
+                bot_token: bot_token.clone(),
+                chat_id: chat_id.clone(),
+            })
+        }
+        NotifyTarget::Webhook => Box::new(WebhookNotifier {
+            url: integrations.webhook_url.clone().ok_or_else(|| missing("webhook_url"))?,
+            template: integrations.webhook_template.clone(),
+            content_type: integrations.webhook_content_type.clone().unwrap_or_else(|| DEFAULT_WEBHOOK_CONTENT_TYPE.to_string()),
+        }),
+    })
+}
@@ -0,0 +1,28 @@
+//! `--offline`: queries are answered from the account cache, the match index and the Data Dragon
+//! cache only. Every place that would go to the network checks here first and fails with a message
+//! naming what is missing, instead of a connection error.
+
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set once by `--offline`, so the network calls deep in the query need no extra parameter.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Fails in offline mode, explaining that `missing` (e.g. "the PUUID of Foo#EUW") is not cached.
+pub fn ensure_online(missing: &str) -> Result<(), Box<dyn Error>> {
+    if is_enabled() {
+        return Err(format!(
+            "--offline: {} is not in the local cache. Run the query once without --offline (or `ptg prefetch`) first.",
+            missing
+        ).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,105 @@
+//! Pipes the human-readable output through a pager, like git does, so long verbose runs stay
+//! scrollable.
+
+use std::io::{IsTerminal, Write};
+
+/// Used when neither `PTG_PAGER` nor `PAGER` is set.
+const DEFAULT_PAGER: &str = "less";
+/// Quit if the output fits on one screen, keep colors and leave the output on screen.
+const DEFAULT_LESS_OPTIONS: &str = "FRX";
+
+/// A running pager receiving this process's stdout. Dropping it waits until the user quits the pager.
+pub struct Pager {
+    #[cfg(unix)]
+    child: std::process::Child,
+    /// Copies stdout into the pager, and discards it once the pager has quit.
+    #[cfg(unix)]
+    relay: Option<std::thread::JoinHandle<()>>,
+}
+
+fn pager_command() -> Option<String> {
+    let command = std::env::var("PTG_PAGER").or_else(|_| std::env::var("PAGER")).unwrap_or_else(|_| DEFAULT_PAGER.to_string());
+    let command = command.trim();
+    (!command.is_empty() && command != "cat").then(|| command.to_string())
+}
+
+/// Starts the pager and redirects stdout into it, unless stdout is not a terminal or no pager is
+/// configured. Only supported on Unix; elsewhere the output is printed as is.
+pub fn start() -> Option<Pager> {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    start_pager(&pager_command()?)
+}
+
+#[cfg(unix)]
+fn start_pager(command: &str) -> Option<Pager> {
+    use std::fs::File;
+    use std::io::Read;
+    use std::os::fd::FromRawFd;
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("LESS", std::env::var("LESS").unwrap_or_else(|_| DEFAULT_LESS_OPTIONS.to_string()))
+        .stdin(Stdio::piped())
+        .spawn()
+        .ok()?;
+    let mut pager_stdin = child.stdin.take()?;
+    // Stdout goes into a pipe of our own rather than straight to the pager, so quitting the pager
+    // early cannot fail a later write: the rest of the run, e.g. saves and exports, still happens.
+    let mut fds = [0; 2];
+    // SAFETY: `fds` has room for the two descriptors pipe writes.
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } < 0 {
+        let _ = child.kill();
+        return None;
+    }
+    let [read_fd, write_fd] = fds;
+    // SAFETY: both descriptors were just opened by pipe; dup2 atomically replaces stdout with the
+    // write end, and the read end is kept from processes spawned later.
+    unsafe {
+        libc::fcntl(read_fd, libc::F_SETFD, libc::FD_CLOEXEC);
+        let redirected = libc::dup2(write_fd, libc::STDOUT_FILENO);
+        libc::close(write_fd);
+        if redirected < 0 {
+            libc::close(read_fd);
+            let _ = child.kill();
+            return None;
+        }
+    }
+    // SAFETY: the read end is owned by nothing else from here on.
+    let mut output = unsafe { File::from_raw_fd(read_fd) };
+    let relay = std::thread::spawn(move || {
+        let mut buffer = [0; 8192];
+        let mut pager_open = true;
+        while let Ok(read) = output.read(&mut buffer) {
+            if read == 0 {
+                break;
+            }
+            pager_open = pager_open && pager_stdin.write_all(&buffer[..read]).is_ok();
+        }
+    });
+    Some(Pager { child, relay: Some(relay) })
+}
+
+#[cfg(not(unix))]
+fn start_pager(_command: &str) -> Option<Pager> {
+    None
+}
+
+impl Drop for Pager {
+    fn drop(&mut self) {
+        let _ = std::io::stdout().flush();
+        #[cfg(unix)]
+        {
+            // The relay only sees the end of the output once every copy of the pipe is closed.
+            // SAFETY: stdout is not written to after this point.
+            unsafe { libc::close(libc::STDOUT_FILENO) };
+            if let Some(relay) = self.relay.take() {
+                let _ = relay.join();
+            }
+            let _ = self.child.wait();
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use chrono::Utc;
+use riven::models::match_v5::Match;
+use std::error::Error;
+use std::time::Instant;
+
+use crate::achievements::find_achievements;
+use crate::api_client::{build_match_details, print_match_box, push_warning, ApiCallStats, MatchDetails, OverallOutput, PlayerIdentity, QuerySummary, UnavailableMatches, Warning, WarningCode};
+use crate::anonymize;
+use crate::duo_timeline::DuoTimeline;
+use crate::sessions::detect_sessions;
+use crate::cache::AccountCache;
+use crate::contacts::nickname_of;
+use crate::ddragon::DataDragon;
+use crate::cli::UserFacingRegion;
+use crate::index::MatchIndex;
+use crate::notes::NoteBook;
+use crate::ranked::{QueueBreakdown, RankedSummary};
+use crate::riot_id::RiotId;
+use crate::stats::{likely_afk, patch_of, CarryTally, MatchStats};
+
+/// Without `--all-time`, only the last 30 days of indexed matches are considered, like a live query.
+const DEFAULT_WINDOW_MS: i64 = 30 * 24 * 60 * 60 * 1000;
+
+pub struct PairHistoryOptions {
+    pub player1_riot_id: RiotId,
+    pub player2_riot_id: RiotId,
+    pub all_time: bool,
+    pub user_selected_region: Option<UserFacingRegion>,
+    pub patch: Option<String>,
+    /// Count shared matches where either player likely went AFK instead of leaving them out.
+    pub include_afk: bool,
+    /// Localizes champion names when set.
+    pub data_dragon: Option<DataDragon>,
+    pub verbose: bool,
+    /// Prints none of the shared matches while scanning, for JSON output or callers that only
+    /// summarize them.
+    pub quiet: bool,
+}
+
+pub fn cached_puuid(account_cache: &AccountCache, riot_id: &RiotId) -> Result<String, Box<dyn Error>> {
+    account_cache
+        .get(riot_id)
+        .map(|account| account.puuid.clone())
+        .ok_or_else(|| format!(
+            "Riot ID '{}' is not in the local cache. Run a regular query or `prefetch` for it first.",
+            anonymize::riot_id(riot_id)
+        ).into())
+}
+
+/// Runs `run_pair_history` over `matches` alone, e.g. a list piped in with `--from-json`, without
+/// touching the local index or account cache. The players are found by the Riot IDs in the match data.
+pub fn pair_history_of_matches(matches: &[Match], options: PairHistoryOptions) -> Result<OverallOutput, Box<dyn Error>> {
+    let match_index = MatchIndex::in_memory()?;
+    let mut account_cache = AccountCache::default();
+    for match_data in matches {
+        match_index.insert_match(match_data)?;
+        for participant in &match_data.info.participants {
+            if let (Some(game_name), Some(tag_line)) = (&participant.riot_id_game_name, &participant.riot_id_tagline) {
+                let riot_id = RiotId { game_name: game_name.clone(), tag_line: tag_line.clone(), puuid: None };
+                account_cache.insert(&riot_id, &participant.puuid, None);
+            }
+        }
+    }
+    for riot_id in [&options.player1_riot_id, &options.player2_riot_id] {
+        if account_cache.get(riot_id).is_none() {
+            return Err(format!("'{}' does not play in any of the given matches.", anonymize::riot_id(riot_id)).into());
+        }
+    }
+    run_pair_history(&account_cache, &match_index, options)
+}
+
+/// Answers a pair query entirely from the local match index, without any API calls.
+/// Every shared match ever indexed is considered, so the result is not limited to the match list window.
+pub fn run_pair_history(
+    account_cache: &AccountCache,
+    match_index: &MatchIndex,
+    options: PairHistoryOptions,
+) -> Result<OverallOutput, Box<dyn Error>> {
+    let PairHistoryOptions {
+        player1_riot_id,
+        player2_riot_id,
+        all_time,
+        user_selected_region,
+        patch,
+        include_afk,
+        data_dragon,
+        verbose,
+        quiet,
+    } = options;
+
+    let started_at = Instant::now();
+    let mut api_calls = ApiCallStats::default();
+    let note_book = NoteBook::load()?;
+
+    let puuid1 = cached_puuid(account_cache, &player1_riot_id)?;
+    let puuid2 = cached_puuid(account_cache, &player2_riot_id)?;
+    let regional_route = account_cache
+        .get(&player1_riot_id)
+        .and_then(|account| account.regional_route())
+        .map(|route| format!("{:?}", route))
+        .unwrap_or_else(|| "Unknown".to_string());
+
+    let since = if all_time { 0 } else { Utc::now().timestamp_millis() - DEFAULT_WINDOW_MS };
+    let checked_matches_count = match_index.match_ids_for_player_since(&puuid1, since)?.len();
+    let shared_match_ids = match_index.shared_match_ids_since(&puuid1, &puuid2, since)?;
+    if verbose {
+        println!(
+            "Found {} indexed matches shared by {} and {} out of {} indexed for {}.",
+            shared_match_ids.len(),
+            anonymize::riot_id(&player1_riot_id),
+            anonymize::riot_id(&player2_riot_id),
+            checked_matches_count,
+            anonymize::riot_id(&player1_riot_id)
+        );
+    }
+
+    let log_region = user_selected_region
+        .as_ref()
+        .map(|r| r.to_log_string())
+        .unwrap_or("eune");
+    let mut player1_games_won_count = 0;
+    let mut found_matches_details: Vec<MatchDetails> = Vec::new();
+    let mut ranked_summary = RankedSummary::default();
+    let mut per_queue = QueueBreakdown::default();
+    let mut likely_afk_matches_count = 0;
+    let mut warnings: Vec<Warning> = Vec::new();
+
+    for match_id in shared_match_ids {
+        let Some(match_data) = match_index.get_match(&match_id)? else {
+            push_warning(&mut warnings, verbose, Warning::new(
+                WarningCode::UnreadableIndexedMatch,
+                Some(&match_id),
+                format!("Warning: Indexed match {} could not be read. Skipping.", match_id),
+            ));
+            continue;
+        };
+        api_calls.cache_hits += 1;
+        let info = match_data.info;
+        if let Some(patch) = &patch
+            && &patch_of(&info.game_version) != patch
+        {
+            continue;
+        }
+        let player1_participant = info.participants.iter().find(|p| p.puuid == puuid1);
+        let player2_participant = info.participants.iter().find(|p| p.puuid == puuid2);
+        let (Some(p1_data), Some(p2_data)) = (player1_participant, player2_participant) else {
+            continue;
+        };
+        if likely_afk(p1_data, &info) || likely_afk(p2_data, &info) {
+            likely_afk_matches_count += 1;
+            if !include_afk {
+                continue;
+            }
+        }
+
+        if p1_data.win {
+            player1_games_won_count += 1;
+        }
+        ranked_summary.record(info.queue_id, p1_data.win);
+        per_queue.record(info.queue_id, p1_data.win);
+
+        let mut match_details = build_match_details(&match_id, &info, p1_data, p2_data, log_region, None, None, data_dragon.as_ref());
+        match_details.note = note_book.get(&match_id).cloned();
+        if verbose && !quiet {
+            print_match_box(&match_details, &player1_riot_id, &player2_riot_id);
+        }
+        found_matches_details.push(match_details);
+    }
+
+    api_calls.finish(started_at);
+
+    let query_summary = QuerySummary {
+        player1: PlayerIdentity {
+            nickname: nickname_of(&player1_riot_id),
+            game_name: player1_riot_id.game_name,
+            tag_line: player1_riot_id.tag_line,
+            profile: None,
+            formerly_known_as: Vec::new(),
+        },
+        player2: PlayerIdentity {
+            nickname: nickname_of(&player2_riot_id),
+            game_name: player2_riot_id.game_name,
+            tag_line: player2_riot_id.tag_line,
+            profile: None,
+            formerly_known_as: Vec::new(),
+        },
+        regional_route,
+        checked_matches_count: checked_matches_count as u32,
+        matches_played_together_count: found_matches_details.len() as u32,
+        player1_wins_together_count: player1_games_won_count,
+        player1_puuid_found: true,
+        player2_puuid_found: true,
+        api_calls,
+        ranked: ranked_summary,
+        carries: CarryTally::from_matches(&found_matches_details),
+        per_queue,
+        unavailable_matches: UnavailableMatches::default(),
+        outline_only_matches_count: 0,
+        likely_afk_matches_count,
+        venn: None,
+        widened_to_days: None,
+    };
+
+    Ok(OverallOutput {
+        query_summary,
+        stats: MatchStats::from_matches(&found_matches_details),
+        duo_timeline: DuoTimeline::from_matches(&found_matches_details),
+        sessions: detect_sessions(&found_matches_details),
+        achievements: find_achievements(&found_matches_details),
+        found_matches: found_matches_details,
+        warnings,
+    })
+}
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::config::Config;
+use crate::contacts::ContactBook;
+use crate::notes::NoteBook;
+
+/// Version of the portable file layout, bumped on incompatible changes.
+const PORTABLE_FORMAT_VERSION: u32 = 1;
+
+/// The user's setup in one file, to move it between machines: the config, contacts with their
+/// favorites and nicknames, and match notes. Caches are left out, they are rebuilt on use.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortableSetup {
+    pub version: u32,
+    pub config: Config,
+    #[serde(default)]
+    pub contacts: ContactBook,
+    #[serde(default)]
+    pub notes: NoteBook,
+}
+
+/// What `config import` changed.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub added_contacts: usize,
+    pub updated_notes: usize,
+    pub imported_api_keys: bool,
+}
+
+/// Writes the config, contacts and notes to `path`. The API keys are only included when asked for.
+pub fn export_setup(config: &Config, path: &Path, include_api_keys: bool) -> Result<(), Box<dyn Error>> {
+    let setup = PortableSetup {
+        version: PORTABLE_FORMAT_VERSION,
+        config: config.portable(include_api_keys),
+        contacts: ContactBook::load()?,
+        notes: NoteBook::load()?,
+    };
+    fs::write(path, serde_json::to_string_pretty(&setup)?)?;
+    Ok(())
+}
+
+/// Merges a file written by `export_setup` into the local setup and saves it: settings in the file
+/// replace local ones, contacts and notes are added to the local ones.
+pub fn import_setup(config: &mut Config, path: &Path) -> Result<ImportReport, Box<dyn Error>> {
+    let content = fs::read_to_string(path)?;
+    let setup: PortableSetup = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    if setup.version > PORTABLE_FORMAT_VERSION {
+        return Err(format!(
+            "{} was exported by a newer version of ptg (format {}); update ptg to import it.",
+            path.display(),
+            setup.version
+        ).into());
+    }
+
+    let imported_api_keys = setup.config.api_key.is_some() || !setup.config.additional_api_keys.is_empty();
+    config.merge(setup.config)?;
+    config.save()?;
+
+    let mut contact_book = ContactBook::load()?;
+    let added_contacts = contact_book.merge(setup.contacts);
+    contact_book.save()?;
+
+    let mut note_book = NoteBook::load()?;
+    let updated_notes = note_book.merge(setup.notes);
+    note_book.save()?;
+
+    Ok(ImportReport { added_contacts, updated_notes, imported_api_keys })
+}
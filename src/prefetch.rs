@@ -0,0 +1,104 @@
+use riven::consts::RegionalRoute;
+use std::error::Error;
+use std::time::Instant;
+
+use crate::api_client::{detect_regional_route, fetch_match, resolve_puuid, ApiCallStats, MatchFetch, RiotApiPool};
+use crate::anonymize;
+use crate::cache::AccountCache;
+use crate::index::MatchIndex;
+use crate::offline;
+use crate::riot_id::RiotId;
+
+/// The match-v5 match list endpoint returns at most this many IDs per call.
+const MATCH_LIST_PAGE_SIZE: i32 = 100;
+
+pub struct PrefetchOptions {
+    pub riot_ids: Vec<RiotId>,
+    /// `None` auto-detects the route per player.
+    pub regional_route: Option<RegionalRoute>,
+    pub max_matches: i32,
+}
+
+/// Populates the local match index with the recent matches of every given player.
+/// Matches already in the index are skipped, so an interrupted run simply resumes where it stopped.
+pub async fn run_prefetch(
+    riot_api: &RiotApiPool,
+    account_cache: &mut AccountCache,
+    match_index: &MatchIndex,
+    options: PrefetchOptions,
+) -> Result<(), Box<dyn Error>> {
+    let started_at = Instant::now();
+    let mut api_calls = ApiCallStats::default();
+
+    for riot_id in &options.riot_ids {
+        let regional_route = match options.regional_route {
+            Some(route) => route,
+            None => detect_regional_route(riot_api, account_cache, riot_id, false, &mut api_calls).await?,
+        };
+        let Some(puuid) = resolve_puuid(riot_api, account_cache, riot_id, regional_route, &mut api_calls).await? else {
+            eprintln!("Warning: Riot ID '{}' not found on regional route '{}'. Skipping.", anonymize::riot_id(riot_id), regional_route);
+            continue;
+        };
+        account_cache.save()?;
+
+        let mut match_ids = Vec::new();
+        while (match_ids.len() as i32) < options.max_matches {
+            let page_size = MATCH_LIST_PAGE_SIZE.min(options.max_matches - match_ids.len() as i32);
+            offline::ensure_online(&format!("the match list of {}", anonymize::riot_id(riot_id)))?;
+            api_calls.match_list_calls += 1;
+            let page = riot_api
+                .client().await
+                .match_v5()
+                .get_match_ids_by_puuid(regional_route, &puuid, Some(page_size), None, None, None, Some(match_ids.len() as i32), None)
+                .await?;
+            let is_last_page = (page.len() as i32) < page_size;
+            match_ids.extend(page);
+            if is_last_page {
+                break;
+            }
+        }
+
+        let (mut newly_indexed, mut already_cached, mut failed) = (0, 0, 0);
+        let mut first_failed = None;
+        let max_matches = options.max_matches as usize;
+        for (position, match_id) in match_ids.iter().enumerate() {
+            if match_index.contains_match(match_id)? {
+                already_cached += 1;
+                continue;
+            }
+            // Outlines are not stored, so they count as failed like missing and forbidden matches.
+            let progress = format!("({} of {})", position + 1, match_ids.len());
+            if let MatchFetch::Found(_) = fetch_match(riot_api, match_index, regional_route, match_id, &mut api_calls).await? {
+                newly_indexed += 1;
+                println!("[{}] Indexed {} {}.", anonymize::riot_id(riot_id), anonymize::match_id(match_id), progress);
+            } else {
+                failed += 1;
+                first_failed.get_or_insert(position);
+                println!("[{}] Could not index {} {}.", anonymize::riot_id(riot_id), anonymize::match_id(match_id), progress);
+            }
+        }
+        // With no start time the list reaches the beginning of the history if it came back short. After a
+        // failed match the coverage stops at the newer matches, so the failed one is fetched again later.
+        match first_failed {
+            None => match_index.record_match_list(&puuid, &match_ids, max_matches, 0)?,
+            Some(0) => {}
+            Some(first_failed) => match_index.record_match_list(&puuid, &match_ids[..first_failed], first_failed, 0)?,
+        }
+        let failures = if failed > 0 { format!(", {} could not be fetched", failed) } else { String::new() };
+        println!(
+            "[{}] {} matches indexed, {} were already cached{}.",
+            anonymize::riot_id(riot_id),
+            newly_indexed,
+            already_cached,
+            failures
+        );
+    }
+
+    api_calls.finish(started_at);
+    println!(
+        "Prefetch finished using {} API calls in {:.1}s.",
+        api_calls.total_calls,
+        api_calls.wall_time_ms as f64 / 1000.0
+    );
+    Ok(())
+}
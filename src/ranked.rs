@@ -0,0 +1,138 @@
+use riven::consts::Queue;
+use serde::{Deserialize, Serialize};
+
+use crate::stats::win_rate;
+
+/// Riot does not expose per-game LP changes, so the net LP is estimated with a flat gain/loss per game.
+const ESTIMATED_LP_PER_GAME: i32 = 20;
+
+/// Net ranked outcome of the shared games for player1, split per ranked queue.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RankedSummary {
+    pub solo: RankedQueueSummary,
+    pub flex: RankedQueueSummary,
+    pub net_wins: i32,
+    pub estimated_net_lp: i32,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RankedQueueSummary {
+    pub games: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub net_wins: i32,
+    pub estimated_net_lp: i32,
+}
+
+impl RankedQueueSummary {
+    fn record(&mut self, won: bool) {
+        self.games += 1;
+        if won {
+            self.wins += 1;
+        } else {
+            self.losses += 1;
+        }
+        self.net_wins = self.wins as i32 - self.losses as i32;
+        self.estimated_net_lp = self.net_wins * ESTIMATED_LP_PER_GAME;
+    }
+}
+
+impl RankedSummary {
+    /// Records a shared game. Non-ranked queues are ignored.
+    pub fn record(&mut self, queue: Queue, won: bool) {
+        let queue_summary = match queue {
+            Queue::SUMMONERS_RIFT_5V5_RANKED_SOLO => &mut self.solo,
+            Queue::SUMMONERS_RIFT_5V5_RANKED_FLEX => &mut self.flex,
+            _ => return,
+        };
+        queue_summary.record(won);
+        self.net_wins = self.solo.net_wins + self.flex.net_wins;
+        self.estimated_net_lp = self.net_wins * ESTIMATED_LP_PER_GAME;
+    }
+
+    pub fn has_games(&self) -> bool {
+        self.solo.games + self.flex.games > 0
+    }
+}
+
+/// Queue families the shared games are broken down by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum QueueGroup {
+    Solo,
+    Flex,
+    Normal,
+    Aram,
+    Arena,
+    Other,
+}
+
+impl QueueGroup {
+    pub fn of(queue: Queue) -> Self {
+        match queue {
+            Queue::SUMMONERS_RIFT_5V5_RANKED_SOLO => QueueGroup::Solo,
+            Queue::SUMMONERS_RIFT_5V5_RANKED_FLEX => QueueGroup::Flex,
+            Queue::SUMMONERS_RIFT_5V5_DRAFT_PICK
+            | Queue::SUMMONERS_RIFT_5V5_BLIND_PICK
+            | Queue::SUMMONERS_RIFT_NORMAL_SWIFTPLAY
+            | Queue::SUMMONERS_RIFT_NORMAL_QUICKPLAY => QueueGroup::Normal,
+            Queue::HOWLING_ABYSS_5V5_ARAM => QueueGroup::Aram,
+            Queue::ARENA_2V2V2V2_CHERRY | Queue::RINGS_OF_WRATH_ARENA_CHERRY_GAMES => QueueGroup::Arena,
+            _ => QueueGroup::Other,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            QueueGroup::Solo => "Solo/Duo",
+            QueueGroup::Flex => "Flex",
+            QueueGroup::Normal => "Normal",
+            QueueGroup::Aram => "ARAM",
+            QueueGroup::Arena => "Arena",
+            QueueGroup::Other => "Other",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueGroupSummary {
+    pub queue: QueueGroup,
+    pub games: u32,
+    pub wins: u32,
+    /// In percent.
+    pub win_rate: f64,
+}
+
+/// Player1's shared games and wins per queue family, in `QueueGroup` order.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct QueueBreakdown(pub Vec<QueueGroupSummary>);
+
+impl QueueBreakdown {
+    pub fn record(&mut self, queue: Queue, won: bool) {
+        let group = QueueGroup::of(queue);
+        let index = match self.0.binary_search_by_key(&group, |summary| summary.queue) {
+            Ok(index) => index,
+            Err(index) => {
+                self.0.insert(index, QueueGroupSummary { queue: group, games: 0, wins: 0, win_rate: 0.0 });
+                index
+            }
+        };
+        let summary = &mut self.0[index];
+        summary.games += 1;
+        summary.wins += won as u32;
+        summary.win_rate = win_rate(summary.wins, summary.games);
+    }
+
+    /// E.g. "Solo/Duo 3W-1L (75%), ARAM 5W-0L (100%)".
+    pub fn summary_line(&self) -> String {
+        self.0
+            .iter()
+            .map(|s| format!("{} {}W-{}L ({:.0}%)", s.queue.label(), s.wins, s.games - s.wins, s.win_rate))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
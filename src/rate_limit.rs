@@ -0,0 +1,110 @@
+//! Makes waits on Riot's rate limits visible. riven obeys the Retry-After header of 429 responses
+//! on its own and only reports it through `log`, so its records about them are turned into messages,
+//! and its records about retried requests are counted.
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+/// Set by `--fail-fast-on-429`: a 429 response ends the request with an error instead of a wait.
+static FAIL_FAST: AtomicBool = AtomicBool::new(false);
+
+/// Requests riven sent again since the count was last taken.
+static RETRIES: AtomicU32 = AtomicU32::new(0);
+
+static LOGGER: RateLimitLogger = RateLimitLogger;
+
+/// Starts reporting rate limit waits on stderr. With `fail_fast`, requests are not retried after a
+/// 429 response (nor after server errors, which riven retries the same way).
+pub fn install(fail_fast: bool) {
+    FAIL_FAST.store(fail_fast, Ordering::Relaxed);
+    if log::set_logger(&LOGGER).is_ok() {
+        // riven reports its retries at debug level.
+        log::set_max_level(LevelFilter::Debug);
+    }
+}
+
+pub fn fail_fast() -> bool {
+    FAIL_FAST.load(Ordering::Relaxed)
+}
+
+/// Returns how many requests riven retried since the last call, after 429 responses, server errors or
+/// failed connections, and starts counting again.
+pub fn take_retries() -> u32 {
+    RETRIES.swap(0, Ordering::Relaxed)
+}
+
+/// Whether a riven record announces that a request is sent again, as in "Response 429 (retried 0
+/// times), `retry-after` set, retrying after 1.5s." or "Request failed with cause ..., retrying after 1s.".
+pub fn is_retry_record(message: &str) -> bool {
+    (message.starts_with("Response ") || message.starts_with("Request failed")) && message.contains("retrying after")
+}
+
+/// A wait riven announced after a 429 response.
+#[derive(Debug, PartialEq)]
+pub struct RateLimitWait {
+    /// "Application" (the whole API key) or "Method" (a single endpoint), as riven names them.
+    pub limit: String,
+    pub delay_secs: f32,
+    /// Whether the delay came from the Retry-After header rather than riven's default.
+    pub from_retry_after: bool,
+}
+
+impl RateLimitWait {
+    /// Reads riven's "429 response, rate limit Method, retry-after header: `5`, delaying 5.5 secs."
+    /// and "429 response, rate limit Method, retry-after header MISSING, defaulting to 2 secs." records.
+    pub fn parse(message: &str) -> Option<Self> {
+        let rest = message.strip_prefix("429 response, rate limit ")?;
+        let (limit, rest) = rest.split_once(',')?;
+        let (delay, from_retry_after) = match rest.split_once("delaying ") {
+            Some((_, delay)) => (delay, true),
+            None => (rest.split_once("defaulting to ")?.1, false),
+        };
+        Some(RateLimitWait {
+            limit: limit.trim().to_string(),
+            delay_secs: delay.strip_suffix(" secs.")?.parse().ok()?,
+            from_retry_after,
+        })
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.limit.as_str() {
+            "Application" => "the API key's application rate limit",
+            _ => "the endpoint's method rate limit",
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let source = if self.from_retry_after { "as asked by Retry-After" } else { "Retry-After was missing" };
+        if fail_fast() {
+            format!(
+                "Rate limited by Riot (429, {}): Riot asks to wait {:.1}s; stopping because of --fail-fast-on-429.",
+                self.reason(),
+                self.delay_secs
+            )
+        } else {
+            format!("Rate limited by Riot (429, {}): waiting {:.1}s ({}).", self.reason(), self.delay_secs, source)
+        }
+    }
+}
+
+struct RateLimitLogger;
+
+impl Log for RateLimitLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Debug && metadata.target().starts_with("riven")
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let message = record.args().to_string();
+        if is_retry_record(&message) {
+            RETRIES.fetch_add(1, Ordering::Relaxed);
+        } else if let Some(wait) = RateLimitWait::parse(&message) {
+            eprintln!("{}", wait.describe());
+        }
+    }
+
+    fn flush(&self) {}
+}
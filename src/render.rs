@@ -0,0 +1,191 @@
+use riven::models::match_v5::Match;
+use std::error::Error;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tera::{Context, Tera};
+
+use crate::api_client::{OverallOutput, ParticipantDetails};
+use crate::chart::win_rate_svg;
+use crate::run_bundle::RunBundle;
+use crate::site::STYLE;
+
+/// Formats `ptg render` can present saved results in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderFormat {
+    Text,
+    Oneline,
+    Json,
+    Html,
+    Markdown,
+}
+
+impl std::str::FromStr for RenderFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(RenderFormat::Text),
+            "oneline" => Ok(RenderFormat::Oneline),
+            "json" => Ok(RenderFormat::Json),
+            "html" => Ok(RenderFormat::Html),
+            "markdown" | "md" => Ok(RenderFormat::Markdown),
+            _ => Err(format!("Invalid render format: {}. Supported formats are text, oneline, json, html, markdown", s)),
+        }
+    }
+}
+
+/// Tera macros of the HTML report.
+const REPORT_MACROS: &str = "\
+{% macro build(details) %}\
+{% if details.summonerSpells %}{% for entry in details.summonerSpells %}{{ self::build_entry(entry=entry) }}{% endfor %}{% endif %}\
+{% if details.runes %}{{ self::build_entry(entry=details.runes.keystone) }}{% endif %}\
+{% if details.items %}<br>{% for entry in details.items %}{{ self::build_entry(entry=entry) }}{% endfor %}{% endif %}\
+{% endmacro build %}
+{% macro build_entry(entry) %}\
+{% if entry.iconUrl %}<img class=\"icon\" src=\"{{ entry.iconUrl }}\" alt=\"{{ entry.name | default(value=entry.id) }}\" title=\"{{ entry.name | default(value=entry.id) }}\">\
+{% else %} {{ entry.name | default(value=entry.id) }}{% endif %}\
+{% endmacro build_entry %}
+";
+
+const REPORT_TEMPLATE: &str = "\
+{% import \"macros.html\" as macros %}\
+<!DOCTYPE html>
+<html>
+<head><meta charset=\"utf-8\"><title>{{ player1 }} and {{ player2 }}</title><style>{{ style | safe }}</style></head>
+<body>
+<h1>{{ player1 }} and {{ player2 }}</h1>
+<p>{{ querySummary.matchesPlayedTogetherCount }} games together out of {{ querySummary.checkedMatchesCount }} checked, {{ querySummary.player1WinsTogetherCount }} won.</p>
+{% if chart %}<h2>Rolling win rate</h2>
+{{ chart | safe }}
+{% endif %}<h2>Games</h2>
+<table>
+<tr><th>Date</th><th>Mode</th><th>{{ player1 }}</th><th>{{ player2 }}</th><th>Result</th></tr>
+{% for m in foundMatches %}<tr><td>{% if m.leagueOfGraphsLink %}<a href=\"{{ m.leagueOfGraphsLink }}\">{{ m.gameDateUtc }}</a>{% else %}{{ m.gameDateUtc }}{% endif %}</td><td>{{ m.gameMode }}</td><td>{{ m.player1Details.champion }} {{ m.player1Details.kills }}/{{ m.player1Details.deaths }}/{{ m.player1Details.assists }}{{ macros::build(details=m.player1Details) }}</td><td>{{ m.player2Details.champion }} {{ m.player2Details.kills }}/{{ m.player2Details.deaths }}/{{ m.player2Details.assists }}{{ macros::build(details=m.player2Details) }}</td><td class=\"{{ m.player1Details.outcome }}\">{{ m.player1Details.outcome }}</td></tr>
+{% endfor %}</table>
+</body>
+</html>
+";
+
+impl RenderFormat {
+    /// Name of the file `--format` writes this format to in `--output-dir`; `None` for the formats
+    /// only meant for the terminal.
+    pub fn file_name(self) -> Option<&'static str> {
+        match self {
+            RenderFormat::Json => Some("results.json"),
+            RenderFormat::Html => Some("results.html"),
+            RenderFormat::Markdown => Some("results.md"),
+            RenderFormat::Text | RenderFormat::Oneline => None,
+        }
+    }
+}
+
+/// Writes `output` in each of `formats` into `dir`, creating it if needed, all from the same results.
+/// Terminal-only formats are skipped. Returns the paths written.
+pub fn write_formats(output: &OverallOutput, formats: &[RenderFormat], dir: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    fs::create_dir_all(dir).map_err(|e| format!("Could not create {}: {}", dir.display(), e))?;
+    let mut written = Vec::new();
+    for &format in formats {
+        let Some(file_name) = format.file_name() else {
+            continue;
+        };
+        let path = dir.join(file_name);
+        if written.contains(&path) {
+            continue;
+        }
+        match format {
+            RenderFormat::Json => output.write_json(fs::File::create(&path)?)?,
+            RenderFormat::Html => fs::write(&path, report_html(output)?)?,
+            _ => fs::write(&path, report_markdown(output))?,
+        }
+        written.push(path);
+    }
+    Ok(written)
+}
+
+/// JSON read with `--from-json` or `render`.
+pub enum JsonInput {
+    /// Results of an earlier run: the output of `--json` or a `--save-run` file.
+    Results(Box<OverallOutput>),
+    /// Raw match-v5 payloads, e.g. collected by a script of your own.
+    Matches(Vec<Match>),
+}
+
+/// The bytes of `source`, or of stdin when it is `-`, and a name for them in errors.
+fn read_source(source: &Path) -> Result<(Vec<u8>, String), Box<dyn Error>> {
+    if source == Path::new("-") {
+        let mut bytes = Vec::new();
+        std::io::stdin().lock().read_to_end(&mut bytes)?;
+        return Ok((bytes, "stdin".to_string()));
+    }
+    let bytes = fs::read(source).map_err(|e| format!("Could not open {}: {}", source.display(), e))?;
+    Ok((bytes, source.display().to_string()))
+}
+
+/// Reads a `--save-run` bundle, the output of `--json` or a JSON array of matches from `source`.
+pub fn read_input(source: &Path) -> Result<JsonInput, Box<dyn Error>> {
+    let (bytes, name) = read_source(source)?;
+    if RunBundle::is_bundle(&bytes) {
+        return Ok(JsonInput::Results(Box::new(RunBundle::decode(&bytes, &name)?.output)));
+    }
+    if bytes.trim_ascii_start().starts_with(b"[") {
+        return serde_json::from_slice(&bytes)
+            .map(JsonInput::Matches)
+            .map_err(|e| format!("{} is not a list of match-v5 matches: {}", name, e).into());
+    }
+    serde_json::from_slice(&bytes)
+        .map(|output| JsonInput::Results(Box::new(output)))
+        .map_err(|e| format!("{} is neither a `--save-run` file nor `--json` output of ptg: {}", name, e).into())
+}
+
+/// Reads results saved earlier: a `--save-run` bundle or the output of `--json`, from `source`
+/// or from stdin when it is `-`.
+pub fn read_results(source: &Path) -> Result<OverallOutput, Box<dyn Error>> {
+    match read_input(source)? {
+        JsonInput::Results(output) => Ok(*output),
+        JsonInput::Matches(_) => Err(format!(
+            "{} holds raw matches, not results; check them with `ptg --from-json {} <RIOT_ID> <RIOT_ID>`.",
+            source.display(),
+            source.display()
+        ).into()),
+    }
+}
+
+/// A standalone HTML page of the results, with the stylesheet inlined so it can be sent as one file.
+/// Items and summoner spells show as icons with `--assets`, by name or ID otherwise.
+pub fn report_html(output: &OverallOutput) -> Result<String, Box<dyn Error>> {
+    let mut context = Context::from_serialize(output)?;
+    context.insert("player1", &output.query_summary.player1.display_name());
+    context.insert("player2", &output.query_summary.player2.display_name());
+    context.insert("style", STYLE);
+    context.insert("chart", &win_rate_svg(&output.found_matches));
+    let mut tera = Tera::default();
+    tera.add_raw_templates([("macros.html", REPORT_MACROS), ("report.html", REPORT_TEMPLATE)])?;
+    Ok(tera.render("report.html", &context)?)
+}
+
+/// The results as a Markdown summary and table, e.g. to paste into a chat or an issue.
+pub fn report_markdown(output: &OverallOutput) -> String {
+    let summary = &output.query_summary;
+    let (player1, player2) = (summary.player1.display_name(), summary.player2.display_name());
+    let mut markdown = format!(
+        "## {} and {}\n\n{} games together out of {} checked, {} won.\n\n",
+        player1, player2, summary.matches_played_together_count, summary.checked_matches_count, summary.player1_wins_together_count
+    );
+    if output.found_matches.is_empty() {
+        return markdown;
+    }
+    markdown.push_str(&format!("| Date | Mode | {} | {} | Result |\n|---|---|---|---|---|\n", player1, player2));
+    for m in &output.found_matches {
+        let kda = |d: &ParticipantDetails| format!("{} {}/{}/{}", d.champion_label(), d.kills, d.deaths, d.assists);
+        markdown.push_str(&format!(
+            "| {} | {} | {} | {} | {} |\n",
+            m.game_date_utc,
+            m.game_mode,
+            kda(&m.player1_details),
+            kda(&m.player2_details),
+            m.player1_details.outcome
+        ));
+    }
+    markdown
+}
@@ -0,0 +1,42 @@
+use chrono::Utc;
+use std::error::Error;
+
+use crate::cache::AccountCache;
+use crate::config::RetentionConfig;
+use crate::index::MatchIndex;
+
+const BYTES_PER_MB: u64 = 1024 * 1024;
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// Applies the retention limits to the account cache and the match index.
+pub fn run_cache_prune(
+    account_cache: &mut AccountCache,
+    match_index: &MatchIndex,
+    retention: &RetentionConfig,
+) -> Result<(), Box<dyn Error>> {
+    if retention.max_cache_size_mb.is_none() && retention.max_account_age_days.is_none() {
+        println!("No retention limits configured; nothing to prune. Set retention.max_cache_size_mb or retention.max_account_age_days in the config.");
+        return Ok(());
+    }
+
+    if let Some(max_age_days) = retention.max_account_age_days {
+        let cutoff = Utc::now().timestamp() - max_age_days * SECONDS_PER_DAY;
+        let removed = account_cache.prune_older_than(cutoff);
+        account_cache.save()?;
+        println!("Removed {} account cache entries older than {} days.", removed, max_age_days);
+    }
+
+    if let Some(max_size_mb) = retention.max_cache_size_mb {
+        let size_before = match_index.size_bytes()?;
+        let removed = match_index.prune_to_size(max_size_mb * BYTES_PER_MB)?;
+        let size_after = match_index.size_bytes()?;
+        println!(
+            "Removed {} matches from the index ({:.1} MB -> {:.1} MB, limit {} MB).",
+            removed,
+            size_before as f64 / BYTES_PER_MB as f64,
+            size_after as f64 / BYTES_PER_MB as f64,
+            max_size_mb
+        );
+    }
+    Ok(())
+}
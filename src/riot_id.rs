@@ -1,13 +1,32 @@
+/// Length of an encrypted PUUID.
+const PUUID_LENGTH: usize = 78;
+
 /// Represents a Riot ID (GameName#TagLine)
 #[derive(Debug, Clone)]
 pub struct RiotId {
     pub game_name: String,
     pub tag_line: String,
+    /// A PUUID given instead of a Riot ID, until it is resolved; the game name and tag line are
+    /// empty then.
+    pub puuid: Option<String>,
+}
+
+impl RiotId {
+    pub fn unresolved_puuid(&self) -> Option<&str> {
+        self.puuid.as_deref()
+    }
+}
+
+pub(crate) fn looks_like_puuid(s: &str) -> bool {
+    s.len() == PUUID_LENGTH && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
 }
 
 impl std::fmt::Display for RiotId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}#{}", self.game_name, self.tag_line)
+        match &self.puuid {
+            Some(puuid) => write!(f, "{}", puuid),
+            None => write!(f, "{}#{}", self.game_name, self.tag_line),
+        }
     }
 }
 
@@ -15,14 +34,18 @@ impl std::str::FromStr for RiotId {
     type Err = &'static str;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if looks_like_puuid(s) {
+            return Ok(RiotId { game_name: String::new(), tag_line: String::new(), puuid: Some(s.to_string()) });
+        }
         let parts: Vec<&str> = s.split('#').collect();
-        if parts.len() == 2 {
+        if parts.len() == 2 && parts.iter().all(|part| !part.is_empty()) {
             Ok(RiotId {
                 game_name: parts[0].to_string(),
                 tag_line: parts[1].to_string(),
+                puuid: None,
             })
         } else {
-            Err("Invalid Riot ID format. Expected 'GameName#TagLine'")
+            Err("Invalid Riot ID format. Expected 'GameName#TagLine' or a PUUID")
         }
     }
 }
\ No newline at end of file
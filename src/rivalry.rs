@@ -0,0 +1,210 @@
+use riven::consts::RegionalRoute;
+use serde::Serialize;
+use std::error::Error;
+
+use crate::api_client::{run_query, MatchDetails, OverallOutput, PlayerIdentity, QueryOptions, RiotApiPool};
+use crate::cache::AccountCache;
+use crate::cli::{MatchType, UserFacingRegion};
+use crate::date_range::DateRange;
+use crate::index::MatchIndex;
+use crate::riot_id::RiotId;
+use crate::stats::{win_rate, SoloBaseline};
+
+/// Two duos checked over the same window, e.g. `ptg rivalry A B -- C D`.
+pub struct RivalryOptions {
+    pub first_pair: (RiotId, RiotId),
+    pub second_pair: (RiotId, RiotId),
+    /// `None` auto-detects the route from the first player of each pair.
+    pub regional_route: Option<RegionalRoute>,
+    pub user_selected_region: Option<UserFacingRegion>,
+    pub number_of_matches: Option<i32>,
+    pub match_type: Option<MatchType>,
+    pub queue_id: Option<u16>,
+    pub patch: Option<String>,
+    pub date_range: Option<DateRange>,
+    pub include_afk: bool,
+    pub verbose: bool,
+}
+
+/// How one duo did in its shared games.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuoRecord {
+    pub player1: PlayerIdentity,
+    pub player2: PlayerIdentity,
+    pub games: u32,
+    pub wins: u32,
+    pub win_rate: f64,
+    /// Kills plus assists of both players over their deaths, over all shared games.
+    pub combined_kda: f64,
+    /// Win rate together minus the average win rate of both players without each other, in
+    /// percentage points; `None` when neither played without the other in the window.
+    pub synergy: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RivalryOutput {
+    pub first: DuoRecord,
+    pub second: DuoRecord,
+    /// 1 or 2 for the duo with the better record, `None` for a draw or without games together.
+    pub winner: Option<u8>,
+}
+
+fn combined_kda(matches: &[MatchDetails]) -> f64 {
+    let (mut kills_and_assists, mut deaths) = (0, 0);
+    for details in matches.iter().flat_map(|m| [&m.player1_details, &m.player2_details]) {
+        kills_and_assists += details.kills + details.assists;
+        deaths += details.deaths;
+    }
+    kills_and_assists as f64 / deaths.max(1) as f64
+}
+
+fn synergy(win_rate: f64, baseline: Option<&SoloBaseline>) -> Option<f64> {
+    let solo_win_rates: Vec<f64> = baseline
+        .into_iter()
+        .flat_map(|baseline| [&baseline.player1, &baseline.player2])
+        .filter(|comparison| comparison.solo.games > 0)
+        .map(|comparison| comparison.solo.win_rate)
+        .collect();
+    if solo_win_rates.is_empty() {
+        return None;
+    }
+    Some(win_rate - solo_win_rates.iter().sum::<f64>() / solo_win_rates.len() as f64)
+}
+
+impl DuoRecord {
+    fn from_output(output: OverallOutput) -> Self {
+        let summary = output.query_summary;
+        let games = summary.matches_played_together_count;
+        let wins = summary.player1_wins_together_count;
+        let win_rate = win_rate(wins, games);
+        DuoRecord {
+            combined_kda: combined_kda(&output.found_matches),
+            synergy: synergy(win_rate, output.stats.solo_baseline.as_ref()),
+            player1: summary.player1,
+            player2: summary.player2,
+            games,
+            wins,
+            win_rate,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        format!("{} & {}", self.player1.display_name(), self.player2.display_name())
+    }
+}
+
+/// 1 or 2 for the better record: the higher win rate together, then the higher synergy, then more
+/// games. A duo without games together never wins; `None` for a draw.
+fn judge(first: &DuoRecord, second: &DuoRecord) -> Option<u8> {
+    match (first.games, second.games) {
+        (0, 0) => return None,
+        (_, 0) => return Some(1),
+        (0, _) => return Some(2),
+        _ => {}
+    }
+    let key = |record: &DuoRecord| (record.win_rate, record.synergy.unwrap_or(0.0), record.games);
+    match key(first).partial_cmp(&key(second)) {
+        Some(std::cmp::Ordering::Greater) => Some(1),
+        Some(std::cmp::Ordering::Less) => Some(2),
+        _ => None,
+    }
+}
+
+async fn query_pair(
+    riot_api: &RiotApiPool,
+    account_cache: &mut AccountCache,
+    match_index: &MatchIndex,
+    (player1_riot_id, player2_riot_id): (RiotId, RiotId),
+    options: &RivalryOptions,
+) -> Result<DuoRecord, Box<dyn Error>> {
+    let output = run_query(
+        riot_api,
+        account_cache,
+        match_index,
+        QueryOptions {
+            player1_riot_id,
+            player2_riot_id,
+            player3_riot_id: None,
+            regional_route: options.regional_route,
+            user_selected_region: options.user_selected_region.clone(),
+            number_of_matches: options.number_of_matches,
+            match_type: options.match_type,
+            auto_widen_max_days: None,
+            queue_id: options.queue_id,
+            patch: options.patch.clone(),
+            date_range: options.date_range,
+            fetch_timelines: false,
+            fetch_profiles: false,
+            opponents_only: false,
+            follow_region_transfer: false,
+            stop_at_first_shared: false,
+            include_afk: options.include_afk,
+            // The synergy score needs each player's games without the other.
+            solo_baseline: true,
+            data_dragon: None,
+            verbose: options.verbose,
+            // The shared games are summarized, not printed one by one.
+            quiet: true,
+        },
+    ).await?;
+    Ok(DuoRecord::from_output(output))
+}
+
+/// Runs the regular pair query for both duos with the same window and compares their records.
+pub async fn run_rivalry(
+    riot_api: &RiotApiPool,
+    account_cache: &mut AccountCache,
+    match_index: &MatchIndex,
+    options: RivalryOptions,
+) -> Result<RivalryOutput, Box<dyn Error>> {
+    let first = query_pair(riot_api, account_cache, match_index, options.first_pair.clone(), &options).await?;
+    let second = query_pair(riot_api, account_cache, match_index, options.second_pair.clone(), &options).await?;
+    let winner = judge(&first, &second);
+    Ok(RivalryOutput { first, second, winner })
+}
+
+impl RivalryOutput {
+    /// One sentence naming the duo with the better record. Formatted when printed, so it shows the
+    /// identities as they are then, e.g. anonymized.
+    pub fn verdict(&self) -> String {
+        let (winner, loser) = match self.winner {
+            Some(1) => (&self.first, &self.second),
+            Some(_) => (&self.second, &self.first),
+            None if self.first.games == 0 => return "Neither duo played together in this window.".to_string(),
+            None => return "A draw: both duos have the same record.".to_string(),
+        };
+        if loser.games == 0 {
+            return format!("Only {} played together in this window.", winner.label());
+        }
+        format!(
+            "{} carry the group: {:.1}% over {} games against {:.1}% over {}.",
+            winner.label(),
+            winner.win_rate,
+            winner.games,
+            loser.win_rate,
+            loser.games
+        )
+    }
+
+    /// Prints both duos side by side, then the verdict.
+    pub fn print(&self) {
+        let (first, second) = (self.first.label(), self.second.label());
+        let width = first.chars().count().max(12);
+        let row = |name: &str, a: String, b: String| println!("{:<16}{:<width$}  {}", name, a, b, width = width);
+        let synergy = |record: &DuoRecord| record.synergy.map_or_else(|| "n/a".to_string(), |s| format!("{:+.1} pts", s));
+        println!("\n--- Rivalry ---");
+        row("", first, second);
+        row("Games together", self.first.games.to_string(), self.second.games.to_string());
+        row(
+            "Record",
+            format!("{}-{}", self.first.wins, self.first.games - self.first.wins),
+            format!("{}-{}", self.second.wins, self.second.games - self.second.wins),
+        );
+        row("Win rate", format!("{:.1}%", self.first.win_rate), format!("{:.1}%", self.second.win_rate));
+        row("Combined KDA", format!("{:.2}", self.first.combined_kda), format!("{:.2}", self.second.combined_kda));
+        row("Synergy", synergy(&self.first), synergy(&self.second));
+        println!("{}", self.verdict());
+    }
+}
@@ -0,0 +1,90 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use riven::models::match_v5::Match;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::api_client::OverallOutput;
+use crate::index::MatchIndex;
+
+/// Version of the bundle layout, bumped on incompatible changes.
+const RUN_BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// A saved query: its results and the raw match data they were computed from, as gzipped JSON.
+/// Loading it needs no API key, so results can be handed to someone else and rendered in any format.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunBundle {
+    pub version: u32,
+    pub output: OverallOutput,
+    /// The match-v5 payloads of the found matches, as far as they are in the local index.
+    pub matches: Vec<Match>,
+}
+
+impl RunBundle {
+    /// Bundles `output` with the raw data of its found matches from `match_index`.
+    pub fn capture(output: OverallOutput, match_index: &MatchIndex) -> Result<Self, Box<dyn Error>> {
+        let mut matches = Vec::new();
+        for found in &output.found_matches {
+            if let Some(match_data) = match_index.get_match(&found.match_id)? {
+                matches.push(match_data);
+            }
+        }
+        Ok(RunBundle { version: RUN_BUNDLE_FORMAT_VERSION, output, matches })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut encoder = GzEncoder::new(BufWriter::new(File::create(path)?), Compression::default());
+        serde_json::to_writer(&mut encoder, self)?;
+        encoder.finish()?.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let file = File::open(path).map_err(|e| format!("Could not open {}: {}", path.display(), e))?;
+        let mut compressed = Vec::new();
+        BufReader::new(file).read_to_end(&mut compressed)?;
+        Self::decode(&compressed, &path.display().to_string())
+    }
+
+    /// Reads a bundle from the bytes of a saved run; `source` names it in errors.
+    pub fn decode(compressed: &[u8], source: &str) -> Result<Self, Box<dyn Error>> {
+        // Read whole: riven's string enums only deserialize from borrowed strings, not from a reader.
+        let mut content = String::new();
+        GzDecoder::new(compressed)
+            .read_to_string(&mut content)
+            .map_err(|e| format!("{} is not a saved ptg run: {}", source, e))?;
+        let bundle: RunBundle = serde_json::from_str(&content)
+            .map_err(|e| format!("{} is not a saved ptg run: {}", source, e))?;
+        if bundle.version > RUN_BUNDLE_FORMAT_VERSION {
+            return Err(format!(
+                "{} was saved by a newer version of ptg (format {}); update ptg to load it.",
+                source,
+                bundle.version
+            ).into());
+        }
+        Ok(bundle)
+    }
+
+    /// Whether `bytes` start like a saved run rather than plain JSON (the gzip magic number).
+    pub fn is_bundle(bytes: &[u8]) -> bool {
+        bytes.starts_with(&[0x1f, 0x8b])
+    }
+
+    /// Adds the bundled matches to `match_index`, so later `pair-history` and `--offline` queries see them.
+    /// Returns how many were not indexed yet.
+    pub fn index_matches(&self, match_index: &MatchIndex) -> Result<usize, Box<dyn Error>> {
+        let mut added = 0;
+        for match_data in &self.matches {
+            if !match_index.contains_match(&match_data.metadata.match_id)? {
+                match_index.insert_match(match_data)?;
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+}
@@ -0,0 +1,377 @@
+use riven::consts::RegionalRoute;
+use riven::models::match_v5::Match;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error::Error;
+
+use crate::api_client::{detect_regional_route, fetch_match, resolve_puuid, ApiCallStats, MatchFetch, RiotApiPool};
+use crate::anonymize;
+use crate::contacts;
+use crate::cache::AccountCache;
+use crate::champion_pool::{ChampionPlays, ROLES};
+use crate::index::MatchIndex;
+use crate::offline;
+use crate::riot_id::RiotId;
+use crate::stats::win_rate;
+
+/// Champions listed per scouted player.
+const TOP_CHAMPIONS: usize = 3;
+/// Length of the ban suggestion list.
+const BAN_SUGGESTIONS: usize = 5;
+
+/// Output formats of `scout --bans`.
+#[derive(Debug, Clone, Copy)]
+pub enum BanListFormat {
+    Text,
+    Json,
+    Markdown,
+}
+
+impl std::str::FromStr for BanListFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(BanListFormat::Text),
+            "json" => Ok(BanListFormat::Json),
+            "markdown" | "md" => Ok(BanListFormat::Markdown),
+            _ => Err(format!("Invalid ban list format: {}. Supported formats are text, json, markdown", s)),
+        }
+    }
+}
+
+pub struct ScoutOptions {
+    pub riot_ids: Vec<RiotId>,
+    /// `None` auto-detects the route from the first player.
+    pub regional_route: Option<RegionalRoute>,
+    /// Most recent matches checked per player.
+    pub number_of_matches: i32,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoutedPlayer {
+    pub player: String,
+    /// Recent matches found for the player, `0` when the Riot ID could not be resolved.
+    pub games: u32,
+    pub wins: u32,
+    pub likely_role: Option<String>,
+    /// Games in the likely role.
+    pub likely_role_games: u32,
+    /// Most played first.
+    pub top_champions: Vec<ChampionPlays>,
+}
+
+/// Two roster players on the same team.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairFrequency {
+    pub player1: String,
+    pub player2: String,
+    pub games: u32,
+    pub wins: u32,
+    pub win_rate: f64,
+}
+
+/// A champion the roster relies on, summed over every roster player who played it.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BanSuggestion {
+    pub champion: String,
+    pub players: Vec<String>,
+    pub games: u32,
+    pub wins: u32,
+    pub win_rate: f64,
+    /// Games times the win rate with one win and one loss added, so a single won game does not top
+    /// a champion played often.
+    pub threat: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoutOutput {
+    /// Distinct recent matches of any roster player.
+    pub checked_matches_count: u32,
+    pub players: Vec<ScoutedPlayer>,
+    /// Most games together first; pairs that never shared a team are left out.
+    pub pairs: Vec<PairFrequency>,
+    /// Games with the whole roster on one team.
+    pub full_roster_games: u32,
+    pub full_roster_wins: u32,
+    /// Highest threat first.
+    pub ban_suggestions: Vec<BanSuggestion>,
+    pub api_calls: ApiCallStats,
+}
+
+/// Scouts an enemy Clash roster from each player's recent matches: how often they queue together,
+/// how those games went, and what each of them plays. Fetched matches are kept in the local index.
+pub async fn run_scout(
+    riot_api: &RiotApiPool,
+    account_cache: &mut AccountCache,
+    match_index: &MatchIndex,
+    options: ScoutOptions,
+) -> Result<ScoutOutput, Box<dyn Error>> {
+    let started_at = std::time::Instant::now();
+    let mut api_calls = ApiCallStats::default();
+    let regional_route = match options.regional_route {
+        Some(route) => route,
+        None => detect_regional_route(riot_api, account_cache, &options.riot_ids[0], false, &mut api_calls).await?,
+    };
+
+    let mut puuids = Vec::new();
+    for riot_id in &options.riot_ids {
+        let puuid = resolve_puuid(riot_api, account_cache, riot_id, regional_route, &mut api_calls).await?;
+        if puuid.is_none() {
+            eprintln!("Warning: Riot ID '{}' not found on regional route '{}'. Skipping.", anonymize::riot_id(riot_id), regional_route);
+        }
+        puuids.push(puuid);
+    }
+
+    let mut matches: HashMap<String, Match> = HashMap::new();
+    for puuid in puuids.iter().flatten() {
+        offline::ensure_online(&format!("the match list of PUUID '{}'", anonymize::puuid(puuid)))?;
+        api_calls.match_list_calls += 1;
+        let match_ids = riot_api
+            .client().await
+            .match_v5()
+            .get_match_ids_by_puuid(regional_route, puuid, Some(options.number_of_matches), None, None, None, None, None)
+            .await?;
+        for match_id in match_ids {
+            if matches.contains_key(&match_id) {
+                continue;
+            }
+            if let MatchFetch::Found(match_data) = fetch_match(riot_api, match_index, regional_route, &match_id, &mut api_calls).await? {
+                matches.insert(match_id, *match_data);
+            }
+        }
+    }
+
+    let mut output = tally_roster(&options.riot_ids, &puuids, matches.values());
+    output.checked_matches_count = matches.len() as u32;
+    api_calls.finish(started_at);
+    output.api_calls = api_calls;
+    Ok(output)
+}
+
+/// One roster player's recent games, wins, roles and champions with their wins.
+#[derive(Clone, Default)]
+struct PlayerRecord {
+    games: u32,
+    wins: u32,
+    roles: HashMap<String, u32>,
+    champions: HashMap<String, (u32, u32)>,
+}
+
+fn tally_roster<'a>(riot_ids: &[RiotId], puuids: &[Option<String>], matches: impl Iterator<Item = &'a Match>) -> ScoutOutput {
+    let mut records = vec![PlayerRecord::default(); puuids.len()];
+    let mut pair_records: HashMap<(usize, usize), (u32, u32)> = HashMap::new();
+    let mut full_roster_games = 0;
+    let mut full_roster_wins = 0;
+
+    for match_data in matches {
+        let participants = &match_data.info.participants;
+        let roster: Vec<_> = puuids
+            .iter()
+            .map(|puuid| puuid.as_ref().and_then(|puuid| participants.iter().find(|p| &p.puuid == puuid)))
+            .collect();
+
+        for (record, participant) in records.iter_mut().zip(&roster) {
+            let Some(participant) = participant else {
+                continue;
+            };
+            record.games += 1;
+            record.wins += participant.win as u32;
+            if ROLES.contains(&participant.team_position.as_str()) {
+                *record.roles.entry(participant.team_position.clone()).or_default() += 1;
+            }
+            let champion = record.champions.entry(participant.champion_name.clone()).or_default();
+            champion.0 += 1;
+            champion.1 += participant.win as u32;
+        }
+
+        for (i, participant) in roster.iter().enumerate() {
+            for (j, other) in roster.iter().enumerate().skip(i + 1) {
+                if let (Some(participant), Some(other)) = (participant, other)
+                    && participant.team_id == other.team_id
+                {
+                    let entry = pair_records.entry((i, j)).or_default();
+                    entry.0 += 1;
+                    entry.1 += participant.win as u32;
+                }
+            }
+        }
+
+        if let Some(roster) = roster.iter().copied().collect::<Option<Vec<_>>>()
+            && roster.iter().all(|p| p.team_id == roster[0].team_id)
+        {
+            full_roster_games += 1;
+            full_roster_wins += roster[0].win as u32;
+        }
+    }
+
+    let ban_suggestions = suggest_bans(riot_ids, &records);
+    let players = riot_ids
+        .iter()
+        .zip(records)
+        .map(|(riot_id, PlayerRecord { games, wins, roles, champions })| {
+            let likely_role = roles.into_iter().max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)));
+            let mut top_champions: Vec<ChampionPlays> = champions
+                .into_iter()
+                .map(|(champion, (games, wins))| ChampionPlays { champion, games, wins, mastery_points: None })
+                .collect();
+            top_champions.sort_by(|a, b| b.games.cmp(&a.games).then_with(|| a.champion.cmp(&b.champion)));
+            top_champions.truncate(TOP_CHAMPIONS);
+            ScoutedPlayer {
+                player: contacts::display_name(riot_id),
+                games,
+                wins,
+                likely_role_games: likely_role.as_ref().map_or(0, |role| role.1),
+                likely_role: likely_role.map(|role| role.0),
+                top_champions,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut pairs: Vec<PairFrequency> = pair_records
+        .into_iter()
+        .map(|((i, j), (games, wins))| PairFrequency {
+            player1: players[i].player.clone(),
+            player2: players[j].player.clone(),
+            games,
+            wins,
+            win_rate: win_rate(wins, games),
+        })
+        .collect();
+    pairs.sort_by(|a, b| b.games.cmp(&a.games).then_with(|| (&a.player1, &a.player2).cmp(&(&b.player1, &b.player2))));
+
+    ScoutOutput {
+        checked_matches_count: 0,
+        players,
+        pairs,
+        full_roster_games,
+        full_roster_wins,
+        ban_suggestions,
+        api_calls: ApiCallStats::default(),
+    }
+}
+
+fn suggest_bans(riot_ids: &[RiotId], records: &[PlayerRecord]) -> Vec<BanSuggestion> {
+    let mut champions: HashMap<&str, BanSuggestion> = HashMap::new();
+    for (riot_id, record) in riot_ids.iter().zip(records) {
+        for (champion, (games, wins)) in &record.champions {
+            let suggestion = champions.entry(champion).or_insert_with(|| BanSuggestion {
+                champion: champion.clone(),
+                players: Vec::new(),
+                games: 0,
+                wins: 0,
+                win_rate: 0.0,
+                threat: 0.0,
+            });
+            suggestion.players.push(contacts::display_name(riot_id));
+            suggestion.games += games;
+            suggestion.wins += wins;
+        }
+    }
+
+    let mut suggestions: Vec<BanSuggestion> = champions
+        .into_values()
+        .map(|mut suggestion| {
+            suggestion.win_rate = win_rate(suggestion.wins, suggestion.games);
+            suggestion.threat = suggestion.games as f64 * (suggestion.wins + 1) as f64 / (suggestion.games + 2) as f64;
+            suggestion
+        })
+        .collect();
+    suggestions.sort_by(|a, b| b.threat.total_cmp(&a.threat).then_with(|| a.champion.cmp(&b.champion)));
+    suggestions.truncate(BAN_SUGGESTIONS);
+    suggestions
+}
+
+impl ScoutOutput {
+    pub fn print(&self) {
+        println!("\n--- Scouting Report ---");
+        println!("Checked {} recent matches of the roster.", self.checked_matches_count);
+        for player in &self.players {
+            if player.games == 0 {
+                println!("{}: no recent matches found", player.player);
+                continue;
+            }
+            let role = match &player.likely_role {
+                Some(role) => format!("likely {} ({} of {} games)", role, player.likely_role_games, player.games),
+                None => format!("no clear role ({} games)", player.games),
+            };
+            let champions: Vec<String> = player
+                .top_champions
+                .iter()
+                .map(|plays| format!("{} {}", plays.champion, plays.games))
+                .collect();
+            println!("{}: {}, {:.0}% win rate, plays {}", player.player, role, win_rate(player.wins, player.games), champions.join(", "));
+        }
+        if self.pairs.is_empty() {
+            println!("None of them played together recently.");
+        } else {
+            println!("Played together:");
+            for pair in &self.pairs {
+                println!(
+                    "  {} + {}: {} games, {}W-{}L ({:.0}%)",
+                    pair.player1,
+                    pair.player2,
+                    pair.games,
+                    pair.wins,
+                    pair.games - pair.wins,
+                    pair.win_rate
+                );
+            }
+        }
+        if self.players.len() > 2 {
+            println!(
+                "Whole roster together: {} games, {}W-{}L.",
+                self.full_roster_games,
+                self.full_roster_wins,
+                self.full_roster_games - self.full_roster_wins
+            );
+        }
+        if !self.ban_suggestions.is_empty() {
+            println!("Ban suggestions:");
+            for line in self.ban_lines() {
+                println!("  {}", line);
+            }
+        }
+    }
+
+    fn ban_lines(&self) -> Vec<String> {
+        self.ban_suggestions
+            .iter()
+            .enumerate()
+            .map(|(rank, ban)| {
+                format!("{}. {} ({}): {} games, {:.0}% win rate", rank + 1, ban.champion, ban.players.join(", "), ban.games, ban.win_rate)
+            })
+            .collect()
+    }
+
+    /// Prints only the ban suggestions, e.g. to paste into the team's Discord.
+    pub fn print_ban_list(&self, format: BanListFormat) -> Result<(), Box<dyn Error>> {
+        match format {
+            BanListFormat::Text => {
+                for line in self.ban_lines() {
+                    println!("{}", line);
+                }
+            }
+            BanListFormat::Json => println!("{}", serde_json::to_string_pretty(&self.ban_suggestions)?),
+            BanListFormat::Markdown => {
+                println!("| # | Champion | Played by | Games | Win rate |");
+                println!("|---|---|---|---|---|");
+                for (rank, ban) in self.ban_suggestions.iter().enumerate() {
+                    println!(
+                        "| {} | {} | {} | {} | {:.0}% |",
+                        rank + 1,
+                        ban.champion,
+                        ban.players.join(", "),
+                        ban.games,
+                        ban.win_rate
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use argon2::Argon2;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::error::Error;
+
+/// Environment variable checked for the config passphrase before prompting, for scripted use.
+pub const PASSPHRASE_ENV_VAR: &str = "PTG_CONFIG_PASSPHRASE";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// A value encrypted with XChaCha20-Poly1305 under a key derived from a passphrase with Argon2id.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedSecret {
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+fn derive_cipher(passphrase: &str, salt: &[u8]) -> Result<XChaCha20Poly1305, Box<dyn Error>> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive key from passphrase: {}", e))?;
+    Ok(XChaCha20Poly1305::new(&key.into()))
+}
+
+impl EncryptedSecret {
+    pub fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<Self, Box<dyn Error>> {
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+        let ciphertext = derive_cipher(passphrase, &salt)?
+            .encrypt(XNonce::from_slice(&nonce), plaintext)
+            .map_err(|_| "Failed to encrypt secret.")?;
+        Ok(EncryptedSecret {
+            salt: BASE64.encode(salt),
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        })
+    }
+
+    pub fn decrypt(&self, passphrase: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        let salt = BASE64.decode(&self.salt)?;
+        let nonce = BASE64.decode(&self.nonce)?;
+        if nonce.len() != NONCE_LEN {
+            return Err("Encrypted secret has an invalid nonce.".into());
+        }
+        let ciphertext = BASE64.decode(&self.ciphertext)?;
+        Ok(derive_cipher(passphrase, &salt)?
+            .decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| "Wrong passphrase or corrupted encrypted API keys.")?)
+    }
+}
+
+/// Reads the config passphrase from the environment, or prompts for it without echo.
+/// With `confirm`, a prompted passphrase has to be typed twice.
+pub fn read_passphrase(confirm: bool) -> Result<String, Box<dyn Error>> {
+    if let Ok(passphrase) = env::var(PASSPHRASE_ENV_VAR) {
+        return Ok(passphrase);
+    }
+    let passphrase = rpassword::prompt_password("Config passphrase: ")?;
+    if confirm && rpassword::prompt_password("Repeat passphrase: ")? != passphrase {
+        return Err("Passphrases do not match.".into());
+    }
+    if passphrase.is_empty() {
+        return Err("The passphrase must not be empty.".into());
+    }
+    Ok(passphrase)
+}
@@ -0,0 +1,222 @@
+use chrono::{DateTime, Datelike, Duration, Local, TimeZone, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::api_client::MatchDetails;
+
+/// Games closer together than this (from the end of one to the start of the next) are one session.
+const SESSION_GAP_MS: i64 = 60 * 60 * 1000;
+/// Games starting before this local hour count towards the previous day's night.
+const NIGHT_ENDS_AT_HOUR: u32 = 5;
+/// Consecutive losses within a session from which it counts as a tilt.
+const TILT_STREAK: usize = 3;
+
+/// A stretch of shared games without a break of an hour or more.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaySession {
+    /// When the session started in local time, e.g. "Friday night".
+    pub label: String,
+    /// Local date the session is counted towards, "YYYY-MM-DD".
+    pub date: String,
+    /// Unix timestamp in milliseconds of the start of the first game.
+    pub start_timestamp: i64,
+    /// Unix timestamp in milliseconds of the end of the last game.
+    pub end_timestamp: i64,
+    pub duration_secs: i64,
+    pub games: u32,
+    pub player1_wins: u32,
+    pub player1_losses: u32,
+    /// In the order they were played.
+    pub match_ids: Vec<String>,
+    /// Runs of three or more losses in a row within the session.
+    #[serde(default)]
+    pub losing_streaks: Vec<LosingStreak>,
+}
+
+/// Consecutive losses within a session, with how the pair's play held up over them.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LosingStreak {
+    /// Position of the first lost game in the session, counting from 1.
+    pub first_game: u32,
+    pub losses: u32,
+    /// Combined KDA of both players in the first and the last game of the streak.
+    pub first_game_kda: f64,
+    pub last_game_kda: f64,
+    /// How much the combined KDA dropped per game over the streak; negative if it improved.
+    pub average_kda_decline: f64,
+}
+
+impl PlaySession {
+    /// The game the session should have ended after, one before its first losing streak.
+    /// `Some(0)` means the streak started with the first game.
+    pub fn should_have_stopped_after(&self) -> Option<u32> {
+        self.losing_streaks.first().map(|streak| streak.first_game - 1)
+    }
+}
+
+fn part_of_day(hour: u32) -> &'static str {
+    match hour {
+        NIGHT_ENDS_AT_HOUR..=11 => "morning",
+        12..=16 => "afternoon",
+        17..=20 => "evening",
+        _ => "night",
+    }
+}
+
+fn weekday_name(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "Monday",
+        Weekday::Tue => "Tuesday",
+        Weekday::Wed => "Wednesday",
+        Weekday::Thu => "Thursday",
+        Weekday::Fri => "Friday",
+        Weekday::Sat => "Saturday",
+        Weekday::Sun => "Sunday",
+    }
+}
+
+/// The label and date of a session starting at `start`; after midnight is still the night before.
+fn label_and_date(start: DateTime<Local>) -> (String, String) {
+    let day = if start.hour() < NIGHT_ENDS_AT_HOUR { start - Duration::days(1) } else { start };
+    let label = format!("{} {}", weekday_name(day.weekday()), part_of_day(start.hour()));
+    (label, day.format("%Y-%m-%d").to_string())
+}
+
+/// Formats a duration as "3h12m", or "45m" under an hour.
+pub fn format_duration(secs: i64) -> String {
+    let minutes = secs / 60;
+    if minutes < 60 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}h{:02}m", minutes / 60, minutes % 60)
+    }
+}
+
+/// Kills plus assists of both players over their deaths (at least one).
+fn combined_kda(game: &MatchDetails) -> f64 {
+    let (p1, p2) = (&game.player1_details, &game.player2_details);
+    (p1.kills + p1.assists + p2.kills + p2.assists) as f64 / (p1.deaths + p2.deaths).max(1) as f64
+}
+
+fn losing_streaks(games: &[&MatchDetails]) -> Vec<LosingStreak> {
+    let mut streaks = Vec::new();
+    let mut i = 0;
+    while i < games.len() {
+        let losses = games[i..].iter().take_while(|game| game.player1_details.outcome == "Defeat").count();
+        if losses >= TILT_STREAK {
+            let (first_game_kda, last_game_kda) = (combined_kda(games[i]), combined_kda(games[i + losses - 1]));
+            streaks.push(LosingStreak {
+                first_game: i as u32 + 1,
+                losses: losses as u32,
+                first_game_kda,
+                last_game_kda,
+                average_kda_decline: (first_game_kda - last_game_kda) / (losses - 1) as f64,
+            });
+        }
+        i += losses.max(1);
+    }
+    streaks
+}
+
+/// Real time between the end of one game and the start of the next within the same session, in
+/// seconds, for every pair of consecutive games.
+pub fn queue_gaps_secs(matches: &[MatchDetails]) -> Vec<i64> {
+    let mut games: Vec<(i64, i64)> = matches
+        .iter()
+        .map(|m| (m.game_start_timestamp, m.game_start_timestamp + m.game_duration_secs * 1000))
+        .collect();
+    games.sort_by_key(|(start, _)| *start);
+    games
+        .windows(2)
+        .map(|pair| (pair[1].0 - pair[0].1).max(0))
+        .filter(|gap| *gap < SESSION_GAP_MS)
+        .map(|gap| gap / 1000)
+        .collect()
+}
+
+/// Groups the games into sessions, oldest first.
+pub fn detect_sessions(matches: &[MatchDetails]) -> Vec<PlaySession> {
+    let mut games: Vec<&MatchDetails> = matches.iter().collect();
+    games.sort_by_key(|m| m.game_start_timestamp);
+
+    let mut sessions: Vec<Vec<&MatchDetails>> = Vec::new();
+    let mut last_end = None;
+    for game in games {
+        match sessions.last_mut() {
+            Some(session) if last_end.is_some_and(|end| game.game_start_timestamp - end < SESSION_GAP_MS) => session.push(game),
+            _ => sessions.push(vec![game]),
+        }
+        let end = game.game_start_timestamp + game.game_duration_secs * 1000;
+        last_end = Some(last_end.map_or(end, |last: i64| last.max(end)));
+    }
+
+    sessions
+        .into_iter()
+        .filter_map(|games| {
+            let start_timestamp = games.first()?.game_start_timestamp;
+            let end_timestamp = games.iter().map(|m| m.game_start_timestamp + m.game_duration_secs * 1000).max()?;
+            let (label, date) = label_and_date(Local.timestamp_millis_opt(start_timestamp).single()?);
+            let player1_wins = games.iter().filter(|m| m.player1_details.outcome == "Victory").count() as u32;
+            let player1_losses = games.iter().filter(|m| m.player1_details.outcome == "Defeat").count() as u32;
+            Some(PlaySession {
+                label,
+                date,
+                start_timestamp,
+                end_timestamp,
+                duration_secs: (end_timestamp - start_timestamp) / 1000,
+                games: games.len() as u32,
+                player1_wins,
+                player1_losses,
+                match_ids: games.iter().map(|m| m.match_id.clone()).collect(),
+                losing_streaks: losing_streaks(&games),
+            })
+        })
+        .collect()
+}
+
+/// Prints one line per session, e.g. "Friday night (2024-10-11): 2W-3L, 3h12m".
+pub fn print_sessions(sessions: &[PlaySession]) {
+    println!("\n--- Sessions ---");
+    if sessions.is_empty() {
+        println!("No games found together.");
+        return;
+    }
+    for session in sessions {
+        println!(
+            "{} ({}): {}W-{}L, {}",
+            session.label,
+            session.date,
+            session.player1_wins,
+            session.player1_losses,
+            format_duration(session.duration_secs)
+        );
+        for streak in &session.losing_streaks {
+            println!(
+                "  Tilt: {} losses in a row from game {}, combined KDA down {:.2} per game.",
+                streak.losses,
+                streak.first_game,
+                streak.average_kda_decline
+            );
+        }
+        if let Some(advice) = stopping_advice(session) {
+            println!("  {}", advice);
+        }
+    }
+}
+
+fn stopping_advice(session: &PlaySession) -> Option<String> {
+    match session.should_have_stopped_after()? {
+        0 => Some("Should not have queued up at all.".to_string()),
+        game => Some(format!("Should have stopped after game {}.", game)),
+    }
+}
+
+/// Prints a line for every session with a losing streak; the summary without `--sessions`.
+pub fn print_tilt(sessions: &[PlaySession]) {
+    for session in sessions {
+        if let Some(advice) = stopping_advice(session) {
+            println!("Tilt on {} ({}): {}", session.label, session.date, advice);
+        }
+    }
+}
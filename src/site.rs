@@ -0,0 +1,180 @@
+use serde::Serialize;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use tera::{Context, Tera};
+
+use crate::anonymize;
+use crate::contacts;
+use crate::cache::AccountCache;
+use crate::chart::win_rate_svg;
+use crate::index::MatchIndex;
+use crate::pair_history::{run_pair_history, PairHistoryOptions};
+use crate::riot_id::RiotId;
+use crate::stats::win_rate;
+
+/// Stylesheet of the site, also inlined into `render --format html` pages.
+pub const STYLE: &str = "\
+body { font-family: sans-serif; max-width: 960px; margin: 2em auto; padding: 0 1em; color: #222; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; padding: 0.3em 0.6em; border-bottom: 1px solid #ddd; }
+.Victory { color: #2e9e44; }
+.Defeat { color: #d13639; }
+img.icon { width: 24px; height: 24px; vertical-align: middle; margin-right: 2px; }
+";
+
+const INDEX_TEMPLATE: &str = "\
+<!DOCTYPE html>
+<html>
+<head><meta charset=\"utf-8\"><title>Played together with {{ player }}</title><link rel=\"stylesheet\" href=\"style.css\"></head>
+<body>
+<h1>Played together with {{ player }}</h1>
+<table>
+<tr><th>Partner</th><th>Games</th><th>Wins</th><th>Win rate</th></tr>
+{% for pair in pairs %}<tr><td><a href=\"{{ pair.slug }}/index.html\">{{ pair.partner }}</a></td><td>{{ pair.games }}</td><td>{{ pair.wins }}</td><td>{{ pair.winRate | round }}%</td></tr>
+{% endfor %}</table>
+</body>
+</html>
+";
+
+const PAIR_TEMPLATE: &str = "\
+<!DOCTYPE html>
+<html>
+<head><meta charset=\"utf-8\"><title>{{ player }} and {{ partner }}</title><link rel=\"stylesheet\" href=\"../style.css\"></head>
+<body>
+<p><a href=\"../index.html\">All partners</a></p>
+<h1>{{ player }} and {{ partner }}</h1>
+<p>{{ querySummary.matchesPlayedTogetherCount }} games together, {{ querySummary.player1WinsTogetherCount }} won.</p>
+{% if chart %}<h2>Rolling win rate</h2>
+{{ chart | safe }}
+{% endif %}<h2>Games</h2>
+<table>
+<tr><th>Date</th><th>Mode</th><th>{{ player }}</th><th>{{ partner }}</th><th>Result</th></tr>
+{% for m in foundMatches %}<tr><td><a href=\"{{ m.matchId }}.html\">{{ m.gameDateUtc }}</a></td><td>{{ m.gameMode }}</td><td>{{ m.player1Details.champion }} {{ m.player1Details.kills }}/{{ m.player1Details.deaths }}/{{ m.player1Details.assists }}</td><td>{{ m.player2Details.champion }} {{ m.player2Details.kills }}/{{ m.player2Details.deaths }}/{{ m.player2Details.assists }}</td><td class=\"{{ m.player1Details.outcome }}\">{{ m.player1Details.outcome }}</td></tr>
+{% endfor %}</table>
+</body>
+</html>
+";
+
+const MATCH_TEMPLATE: &str = "\
+<!DOCTYPE html>
+<html>
+<head><meta charset=\"utf-8\"><title>{{ m.matchId }}</title><link rel=\"stylesheet\" href=\"../style.css\"></head>
+<body>
+<p><a href=\"index.html\">{{ player }} and {{ partner }}</a></p>
+<h1 class=\"{{ m.player1Details.outcome }}\">{{ m.player1Details.outcome }}: {{ m.gameMode }}</h1>
+<p>{{ m.gameDateUtc }}, {{ m.gameDurationSecs / 60 | round(method=\"floor\") }} minutes, patch {{ m.gameVersion }}{% if m.sameTeam %}, same team{% else %}, opposite teams{% endif %}</p>
+<table>
+<tr><th>Player</th><th>Champion</th><th>Role</th><th>KDA</th><th>Result</th></tr>
+<tr><td>{{ player }}</td><td>{{ m.player1Details.champion }}</td><td>{{ m.player1Details.role }}</td><td>{{ m.player1Details.kills }}/{{ m.player1Details.deaths }}/{{ m.player1Details.assists }}</td><td class=\"{{ m.player1Details.outcome }}\">{{ m.player1Details.outcome }}</td></tr>
+<tr><td>{{ partner }}</td><td>{{ m.player2Details.champion }}</td><td>{{ m.player2Details.role }}</td><td>{{ m.player2Details.kills }}/{{ m.player2Details.deaths }}/{{ m.player2Details.assists }}</td><td class=\"{{ m.player2Details.outcome }}\">{{ m.player2Details.outcome }}</td></tr>
+</table>
+{% if m.leagueOfGraphsLink %}<p><a href=\"{{ m.leagueOfGraphsLink }}\">League of Graphs</a></p>{% endif %}
+</body>
+</html>
+";
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SitePair {
+    partner: String,
+    /// Directory of the pair's pages.
+    slug: String,
+    games: u32,
+    wins: u32,
+    win_rate: f64,
+}
+
+/// What `build_site` wrote.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SiteReport {
+    pub pair_pages: u32,
+    pub match_pages: u32,
+}
+
+/// A file-name-safe form of a Riot ID, e.g. "Faker#KR1" becomes "faker-kr1".
+fn slug(riot_id: &str) -> String {
+    riot_id
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect()
+}
+
+/// Renders the indexed shared history of the self ID with every partner into a static website in
+/// `dir`: an index page, one page per partner with a rolling win-rate chart, and one page per
+/// shared match. Only the local index is read, so run `prefetch` first to publish a complete history.
+pub fn build_site(
+    account_cache: &AccountCache,
+    match_index: &MatchIndex,
+    self_riot_id: &RiotId,
+    partners: Vec<RiotId>,
+    dir: &Path,
+) -> Result<SiteReport, Box<dyn Error>> {
+    fs::create_dir_all(dir)?;
+    fs::write(dir.join("style.css"), STYLE)?;
+    let player = contacts::display_name(self_riot_id);
+    let mut report = SiteReport { pair_pages: 0, match_pages: 0 };
+
+    let mut pairs = Vec::new();
+    for partner_riot_id in partners {
+        if account_cache.get(&partner_riot_id).is_none() {
+            eprintln!("Warning: '{}' is not in the local cache yet. Skipping.", anonymize::riot_id(&partner_riot_id));
+            continue;
+        }
+        let output = run_pair_history(
+            account_cache,
+            match_index,
+            PairHistoryOptions {
+                player1_riot_id: self_riot_id.clone(),
+                player2_riot_id: partner_riot_id.clone(),
+                all_time: true,
+                user_selected_region: None,
+                patch: None,
+                include_afk: false,
+                data_dragon: None,
+                verbose: false,
+                quiet: true,
+            },
+        )?;
+        let partner = contacts::display_name(&partner_riot_id);
+        let pair_slug = slug(&anonymize::riot_id(&partner_riot_id));
+        let pair_dir = dir.join(&pair_slug);
+        fs::create_dir_all(&pair_dir)?;
+
+        let mut context = Context::from_serialize(&output)?;
+        context.insert("player", &player);
+        context.insert("partner", &partner);
+        context.insert("chart", &win_rate_svg(&output.found_matches));
+        fs::write(pair_dir.join("index.html"), Tera::one_off(PAIR_TEMPLATE, &context, true)?)?;
+        report.pair_pages += 1;
+
+        for match_details in &output.found_matches {
+            let mut context = Context::new();
+            context.insert("m", match_details);
+            context.insert("player", &player);
+            context.insert("partner", &partner);
+            fs::write(
+                pair_dir.join(format!("{}.html", match_details.match_id)),
+                Tera::one_off(MATCH_TEMPLATE, &context, true)?,
+            )?;
+            report.match_pages += 1;
+        }
+
+        let summary = &output.query_summary;
+        pairs.push(SitePair {
+            slug: pair_slug,
+            partner,
+            games: summary.matches_played_together_count,
+            wins: summary.player1_wins_together_count,
+            win_rate: win_rate(summary.player1_wins_together_count, summary.matches_played_together_count),
+        });
+    }
+    pairs.sort_by(|a, b| b.games.cmp(&a.games).then_with(|| a.partner.cmp(&b.partner)));
+
+    let mut context = Context::new();
+    context.insert("player", &player);
+    context.insert("pairs", &pairs);
+    fs::write(dir.join("index.html"), Tera::one_off(INDEX_TEMPLATE, &context, true)?)?;
+    Ok(report)
+}
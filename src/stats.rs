@@ -0,0 +1,691 @@
+use riven::consts::GameMode;
+use riven::models::match_v5::{Info, Match, Participant};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::api_client::{BuildEntry, MatchDetails};
+use crate::console;
+use crate::sessions::{format_duration, queue_gaps_secs};
+use crate::utils;
+
+/// Aggregated statistics over the shared matches of a query.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchStats {
+    pub per_patch: Vec<PatchStats>,
+    pub game_flow: GameFlowDistribution,
+    /// Average share of the pair's kills the other player assisted on, over games with a fetched timeline.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kills_together_rate: Option<f64>,
+    /// Average pings per game of each player, over games that report ping counts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pings_per_game: Option<PingsPerGame>,
+    /// Recency-weighted win rate of the pair; needs at least `MIN_FORM_GAMES` shared games.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recent_form: Option<RecentForm>,
+    pub early_game: EarlyGameStats,
+    #[serde(default)]
+    pub durations: DurationStats,
+    #[serde(default)]
+    pub vision_duel: VisionDuel,
+    /// The keystone combinations the players ran together most, most games first.
+    #[serde(default)]
+    pub keystones_together: Vec<KeystonePair>,
+    /// Each player's shared games against their other games in the window; only with `--solo-baseline`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub solo_baseline: Option<SoloBaseline>,
+}
+
+/// Which of the two players had the higher vision score, game by game.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VisionDuel {
+    pub player1_wins: u32,
+    pub player2_wins: u32,
+    pub ties: u32,
+    /// Player1's duel wins minus player2's after each shared game, oldest first.
+    pub running_tally: Vec<i32>,
+}
+
+impl VisionDuel {
+    pub fn from_matches(matches: &[MatchDetails]) -> Self {
+        let mut games: Vec<&MatchDetails> = matches.iter().collect();
+        games.sort_by_key(|m| m.game_start_timestamp);
+        let mut duel = VisionDuel::default();
+        for m in games {
+            match m.player1_details.vision_score.cmp(&m.player2_details.vision_score) {
+                std::cmp::Ordering::Greater => duel.player1_wins += 1,
+                std::cmp::Ordering::Less => duel.player2_wins += 1,
+                std::cmp::Ordering::Equal => duel.ties += 1,
+            }
+            duel.running_tally.push(duel.player1_wins as i32 - duel.player2_wins as i32);
+        }
+        duel
+    }
+
+    pub fn games(&self) -> u32 {
+        self.player1_wins + self.player2_wins + self.ties
+    }
+}
+
+/// Keystone combinations listed in `keystones_together`.
+const MAX_KEYSTONE_PAIRS: usize = 5;
+
+/// A combination of keystones the two players ran in the same shared games.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeystonePair {
+    pub player1_keystone: BuildEntry,
+    pub player2_keystone: BuildEntry,
+    pub games: u32,
+    pub player1_wins: u32,
+    pub win_rate: f64,
+}
+
+fn keystones_together(matches: &[MatchDetails]) -> Vec<KeystonePair> {
+    let mut pairs: Vec<KeystonePair> = Vec::new();
+    for m in matches {
+        let (Some(p1_runes), Some(p2_runes)) = (&m.player1_details.runes, &m.player2_details.runes) else {
+            continue;
+        };
+        let won = (m.player1_details.outcome == "Victory") as u32;
+        let existing = pairs
+            .iter_mut()
+            .find(|pair| pair.player1_keystone.id == p1_runes.keystone.id && pair.player2_keystone.id == p2_runes.keystone.id);
+        match existing {
+            Some(pair) => {
+                pair.games += 1;
+                pair.player1_wins += won;
+            }
+            None => pairs.push(KeystonePair {
+                player1_keystone: p1_runes.keystone.clone(),
+                player2_keystone: p2_runes.keystone.clone(),
+                games: 1,
+                player1_wins: won,
+                win_rate: 0.0,
+            }),
+        }
+    }
+    for pair in &mut pairs {
+        pair.win_rate = win_rate(pair.player1_wins, pair.games);
+    }
+    // Stable, so ties keep the order of the first game with the combination.
+    pairs.sort_by_key(|pair| std::cmp::Reverse(pair.games));
+    pairs.truncate(MAX_KEYSTONE_PAIRS);
+    pairs
+}
+
+/// Width of a bucket of the game duration histogram, in minutes.
+const DURATION_BUCKET_MINUTES: i64 = 5;
+
+/// How long the shared games took and how long the pair waited between them.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DurationStats {
+    /// Games per `DURATION_BUCKET_MINUTES` of game length, from the shortest game's bucket to the
+    /// longest game's, including empty buckets in between.
+    pub histogram: Vec<DurationBucket>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub average_duration_secs: Option<f64>,
+    /// Average time from the end of one game to the start of the next within a play session.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub average_queue_gap_secs: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DurationBucket {
+    /// Game length the bucket starts at, included.
+    pub from_minutes: i64,
+    /// Game length the bucket ends at, excluded.
+    pub to_minutes: i64,
+    pub games: u32,
+}
+
+fn average(values: &[i64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<i64>() as f64 / values.len() as f64)
+}
+
+impl DurationStats {
+    pub fn from_matches(matches: &[MatchDetails]) -> Self {
+        let durations: Vec<i64> = matches.iter().map(|m| m.game_duration_secs).collect();
+        let bucket_of = |secs: i64| secs / 60 / DURATION_BUCKET_MINUTES;
+        let histogram = match (durations.iter().copied().min(), durations.iter().copied().max()) {
+            (Some(shortest), Some(longest)) => (bucket_of(shortest)..=bucket_of(longest))
+                .map(|bucket| DurationBucket {
+                    from_minutes: bucket * DURATION_BUCKET_MINUTES,
+                    to_minutes: (bucket + 1) * DURATION_BUCKET_MINUTES,
+                    games: durations.iter().filter(|secs| bucket_of(**secs) == bucket).count() as u32,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        DurationStats {
+            histogram,
+            average_duration_secs: average(&durations),
+            average_queue_gap_secs: average(&queue_gaps_secs(matches)),
+        }
+    }
+}
+
+/// First blood, lane gold and early surrenders over the shared games.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EarlyGameStats {
+    /// Games where either player got or assisted on first blood.
+    pub first_blood_games: u32,
+    /// In percent of the shared games.
+    pub first_blood_rate: f64,
+    pub player1_first_bloods: u32,
+    pub player2_first_bloods: u32,
+    /// Average gold lead over the lane opponent at 10 minutes, over games with a fetched timeline.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub player1_lane_gold_diff_at10: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub player2_lane_gold_diff_at10: Option<f64>,
+    pub early_surrenders: u32,
+    /// In percent of the shared games.
+    pub early_surrender_rate: f64,
+}
+
+impl EarlyGameStats {
+    fn from_matches(matches: &[MatchDetails]) -> Self {
+        let games = matches.len() as u32;
+        let count = |predicate: &dyn Fn(&MatchDetails) -> bool| matches.iter().filter(|m| predicate(m)).count() as u32;
+        let average = |values: Vec<i32>| (!values.is_empty()).then(|| values.iter().sum::<i32>() as f64 / values.len() as f64);
+        let first_blood_games = count(&|m| m.player1_details.first_blood || m.player2_details.first_blood);
+        let early_surrenders = count(&|m| m.early_surrendered);
+        EarlyGameStats {
+            first_blood_games,
+            first_blood_rate: win_rate(first_blood_games, games),
+            player1_first_bloods: count(&|m| m.player1_details.first_blood),
+            player2_first_bloods: count(&|m| m.player2_details.first_blood),
+            player1_lane_gold_diff_at10: average(matches.iter().filter_map(|m| m.player1_details.lane_gold_diff_at10).collect()),
+            player2_lane_gold_diff_at10: average(matches.iter().filter_map(|m| m.player2_details.lane_gold_diff_at10).collect()),
+            early_surrenders,
+            early_surrender_rate: win_rate(early_surrenders, games),
+        }
+    }
+}
+
+/// How one player did over a set of games.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PerformanceLine {
+    pub games: u32,
+    pub wins: u32,
+    pub win_rate: f64,
+    /// Kills plus assists per death over all the games, with at least one death counted.
+    pub kda: f64,
+    pub damage_to_champions_per_game: f64,
+}
+
+impl PerformanceLine {
+    pub fn from_participants<'a>(participants: impl IntoIterator<Item = &'a Participant>) -> Self {
+        let (mut games, mut wins, mut kills_and_assists, mut deaths, mut damage) = (0, 0, 0, 0, 0i64);
+        for participant in participants {
+            games += 1;
+            wins += u32::from(participant.win);
+            kills_and_assists += participant.kills + participant.assists;
+            deaths += participant.deaths;
+            damage += i64::from(participant.total_damage_dealt_to_champions);
+        }
+        PerformanceLine {
+            games,
+            wins,
+            win_rate: win_rate(wins, games),
+            kda: kills_and_assists as f64 / deaths.max(1) as f64,
+            damage_to_champions_per_game: if games == 0 { 0.0 } else { damage as f64 / games as f64 },
+        }
+    }
+}
+
+/// One player's shared games next to the games they played without the other player.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SoloDuoComparison {
+    pub solo: PerformanceLine,
+    pub duo: PerformanceLine,
+}
+
+fn participant<'a>(match_data: &'a Match, puuid: &str) -> Option<&'a Participant> {
+    match_data.info.participants.iter().find(|p| p.puuid == puuid)
+}
+
+/// Solo vs duo performance of both players, over the same window as the shared games.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SoloBaseline {
+    pub player1: SoloDuoComparison,
+    pub player2: SoloDuoComparison,
+}
+
+impl SoloBaseline {
+    /// Compares the `shared` games with each player's listed games that lack the other player.
+    /// Solo games of another patch than `patch` are left out, like the shared ones.
+    pub fn from_matches(
+        (puuid1, player1_games): (&str, &[Match]),
+        (puuid2, player2_games): (&str, &[Match]),
+        shared: &[Match],
+        patch: Option<&str>,
+    ) -> Self {
+        let comparison = |puuid: &str, other_puuid: &str, games: &[Match]| {
+            let solo = games.iter().filter(|m| {
+                participant(m, other_puuid).is_none() && patch.is_none_or(|patch| patch_of(&m.info.game_version) == patch)
+            });
+            SoloDuoComparison {
+                solo: PerformanceLine::from_participants(solo.filter_map(|m| participant(m, puuid))),
+                duo: PerformanceLine::from_participants(shared.iter().filter_map(|m| participant(m, puuid))),
+            }
+        };
+        SoloBaseline {
+            player1: comparison(puuid1, puuid2, player1_games),
+            player2: comparison(puuid2, puuid1, player2_games),
+        }
+    }
+
+    /// Prints a solo and a duo row per player.
+    pub fn print(&self, player1_name: &str, player2_name: &str) {
+        println!("\n--- Solo vs Duo ---");
+        let width = player1_name.chars().count().max(player2_name.chars().count()) + 5;
+        println!("{:<width$} {:>6} {:>9} {:>6} {:>12}", "", "Games", "Win rate", "KDA", "Damage/game", width = width);
+        for (name, comparison) in [(player1_name, &self.player1), (player2_name, &self.player2)] {
+            for (label, line) in [("solo", &comparison.solo), ("duo", &comparison.duo)] {
+                println!(
+                    "{:<width$} {:>6} {:>8.1}% {:>6.2} {:>12.0}",
+                    format!("{} {}", name, label),
+                    line.games,
+                    line.win_rate,
+                    line.kda,
+                    line.damage_to_champions_per_game,
+                    width = width
+                );
+            }
+        }
+    }
+}
+
+/// Shared games weighted above the rest in the form score.
+const RECENT_FORM_GAMES: usize = 10;
+/// Each of the recent games weighs this much of the next newer one; older games weigh as much as the last recent one.
+const FORM_DECAY: f64 = 0.8;
+/// Percentage points the form score must differ from the plain win rate to count as a trend.
+const FORM_TREND_THRESHOLD: f64 = 5.0;
+/// Fewer shared games than this give no form score.
+const MIN_FORM_GAMES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FormTrend {
+    Up,
+    Steady,
+    Down,
+}
+
+/// Player1's win rate over the shared games with the last `RECENT_FORM_GAMES` weighted higher, the
+/// newest most. Compared with the plain win rate it shows whether the duo is trending up or down.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentForm {
+    /// Weighted win rate in percent.
+    pub score: f64,
+    /// Unweighted win rate in percent, for comparison.
+    pub win_rate: f64,
+    pub trend: FormTrend,
+    pub games: u32,
+}
+
+impl RecentForm {
+    /// `matches` in any order; they are weighted by game start.
+    pub fn from_matches(matches: &[MatchDetails]) -> Option<Self> {
+        if matches.len() < MIN_FORM_GAMES {
+            return None;
+        }
+        let mut newest_first: Vec<&MatchDetails> = matches.iter().collect();
+        newest_first.sort_by_key(|m| std::cmp::Reverse(m.game_start_timestamp));
+        let (mut weighted_wins, mut total_weight, mut wins) = (0.0, 0.0, 0);
+        for (age, match_details) in newest_first.iter().enumerate() {
+            let weight = FORM_DECAY.powi(age.min(RECENT_FORM_GAMES - 1) as i32);
+            total_weight += weight;
+            if match_details.player1_details.outcome == "Victory" {
+                weighted_wins += weight;
+                wins += 1;
+            }
+        }
+        let score = weighted_wins / total_weight * 100.0;
+        let win_rate = win_rate(wins, matches.len() as u32);
+        let trend = if score - win_rate >= FORM_TREND_THRESHOLD {
+            FormTrend::Up
+        } else if win_rate - score >= FORM_TREND_THRESHOLD {
+            FormTrend::Down
+        } else {
+            FormTrend::Steady
+        };
+        Some(RecentForm { score, win_rate, trend, games: matches.len() as u32 })
+    }
+
+    pub fn label(&self) -> String {
+        let trend = match self.trend {
+            FormTrend::Up => console::pick("trending up ↑", "trending up ^"),
+            FormTrend::Steady => console::pick("steady →", "steady ->"),
+            FormTrend::Down => console::pick("trending down ↓", "trending down v"),
+        };
+        format!("{:.0}/100, {} (win rate {:.0}%)", self.score, trend, self.win_rate)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PingsPerGame {
+    pub games: u32,
+    pub player1: f64,
+    pub player2: f64,
+}
+
+/// Gold lead at 15 minutes above which a win counts as a stomp.
+const STOMP_GOLD_LEAD: i32 = 5000;
+/// Gold deficit at 15 minutes the winning team must have overcome for a comeback.
+const COMEBACK_GOLD_DEFICIT: i32 = 2500;
+
+/// How one-sided a game was, judged from the gold difference at 15 minutes and surrenders.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GameFlow {
+    Stomp,
+    Close,
+    Comeback,
+}
+
+impl std::fmt::Display for GameFlow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameFlow::Stomp => write!(f, "Stomp"),
+            GameFlow::Close => write!(f, "Close"),
+            GameFlow::Comeback => write!(f, "Comeback"),
+        }
+    }
+}
+
+/// Classifies a game from player1's side. `gold_diff_at_15` is player1's team gold minus the enemy's,
+/// and is only known when the timeline was fetched; without it only surrenders can be classified.
+pub fn classify_game_flow(player1_won: bool, gold_diff_at_15: Option<i32>, early_surrender: bool, surrender: bool) -> Option<GameFlow> {
+    let Some(gold_diff) = gold_diff_at_15 else {
+        return (early_surrender || surrender).then_some(GameFlow::Stomp);
+    };
+    let winner_gold_lead = if player1_won { gold_diff } else { -gold_diff };
+    if winner_gold_lead <= -COMEBACK_GOLD_DEFICIT {
+        Some(GameFlow::Comeback)
+    } else if winner_gold_lead >= STOMP_GOLD_LEAD || early_surrender || (surrender && winner_gold_lead > 0) {
+        Some(GameFlow::Stomp)
+    } else {
+        Some(GameFlow::Close)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GameFlowDistribution {
+    pub stomps: u32,
+    pub close: u32,
+    pub comebacks: u32,
+    pub unclassified: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchStats {
+    pub patch: String,
+    pub games: u32,
+    pub wins: u32,
+    pub win_rate: f64,
+}
+
+/// Reduces a full game version such as "14.20.628.3370" to its patch, "14.20".
+pub fn patch_of(game_version: &str) -> String {
+    game_version.split('.').take(2).collect::<Vec<_>>().join(".")
+}
+
+/// Orders patches by their numbers, so "14.3" comes before "14.20"; versions that are not
+/// numbered sort last, by name.
+fn patch_sort_key(patch: &str) -> (u32, u32, String) {
+    let mut numbers = patch.split('.').map(|part| part.parse::<u32>().ok());
+    match (numbers.next().flatten(), numbers.next().flatten()) {
+        (Some(major), Some(minor)) => (major, minor, patch.to_string()),
+        _ => (u32::MAX, u32::MAX, patch.to_string()),
+    }
+}
+
+/// Rough performance score from 0 to 100: the average of the player's shares of their team's damage to
+/// champions, kills (kill participation), vision score and gold.
+pub fn performance_score(player: &Participant, participants: &[Participant]) -> f64 {
+    let team: Vec<&Participant> = participants.iter().filter(|p| p.team_id == player.team_id).collect();
+    let share = |value: i32, of: fn(&Participant) -> i32| {
+        let total: i32 = team.iter().map(|p| of(p)).sum();
+        if total > 0 { value as f64 / total as f64 } else { 0.0 }
+    };
+    let shares = [
+        share(player.total_damage_dealt_to_champions, |p| p.total_damage_dealt_to_champions),
+        share(player.kills + player.assists, |p| p.kills).min(1.0),
+        share(player.vision_score, |p| p.vision_score),
+        share(player.gold_earned, |p| p.gold_earned),
+    ];
+    shares.iter().sum::<f64>() / shares.len() as f64 * 100.0
+}
+
+/// Share of the game spent dead from which a player likely disconnected or went AFK.
+const AFK_DEAD_SHARE: f64 = 0.5;
+/// Kill participation below this, together with little gold, marks a player who barely took part.
+const AFK_KILL_PARTICIPATION: f64 = 0.1;
+/// Gold below this share of the teammates' average, together with no kill participation to speak of.
+const AFK_GOLD_SHARE: f64 = 0.5;
+/// Teams with fewer kills than this say too little about participation.
+const AFK_MIN_TEAM_KILLS: i32 = 5;
+
+/// Whether `player` likely went AFK or disconnected: dead for half the game, hardly any kill
+/// participation on far less gold than the teammates, or an early surrender without a kill, death or
+/// assist. Arena subteams are too small to compare, so Arena games are never flagged.
+pub fn likely_afk(player: &Participant, info: &Info) -> bool {
+    if info.game_mode == GameMode::CHERRY || info.game_duration <= 0 {
+        return false;
+    }
+    if player.total_time_spent_dead as f64 >= AFK_DEAD_SHARE * info.game_duration as f64 {
+        return true;
+    }
+    if player.game_ended_in_early_surrender && player.kills + player.deaths + player.assists == 0 {
+        return true;
+    }
+    let teammates: Vec<&Participant> = info
+        .participants
+        .iter()
+        .filter(|p| p.team_id == player.team_id && p.puuid != player.puuid)
+        .collect();
+    let team_kills: i32 = teammates.iter().map(|p| p.kills).sum::<i32>() + player.kills;
+    if teammates.is_empty() || team_kills < AFK_MIN_TEAM_KILLS {
+        return false;
+    }
+    let kill_participation = player
+        .challenges
+        .as_ref()
+        .and_then(|challenges| challenges.kill_participation)
+        .map(f64::from)
+        .unwrap_or((player.kills + player.assists) as f64 / team_kills as f64);
+    let teammate_gold = teammates.iter().map(|p| p.gold_earned as f64).sum::<f64>() / teammates.len() as f64;
+    kill_participation < AFK_KILL_PARTICIPATION && (player.gold_earned as f64) < AFK_GOLD_SHARE * teammate_gold
+}
+
+/// How many shared games each player carried, i.e. had the higher performance score in; games
+/// against each other are left out.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CarryTally {
+    pub player1: u32,
+    pub player2: u32,
+}
+
+impl CarryTally {
+    pub fn from_matches(matches: &[MatchDetails]) -> Self {
+        let count = |player| matches.iter().filter(|m| m.carried_by.as_deref() == Some(player)).count() as u32;
+        CarryTally { player1: count("player1"), player2: count("player2") }
+    }
+}
+
+/// Win rate (or any share of games) in percent, 0 when no games were played.
+pub fn win_rate(wins: u32, games: u32) -> f64 {
+    if games == 0 {
+        0.0
+    } else {
+        wins as f64 / games as f64 * 100.0
+    }
+}
+
+impl MatchStats {
+    pub fn from_matches(matches: &[MatchDetails]) -> Self {
+        let mut per_patch: BTreeMap<(u32, u32, String), (u32, u32)> = BTreeMap::new();
+        let mut game_flow = GameFlowDistribution::default();
+        for match_details in matches {
+            match match_details.game_flow {
+                Some(GameFlow::Stomp) => game_flow.stomps += 1,
+                Some(GameFlow::Close) => game_flow.close += 1,
+                Some(GameFlow::Comeback) => game_flow.comebacks += 1,
+                None => game_flow.unclassified += 1,
+            }
+
+            let entry = per_patch.entry(patch_sort_key(&patch_of(&match_details.game_version))).or_default();
+            entry.0 += 1;
+            if match_details.player1_details.outcome == "Victory" {
+                entry.1 += 1;
+            }
+        }
+
+        let together_rates: Vec<f64> = matches
+            .iter()
+            .filter_map(|m| m.kill_participation.as_ref()?.together_rate)
+            .collect();
+        let kills_together_rate =
+            (!together_rates.is_empty()).then(|| together_rates.iter().sum::<f64>() / together_rates.len() as f64);
+
+        let ping_totals: Vec<(i32, i32)> = matches
+            .iter()
+            .filter_map(|m| Some((m.player1_details.pings.as_ref()?.total, m.player2_details.pings.as_ref()?.total)))
+            .collect();
+        let pings_per_game = (!ping_totals.is_empty()).then(|| {
+            let games = ping_totals.len() as f64;
+            PingsPerGame {
+                games: ping_totals.len() as u32,
+                player1: ping_totals.iter().map(|(p1, _)| *p1 as f64).sum::<f64>() / games,
+                player2: ping_totals.iter().map(|(_, p2)| *p2 as f64).sum::<f64>() / games,
+            }
+        });
+
+        MatchStats {
+            per_patch: per_patch
+                .into_iter()
+                .map(|((_, _, patch), (games, wins))| PatchStats {
+                    patch,
+                    games,
+                    wins,
+                    win_rate: win_rate(wins, games),
+                })
+                .collect(),
+            game_flow,
+            kills_together_rate,
+            pings_per_game,
+            recent_form: RecentForm::from_matches(matches),
+            early_game: EarlyGameStats::from_matches(matches),
+            durations: DurationStats::from_matches(matches),
+            vision_duel: VisionDuel::from_matches(matches),
+            keystones_together: keystones_together(matches),
+            solo_baseline: None,
+        }
+    }
+
+    /// Prints the statistics as plain-text tables.
+    pub fn print(&self) {
+        println!("\n--- Per-Patch Breakdown ---");
+        if self.per_patch.is_empty() {
+            println!("No games found together.");
+            return;
+        }
+        println!("{:<8} {:>6} {:>6} {:>9}", "Patch", "Games", "Wins", "Win rate");
+        for patch in &self.per_patch {
+            println!("{:<8} {:>6} {:>6} {:>8.1}%", patch.patch, patch.games, patch.wins, patch.win_rate);
+        }
+
+        println!("\n--- Game Flow ---");
+        println!(
+            "Stomps: {}, Close: {}, Comebacks: {}, Unclassified: {}",
+            self.game_flow.stomps, self.game_flow.close, self.game_flow.comebacks, self.game_flow.unclassified
+        );
+        if let Some(rate) = self.kills_together_rate {
+            println!("Kills together: {:.1}% of the pair's kills were assisted by the other player on average", rate);
+        }
+
+        let early = &self.early_game;
+        println!("\n--- Early Game ---");
+        println!(
+            "First blood involvement: {:.1}% of games (Player 1 {}, Player 2 {})",
+            early.first_blood_rate, early.player1_first_bloods, early.player2_first_bloods
+        );
+        if early.player1_lane_gold_diff_at10.is_some() || early.player2_lane_gold_diff_at10.is_some() {
+            let gold = |diff: Option<f64>| diff.map_or("-".to_string(), |diff| format!("{:+.0}", diff));
+            println!(
+                "Lane gold diff at 10: Player 1 {}, Player 2 {}",
+                gold(early.player1_lane_gold_diff_at10),
+                gold(early.player2_lane_gold_diff_at10)
+            );
+        }
+        println!("Early surrenders: {} ({:.1}%)", early.early_surrenders, early.early_surrender_rate);
+
+        let durations = &self.durations;
+        if let (Some(first), Some(last)) = (durations.histogram.first(), durations.histogram.last()) {
+            println!("\n--- Game Durations ---");
+            let games: Vec<f64> = durations.histogram.iter().map(|bucket| bucket.games as f64).collect();
+            println!(
+                "{} min {} {} min (per {} minutes)",
+                first.from_minutes,
+                utils::sparkline(&games),
+                last.to_minutes,
+                DURATION_BUCKET_MINUTES
+            );
+            if let Some(average) = durations.average_duration_secs {
+                println!("Average game: {}", format_duration(average as i64));
+            }
+            if let Some(gap) = durations.average_queue_gap_secs {
+                println!("Average gap between games in a session: {}", format_duration(gap as i64));
+            }
+        }
+
+        let duel = &self.vision_duel;
+        if duel.games() > 0 {
+            println!("\n--- Vision Duel ---");
+            println!(
+                "Higher vision score: Player 1 {}, Player 2 {}, tied {}",
+                duel.player1_wins, duel.player2_wins, duel.ties
+            );
+            let tally: Vec<f64> = duel.running_tally.iter().map(|diff| *diff as f64).collect();
+            let bound = tally.iter().fold(1.0, |bound: f64, diff| bound.max(diff.abs()));
+            println!("Running tally (Player 1 ahead up): {}", utils::sparkline_in_range(&tally, -bound, bound));
+        }
+
+        if !self.keystones_together.is_empty() {
+            println!("\n--- Keystones Together ---");
+            for pair in &self.keystones_together {
+                println!(
+                    "{} + {}: {} games, {:.1}% won",
+                    pair.player1_keystone.label(),
+                    pair.player2_keystone.label(),
+                    pair.games,
+                    pair.win_rate
+                );
+            }
+        }
+
+        if let Some(pings) = &self.pings_per_game {
+            println!("\n--- Pings ---");
+            println!(
+                "Pings per game over {} games: Player 1 {:.1}, Player 2 {:.1}",
+                pings.games, pings.player1, pings.player2
+            );
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use riven::consts::PlatformRoute;
+use riven::models::lol_status_v4::Status;
+use std::error::Error;
+
+use crate::api_client::RiotApiPool;
+use crate::offline;
+
+/// Locale status titles are shown in when Riot provides it.
+const STATUS_LOCALE: &str = "en_US";
+
+/// Ongoing maintenances and incidents of a platform, from lol-status-v4.
+pub struct PlatformStatus {
+    pub platform: PlatformRoute,
+    pub maintenances: Vec<String>,
+    pub incidents: Vec<String>,
+}
+
+impl PlatformStatus {
+    pub fn is_degraded(&self) -> bool {
+        !self.maintenances.is_empty() || !self.incidents.is_empty()
+    }
+
+    pub fn print_warning(&self) {
+        for maintenance in &self.maintenances {
+            eprintln!("Warning: {} is in maintenance: {}", self.platform, maintenance);
+        }
+        for incident in &self.incidents {
+            eprintln!("Warning: {} reports an incident: {}", self.platform, incident);
+        }
+    }
+}
+
+fn status_title(status: &Status) -> String {
+    status
+        .titles
+        .iter()
+        .find(|title| title.locale == STATUS_LOCALE)
+        .or(status.titles.first())
+        .map(|title| title.content.clone())
+        .unwrap_or_else(|| format!("status #{}", status.id))
+}
+
+/// Looks up the service status of `platform`. Completed maintenances are left out.
+pub async fn check_platform_status(riot_api: &RiotApiPool, platform: PlatformRoute) -> Result<PlatformStatus, Box<dyn Error>> {
+    offline::ensure_online(&format!("the service status of {}", platform))?;
+    let platform_data = riot_api.client().await.lol_status_v4().get_platform_data(platform).await?;
+    Ok(PlatformStatus {
+        platform,
+        maintenances: platform_data
+            .maintenances
+            .iter()
+            .filter(|status| status.maintenance_status.as_deref() != Some("complete"))
+            .map(status_title)
+            .collect(),
+        incidents: platform_data.incidents.iter().map(status_title).collect(),
+    })
+}
@@ -0,0 +1,30 @@
+use serde::Serialize;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use tera::{Context, Tera};
+
+/// Template file extensions whose output is HTML-escaped, matching Tera's own defaults.
+const AUTOESCAPE_EXTENSIONS: [&str; 3] = ["html", "htm", "xml"];
+
+/// Renders a user-provided Tera template file with `value` as its context,
+/// so every field of the serialized value (e.g. `querySummary`, `foundMatches`) is available by name.
+pub fn render_template_file(template_path: &Path, value: &impl Serialize) -> Result<String, Box<dyn Error>> {
+    let template = fs::read_to_string(template_path)
+        .map_err(|e| format!("Could not read template '{}': {}", template_path.display(), e))?;
+    let context = Context::from_serialize(value)?;
+    let autoescape = template_path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| AUTOESCAPE_EXTENSIONS.contains(&extension.to_lowercase().as_str()));
+    Tera::one_off(&template, &context, autoescape).map_err(|e| {
+        // Tera's top-level message only names the template; the cause is in the source chain.
+        let mut message = format!("Could not render template '{}'", template_path.display());
+        let mut source: Option<&dyn Error> = Some(&e);
+        while let Some(cause) = source {
+            message.push_str(&format!(": {}", cause));
+            source = cause.source();
+        }
+        message.into()
+    })
+}
@@ -0,0 +1,182 @@
+use chrono::{TimeZone, Utc};
+use riven::consts::RegionalRoute;
+use serde::Serialize;
+use std::error::Error;
+use std::time::Instant;
+
+use crate::anonymize;
+use crate::api_client::{detect_regional_route, resolve_puuid, ApiCallStats, PlayerIdentity, RiotApiPool};
+use crate::cache::AccountCache;
+use crate::contacts::nickname_of;
+use crate::offline;
+use crate::riot_id::RiotId;
+
+/// `tft_game_type` of Double Up matches.
+const DOUBLE_UP_GAME_TYPE: &str = "pairs";
+pub const DEFAULT_TFT_MATCH_COUNT: i32 = 20;
+
+pub struct TftOptions {
+    pub player1_riot_id: RiotId,
+    pub player2_riot_id: RiotId,
+    /// `None` auto-detects the route from player1.
+    pub regional_route: Option<RegionalRoute>,
+    pub number_of_matches: i32,
+    pub verbose: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TftOutput {
+    pub player1: PlayerIdentity,
+    pub player2: PlayerIdentity,
+    pub checked_matches_count: u32,
+    pub double_up_partner_count: u32,
+    pub found_matches: Vec<TftMatchDetails>,
+    pub api_calls: ApiCallStats,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TftMatchDetails {
+    pub match_id: String,
+    pub game_date_utc: String,
+    pub queue_id: u16,
+    pub game_type: Option<String>,
+    pub double_up: bool,
+    /// In Double Up, whether the players were queue partners rather than opponents in the same lobby.
+    pub partners: bool,
+    pub player1_placement: i32,
+    pub player2_placement: i32,
+    /// Placement of the pair (1-4) when they were Double Up partners.
+    pub pair_placement: Option<i32>,
+}
+
+fn ordinal(placement: i32) -> String {
+    let suffix = match (placement % 10, placement % 100) {
+        (1, n) if n != 11 => "st",
+        (2, n) if n != 12 => "nd",
+        (3, n) if n != 13 => "rd",
+        _ => "th",
+    };
+    format!("{}{}", placement, suffix)
+}
+
+/// Checks player1's recent TFT matches for player2, telling Double Up partners apart from lobby opponents.
+pub async fn run_tft_query(
+    riot_api: &RiotApiPool,
+    account_cache: &mut AccountCache,
+    options: TftOptions,
+) -> Result<TftOutput, Box<dyn Error>> {
+    let TftOptions { player1_riot_id, player2_riot_id, regional_route, number_of_matches, verbose } = options;
+    let started_at = Instant::now();
+    let mut api_calls = ApiCallStats::default();
+
+    let regional_route = match regional_route {
+        Some(route) => route,
+        None => detect_regional_route(riot_api, account_cache, &player1_riot_id, verbose, &mut api_calls).await?,
+    };
+    let puuid1 = resolve_puuid(riot_api, account_cache, &player1_riot_id, regional_route, &mut api_calls)
+        .await?
+        .ok_or_else(|| format!("Riot ID '{}' not found on regional route '{:?}'.", anonymize::riot_id(&player1_riot_id), regional_route))?;
+    let puuid2 = resolve_puuid(riot_api, account_cache, &player2_riot_id, regional_route, &mut api_calls)
+        .await?
+        .ok_or_else(|| format!("Riot ID '{}' not found on regional route '{:?}'.", anonymize::riot_id(&player2_riot_id), regional_route))?;
+
+    // TFT matches are never indexed.
+    offline::ensure_online(&format!("the TFT match list of {}", anonymize::riot_id(&player1_riot_id)))?;
+    api_calls.match_list_calls += 1;
+    let match_ids = riot_api
+        .client().await
+        .tft_match_v1()
+        .get_match_ids_by_puuid(regional_route, &puuid1, Some(number_of_matches), None, None, None)
+        .await?;
+
+    let mut found_matches = Vec::new();
+    for (position, match_id) in match_ids.iter().enumerate() {
+        if verbose {
+            println!("Checking TFT match {} ({} of {})...", match_id, position + 1, match_ids.len());
+        }
+        api_calls.match_detail_calls += 1;
+        let Some(tft_match) = riot_api.client().await.tft_match_v1().get_match(regional_route, match_id).await? else {
+            continue;
+        };
+        let info = tft_match.info;
+        let player1 = info.participants.iter().find(|p| p.puuid == puuid1);
+        let player2 = info.participants.iter().find(|p| p.puuid == puuid2);
+        let (Some(player1), Some(player2)) = (player1, player2) else {
+            continue;
+        };
+
+        let double_up = info.tft_game_type.as_deref() == Some(DOUBLE_UP_GAME_TYPE);
+        let partners = double_up && player1.partner_group_id.is_some() && player1.partner_group_id == player2.partner_group_id;
+        found_matches.push(TftMatchDetails {
+            match_id: match_id.clone(),
+            game_date_utc: Utc
+                .timestamp_millis_opt(info.game_datetime)
+                .single()
+                .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+                .unwrap_or_else(|| "Unknown Date".to_string()),
+            queue_id: info.queue_id.0,
+            game_type: info.tft_game_type.clone(),
+            double_up,
+            partners,
+            player1_placement: player1.placement,
+            player2_placement: player2.placement,
+            // Partners share their team's placement: individual places 1-2 are team 1st, 3-4 team 2nd, ...
+            pair_placement: partners.then(|| (player1.placement.min(player2.placement) + 1) / 2),
+        });
+    }
+
+    api_calls.finish(started_at);
+    Ok(TftOutput {
+        player1: PlayerIdentity {
+            nickname: nickname_of(&player1_riot_id),
+            game_name: player1_riot_id.game_name,
+            tag_line: player1_riot_id.tag_line,
+            profile: None,
+            formerly_known_as: Vec::new(),
+        },
+        player2: PlayerIdentity {
+            nickname: nickname_of(&player2_riot_id),
+            game_name: player2_riot_id.game_name,
+            tag_line: player2_riot_id.tag_line,
+            profile: None,
+            formerly_known_as: Vec::new(),
+        },
+        checked_matches_count: match_ids.len() as u32,
+        double_up_partner_count: found_matches.iter().filter(|m| m.partners).count() as u32,
+        found_matches,
+        api_calls,
+    })
+}
+
+impl TftOutput {
+    pub fn print(&self) {
+        println!("\n--- TFT Summary ---");
+        println!(
+            "Checked {} TFT matches for {}.",
+            self.checked_matches_count, self.player1.display_name()
+        );
+        println!(
+            "Found {} matches with {}, {} of them as Double Up partners.",
+            self.found_matches.len(), self.player2.display_name(), self.double_up_partner_count
+        );
+        for tft_match in &self.found_matches {
+            let relation = match (tft_match.double_up, tft_match.pair_placement) {
+                (true, Some(pair_placement)) => format!("Double Up partners, team placed {}", ordinal(pair_placement)),
+                (true, None) => "Double Up opponents".to_string(),
+                (false, _) => "same lobby".to_string(),
+            };
+            println!(
+                "{} {}: {} ({} {}, {} {})",
+                tft_match.game_date_utc,
+                tft_match.match_id,
+                relation,
+                self.player1.game_name,
+                ordinal(tft_match.player1_placement),
+                self.player2.game_name,
+                ordinal(tft_match.player2_placement)
+            );
+        }
+    }
+}
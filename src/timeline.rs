@@ -0,0 +1,248 @@
+use riven::consts::Team;
+use serde::{Deserialize, Serialize};
+use riven::models::match_v5::{FramesTimeLine, Participant, Timeline};
+
+const MILLIS_PER_MINUTE: i32 = 60_000;
+const SPARKLINE_WIDTH: f64 = 120.0;
+const SPARKLINE_HEIGHT: f64 = 32.0;
+/// Kills after this minute are no longer counted as lane kills.
+const LANING_PHASE_END_MINUTE: i64 = 14;
+
+/// Head-to-head of two direct lane opponents.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaneMatchup {
+    /// Player1's creep score minus player2's at 10 minutes.
+    pub cs_diff_at10: Option<i32>,
+    /// Kills on the other player without any assists during the laning phase.
+    pub player1_solo_kills: u32,
+    pub player2_solo_kills: u32,
+}
+
+/// How often two teammates' kills involved each other, from the timeline's kill events.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairKillParticipation {
+    pub player1_kills: u32,
+    /// Kills of player1 that player2 assisted on.
+    pub player1_kills_assisted_by_player2: u32,
+    pub player2_kills: u32,
+    /// Kills of player2 that player1 assisted on.
+    pub player2_kills_assisted_by_player1: u32,
+    /// Percentage of the pair's kills the other player assisted on; `None` when neither got a kill.
+    pub together_rate: Option<f64>,
+}
+
+/// Gold lead of player1's team over the course of a game, with the pair's kills and deaths.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoldGraph {
+    /// Player1's team gold minus the other team's, one value per timeline frame (about a minute apart).
+    pub gold_diff_by_minute: Vec<i32>,
+    pub duo_events: Vec<DuoEvent>,
+    /// The graph as a self-contained inline `<svg>` sparkline; render it with Tera's `safe` filter.
+    pub svg: String,
+}
+
+/// A kill or death of one of the two players.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuoEvent {
+    pub minute: f64,
+    /// "player1" or "player2".
+    pub player: String,
+    /// "kill" or "death".
+    pub kind: String,
+}
+
+fn frame_gold_diff(frame: &FramesTimeLine, participants: &[Participant], team: Team) -> Option<i32> {
+    let participant_frames = frame.participant_frames.as_ref()?;
+    let mut gold_diff = 0;
+    for participant in participants {
+        let total_gold = participant_frames.get(&participant.participant_id)?.total_gold;
+        if participant.team_id == team {
+            gold_diff += total_gold;
+        } else {
+            gold_diff -= total_gold;
+        }
+    }
+    Some(gold_diff)
+}
+
+/// Total gold of `team` minus the total gold of the other team at `minute`,
+/// or `None` if the game ended before that minute.
+pub fn team_gold_diff_at(timeline: &Timeline, participants: &[Participant], team: Team, minute: i32) -> Option<i32> {
+    let frame = timeline
+        .info
+        .frames
+        .iter()
+        .find(|frame| frame.timestamp >= minute * MILLIS_PER_MINUTE)?;
+    frame_gold_diff(frame, participants, team)
+}
+
+/// Gold of `player` minus the gold of the enemy in the same position at `minute`, or `None` without a
+/// position, a lane opponent or a frame at that minute.
+pub fn lane_gold_diff_at(timeline: &Timeline, participants: &[Participant], player: &Participant, minute: i32) -> Option<i32> {
+    if player.team_position.is_empty() {
+        return None;
+    }
+    let opponent = participants
+        .iter()
+        .find(|p| p.team_id != player.team_id && p.team_position == player.team_position)?;
+    let frame = timeline
+        .info
+        .frames
+        .iter()
+        .find(|frame| frame.timestamp >= minute * MILLIS_PER_MINUTE)?;
+    let participant_frames = frame.participant_frames.as_ref()?;
+    Some(participant_frames.get(&player.participant_id)?.total_gold - participant_frames.get(&opponent.participant_id)?.total_gold)
+}
+
+/// Builds the gold graph of `team` (player1's team), marking the kills and deaths of the two players
+/// identified by their participant IDs. `None` if the timeline has fewer than two usable frames.
+pub fn gold_graph(timeline: &Timeline, participants: &[Participant], team: Team, player1_id: i32, player2_id: i32) -> Option<GoldGraph> {
+    let gold_diff_by_minute = timeline
+        .info
+        .frames
+        .iter()
+        .map(|frame| frame_gold_diff(frame, participants, team))
+        .collect::<Option<Vec<_>>>()?;
+    if gold_diff_by_minute.len() < 2 {
+        return None;
+    }
+
+    let mut duo_events = Vec::new();
+    let kills = timeline
+        .info
+        .frames
+        .iter()
+        .flat_map(|frame| &frame.events)
+        .filter(|event| event.r#type == "CHAMPION_KILL");
+    for event in kills {
+        let minute = event.timestamp as f64 / MILLIS_PER_MINUTE as f64;
+        for (participant_id, player) in [(player1_id, "player1"), (player2_id, "player2")] {
+            if event.killer_id == Some(participant_id) {
+                duo_events.push(DuoEvent { minute, player: player.to_string(), kind: "kill".to_string() });
+            } else if event.victim_id == Some(participant_id) {
+                duo_events.push(DuoEvent { minute, player: player.to_string(), kind: "death".to_string() });
+            }
+        }
+    }
+
+    let svg = sparkline_svg(&gold_diff_by_minute, &duo_events);
+    Some(GoldGraph { gold_diff_by_minute, duo_events, svg })
+}
+
+/// Draws the gold difference as a line around a zero baseline, with a dot per kill (green) and death (red).
+fn sparkline_svg(gold_diff_by_minute: &[i32], duo_events: &[DuoEvent]) -> String {
+    let last_minute = (gold_diff_by_minute.len() - 1) as f64;
+    let max_abs = gold_diff_by_minute.iter().map(|diff| diff.unsigned_abs()).max().unwrap_or(0).max(1) as f64;
+    let middle = SPARKLINE_HEIGHT / 2.0;
+    let x_of = |minute: f64| minute.clamp(0.0, last_minute) / last_minute * SPARKLINE_WIDTH;
+    let y_of = |gold_diff: i32| middle - gold_diff as f64 / max_abs * (middle - 2.0);
+
+    let points: Vec<String> = gold_diff_by_minute
+        .iter()
+        .enumerate()
+        .map(|(minute, diff)| format!("{:.1},{:.1}", x_of(minute as f64), y_of(*diff)))
+        .collect();
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\
+         <line x1=\"0\" y1=\"{m}\" x2=\"{w}\" y2=\"{m}\" stroke=\"#999\" stroke-width=\"0.5\"/>\
+         <polyline points=\"{points}\" fill=\"none\" stroke=\"#c89b3c\" stroke-width=\"1.5\"/>",
+        w = SPARKLINE_WIDTH,
+        h = SPARKLINE_HEIGHT,
+        m = middle,
+        points = points.join(" ")
+    );
+    for event in duo_events {
+        let frame = (event.minute.round() as usize).min(gold_diff_by_minute.len() - 1);
+        let color = if event.kind == "kill" { "#2e9e44" } else { "#d13639" };
+        svg.push_str(&format!(
+            "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"2\" fill=\"{}\"><title>{} {} at {:.0}'</title></circle>",
+            x_of(event.minute),
+            y_of(gold_diff_by_minute[frame]),
+            color,
+            event.player,
+            event.kind,
+            event.minute
+        ));
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Compares two lane opponents, identified by their participant IDs, from the timeline.
+pub fn lane_matchup(timeline: &Timeline, player1_id: i32, player2_id: i32) -> LaneMatchup {
+    let creep_score_at = |participant_id: i32, minute: i32| {
+        let frame = timeline
+            .info
+            .frames
+            .iter()
+            .find(|frame| frame.timestamp >= minute * MILLIS_PER_MINUTE)?;
+        let participant_frame = frame.participant_frames.as_ref()?.get(&participant_id)?;
+        Some(participant_frame.minions_killed + participant_frame.jungle_minions_killed)
+    };
+    let cs_diff_at10 = creep_score_at(player1_id, 10)
+        .zip(creep_score_at(player2_id, 10))
+        .map(|(player1_cs, player2_cs)| player1_cs - player2_cs);
+
+    let mut player1_solo_kills = 0;
+    let mut player2_solo_kills = 0;
+    let solo_kills = timeline
+        .info
+        .frames
+        .iter()
+        .flat_map(|frame| &frame.events)
+        .filter(|event| event.r#type == "CHAMPION_KILL")
+        .filter(|event| event.timestamp <= LANING_PHASE_END_MINUTE * MILLIS_PER_MINUTE as i64)
+        .filter(|event| event.assisting_participant_ids.as_ref().is_none_or(|ids| ids.is_empty()));
+    for event in solo_kills {
+        match (event.killer_id, event.victim_id) {
+            (Some(killer), Some(victim)) if killer == player1_id && victim == player2_id => player1_solo_kills += 1,
+            (Some(killer), Some(victim)) if killer == player2_id && victim == player1_id => player2_solo_kills += 1,
+            _ => {}
+        }
+    }
+
+    LaneMatchup { cs_diff_at10, player1_solo_kills, player2_solo_kills }
+}
+
+/// Counts the kills of two teammates, identified by their participant IDs, that the other assisted on.
+pub fn pair_kill_participation(timeline: &Timeline, player1_id: i32, player2_id: i32) -> PairKillParticipation {
+    let mut participation = PairKillParticipation {
+        player1_kills: 0,
+        player1_kills_assisted_by_player2: 0,
+        player2_kills: 0,
+        player2_kills_assisted_by_player1: 0,
+        together_rate: None,
+    };
+    let kills = timeline
+        .info
+        .frames
+        .iter()
+        .flat_map(|frame| &frame.events)
+        .filter(|event| event.r#type == "CHAMPION_KILL");
+    for event in kills {
+        let assisted_by = |participant_id: i32| {
+            event.assisting_participant_ids.as_ref().is_some_and(|ids| ids.contains(&participant_id))
+        };
+        if event.killer_id == Some(player1_id) {
+            participation.player1_kills += 1;
+            if assisted_by(player2_id) {
+                participation.player1_kills_assisted_by_player2 += 1;
+            }
+        } else if event.killer_id == Some(player2_id) {
+            participation.player2_kills += 1;
+            if assisted_by(player1_id) {
+                participation.player2_kills_assisted_by_player1 += 1;
+            }
+        }
+    }
+    let kills = participation.player1_kills + participation.player2_kills;
+    if kills > 0 {
+        let assisted = participation.player1_kills_assisted_by_player2 + participation.player2_kills_assisted_by_player1;
+        participation.together_rate = Some(assisted as f64 / kills as f64 * 100.0);
+    }
+    participation
+}
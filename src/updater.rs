@@ -0,0 +1,57 @@
+use std::env;
+use std::error::Error;
+
+const REPO_OWNER: &str = "domahet";
+const REPO_NAME: &str = "playedtogether";
+/// Published with every release by the release workflow.
+const CHECKSUMS_ASSET: &str = "SHA256SUMS";
+
+/// Platform part of the release asset names, e.g. `ptg-x86_64-linux-gnu-v0.2.5.tar.gz`.
+fn release_target() -> Option<&'static str> {
+    match (env::consts::ARCH, env::consts::OS) {
+        ("x86_64", "linux") => Some("x86_64-linux-gnu"),
+        ("x86_64", "windows") => Some("x86_64-windows"),
+        ("x86_64", "macos") => Some("x86_64-macos"),
+        ("aarch64", "macos") => Some("aarch64-macos"),
+        _ => None,
+    }
+}
+
+/// Replaces the running binary with the latest GitHub release for this platform,
+/// after verifying the download against the release's SHA256SUMS.
+pub async fn run_self_update(no_confirm: bool) -> Result<(), Box<dyn Error>> {
+    let target = release_target().ok_or_else(|| format!(
+        "No prebuilt binaries are published for {}-{}. Update with `cargo install --locked ptg` instead.",
+        env::consts::ARCH, env::consts::OS
+    ))?;
+    // The Windows archive holds `ptg.exe`, the others a binary named after the asset.
+    let bin_path_in_archive = if cfg!(windows) {
+        "ptg.exe".to_string()
+    } else {
+        format!("ptg-{}", target)
+    };
+
+    // self_update performs blocking HTTP requests, which must not run on the async runtime.
+    let status = tokio::task::spawn_blocking(move || {
+        self_update::backends::github::Update::configure()
+            .repo_owner(REPO_OWNER)
+            .repo_name(REPO_NAME)
+            .bin_name("ptg")
+            .target(target)
+            .bin_path_in_archive(bin_path_in_archive)
+            .checksum_from_asset(CHECKSUMS_ASSET)
+            .current_version(env!("CARGO_PKG_VERSION"))
+            .show_download_progress(true)
+            .no_confirm(no_confirm)
+            .build()?
+            .update()
+    })
+    .await??;
+
+    if status.is_updated() {
+        println!("Updated ptg to version {}.", status.version());
+    } else {
+        println!("ptg {} is already the latest version.", status.version());
+    }
+    Ok(())
+}
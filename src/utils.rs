@@ -2,4 +2,27 @@ pub fn print_in_box(lines: &[&str]) {
     for line in lines {
         println!("\t| {}", line);
     }
-}
\ No newline at end of file
+}
+
+const SPARK_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+/// Stand-ins for `SPARK_LEVELS` on consoles limited to ASCII.
+const ASCII_SPARK_LEVELS: [char; 8] = ['_', '.', ',', '-', '~', '=', '*', '#'];
+
+/// Renders `values` as a one-line sparkline, scaled so `min` is the lowest and `max` the highest
+/// bar. Values outside the range are clamped. Uses plain ASCII on consoles limited to it.
+pub fn sparkline_in_range(values: &[f64], min: f64, max: f64) -> String {
+    let levels = if crate::console::is_ascii() { &ASCII_SPARK_LEVELS } else { &SPARK_LEVELS };
+    let span = (max - min).max(f64::EPSILON);
+    values
+        .iter()
+        .map(|value| {
+            let level = ((value - min) / span * (levels.len() - 1) as f64).round();
+            levels[level.clamp(0.0, (levels.len() - 1) as f64) as usize]
+        })
+        .collect()
+}
+
+/// Renders `values` as a sparkline scaled from zero to their maximum.
+pub fn sparkline(values: &[f64]) -> String {
+    sparkline_in_range(values, 0.0, values.iter().copied().fold(0.0, f64::max))
+}
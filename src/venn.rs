@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+use crate::api_client::{PlayerIdentity, QuerySummary};
+
+/// How the matches of three players overlap. Player1's checked matches decide the splits involving
+/// player1; matches of only player2 and player3 are not in player1's history and come from the local
+/// match index, within the same time span. The query's patch, queue and date filters apply as in the main
+/// summary, and with `--opponents` a player only counts when they played against the other.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VennSummary {
+    pub player3: PlayerIdentity,
+    pub player1_and_player2_only: u32,
+    pub player1_and_player3_only: u32,
+    /// Indexed matches only, so it may miss games that were never fetched.
+    pub player2_and_player3_only: u32,
+    pub all_three: u32,
+}
+
+impl VennSummary {
+    pub fn new(player3: PlayerIdentity) -> Self {
+        VennSummary {
+            player3,
+            player1_and_player2_only: 0,
+            player1_and_player3_only: 0,
+            player2_and_player3_only: 0,
+            all_three: 0,
+        }
+    }
+
+    /// Counts one of player1's matches by which of the other two players were in it.
+    pub fn record(&mut self, with_player2: bool, with_player3: bool) {
+        match (with_player2, with_player3) {
+            (true, true) => self.all_three += 1,
+            (true, false) => self.player1_and_player2_only += 1,
+            (false, true) => self.player1_and_player3_only += 1,
+            (false, false) => {}
+        }
+    }
+
+    pub fn print(&self, summary: &QuerySummary) {
+        let name = |player: &PlayerIdentity| player.display_name();
+        let (player1, player2, player3) = (name(&summary.player1), name(&summary.player2), name(&self.player3));
+        println!("\n--- Shared Matches of Three ---");
+        println!("{} + {} only: {}", player1, player2, self.player1_and_player2_only);
+        println!("{} + {} only: {}", player1, player3, self.player1_and_player3_only);
+        println!("{} + {} only: {} (indexed matches)", player2, player3, self.player2_and_player3_only);
+        println!("All three: {}", self.all_three);
+    }
+}
@@ -0,0 +1,2050 @@
+//! End-to-end runs of the `ptg` binary against a wiremock server standing in for the Riot API.
+//! Every test gets its own config and cache directories, pointed at the mock server through
+//! the `[network] base_url` setting.
+
+mod common;
+
+use serde_json::Value;
+use std::collections::HashSet;
+use std::process::Output;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, ResponseTemplate};
+
+use common::{TestEnv, FIXTURE_MATCH, PLAYER1_PUUID, PLAYER2_PUUID};
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+fn json_stdout(output: &Output) -> Value {
+    assert!(output.status.success(), "ptg failed: {}", stderr(output));
+    serde_json::from_slice(&output.stdout).expect("stdout should be the JSON output")
+}
+
+#[tokio::test]
+async fn finds_shared_matches() {
+    let env = TestEnv::start("shared").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    let summary = &output["querySummary"];
+    assert_eq!(summary["checkedMatchesCount"], 2);
+    assert_eq!(summary["matchesPlayedTogetherCount"], 1);
+    assert_eq!(summary["player1WinsTogetherCount"], 1);
+    let found_matches = output["foundMatches"].as_array().unwrap();
+    assert_eq!(found_matches.len(), 1);
+    assert_eq!(found_matches[0]["matchId"], "EUW1_2");
+    assert_eq!(found_matches[0]["player1Details"]["champion"], "Garen");
+    assert_eq!(found_matches[0]["player2Details"]["champion"], "Jinx");
+}
+
+#[tokio::test]
+async fn serves_repeated_queries_from_the_cache() {
+    let env = TestEnv::start("cached").await;
+    env.mock_fixture_history(&["EUW1_1"]).await;
+    let args = ["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"];
+
+    let output = json_stdout(&env.run(&args).await);
+    let api_calls = &output["querySummary"]["apiCalls"];
+    assert_eq!(api_calls["accountCalls"], 2);
+    assert_eq!(api_calls["matchListCalls"], 1);
+    assert_eq!(api_calls["matchDetailCalls"], 1);
+    assert_eq!(api_calls["cacheHits"], 0);
+    assert_eq!(api_calls["totalCalls"], 4);
+    assert!(api_calls["wallTimeMs"].is_u64());
+
+    let output = json_stdout(&env.run(&args).await);
+    let api_calls = &output["querySummary"]["apiCalls"];
+    assert_eq!(api_calls["accountCalls"], 0);
+    assert_eq!(api_calls["matchDetailCalls"], 0);
+    assert_eq!(api_calls["cacheHits"], 3); // Both accounts and the match.
+    assert_eq!(output["querySummary"]["matchesPlayedTogetherCount"], 1);
+}
+
+#[tokio::test]
+async fn rotates_requests_between_api_keys() {
+    let env = TestEnv::start("key-rotation").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    let output = env.run(&["--api-key", "RGAPI-first", "RGAPI-second"]).await;
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Stored 2 API keys locally."));
+
+    json_stdout(&env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    let keys: Vec<String> = env
+        .server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| request.headers.get("X-Riot-Token").unwrap().to_str().unwrap().to_string())
+        .collect();
+    // Two accounts, the match list and two matches, sent in turn with each key.
+    assert_eq!(keys, ["RGAPI-first", "RGAPI-second", "RGAPI-first", "RGAPI-second", "RGAPI-first"]);
+}
+
+#[tokio::test]
+async fn applies_the_network_settings() {
+    let env = TestEnv::start("network").await;
+    env.mock_fixture_history(&["EUW1_1"]).await;
+    let args = ["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"];
+    let user_agents = || async {
+        let requests = env.server.received_requests().await.unwrap();
+        requests.iter().map(|request| request.headers.get("User-Agent").unwrap().to_str().unwrap().to_string()).collect::<HashSet<_>>()
+    };
+
+    json_stdout(&env.run(&args).await);
+    assert_eq!(user_agents().await, HashSet::from([format!("ptg/{}", env!("CARGO_PKG_VERSION"))]));
+
+    // The config ends in the [network] section, so appended keys land in it.
+    let config_path = env.home.join(".config/ptg/config.toml");
+    let config = std::fs::read_to_string(&config_path).unwrap();
+    std::fs::write(&config_path, config + "user_agent = \"duo-bot/1.0\"\nhttp2 = false\npool_max_idle_per_host = 1\n").unwrap();
+    env.server.reset().await;
+    env.mock_fixture_history(&["EUW1_2"]).await;
+    json_stdout(&env.run(&args).await);
+    assert_eq!(user_agents().await, HashSet::from(["duo-bot/1.0".to_string()]));
+}
+
+#[tokio::test]
+async fn joins_riot_ids_split_at_spaces() {
+    let env = TestEnv::start("split-riot-ids").await;
+    Mock::given(method("GET"))
+        .and(path("/EUROPE/riot/account/v1/accounts/by-riot-id/Fixture%20Zero/EUW"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "puuid": PLAYER1_PUUID,
+            "gameName": "Fixture Zero",
+            "tagLine": "EUW",
+        })))
+        .mount(&env.server)
+        .await;
+    env.mock_account("Fixture3", PLAYER2_PUUID).await;
+    env.mock_match_ids(&["EUW1_2", "EUW1_1"]).await;
+    env.mock_match("EUW1_2", true).await;
+    env.mock_match("EUW1_1", false).await;
+
+    for args in [
+        &["--region", "EUW", "--json", "Fixture", "Zero", "#EUW", "Fixture3#EUW"][..],
+        &["--region", "EUW", "--json", "Fixture", "Zero#EUW", "Fixture3#EUW"],
+        &["--region", "EUW", "--json", "--player1", "Fixture Zero#EUW", "--player2", "Fixture3#EUW"],
+    ] {
+        let output = env.run(args).await;
+        let summary = &json_stdout(&output)["querySummary"];
+        assert_eq!(summary["player1"]["gameName"], "Fixture Zero", "{:?}", args);
+        assert_eq!(summary["matchesPlayedTogetherCount"], 1, "{:?}", args);
+    }
+
+    let output = env.run(&["--region", "EUW", "--player1", "Fixture Zero#EUW"]).await;
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("--player2"), "{}", stderr(&output));
+}
+
+#[tokio::test]
+async fn reports_unknown_riot_id() {
+    let env = TestEnv::start("unknown").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    Mock::given(method("GET"))
+        .and(path("/EUROPE/riot/account/v1/accounts/by-riot-id/Nobody/EUW"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&env.server)
+        .await;
+
+    let output = env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Nobody#EUW"]).await;
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Player 2 Riot ID"), "unexpected error: {}", stderr(&output));
+}
+
+#[tokio::test]
+async fn retries_after_rate_limit() {
+    let env = TestEnv::start("rate-limit").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    env.mock_account("Fixture3", PLAYER2_PUUID).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/EUROPE/lol/match/v5/matches/by-puuid/{}/ids", PLAYER1_PUUID)))
+        .respond_with(
+            ResponseTemplate::new(429)
+                .insert_header("Retry-After", "1")
+                .insert_header("X-Rate-Limit-Type", "application"),
+        )
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&env.server)
+        .await;
+    env.mock_match_ids(&["EUW1_1"]).await;
+    env.mock_match("EUW1_1", true).await;
+
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    assert_eq!(output["querySummary"]["matchesPlayedTogetherCount"], 1);
+}
+
+#[tokio::test]
+async fn fails_cleanly_on_malformed_match() {
+    let env = TestEnv::start("malformed").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    env.mock_account("Fixture3", PLAYER2_PUUID).await;
+    env.mock_match_ids(&["EUW1_1"]).await;
+    Mock::given(method("GET"))
+        .and(path("/EUROPE/lol/match/v5/matches/EUW1_1"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{\"metadata\": {\"matchId\": "))
+        .mount(&env.server)
+        .await;
+
+    let output = env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    assert!(!output.status.success());
+    assert!(!stderr(&output).contains("panicked"), "ptg panicked: {}", stderr(&output));
+}
+
+#[tokio::test]
+async fn skips_and_counts_unavailable_matches() {
+    let env = TestEnv::start("unavailable").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    env.mock_account("Fixture3", PLAYER2_PUUID).await;
+    // EUW1_2 is not mocked, so it is answered with a 404.
+    env.mock_match_ids(&["EUW1_3", "EUW1_2", "EUW1_1"]).await;
+    env.mock_match("EUW1_1", true).await;
+    Mock::given(method("GET"))
+        .and(path("/EUROPE/lol/match/v5/matches/EUW1_3"))
+        .respond_with(ResponseTemplate::new(403))
+        .mount(&env.server)
+        .await;
+
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    let unavailable = &output["querySummary"]["unavailableMatches"];
+    assert_eq!(unavailable["forbidden"], 1);
+    assert_eq!(unavailable["missing"], 1);
+    assert_eq!(unavailable["expired"], 0);
+    assert_eq!(output["querySummary"]["matchesPlayedTogetherCount"], 1);
+
+    let warnings = output["warnings"].as_array().unwrap();
+    assert_eq!(warnings.len(), 2);
+    assert_eq!(warnings[0]["code"], "matchForbidden");
+    assert_eq!(warnings[0]["matchId"], "EUW1_3");
+    assert_eq!(warnings[1]["code"], "matchMissing");
+    assert_eq!(warnings[1]["matchId"], "EUW1_2");
+}
+
+#[tokio::test]
+async fn blames_riot_for_failures_during_maintenance() {
+    let env = TestEnv::start("maintenance").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    env.mock_account("Fixture3", PLAYER2_PUUID).await;
+    Mock::given(method("GET"))
+        .and(path("/EUW1/lol/status/v4/platform-data"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "id": "EUW1",
+            "name": "EU West",
+            "locales": ["en_US"],
+            "maintenances": [{
+                "id": 1,
+                "maintenance_status": "in_progress",
+                "titles": [{ "locale": "en_US", "content": "Match history unavailable" }],
+                "updates": [],
+                "created_at": "2024-10-14T10:00:00Z",
+                "platforms": ["windows"],
+            }],
+            "incidents": [],
+        })))
+        .mount(&env.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/EUROPE/lol/match/v5/matches/by-puuid/{}/ids", PLAYER1_PUUID)))
+        .respond_with(ResponseTemplate::new(400))
+        .mount(&env.server)
+        .await;
+
+    let output = env.run(&["--region", "EUW", "--status-check", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    assert!(!output.status.success());
+    let stderr = stderr(&output);
+    assert!(stderr.contains("EUW1 is in maintenance: Match history unavailable"), "missing warning: {}", stderr);
+    assert!(stderr.contains("Riot is reporting problems"), "missing hint: {}", stderr);
+}
+
+#[tokio::test]
+async fn explains_accounts_without_league_matches() {
+    let env = TestEnv::start("valorant-only").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    env.mock_account("Fixture3", PLAYER2_PUUID).await;
+    env.mock_match_ids(&[]).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/EUROPE/riot/account/v1/active-shards/by-game/val/by-puuid/{}", PLAYER1_PUUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "puuid": PLAYER1_PUUID,
+            "game": "val",
+            "activeShard": "eu",
+        })))
+        .mount(&env.server)
+        .await;
+
+    let output = env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(json_stdout(&output)["warnings"][0]["code"], "noLeagueMatches");
+    assert!(stderr(&output).contains("plays Valorant"), "{}", stderr(&output));
+}
+
+#[tokio::test]
+async fn widens_an_empty_window_on_request() {
+    let env = TestEnv::start("auto-widen").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    env.mock_account("Fixture3", PLAYER2_PUUID).await;
+    env.mock_match("EUW1_1", true).await;
+    // Player1's last match is more than 45 days old: lists starting later are empty.
+    let cutoff = chrono::Utc::now().timestamp() - 45 * 24 * 60 * 60;
+    Mock::given(method("GET"))
+        .and(path(format!("/EUROPE/lol/match/v5/matches/by-puuid/{}/ids", PLAYER1_PUUID)))
+        .and(move |request: &wiremock::Request| {
+            request.url.query_pairs().find(|(key, _)| key == "startTime").is_none_or(|(_, start)| start.parse::<i64>().unwrap() < cutoff)
+        })
+        .respond_with(ResponseTemplate::new(200).set_body_json(["EUW1_1"]))
+        .mount(&env.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/EUROPE/lol/match/v5/matches/by-puuid/{}/ids", PLAYER1_PUUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(Vec::<String>::new()))
+        .with_priority(10)
+        .mount(&env.server)
+        .await;
+
+    let output = env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    let json = json_stdout(&output);
+    assert_eq!(json["warnings"][0]["code"], "noMatchesInWindow");
+    assert!(stderr(&output).contains("Pass --auto-widen"), "{}", stderr(&output));
+
+    let output = env.run(&["--region", "EUW", "--json", "--auto-widen", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    let json = json_stdout(&output);
+    assert_eq!(json["querySummary"]["widenedToDays"], 60);
+    assert_eq!(json["querySummary"]["matchesPlayedTogetherCount"], 1);
+    assert!(stderr(&output).contains("widened the window to the last 60 days"), "{}", stderr(&output));
+}
+
+#[tokio::test]
+async fn warns_about_transferred_accounts() {
+    let env = TestEnv::start("transfer").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    env.mock_account("Fixture3", PLAYER2_PUUID).await;
+    env.mock_match_ids(&["NA1_1"]).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/EUROPE/riot/account/v1/region/by-game/lol/by-puuid/{}", PLAYER1_PUUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "puuid": PLAYER1_PUUID,
+            "game": "lol",
+            "region": "na1",
+        })))
+        .mount(&env.server)
+        .await;
+
+    let output = env.run(&["--region", "EUW", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    let stderr = stderr(&output);
+    assert!(stderr.contains("plays on NA now, not EUW"), "missing warning: {}", stderr);
+    assert!(stderr.contains("--follow-transfer"), "missing hint: {}", stderr);
+}
+
+#[tokio::test]
+async fn counts_unmodeled_matches_from_their_outline() {
+    let env = TestEnv::start("unmodeled").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    env.mock_account("Fixture3", PLAYER2_PUUID).await;
+    env.mock_match_ids(&["EUW1_2", "EUW1_1"]).await;
+    env.mock_match("EUW1_1", true).await;
+    // A field riven models differently, as happens when Riot ships a new game mode.
+    let mut match_data: Value = serde_json::from_str(FIXTURE_MATCH).unwrap();
+    match_data["metadata"]["matchId"] = "EUW1_2".into();
+    match_data["info"]["gameDuration"] = "unknown".into();
+    Mock::given(method("GET"))
+        .and(path("/EUROPE/lol/match/v5/matches/EUW1_2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(match_data))
+        .mount(&env.server)
+        .await;
+
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    assert_eq!(output["querySummary"]["matchesPlayedTogetherCount"], 2);
+    assert_eq!(output["querySummary"]["player1WinsTogetherCount"], 2);
+    assert_eq!(output["querySummary"]["outlineOnlyMatchesCount"], 1);
+    assert_eq!(output["foundMatches"].as_array().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn breaks_shared_matches_down_by_a_third_player() {
+    let env = TestEnv::start("venn").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    env.mock_account("Fixture3", PLAYER2_PUUID).await;
+    env.mock_account("Fixture5", "fixture-puuid-5").await;
+    env.mock_match_ids(&["EUW1_2", "EUW1_1"]).await;
+    env.mock_match("EUW1_2", true).await;
+    env.mock_match("EUW1_1", false).await;
+
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "--venn", "Fixture5#EUW", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    let venn = &output["querySummary"]["venn"];
+    assert_eq!(venn["player3"]["gameName"], "Fixture5");
+    assert_eq!(venn["allThree"], 1);
+    assert_eq!(venn["player1AndPlayer3Only"], 1);
+    assert_eq!(venn["player1AndPlayer2Only"], 0);
+    assert_eq!(venn["player2AndPlayer3Only"], 0);
+
+    // Player2 played with player1 and so is left out against opponents, unlike player3.
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "--opponents", "--venn", "Fixture5#EUW", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    let venn = &output["querySummary"]["venn"];
+    assert_eq!(venn["allThree"], 0);
+    assert_eq!(venn["player1AndPlayer3Only"], 2);
+
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "--patch", "14.19", "--venn", "Fixture5#EUW", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    let venn = &output["querySummary"]["venn"];
+    assert_eq!(venn["allThree"], 0);
+    assert_eq!(venn["player1AndPlayer3Only"], 0);
+}
+
+#[tokio::test]
+async fn credits_carries_only_between_teammates() {
+    let env = TestEnv::start("carries").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    env.mock_account("Fixture3", PLAYER2_PUUID).await;
+    env.mock_account("Fixture5", "fixture-puuid-5").await;
+    env.mock_match_ids(&["EUW1_1"]).await;
+    env.mock_match("EUW1_1", true).await;
+
+    let teammates = json_stdout(&env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    assert_eq!(teammates["foundMatches"][0]["carriedBy"], "player2");
+    assert_eq!(teammates["querySummary"]["carries"]["player2"], 1);
+
+    let opponents = json_stdout(&env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture5#EUW"]).await);
+    assert_eq!(opponents["foundMatches"][0]["sameTeam"], false);
+    assert!(opponents["foundMatches"][0]["carriedBy"].is_null());
+    assert_eq!(opponents["querySummary"]["carries"]["player1"], 0);
+    assert_eq!(opponents["querySummary"]["carries"]["player2"], 0);
+}
+
+#[tokio::test]
+async fn highlights_copycats_and_mirror_matches() {
+    let env = TestEnv::start("mirror").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    env.mock_account("Fixture5", "fixture-puuid-5").await;
+    env.mock_match_ids(&["EUW1_2", "EUW1_1"]).await;
+    env.mock_match("EUW1_1", true).await;
+    let mut copycat: Value = serde_json::from_str(FIXTURE_MATCH).unwrap();
+    copycat["metadata"]["matchId"] = "EUW1_2".into();
+    copycat["info"]["participants"][5]["championName"] = "Garen".into();
+    Mock::given(method("GET"))
+        .and(path("/EUROPE/lol/match/v5/matches/EUW1_2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(copycat))
+        .mount(&env.server)
+        .await;
+
+    // Both players play top, against each other.
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture5#EUW"]).await);
+    let found = output["foundMatches"].as_array().unwrap();
+    assert!(found.iter().all(|m| m["mirrorRoles"] == true && m["sameTeam"] == false));
+    assert_eq!(found.iter().map(|m| m["sameChampion"].as_bool().unwrap()).collect::<Vec<_>>(), [true, false]);
+    let achievement = |id: &str| output["achievements"].as_array().unwrap().iter().find(|a| a["id"] == id).cloned().unwrap();
+    assert_eq!(achievement("sameChampion")["matchIds"], serde_json::json!(["EUW1_2"]));
+    assert_eq!(achievement("mirrorRoles")["description"], "Played the same role on opposite teams in 2 game(s).");
+}
+
+#[tokio::test]
+async fn passes_match_type_to_the_match_list() {
+    let env = TestEnv::start("match-type").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    env.mock_account("Fixture3", PLAYER2_PUUID).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/EUROPE/lol/match/v5/matches/by-puuid/{}/ids", PLAYER1_PUUID)))
+        .and(query_param("type", "ranked"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(["EUW1_1"]))
+        .mount(&env.server)
+        .await;
+    env.mock_match("EUW1_1", true).await;
+
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "--match-type", "ranked", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    assert_eq!(output["querySummary"]["matchesPlayedTogetherCount"], 1);
+}
+
+#[tokio::test]
+async fn passes_raw_queue_ids_through() {
+    let env = TestEnv::start("queue-id").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    env.mock_account("Fixture3", PLAYER2_PUUID).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/EUROPE/lol/match/v5/matches/by-puuid/{}/ids", PLAYER1_PUUID)))
+        .and(query_param("queue", "1700"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(["EUW1_2", "EUW1_1"]))
+        .mount(&env.server)
+        .await;
+    // Only EUW1_2 is in the requested queue; EUW1_1 is left out by the post-filter.
+    let mut arena: Value = serde_json::from_str(FIXTURE_MATCH).unwrap();
+    arena["metadata"]["matchId"] = "EUW1_2".into();
+    arena["info"]["queueId"] = 1700.into();
+    Mock::given(method("GET"))
+        .and(path("/EUROPE/lol/match/v5/matches/EUW1_2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(arena))
+        .mount(&env.server)
+        .await;
+    env.mock_match("EUW1_1", true).await;
+
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "--queue-id", "1700", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    assert_eq!(output["querySummary"]["matchesPlayedTogetherCount"], 1);
+    assert_eq!(output["foundMatches"][0]["matchId"], "EUW1_2");
+}
+
+#[tokio::test]
+async fn checks_duo_rank_restrictions() {
+    let env = TestEnv::start("can-duo").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    env.mock_account("Fixture3", PLAYER2_PUUID).await;
+    for (puuid, tier, rank) in [(PLAYER1_PUUID, "GOLD", "II"), (PLAYER2_PUUID, "PLATINUM", "IV")] {
+        Mock::given(method("GET"))
+            .and(path(format!("/EUW1/lol/league/v4/entries/by-puuid/{}", puuid)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([{
+                "leagueId": "league",
+                "summonerId": "summoner",
+                "puuid": puuid,
+                "queueType": "RANKED_SOLO_5x5",
+                "tier": tier,
+                "rank": rank,
+                "leaguePoints": 50,
+                "wins": 10,
+                "losses": 10,
+                "hotStreak": false,
+                "veteran": false,
+                "freshBlood": false,
+                "inactive": false,
+            }])))
+            .mount(&env.server)
+            .await;
+    }
+
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "can-duo", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    assert_eq!(output["eligibility"], "allowed");
+    assert_eq!(output["divisionsApart"], 2);
+    assert_eq!(output["player2"]["tier"], "PLATINUM");
+    assert_eq!(output["apiCalls"]["leagueCalls"], 2);
+    assert_eq!(output["apiCalls"]["summonerCalls"], 0);
+}
+
+#[tokio::test]
+async fn ranks_contacts_from_the_index() {
+    let env = TestEnv::start("leaderboard").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    json_stdout(&env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    env.run(&["--self", "Fixture0#EUW"]).await;
+    env.run(&["fav", "add", "Fixture3#EUW"]).await;
+    env.run(&["fav", "add", "Uncached#EUW"]).await;
+
+    let output = env.run(&["--json", "leaderboard", "--by", "games"]).await;
+    assert!(stderr(&output).contains("'Uncached#EUW' is not in the local cache"), "missing warning: {}", stderr(&output));
+    let entries = json_stdout(&output);
+    let entries = entries.as_array().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["partner"], "Fixture3#EUW");
+    assert_eq!(entries[0]["games"], 1);
+    assert_eq!(entries[0]["winRate"], 100.0);
+    assert!(entries[0]["averageKda"].is_f64());
+    assert_eq!(entries[0]["averageTeammateAssists"], 8.0);
+    assert_eq!(entries[0]["visionDuelsWon"], 1);
+    assert_eq!(entries[0]["visionDuelsLost"], 0);
+}
+
+#[tokio::test]
+async fn checks_the_other_favorites_when_one_fails() {
+    let env = TestEnv::start("fav-check").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    env.run(&["--self", "Fixture0#EUW"]).await;
+    env.run(&["fav", "add", "Missing#EUW"]).await;
+    env.run(&["fav", "add", "Fixture3#EUW"]).await;
+
+    let output = env.run(&["--region", "EUW", "--json", "fav", "check"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    let summaries = json_stdout(&output);
+    let summary = |partner: &str| summaries.as_array().unwrap().iter().find(|s| s["partner"] == partner).unwrap().clone();
+    assert!(summary("Missing#EUW")["error"].as_str().unwrap().contains("Missing#EUW"), "{}", summaries);
+    assert_eq!(summary("Fixture3#EUW")["matchesPlayedTogetherCount"], 1);
+    assert!(summary("Fixture3#EUW").get("error").is_none());
+}
+
+#[tokio::test]
+async fn shows_contact_nicknames_next_to_riot_ids() {
+    let env = TestEnv::start("nicknames").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    env.run(&["contacts", "nick", "Fixture3#EUW", "Bob"]).await;
+
+    let output = env.run(&["--region", "EUW", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    let text = String::from_utf8_lossy(&output.stdout);
+    assert!(text.contains("Bob (Fixture3#EUW)"), "missing nickname: {}", text);
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    assert_eq!(output["querySummary"]["player2"]["nickname"], "Bob");
+    assert!(output["querySummary"]["player1"].get("nickname").is_none());
+
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "--anonymize", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    assert!(output["querySummary"]["player2"].get("nickname").is_none());
+
+    env.run(&["contacts", "nick", "Fixture3#EUW"]).await;
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    assert!(output["querySummary"]["player2"].get("nickname").is_none());
+
+    // A broken contact book only costs the nicknames.
+    std::fs::write(env.home.join(".config/ptg/contacts.json"), "{").unwrap();
+    let output = env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    assert!(stderr(&output).contains("Could not read the contact book"), "stderr: {}", stderr(&output));
+    assert_eq!(json_stdout(&output)["querySummary"]["matchesPlayedTogetherCount"], 1);
+}
+
+#[tokio::test]
+async fn anonymizes_match_ids_and_links() {
+    let env = TestEnv::start("anonymize").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "--anonymize", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    let found = &output["foundMatches"][0];
+    assert!(found["matchId"].as_str().unwrap().starts_with("match-"), "{}", found["matchId"]);
+    assert!(found["leagueOfGraphsLink"].is_null());
+    assert_eq!(output["sessions"][0]["matchIds"][0], found["matchId"]);
+    assert!(!output.to_string().contains("EUW1_"), "{}", output);
+
+    let output = env.run(&["--region", "EUW", "--anonymize", "--verbose", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("EUW1_") && !stdout.contains("leagueofgraphs"), "{}", stdout);
+    let output = env.run(&["--region", "EUW", "--anonymize", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("EUW1_") && !stdout.contains("leagueofgraphs"), "{}", stdout);
+}
+
+#[tokio::test]
+async fn moves_the_setup_between_machines() {
+    let laptop = TestEnv::start("export-laptop").await;
+    laptop.run(&["--self", "Fixture0#EUW"]).await;
+    laptop.run(&["fav", "add", "Fixture3#EUW"]).await;
+    laptop.run(&["contacts", "nick", "Fixture3#EUW", "Bob"]).await;
+    laptop.run(&["note", "EUW1_1", "the baron throw game"]).await;
+    let file = laptop.home.join("setup.json");
+    let export = laptop.run(&["config", "export", file.to_str().unwrap()]).await;
+    assert!(export.status.success(), "export failed: {}", stderr(&export));
+    assert!(!std::fs::read_to_string(&file).unwrap().contains("RGAPI-test"));
+
+    let desktop = TestEnv::start("export-desktop").await;
+    desktop.run(&["fav", "add", "Other#EUW"]).await;
+    let report = json_stdout(&desktop.run(&["--json", "config", "import", file.to_str().unwrap()]).await);
+    assert_eq!(report["addedContacts"], 1);
+    assert_eq!(report["updatedNotes"], 1);
+    assert_eq!(report["importedApiKeys"], false);
+
+    let favorites = String::from_utf8_lossy(&desktop.run(&["fav", "list"]).await.stdout).to_string();
+    assert!(favorites.contains("Other#EUW") && favorites.contains("Fixture3#EUW"), "favorites: {}", favorites);
+    let note = desktop.run(&["note", "EUW1_1"]).await;
+    assert!(String::from_utf8_lossy(&note.stdout).contains("the baron throw game"));
+    let config = std::fs::read_to_string(desktop.home.join(".config/ptg/config.toml")).unwrap();
+    assert!(config.contains("game_name = \"Fixture0\""), "config: {}", config);
+    assert!(config.contains("RGAPI-test"), "the local API key should be kept: {}", config);
+
+    // Extra keys alone replace the local ones too, and the report has to say so.
+    let mut setup: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&file).unwrap()).unwrap();
+    setup["config"]["additional_api_keys"] = serde_json::json!(["RGAPI-extra"]);
+    std::fs::write(&file, setup.to_string()).unwrap();
+    let report = json_stdout(&desktop.run(&["--json", "config", "import", file.to_str().unwrap()]).await);
+    assert_eq!(report["importedApiKeys"], true);
+    let config = std::fs::read_to_string(desktop.home.join(".config/ptg/config.toml")).unwrap();
+    assert!(config.contains("RGAPI-extra"), "config: {}", config);
+}
+
+#[tokio::test]
+async fn moves_the_legacy_rito_directory_into_place() {
+    let env = TestEnv::start("legacy-dir").await;
+    let config_dir = env.home.join(".config/ptg");
+    std::fs::remove_file(config_dir.join("config.toml")).unwrap();
+    let legacy_dir = env.home.join(".config/rito");
+    std::fs::create_dir_all(&legacy_dir).unwrap();
+    std::fs::write(
+        legacy_dir.join("config.json"),
+        r#"{"self_riot_id": {"game_name": "Fixture0", "tag_line": "EUW"}, "api_key": "RGAPI-test"}"#,
+    )
+    .unwrap();
+    std::fs::write(legacy_dir.join("accounts.json"), r#"{"accounts": {}}"#).unwrap();
+
+    let output = env.run(&["fav", "list"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(!legacy_dir.exists(), "the legacy directory should be gone once emptied");
+    assert!(env.home.join(".cache/ptg/accounts.json").exists());
+    let config = std::fs::read_to_string(config_dir.join("config.toml")).unwrap();
+    assert!(config.contains("game_name = \"Fixture0\""), "config: {}", config);
+    assert!(config_dir.join("config.json.bak").exists());
+}
+
+#[tokio::test]
+async fn uses_the_stored_region_of_player1() {
+    let env = TestEnv::start("contact-region").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    env.run(&["--self", "Fixture0#EUW", "--region", "EUW"]).await;
+
+    // Without a stored region, the route would be auto-detected on AMERICAS and ASIA first.
+    let output = json_stdout(&env.run(&["--json", "Fixture3#EUW"]).await);
+    assert_eq!(output["querySummary"]["regionalRoute"], "EUROPE");
+    assert_eq!(output["querySummary"]["apiCalls"]["accountCalls"], 2);
+
+    env.run(&["contacts", "region", "Fixture3#EUW", "NA"]).await;
+    let output = env.run(&["--json", "Fixture3#EUW"]).await;
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("Fixture0#EUW plays on EUW and Fixture3#EUW on NA"), "stderr: {}", stderr(&output));
+}
+
+#[tokio::test]
+async fn detects_sea_past_a_failing_route() {
+    let env = TestEnv::start("detect-sea").await;
+    // account-v1 is served from ASIA for SEA players; AMERICAS is down.
+    Mock::given(method("GET"))
+        .and(path("/AMERICAS/riot/account/v1/accounts/by-riot-id/Islander/SG2"))
+        .respond_with(ResponseTemplate::new(400))
+        .mount(&env.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/ASIA/riot/account/v1/accounts/by-riot-id/Islander/SG2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "puuid": PLAYER1_PUUID,
+            "gameName": "Islander",
+            "tagLine": "SG2",
+        })))
+        .mount(&env.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/ASIA/lol/match/v5/matches/by-puuid/{}/ids", PLAYER1_PUUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(Vec::<String>::new()))
+        .mount(&env.server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/SEA/lol/match/v5/matches/by-puuid/{}/ids", PLAYER1_PUUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(["SG2_1"]))
+        .mount(&env.server)
+        .await;
+
+    let output = env.run(&["--verbose", "Islander#SG2", "Stranger#SG2"]).await;
+    assert!(
+        String::from_utf8_lossy(&output.stdout).contains("Detected regional route SEA for Islander#SG2."),
+        "stdout: {}\nstderr: {}",
+        String::from_utf8_lossy(&output.stdout),
+        stderr(&output)
+    );
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn quitting_the_pager_early_still_exports() {
+    let env = TestEnv::start("pager-quit").await;
+    env.mock_fixture_history(&["EUW1_3", "EUW1_2", "EUW1_1"]).await;
+    let path = env.home.join("games.ics");
+
+    // `true` quits at once, like pressing q in less before the run is done.
+    let args = ["--region", "EUW", "--verbose", "--export", "ics", path.to_str().unwrap(), "Fixture0#EUW", "Fixture3#EUW"];
+    let output = env.run_in_terminal(&args, "true").await;
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+    assert!(std::fs::read_to_string(&path).unwrap().contains("BEGIN:VEVENT"));
+}
+
+#[tokio::test]
+async fn runs_share_the_rate_limit_of_the_api_key() {
+    let env = TestEnv::start("key-budget").await;
+    env.mock_fixture_history(&["EUW1_1"]).await;
+    let config_path = env.home.join(".config/ptg/config.toml");
+    let config = std::fs::read_to_string(&config_path).unwrap();
+    std::fs::write(&config_path, format!("{}lock_api_key = true\n", config)).unwrap();
+
+    let args = ["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"];
+    let output = env.run(&args).await;
+    assert_eq!(json_stdout(&output)["querySummary"]["matchesPlayedTogetherCount"], 1);
+    assert!(!stderr(&output).contains("Waiting"), "stderr: {}", stderr(&output));
+
+    // Another run just used up the second's budget of 20 requests.
+    let locks_dir = env.home.join(".cache/ptg/locks");
+    let budget = std::fs::read_dir(&locks_dir).unwrap().next().unwrap().unwrap().path();
+    let now_ms = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis();
+    std::fs::write(&budget, format!("{}\n", now_ms + 500).repeat(20)).unwrap();
+    let output = env.run(&args).await;
+    assert_eq!(json_stdout(&output)["querySummary"]["matchesPlayedTogetherCount"], 1);
+    assert!(stderr(&output).contains("Waiting for the API key's rate limit"), "stderr: {}", stderr(&output));
+
+    // A production key has room for them.
+    std::fs::write(&config_path, format!("{}lock_api_key = true\napi_key_rate_limits = \"500:10,30000:600\"\n", config)).unwrap();
+    std::fs::write(&budget, format!("{}\n", now_ms + 500).repeat(20)).unwrap();
+    let output = env.run(&args).await;
+    assert_eq!(json_stdout(&output)["querySummary"]["matchesPlayedTogetherCount"], 1);
+    assert!(!stderr(&output).contains("Waiting"), "stderr: {}", stderr(&output));
+}
+
+#[tokio::test]
+async fn keeps_one_rate_limit_budget_per_api_key() {
+    let env = TestEnv::start("key-budgets").await;
+    env.mock_fixture_history(&["EUW1_1"]).await;
+    let config_path = env.home.join(".config/ptg/config.toml");
+    let locks_dir = env.home.join(".cache/ptg/locks");
+    let budget_sizes = || {
+        let mut sizes: Vec<usize> = std::fs::read_dir(&locks_dir)
+            .unwrap()
+            .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap().lines().count())
+            .collect();
+        sizes.sort_unstable();
+        sizes
+    };
+    let args = ["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"];
+
+    env.run(&["--api-key", "RGAPI-first", "RGAPI-second"]).await;
+    let config = std::fs::read_to_string(&config_path).unwrap();
+    std::fs::write(&config_path, format!("{}lock_api_key = true\n", config)).unwrap();
+    json_stdout(&env.run(&args).await);
+    // Two accounts, the match list and the match, sent in turn with each key.
+    assert_eq!(budget_sizes(), [2, 2]);
+
+    // A run sharing only the second key counts against its budget too: the accounts and the match
+    // are cached, so only the match list is fetched again, with the second key.
+    env.run(&["--api-key", "RGAPI-second", "RGAPI-third"]).await;
+    json_stdout(&env.run(&args).await);
+    assert_eq!(budget_sizes(), [2, 3]);
+}
+
+#[tokio::test]
+async fn reports_rate_limit_waits() {
+    let env = TestEnv::start("retry-after").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    env.mock_account("Fixture3", PLAYER2_PUUID).await;
+    env.mock_match_ids(&["EUW1_1"]).await;
+    Mock::given(method("GET"))
+        .and(path("/EUROPE/lol/match/v5/matches/EUW1_1"))
+        .respond_with(
+            ResponseTemplate::new(429)
+                .insert_header("Retry-After", "1")
+                .insert_header("X-Rate-Limit-Type", "application"),
+        )
+        .up_to_n_times(1)
+        .mount(&env.server)
+        .await;
+    env.mock_match("EUW1_1", true).await;
+
+    let output = env.run(&["--region", "EUW", "--json", "--fail-fast-on-429", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("stopping because of --fail-fast-on-429"), "stderr: {}", stderr(&output));
+
+    Mock::given(method("GET"))
+        .and(path("/EUROPE/lol/match/v5/matches/EUW1_1"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "1").insert_header("X-Rate-Limit-Type", "method"))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&env.server)
+        .await;
+    let output = env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    assert!(
+        stderr(&output).contains("Rate limited by Riot (429, the endpoint's method rate limit): waiting 1.5s (as asked by Retry-After)."),
+        "stderr: {}",
+        stderr(&output)
+    );
+    let summary = &json_stdout(&output)["querySummary"];
+    assert_eq!(summary["matchesPlayedTogetherCount"], 1);
+    assert_eq!(summary["apiCalls"]["retries"], 1);
+    assert_eq!(summary["apiCalls"]["matchDetailCalls"], 1);
+}
+
+#[tokio::test]
+async fn prefetch_resumes_without_fetching_indexed_matches_again() {
+    let env = TestEnv::start("prefetch").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/EUROPE/lol/match/v5/matches/by-puuid/{}/ids", PLAYER1_PUUID)))
+        .and(query_param("count", "3"))
+        .and(query_param("start", "0"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(["EUW1_3", "EUW1_2", "EUW1_1"]))
+        .mount(&env.server)
+        .await;
+    for match_id in ["EUW1_3", "EUW1_2", "EUW1_1"] {
+        env.mock_match(match_id, true).await;
+    }
+    let args = ["--region", "EUW", "prefetch", "--max-matches", "3", "Fixture0#EUW"];
+
+    let output = env.run(&args).await;
+    assert!(String::from_utf8_lossy(&output.stdout).contains("[Fixture0#EUW] 3 matches indexed, 0 were already cached."), "{}", stderr(&output));
+
+    env.server.reset().await;
+    env.mock_match_ids(&["EUW1_4", "EUW1_3", "EUW1_2"]).await;
+    env.mock_match("EUW1_4", true).await;
+    let output = env.run(&args).await;
+    assert!(String::from_utf8_lossy(&output.stdout).contains("[Fixture0#EUW] 1 matches indexed, 2 were already cached."), "{}", stderr(&output));
+    let fetched: Vec<String> = env.server.received_requests().await.unwrap().iter().map(|request| request.url.path().to_string()).collect();
+    assert_eq!(fetched, [format!("/EUROPE/lol/match/v5/matches/by-puuid/{}/ids", PLAYER1_PUUID), "/EUROPE/lol/match/v5/matches/EUW1_4".to_string()]);
+}
+
+#[tokio::test]
+async fn prefetch_reports_and_does_not_cover_failed_matches() {
+    let env = TestEnv::start("prefetch-failures").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    env.mock_match_ids(&["EUW1_3", "EUW1_2", "EUW1_1"]).await;
+    // EUW1_2 is not mocked, so the API answers 404.
+    env.mock_match("EUW1_3", true).await;
+    env.mock_match("EUW1_1", true).await;
+    let args = ["--region", "EUW", "prefetch", "--max-matches", "3", "Fixture0#EUW"];
+
+    let output = env.run(&args).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Indexed EUW1_3 (1 of 3)") && stdout.contains("Could not index EUW1_2 (2 of 3)"), "{}", stdout);
+    assert!(!stdout.contains("Indexed EUW1_2"), "{}", stdout);
+    assert!(stdout.contains("[Fixture0#EUW] 2 matches indexed, 0 were already cached, 1 could not be fetched."), "{}", stdout);
+    // The coverage stops at EUW1_3 instead of claiming the whole history.
+    let index = ptg::index::MatchIndex::open_at(&env.home.join(".cache/ptg/index.sqlite")).unwrap();
+    let game_start = serde_json::from_str::<Value>(FIXTURE_MATCH).unwrap()["info"]["gameStartTimestamp"].as_i64().unwrap();
+    assert_eq!(index.player_coverage(PLAYER1_PUUID).unwrap().unwrap().covered_since, game_start);
+
+    let output = env.run(&[&["--anonymize"], &args[..]].concat()).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("2 were already cached, 1 could not be fetched."), "{}", stdout);
+    assert!(!stdout.contains("Fixture") && !stdout.contains("EUW1_"), "{}", stdout);
+}
+
+#[tokio::test]
+async fn answers_from_the_cache_offline() {
+    let env = TestEnv::start("offline").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/EUROPE/lol/match/v5/matches/by-puuid/{}/ids", PLAYER2_PUUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(["EUW1_2"]))
+        .mount(&env.server)
+        .await;
+    let output = env.run(&["--region", "EUW", "prefetch", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    env.server.reset().await;
+    let args = ["--region", "EUW", "--json", "--offline", "--between", "2024-10-01", "2024-10-31"];
+    let output = env.run(&[&args[..], &["Fixture0#EUW", "Fixture3#EUW"]].concat()).await;
+    let summary = &json_stdout(&output)["querySummary"];
+    assert_eq!(summary["checkedMatchesCount"], 2);
+    assert_eq!(summary["matchesPlayedTogetherCount"], 1);
+    assert_eq!(summary["apiCalls"]["matchDetailCalls"], 0);
+    assert!(env.server.received_requests().await.unwrap().is_empty());
+
+    let output = env.run(&[&args[..], &["Fixture0#EUW", "Unknown#EUW"]].concat()).await;
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("--offline: the PUUID of Unknown#EUW is not in the local cache"), "stderr: {}", stderr(&output));
+
+    let output = env.run(&[&args[..], &["--match-type", "ranked", "Fixture0#EUW", "Fixture3#EUW"]].concat()).await;
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("drop --match-type (or default_match_type)"), "stderr: {}", stderr(&output));
+}
+
+/// Writes `template` to `file_name` in the test home and renders the fixture query with it.
+async fn render_with_template(env: &TestEnv, file_name: &str, template: &str) -> Output {
+    let path = env.home.join(file_name);
+    std::fs::write(&path, template).unwrap();
+    env.run(&["--region", "EUW", "--template", path.to_str().unwrap(), "Fixture0#EUW", "Fixture3#EUW"]).await
+}
+
+#[tokio::test]
+async fn renders_custom_templates_escaping_only_html() {
+    let env = TestEnv::start("custom-template").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    env.run(&["contacts", "nick", "Fixture3#EUW", "<Bob>"]).await;
+    let text = render_with_template(&env, "duo.txt", "{{ querySummary.player2.nickname }}: {{ querySummary.matchesPlayedTogetherCount }}").await;
+    assert_eq!(String::from_utf8_lossy(&text.stdout).trim(), "<Bob>: 1");
+    let html = render_with_template(&env, "duo.html", "{{ querySummary.player2.nickname }}").await;
+    assert_eq!(String::from_utf8_lossy(&html.stdout).trim(), "&lt;Bob&gt;");
+
+    let broken = render_with_template(&env, "broken.txt", "{{ querySummary.missing.field }}").await;
+    assert!(!broken.status.success());
+    let message = stderr(&broken);
+    assert!(message.contains("Could not render template") && message.contains("querySummary.missing.field"), "{}", message);
+}
+
+#[tokio::test]
+async fn renders_a_saved_run_without_the_api() {
+    let env = TestEnv::start("save-run").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    let run_path = env.home.join("duo.ptgrun");
+    let run_path = run_path.to_str().unwrap();
+    let saved = json_stdout(&env.run(&["--region", "EUW", "--json", "--save-run", run_path, "Fixture0#EUW", "Fixture3#EUW"]).await);
+
+    let friend = TestEnv::start("load-run").await;
+    let output = friend.run(&["--json", "--load-run", run_path]).await;
+    assert!(stderr(&output).contains("1 matches added to the local index"), "stderr: {}", stderr(&output));
+    assert_eq!(json_stdout(&output), saved);
+    let output = friend.run(&["--oneline", "--load-run", run_path]).await;
+    assert!(String::from_utf8_lossy(&output.stdout).contains("CLASSIC"), "{}", stderr(&output));
+    assert!(friend.server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn renders_saved_json_results_as_html() {
+    let env = TestEnv::start("render").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    let output = env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    let json_path = env.home.join("results.json");
+    std::fs::write(&json_path, &output.stdout).unwrap();
+    env.server.reset().await;
+
+    let output = env.run(&["render", json_path.to_str().unwrap(), "--format", "html"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    let html = String::from_utf8_lossy(&output.stdout);
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<h1>Fixture0#EUW and Fixture3#EUW</h1>"), "{}", html);
+    assert!(html.contains("1 games together out of 2 checked"), "{}", html);
+
+    let output = env.run(&["render", json_path.to_str().unwrap(), "--format", "json"]).await;
+    assert_eq!(json_stdout(&output)["querySummary"]["matchesPlayedTogetherCount"], 1);
+    assert!(env.server.received_requests().await.unwrap().is_empty());
+}
+
+/// Caches Data Dragon patch 14.20.1 in en_US with Garen, Jinx and Flash, and `item_json` as item.json.
+fn write_cached_data_dragon(env: &TestEnv, item_json: &str) {
+    let ddragon = env.home.join(".cache/ptg/ddragon");
+    std::fs::create_dir_all(ddragon.join("14.20.1/en_US")).unwrap();
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+    std::fs::write(ddragon.join("versions.json"), format!(r#"{{"fetched_at":{},"versions":["14.20.1"]}}"#, now)).unwrap();
+    for (file_name, content) in [
+        ("champion.json", r#"{"data":{"Garen":{"id":"Garen","key":"86","name":"Garen"},"Jinx":{"id":"Jinx","key":"222","name":"Jinx"}}}"#),
+        ("summoner.json", r#"{"data":{"SummonerFlash":{"id":"SummonerFlash","key":"4","name":"Flash"}}}"#),
+        ("runesReforged.json", "[]"),
+        ("item.json", item_json),
+    ] {
+        std::fs::write(ddragon.join("14.20.1/en_US").join(file_name), content).unwrap();
+    }
+}
+
+#[tokio::test]
+async fn adds_champion_assets_from_data_dragon() {
+    let env = TestEnv::start("assets").await;
+    env.mock_fixture_history(&["EUW1_1"]).await;
+    write_cached_data_dragon(&env, r#"{"data":{}}"#);
+
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "--assets", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    let player2 = &output["foundMatches"][0]["player2Details"];
+    assert_eq!(player2["championIconUrl"], "https://ddragon.leagueoflegends.com/cdn/14.20.1/img/champion/Jinx.png");
+    assert_eq!(player2["championSplashUrl"], "https://ddragon.leagueoflegends.com/cdn/img/champion/splash/Jinx_0.jpg");
+}
+
+#[tokio::test]
+async fn shows_item_builds_and_summoner_spells() {
+    let env = TestEnv::start("builds").await;
+    env.mock_fixture_history(&["EUW1_1"]).await;
+    let mut match_data: Value = serde_json::from_str(FIXTURE_MATCH).unwrap();
+    match_data["metadata"]["matchId"] = "EUW1_1".into();
+    let player1 = &mut match_data["info"]["participants"][0];
+    player1["item0"] = 3078.into();
+    player1["item2"] = 3047.into();
+    player1["item6"] = 3340.into();
+    player1["summoner1Id"] = 4.into();
+    player1["summoner2Id"] = 14.into();
+    Mock::given(method("GET"))
+        .and(path("/EUROPE/lol/match/v5/matches/EUW1_1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(match_data))
+        .with_priority(1)
+        .mount(&env.server)
+        .await;
+
+    let output = env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    let details = &json_stdout(&output)["foundMatches"][0]["player1Details"];
+    assert_eq!(details["items"], serde_json::json!([{ "id": 3078 }, { "id": 3047 }]));
+    assert_eq!(details["summonerSpells"], serde_json::json!([{ "id": 4 }, { "id": 14 }]));
+    let json_path = env.home.join("results.json");
+    std::fs::write(&json_path, &output.stdout).unwrap();
+
+    let output = env.run(&["--region", "EUW", "--verbose", "--no-pager", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("  Summoner Spells: #4, #14"), "{}", stdout);
+    assert!(stdout.contains("  Items: #3078, #3047"), "{}", stdout);
+
+    let output = env.run(&["render", json_path.to_str().unwrap(), "--format", "html"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    let html = String::from_utf8_lossy(&output.stdout);
+    assert!(html.contains(" 4 14<br> 3078 3047</td>"), "{}", html);
+
+    // A cached patch whose item.json was cut off.
+    write_cached_data_dragon(&env, r#"{"data":{"3078":"#);
+
+    let args = ["--region", "EUW", "--language", "en_US", "--verbose", "--no-pager", "Fixture0#EUW", "Fixture3#EUW"];
+    let output = env.run(&args).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stderr(&output).contains("Could not load the Data Dragon item.json"), "{}", stderr(&output));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("  Summoner Spells: Flash, #14"), "{}", stdout);
+    assert!(stdout.contains("  Items: #3078, #3047"), "{}", stdout);
+}
+
+#[tokio::test]
+async fn summarizes_rune_pages() {
+    let env = TestEnv::start("runes").await;
+    env.mock_fixture_history(&["EUW1_3", "EUW1_2", "EUW1_1"]).await;
+    let rune_page = |keystone: i32, primary: i32, secondary: i32| serde_json::json!({
+        "statPerks": { "defense": 5001, "flex": 5008, "offense": 5005 },
+        "styles": [
+            { "description": "primaryStyle", "style": primary, "selections": [{ "perk": keystone, "var1": 0, "var2": 0, "var3": 0 }] },
+            { "description": "subStyle", "style": secondary, "selections": [] },
+        ],
+    });
+    for match_id in ["EUW1_3", "EUW1_1"] {
+        let mut match_data: Value = serde_json::from_str(FIXTURE_MATCH).unwrap();
+        match_data["metadata"]["matchId"] = match_id.into();
+        match_data["info"]["participants"][0]["perks"] = rune_page(8010, 8000, 8400);
+        match_data["info"]["participants"][3]["perks"] = rune_page(8005, 8000, 8300);
+        Mock::given(method("GET"))
+            .and(path(format!("/EUROPE/lol/match/v5/matches/{}", match_id)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(match_data))
+            .with_priority(1)
+            .mount(&env.server)
+            .await;
+    }
+
+    let output = env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    let json = json_stdout(&output);
+    let runes = &json["foundMatches"][0]["player1Details"]["runes"];
+    assert_eq!(runes["keystone"]["id"], 8010);
+    assert_eq!(runes["secondaryTree"]["id"], 8400);
+    let keystones = &json["stats"]["keystonesTogether"];
+    assert_eq!(keystones.as_array().unwrap().len(), 1);
+    assert_eq!(keystones[0]["player2Keystone"]["id"], 8005);
+    assert_eq!(keystones[0]["games"], 2);
+
+    let output = env.run(&["--region", "EUW", "--verbose", "--stats", "--no-pager", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("  Runes: #8010 (#8000 / #8400)"), "{}", stdout);
+    assert!(stdout.contains("#8010 + #8005: 2 games, 100.0% won"), "{}", stdout);
+}
+
+#[tokio::test]
+async fn shows_notes_in_the_match_boxes() {
+    let env = TestEnv::start("verbose-notes").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    env.run(&["note", "EUW1_2", "the baron throw game", "--tag", "throw"]).await;
+
+    let output = env.run(&["--region", "EUW", "--verbose", "--no-pager", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("  Notes: [throw] the baron throw game"), "{}", stdout);
+}
+
+#[tokio::test]
+async fn orders_patches_by_number() {
+    let env = TestEnv::start("patch-order").await;
+    env.mock_fixture_history(&["EUW1_3", "EUW1_2", "EUW1_1"]).await;
+    for (match_id, game_version) in [("EUW1_3", "14.20.628.3370"), ("EUW1_1", "14.3.557.1234")] {
+        let mut match_data: Value = serde_json::from_str(FIXTURE_MATCH).unwrap();
+        match_data["metadata"]["matchId"] = match_id.into();
+        match_data["info"]["gameVersion"] = game_version.into();
+        Mock::given(method("GET"))
+            .and(path(format!("/EUROPE/lol/match/v5/matches/{}", match_id)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(match_data))
+            .with_priority(1)
+            .mount(&env.server)
+            .await;
+    }
+
+    let json = json_stdout(&env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    let patches: Vec<&str> = json["stats"]["perPatch"].as_array().unwrap().iter().map(|p| p["patch"].as_str().unwrap()).collect();
+    assert_eq!(patches, ["14.3", "14.20"]);
+}
+
+#[tokio::test]
+async fn keeps_score_of_the_vision_duel() {
+    let env = TestEnv::start("vision-duel").await;
+    env.mock_fixture_history(&["EUW1_3", "EUW1_2", "EUW1_1"]).await;
+
+    let output = env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    let json = json_stdout(&output);
+    let duel = &json["stats"]["visionDuel"];
+    assert_eq!(duel["player2Wins"], 2);
+    assert_eq!(duel["runningTally"], serde_json::json!([-1, -2]));
+    let achievement = json["achievements"].as_array().unwrap().iter().find(|a| a["id"] == "visionDuel").unwrap();
+    assert_eq!(achievement["description"], "Player 2 had the higher vision score in 2 of 2 game(s).");
+
+    let output = env.run(&["--region", "EUW", "--stats", "--no-pager", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Higher vision score: Player 1 0, Player 2 2, tied 0"), "{}", stdout);
+}
+
+#[tokio::test]
+async fn draws_charts_in_ascii_on_request() {
+    let env = TestEnv::start("ascii").await;
+    env.mock_fixture_history(&["EUW1_3", "EUW1_2", "EUW1_1"]).await;
+    let args = ["--region", "EUW", "--stats", "--duo-timeline", "--no-pager", "Fixture0#EUW", "Fixture3#EUW"];
+
+    let output = env.run(&args).await;
+    assert!(!String::from_utf8_lossy(&output.stdout).is_ascii());
+
+    let output = env.run(&[&args[..], &["--ascii"]].concat()).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.is_ascii(), "{}", stdout);
+    assert!(stdout.contains("--- Duo Timeline ---"), "{}", stdout);
+}
+
+#[tokio::test]
+async fn prints_extended_help_and_a_man_page() {
+    let env = TestEnv::start("help-topics").await;
+
+    let output = env.run(&["--help-all"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    let help = String::from_utf8_lossy(&output.stdout);
+    for section in ["ptg config encrypt", "ptg cache prune", "--max-size-mb <MB>", "self_riot_id", "RGAPI_KEY", "Exit status:"] {
+        assert!(help.contains(section), "{} missing from:\n{}", section, help);
+    }
+
+    let output = env.run(&["help-topics", "man"]).await;
+    let page = String::from_utf8_lossy(&output.stdout);
+    assert!(page.contains(".TH ptg 1 "), "{}", page);
+    assert!(page.contains(".SH SUBCOMMANDS\n"), "{}", page);
+    assert!(page.contains(".SH CONFIGURATION\n"), "{}", page);
+    assert!(page.contains(".SH ENVIRONMENT\n.TP\n\\fBRGAPI_KEY\\fR"), "{}", page);
+
+    let output = env.run(&["help-topics", "exit-codes"]).await;
+    assert!(String::from_utf8_lossy(&output.stdout).contains("with --exists, the query itself failed"));
+    let output = env.run(&["help-topics", "everything"]).await;
+    assert!(stderr(&output).contains("Invalid help topic"), "{}", stderr(&output));
+}
+
+#[tokio::test]
+async fn compares_two_duos_in_a_rivalry() {
+    let env = TestEnv::start("rivalry").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    env.mock_account("Fixture5", "fixture-puuid-5").await;
+    env.mock_account("Fixture6", "fixture-puuid-6").await;
+    for (puuid, match_ids) in [(PLAYER2_PUUID, &["EUW1_2"][..]), ("fixture-puuid-5", &["EUW1_2", "EUW1_1"]), ("fixture-puuid-6", &["EUW1_2", "EUW1_1"])] {
+        Mock::given(method("GET"))
+            .and(path(format!("/EUROPE/lol/match/v5/matches/by-puuid/{}/ids", puuid)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(match_ids))
+            .mount(&env.server)
+            .await;
+    }
+
+    let args = ["--region", "EUW", "rivalry", "Fixture0#EUW", "Fixture3#EUW", "--", "Fixture5#EUW", "Fixture6#EUW"];
+    let output = env.run(&[&["--json"], &args[..]].concat()).await;
+    let json = json_stdout(&output);
+    assert_eq!(json["first"]["games"], 1);
+    assert_eq!(json["first"]["winRate"], 100.0);
+    assert_eq!(json["first"]["synergy"], 0.0);
+    assert_eq!(json["second"]["games"], 2);
+    assert_eq!(json["second"]["wins"], 0);
+    assert_eq!(json["second"]["synergy"], Value::Null);
+    assert_eq!(json["winner"], 1);
+
+    let output = env.run(&args).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Record          1-0"), "{}", stdout);
+    assert!(stdout.contains("Fixture0#EUW & Fixture3#EUW carry the group: 100.0% over 1 games against 0.0% over 2."), "{}", stdout);
+
+    let output = env.run(&[&["--anonymize"], &args[..]].concat()).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("carry the group"), "{}", stdout);
+    assert!(!stdout.contains("Fixture"), "{}", stdout);
+
+    let output = env.run(&["rivalry", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    assert!(!output.status.success());
+}
+
+#[tokio::test]
+async fn writes_several_formats_in_one_run() {
+    let env = TestEnv::start("formats").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    let out = env.home.join("out");
+
+    let output = env.run(&[
+        "--region", "EUW", "--format", "json", "--format", "html", "--format", "markdown",
+        "--output-dir", out.to_str().unwrap(), "Fixture0#EUW", "Fixture3#EUW",
+    ]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("--- Query Summary ---"));
+    let json: Value = serde_json::from_str(&std::fs::read_to_string(out.join("results.json")).unwrap()).unwrap();
+    assert_eq!(json["querySummary"]["matchesPlayedTogetherCount"], 1);
+    assert!(std::fs::read_to_string(out.join("results.html")).unwrap().starts_with("<!DOCTYPE html>"));
+    assert!(std::fs::read_to_string(out.join("results.md")).unwrap().starts_with("## Fixture0#EUW and Fixture3#EUW"));
+
+    let output = env.run(&["--format", "text", "--output-dir", out.to_str().unwrap(), "Fixture0#EUW", "Fixture3#EUW"]).await;
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("only print to the terminal"), "{}", stderr(&output));
+}
+
+#[tokio::test]
+async fn reads_piped_results_and_raw_matches() {
+    let env = TestEnv::start("from-json").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    let results = env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    assert!(results.status.success(), "{}", stderr(&results));
+    env.server.reset().await;
+
+    let output = env.run_with_stdin(&["render", "-", "--format", "md"], &results.stdout).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    let markdown = String::from_utf8_lossy(&output.stdout);
+    assert!(markdown.starts_with("## Fixture0#EUW and Fixture3#EUW\n\n1 games together out of 2 checked"), "{}", markdown);
+    assert!(markdown.contains("| Date | Mode | Fixture0#EUW | Fixture3#EUW | Result |"), "{}", markdown);
+
+    let output = env.run_with_stdin(&["--from-json", "-", "--oneline"], &results.stdout).await;
+    assert!(String::from_utf8_lossy(&output.stdout).contains("CLASSIC"), "{}", stderr(&output));
+
+    let mut shared: Value = serde_json::from_str(FIXTURE_MATCH).unwrap();
+    shared["metadata"]["matchId"] = "EUW1_7".into();
+    let mut solo = shared.clone();
+    solo["metadata"]["matchId"] = "EUW1_8".into();
+    solo["metadata"]["participants"][3] = "stranger-puuid".into();
+    solo["info"]["participants"][3]["puuid"] = "stranger-puuid".into();
+    solo["info"]["participants"][3]["riotIdGameName"] = "Stranger".into();
+    let matches = serde_json::to_vec(&[shared, solo]).unwrap();
+    let output = env.run_with_stdin(&["--json", "--from-json", "-", "Fixture0#EUW", "Fixture3#EUW"], &matches).await;
+    let summary = &json_stdout(&output)["querySummary"];
+    assert_eq!(summary["checkedMatchesCount"], 2);
+    assert_eq!(summary["matchesPlayedTogetherCount"], 1);
+    assert!(env.server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn compares_solo_and_duo_games() {
+    let env = TestEnv::start("solo-baseline").await;
+    env.mock_fixture_history(&["EUW1_3", "EUW1_2", "EUW1_1"]).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/EUROPE/lol/match/v5/matches/by-puuid/{}/ids", PLAYER2_PUUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(["EUW1_9", "EUW1_3"]))
+        .mount(&env.server)
+        .await;
+    let mut without_player1: Value = serde_json::from_str(FIXTURE_MATCH).unwrap();
+    without_player1["metadata"]["matchId"] = "EUW1_9".into();
+    without_player1["metadata"]["participants"][0] = "stranger-puuid".into();
+    without_player1["info"]["participants"][0]["puuid"] = "stranger-puuid".into();
+    Mock::given(method("GET"))
+        .and(path("/EUROPE/lol/match/v5/matches/EUW1_9"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(without_player1))
+        .mount(&env.server)
+        .await;
+
+    let output = env.run(&["--region", "EUW", "--json", "--solo-baseline", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    let baseline = &json_stdout(&output)["stats"]["soloBaseline"];
+    assert_eq!(baseline["player1"]["solo"]["games"], 1);
+    assert_eq!(baseline["player1"]["duo"]["games"], 2);
+    assert_eq!(baseline["player2"]["solo"]["games"], 1);
+    assert_eq!(baseline["player2"]["duo"]["games"], 2);
+
+    let output = env.run(&["--region", "EUW", "--solo-baseline", "--no-pager", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("--- Solo vs Duo ---"), "{}", stdout);
+    assert!(stdout.contains("Fixture3#EUW solo"), "{}", stdout);
+}
+
+#[tokio::test]
+async fn groups_games_into_sessions() {
+    let env = TestEnv::start("sessions").await;
+    env.mock_fixture_history(&["EUW1_5", "EUW1_4", "EUW1_3", "EUW1_2", "EUW1_1"]).await;
+    // The oldest shared game is a day before the others, so it is a session of its own.
+    let mut day_before: Value = serde_json::from_str(FIXTURE_MATCH).unwrap();
+    day_before["metadata"]["matchId"] = "EUW1_1".into();
+    day_before["info"]["gameStartTimestamp"] = (1728900030000i64 - 24 * 60 * 60 * 1000).into();
+    Mock::given(method("GET"))
+        .and(path("/EUROPE/lol/match/v5/matches/EUW1_1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(day_before))
+        .with_priority(1)
+        .mount(&env.server)
+        .await;
+
+    let output = env.run(&["--region", "EUW", "--json", "--between", "2024-10-01", "2024-10-31", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    let sessions = json_stdout(&output)["sessions"].as_array().unwrap().clone();
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions[0]["label"], "Sunday morning");
+    assert_eq!(sessions[0]["matchIds"], serde_json::json!(["EUW1_1"]));
+    assert_eq!(sessions[1]["label"], "Monday morning");
+    assert_eq!(sessions[1]["date"], "2024-10-14");
+    assert_eq!(sessions[1]["games"], 2);
+
+    let output = env.run(&["--region", "EUW", "--sessions", "--no-pager", "--between", "2024-10-01", "2024-10-31", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("--- Sessions ---"), "{}", stdout);
+    assert!(stdout.contains("Monday morning (2024-10-14): 2W-0L, 30m"), "{}", stdout);
+}
+
+/// Overrides the fixture matches `match_ids`, oldest first, to start 40 minutes apart (ten minutes
+/// after the previous one ended), won by player1's team where `won` says so.
+async fn mock_games_40_minutes_apart(env: &TestEnv, match_ids: &[&str], won: impl Fn(usize) -> bool) {
+    for (n, match_id) in match_ids.iter().enumerate() {
+        let mut match_data: Value = serde_json::from_str(FIXTURE_MATCH).unwrap();
+        match_data["metadata"]["matchId"] = (*match_id).into();
+        match_data["info"]["gameStartTimestamp"] = (1728900030000i64 + n as i64 * 40 * 60 * 1000).into();
+        for participant in match_data["info"]["participants"].as_array_mut().unwrap() {
+            participant["win"] = ((participant["teamId"] == 100) == won(n)).into();
+        }
+        Mock::given(method("GET"))
+            .and(path(format!("/EUROPE/lol/match/v5/matches/{}", match_id)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(match_data))
+            .with_priority(1)
+            .mount(&env.server)
+            .await;
+    }
+}
+
+#[tokio::test]
+async fn flags_losing_streaks_within_a_session() {
+    let env = TestEnv::start("tilt").await;
+    env.mock_fixture_history(&["EUW1_8", "EUW1_7", "EUW1_6", "EUW1_5", "EUW1_4", "EUW1_3", "EUW1_2", "EUW1_1"]).await;
+    // A win, then three losses.
+    mock_games_40_minutes_apart(&env, &["EUW1_2", "EUW1_4", "EUW1_6", "EUW1_8"], |n| n == 0).await;
+
+    let args = ["--region", "EUW", "--between", "2024-10-01", "2024-10-31", "Fixture0#EUW", "Fixture3#EUW"];
+    let output = env.run(&[&["--json"], &args[..]].concat()).await;
+    let sessions = json_stdout(&output)["sessions"].as_array().unwrap().clone();
+    assert_eq!(sessions.len(), 1);
+    let streaks = sessions[0]["losingStreaks"].as_array().unwrap();
+    assert_eq!(streaks.len(), 1);
+    assert_eq!(streaks[0]["firstGame"], 2);
+    assert_eq!(streaks[0]["losses"], 3);
+
+    let output = env.run(&[&["--no-pager"], &args[..]].concat()).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Tilt on Monday morning (2024-10-14): Should have stopped after game 1."), "{}", stdout);
+}
+
+#[tokio::test]
+async fn reports_game_durations_and_queue_gaps() {
+    let env = TestEnv::start("durations").await;
+    env.mock_fixture_history(&["EUW1_6", "EUW1_5", "EUW1_4", "EUW1_3", "EUW1_2", "EUW1_1"]).await;
+    mock_games_40_minutes_apart(&env, &["EUW1_2", "EUW1_4", "EUW1_6"], |_| true).await;
+
+    let args = ["--region", "EUW", "--stats", "--between", "2024-10-01", "2024-10-31", "Fixture0#EUW", "Fixture3#EUW"];
+    let output = env.run(&[&["--json"], &args[..]].concat()).await;
+    let durations = &json_stdout(&output)["stats"]["durations"];
+    assert_eq!(durations["histogram"], serde_json::json!([{ "fromMinutes": 30, "toMinutes": 35, "games": 3 }]));
+    assert_eq!(durations["averageDurationSecs"], 1800.0);
+    assert_eq!(durations["averageQueueGapSecs"], 600.0);
+
+    let output = env.run(&[&["--no-pager"], &args[..]].concat()).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("--- Game Durations ---"), "{}", stdout);
+    assert!(stdout.contains("Average gap between games in a session: 10m"), "{}", stdout);
+}
+
+#[tokio::test]
+async fn compares_champion_pools_from_the_index() {
+    let env = TestEnv::start("champion-pool").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    json_stdout(&env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await);
+
+    let output = json_stdout(&env.run(&["--json", "champion-pool", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    assert_eq!(output["player1Games"], 2);
+    assert_eq!(output["player2Games"], 1);
+    assert_eq!(output["player1MainRole"], "TOP");
+    assert_eq!(output["player2MainRole"], "BOTTOM");
+    assert_eq!(output["roles"][0]["player1"][0]["champion"], "Garen");
+    assert_eq!(output["sharedChampions"].as_array().unwrap().len(), 0);
+}
+
+#[tokio::test]
+async fn tracks_riot_id_changes_of_contacts() {
+    let env = TestEnv::start("renames").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/EUROPE/riot/account/v1/accounts/by-puuid/{}", PLAYER2_PUUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "puuid": PLAYER2_PUUID,
+            "gameName": "Renamed",
+            "tagLine": "EUW",
+        })))
+        .mount(&env.server)
+        .await;
+    // A contact that cannot be looked up comes first and must not hold up the others.
+    Mock::given(method("GET"))
+        .and(path("/EUROPE/riot/account/v1/accounts/by-riot-id/Broken/EUW"))
+        .respond_with(ResponseTemplate::new(403))
+        .mount(&env.server)
+        .await;
+    env.run(&["fav", "add", "Broken#EUW"]).await;
+    env.run(&["fav", "add", "Fixture3#EUW"]).await;
+
+    let refresh = env.run(&["--region", "EUW", "--json", "contacts", "refresh"]).await;
+    assert!(stderr(&refresh).contains("Warning: Could not look up 'Broken#EUW'"), "{}", stderr(&refresh));
+    let changes = json_stdout(&refresh);
+    assert_eq!(changes[0]["previous"], "Fixture3#EUW");
+    assert_eq!(changes[0]["current"], "Renamed#EUW");
+    let list = env.run(&["contacts", "list"]).await;
+    assert!(String::from_utf8_lossy(&list.stdout).contains("Renamed#EUW (formerly Fixture3#EUW)"));
+
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Renamed#EUW"]).await);
+    assert_eq!(output["querySummary"]["player2"]["formerlyKnownAs"][0], "Fixture3#EUW");
+    assert_eq!(output["querySummary"]["matchesPlayedTogetherCount"], 1);
+}
+
+#[tokio::test]
+async fn contacts_follow_renames_seen_in_queries() {
+    let env = TestEnv::start("renames-from-queries").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    env.mock_account("Renamed", PLAYER2_PUUID).await;
+    json_stdout(&env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    env.run(&["contacts", "nick", "Fixture3#EUW", "Bob"]).await;
+
+    // The query under the new Riot ID caches it for the stored PUUID; the next run renames the contact.
+    json_stdout(&env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Renamed#EUW"]).await);
+    let list = env.run(&["contacts", "list"]).await;
+    assert!(stderr(&list).contains("Fixture3#EUW is now Renamed#EUW"), "{}", stderr(&list));
+    assert!(String::from_utf8_lossy(&list.stdout).contains("Bob (Renamed#EUW) (formerly Fixture3#EUW)"), "{}", String::from_utf8_lossy(&list.stdout));
+
+    // Adding the contact again under its new Riot ID finds the same contact by PUUID.
+    env.run(&["fav", "add", "Renamed#EUW"]).await;
+    let list = String::from_utf8_lossy(&env.run(&["contacts", "list"]).await.stdout).to_string();
+    assert_eq!(list.lines().count(), 1, "{}", list);
+}
+
+#[tokio::test]
+async fn accepts_puuids_in_place_of_riot_ids() {
+    let env = TestEnv::start("puuid-input").await;
+    let puuid = "p".repeat(78);
+    Mock::given(method("GET"))
+        .and(path(format!("/EUROPE/riot/account/v1/accounts/by-puuid/{}", puuid)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "puuid": puuid,
+            "gameName": "Exported",
+            "tagLine": "EUW",
+        })))
+        .expect(1)
+        .mount(&env.server)
+        .await;
+
+    let output = env.run(&["fav", "add", &puuid]).await;
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Added 'Exported#EUW'"), "{}", stderr(&output));
+    // The second lookup is answered from the account cache.
+    let output = env.run(&["fav", "remove", &puuid]).await;
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Removed 'Exported#EUW'"), "{}", stderr(&output));
+}
+
+#[tokio::test]
+async fn exists_stops_at_the_first_shared_match() {
+    let env = TestEnv::start("exists").await;
+    env.mock_fixture_history(&["EUW1_3", "EUW1_2", "EUW1_1"]).await;
+
+    let output = env.run(&["--region", "EUW", "--exists", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("played together in EUW1_3"));
+    let requests = env.server.received_requests().await.unwrap();
+    assert!(!requests.iter().any(|request| request.url.path().ends_with("/EUW1_2")), "kept scanning after the first shared match");
+
+    let output = env.run(&["--region", "EUW", "--exists", "--anonymize", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    assert_eq!(output.status.code(), Some(0), "{}", stderr(&output));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("#ANON and Player-") && stdout.contains("played together in match-"), "{}", stdout);
+    assert!(!stdout.contains("Fixture") && !stdout.contains("EUW1_"), "{}", stdout);
+
+    // The matches skipped by --exists are not indexed, so the index must not claim to cover the window.
+    let output = env.run(&["--region", "EUW", "--offline", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    assert!(stderr(&output).contains("the match list of PUUID"), "{}", stderr(&output));
+
+    let output = env.run(&["--region", "EUW", "--exists", "--silent", "Fixture0#EUW", "Stranger#EUW"]).await;
+    assert_eq!(output.status.code(), Some(2), "{}", stderr(&output));
+}
+
+#[tokio::test]
+async fn exists_fails_when_no_match_is_shared() {
+    let env = TestEnv::start("exists-not-found").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    env.mock_account("Fixture3", PLAYER2_PUUID).await;
+    env.mock_match_ids(&["EUW1_9"]).await;
+    env.mock_match("EUW1_9", false).await;
+
+    let output = env.run(&["--region", "EUW", "--exists", "--silent", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    assert!(output.stdout.is_empty());
+}
+
+#[tokio::test]
+async fn weights_recent_games_in_the_form_score() {
+    let env = TestEnv::start("recent-form").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    env.mock_account("Fixture3", PLAYER2_PUUID).await;
+    env.mock_match_ids(&["EUW1_4", "EUW1_3", "EUW1_2", "EUW1_1"]).await;
+    // The two newest games were won, the two oldest lost.
+    for (age, match_id) in ["EUW1_4", "EUW1_3", "EUW1_2", "EUW1_1"].iter().enumerate() {
+        let mut match_data: Value = serde_json::from_str(FIXTURE_MATCH).unwrap();
+        match_data["metadata"]["matchId"] = (*match_id).into();
+        match_data["info"]["gameStartTimestamp"] = (1_728_900_030_000_i64 - age as i64 * 3_600_000).into();
+        if age >= 2 {
+            for participant in match_data["info"]["participants"].as_array_mut().unwrap() {
+                let won = participant["win"].as_bool().unwrap();
+                participant["win"] = (!won).into();
+            }
+        }
+        Mock::given(method("GET"))
+            .and(path(format!("/EUROPE/lol/match/v5/matches/{}", match_id)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(match_data))
+            .mount(&env.server)
+            .await;
+    }
+
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    let form = &output["stats"]["recentForm"];
+    assert_eq!(form["games"], 4);
+    assert_eq!(form["winRate"], 50.0);
+    assert_eq!(form["trend"], "up");
+    assert!(form["score"].as_f64().unwrap() > 50.0);
+}
+
+#[tokio::test]
+async fn awards_the_more_supportive_friend_from_timelines() {
+    let env = TestEnv::start("supportive").await;
+    env.mock_fixture_history(&["EUW1_1"]).await;
+    let kill = |killer: i32, assister: i32| {
+        serde_json::json!({"timestamp": 60000, "type": "CHAMPION_KILL", "killerId": killer, "assistingParticipantIds": [assister]})
+    };
+    Mock::given(method("GET"))
+        .and(path("/EUROPE/lol/match/v5/matches/EUW1_1/timeline"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "metadata": {"dataVersion": "2", "matchId": "EUW1_1", "participants": [PLAYER1_PUUID, PLAYER2_PUUID]},
+            "info": {"frameInterval": 60000, "frames": [{"timestamp": 60000, "events": [kill(1, 4), kill(1, 4), kill(4, 1)]}]},
+        })))
+        .mount(&env.server)
+        .await;
+
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "--timeline", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    let achievements = output["achievements"].as_array().unwrap();
+    let supportive = achievements.iter().find(|a| a["id"] == "mostSupportive").expect("no mostSupportive achievement");
+    assert_eq!(
+        supportive["description"],
+        "Player 1 assisted on 1 of Player 2's kills and Player 2 on 2 of Player 1's. Player 2 is the more supportive friend."
+    );
+}
+
+#[tokio::test]
+async fn leaves_out_games_with_a_likely_afk() {
+    let env = TestEnv::start("afk").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    env.mock_account("Fixture3", PLAYER2_PUUID).await;
+    env.mock_match_ids(&["EUW1_2", "EUW1_1"]).await;
+    for (match_id, time_spent_dead) in [("EUW1_2", 1200), ("EUW1_1", 60)] {
+        let mut match_data: Value = serde_json::from_str(FIXTURE_MATCH).unwrap();
+        match_data["metadata"]["matchId"] = match_id.into();
+        match_data["info"]["participants"][3]["totalTimeSpentDead"] = time_spent_dead.into();
+        Mock::given(method("GET"))
+            .and(path(format!("/EUROPE/lol/match/v5/matches/{}", match_id)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(match_data))
+            .mount(&env.server)
+            .await;
+    }
+
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    assert_eq!(output["querySummary"]["matchesPlayedTogetherCount"], 1);
+    assert_eq!(output["querySummary"]["likelyAfkMatchesCount"], 1);
+    assert_eq!(output["foundMatches"][0]["matchId"], "EUW1_1");
+
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "--include-afk", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    assert_eq!(output["querySummary"]["matchesPlayedTogetherCount"], 2);
+    assert_eq!(output["foundMatches"][0]["player2Details"]["likelyAfk"], true);
+    assert_eq!(output["foundMatches"][0]["player1Details"]["likelyAfk"], false);
+
+    let output = env.run(&["--region", "EUW", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Left out 1 shared games where one of the players likely went AFK"));
+}
+
+#[tokio::test]
+async fn breaks_the_summary_down_per_queue() {
+    let env = TestEnv::start("per-queue").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    env.mock_account("Fixture3", PLAYER2_PUUID).await;
+    env.mock_match_ids(&["EUW1_3", "EUW1_2", "EUW1_1"]).await;
+    for (match_id, queue_id) in [("EUW1_3", 450), ("EUW1_2", 450), ("EUW1_1", 420)] {
+        let mut match_data: Value = serde_json::from_str(FIXTURE_MATCH).unwrap();
+        match_data["metadata"]["matchId"] = match_id.into();
+        match_data["info"]["queueId"] = queue_id.into();
+        Mock::given(method("GET"))
+            .and(path(format!("/EUROPE/lol/match/v5/matches/{}", match_id)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(match_data))
+            .mount(&env.server)
+            .await;
+    }
+
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    let per_queue = &output["querySummary"]["perQueue"];
+    assert_eq!(per_queue[0]["queue"], "solo");
+    assert_eq!(per_queue[0]["games"], 1);
+    assert_eq!(per_queue[1]["queue"], "aram");
+    assert_eq!(per_queue[1]["games"], 2);
+    assert_eq!(per_queue[1]["winRate"], 100.0);
+
+    let output = env.run(&["--region", "EUW", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Per queue: Solo/Duo 1W-0L (100%), ARAM 2W-0L (100%)."));
+}
+
+#[tokio::test]
+async fn reports_first_blood_and_early_surrenders() {
+    let env = TestEnv::start("early-game").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    env.mock_account("Fixture3", PLAYER2_PUUID).await;
+    env.mock_match_ids(&["EUW1_2", "EUW1_1"]).await;
+    for match_id in ["EUW1_2", "EUW1_1"] {
+        let mut match_data: Value = serde_json::from_str(FIXTURE_MATCH).unwrap();
+        match_data["metadata"]["matchId"] = match_id.into();
+        for participant in match_data["info"]["participants"].as_array_mut().unwrap() {
+            participant["firstBloodKill"] = false.into();
+            participant["firstBloodAssist"] = false.into();
+            participant["gameEndedInEarlySurrender"] = (match_id == "EUW1_1").into();
+        }
+        if match_id == "EUW1_2" {
+            match_data["info"]["participants"][3]["firstBloodKill"] = true.into();
+        }
+        Mock::given(method("GET"))
+            .and(path(format!("/EUROPE/lol/match/v5/matches/{}", match_id)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(match_data))
+            .mount(&env.server)
+            .await;
+    }
+
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    let early_game = &output["stats"]["earlyGame"];
+    assert_eq!(early_game["firstBloodGames"], 1);
+    assert_eq!(early_game["firstBloodRate"], 50.0);
+    assert_eq!(early_game["player1FirstBloods"], 0);
+    assert_eq!(early_game["player2FirstBloods"], 1);
+    assert_eq!(early_game["earlySurrenders"], 1);
+    assert_eq!(early_game["earlySurrenderRate"], 50.0);
+}
+
+#[tokio::test]
+async fn suggests_a_lineup_from_the_index() {
+    let env = TestEnv::start("lineup").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    json_stdout(&env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await);
+
+    let output = json_stdout(&env.run(&["--json", "lineup", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    assert_eq!(output["games"], 1);
+    assert_eq!(output["players"][0]["mainRole"], "TOP");
+    assert_eq!(output["players"][1]["consistency"], 100.0);
+    assert_eq!(output["suggestedLineup"], serde_json::json!(["TOP", "BOTTOM"]));
+    assert_eq!(output["warnings"].as_array().unwrap().len(), 0);
+
+    let output = env.run(&["lineup", "Fixture0#EUW", "Uncached#EUW"]).await;
+    assert!(stderr(&output).contains("not in the local cache"));
+}
+
+#[tokio::test]
+async fn scouts_how_often_a_roster_plays_together() {
+    let env = TestEnv::start("scout").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/EUROPE/lol/match/v5/matches/by-puuid/{}/ids", PLAYER2_PUUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(["EUW1_2"]))
+        .mount(&env.server)
+        .await;
+
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "scout", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    assert_eq!(output["checkedMatchesCount"], 2);
+    assert_eq!(output["players"][0]["games"], 2);
+    assert_eq!(output["players"][0]["likelyRole"], "TOP");
+    assert_eq!(output["players"][0]["topChampions"][0]["champion"], "Garen");
+    assert_eq!(output["players"][1]["games"], 1);
+    assert_eq!(output["pairs"][0]["games"], 1);
+    assert_eq!(output["pairs"][0]["winRate"], 100.0);
+    assert_eq!(output["fullRosterGames"], 1);
+
+    // The PUUIDs are cached, so scouting again looks up no account.
+    env.run(&["--region", "EUW", "--json", "scout", "Fixture0#EUW", "Fixture3#EUW"]).await;
+    let account_lookups = env
+        .server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.url.path().contains("/accounts/by-riot-id/"))
+        .count();
+    assert_eq!(account_lookups, 2);
+}
+
+#[tokio::test]
+async fn suggests_bans_against_a_scouted_roster() {
+    let env = TestEnv::start("scout-bans").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/EUROPE/lol/match/v5/matches/by-puuid/{}/ids", PLAYER2_PUUID)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(["EUW1_2"]))
+        .mount(&env.server)
+        .await;
+
+    let bans = json_stdout(&env.run(&["--region", "EUW", "scout", "Fixture0#EUW", "Fixture3#EUW", "--bans", "json"]).await);
+    assert_eq!(bans[0]["champion"], "Garen");
+    assert_eq!(bans[0]["games"], 2);
+    assert_eq!(bans[1]["champion"], "Jinx");
+
+    let output = env.run(&["--region", "EUW", "scout", "Fixture0#EUW", "Fixture3#EUW", "--bans", "markdown"]).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("| 1 | Garen | Fixture0#EUW | 2 | 100% |"), "{}", stdout);
+}
+
+#[tokio::test]
+async fn digests_the_week_with_favorites() {
+    let env = TestEnv::start("digest").await;
+    env.mock_account("Fixture0", PLAYER1_PUUID).await;
+    env.mock_account("Fixture3", PLAYER2_PUUID).await;
+    env.mock_match_ids(&["EUW1_2", "EUW1_1"]).await;
+    let now_ms = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as i64;
+    // Only the newer game falls into the past week.
+    for (match_id, game_start) in [("EUW1_2", now_ms - 3_600_000), ("EUW1_1", now_ms - 30 * 24 * 3_600_000)] {
+        let mut match_data: Value = serde_json::from_str(FIXTURE_MATCH).unwrap();
+        match_data["metadata"]["matchId"] = match_id.into();
+        match_data["info"]["gameStartTimestamp"] = game_start.into();
+        Mock::given(method("GET"))
+            .and(path(format!("/EUROPE/lol/match/v5/matches/{}", match_id)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(match_data))
+            .mount(&env.server)
+            .await;
+    }
+    env.run(&["--self", "Fixture0#EUW"]).await;
+    env.run(&["fav", "add", "Fixture3#EUW"]).await;
+    env.run(&["fav", "add", "Missing#EUW"]).await;
+
+    let digest = json_stdout(&env.run(&["--region", "EUW", "--json", "digest", "--weekly"]).await);
+    assert_eq!(digest["games"], 1);
+    assert_eq!(digest["pairs"][0]["partner"], "Fixture3#EUW");
+    assert_eq!(digest["pairs"][0]["winRate"], 100.0);
+    assert_eq!(digest["pairs"].as_array().unwrap().len(), 1);
+    assert_eq!(digest["unchecked"][0]["partner"], "Missing#EUW");
+
+    let output = env.run(&["--region", "EUW", "digest", "--weekly"]).await;
+    assert!(String::from_utf8_lossy(&output.stdout).contains("- Could not check Missing#EUW: "), "{}", String::from_utf8_lossy(&output.stdout));
+
+    let output = env.run(&["--region", "EUW", "digest", "--weekly", "--format", "html"]).await;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("<tr><td>Fixture3#EUW</td><td>1</td><td>1</td><td>100%</td></tr>"), "{}", stdout);
+}
+
+#[tokio::test]
+async fn repeated_sqlite_exports_accumulate_without_duplicates() {
+    let env = TestEnv::start("sqlite").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    let path = env.home.join("games.sqlite");
+    let args = ["--region", "EUW", "--json", "--export", "sqlite", path.to_str().unwrap(), "Fixture0#EUW", "Fixture3#EUW"];
+    let count = |table: &str| -> i64 {
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        connection.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0)).unwrap()
+    };
+
+    json_stdout(&env.run(&args).await);
+    assert_eq!([count("query_runs"), count("matches"), count("participants"), count("query_run_matches")], [1, 1, 2, 1]);
+
+    // EUW1_2 is found again and EUW1_3 is new.
+    env.server.reset().await;
+    env.mock_fixture_history(&["EUW1_3", "EUW1_2", "EUW1_1"]).await;
+    assert_eq!(json_stdout(&env.run(&args).await)["querySummary"]["matchesPlayedTogetherCount"], 2);
+    assert_eq!([count("query_runs"), count("matches"), count("participants"), count("query_run_matches")], [2, 2, 4, 3]);
+    let connection = rusqlite::Connection::open(&path).unwrap();
+    let champion: String = connection
+        .query_row("SELECT champion FROM participants WHERE match_id = 'EUW1_3' AND riot_id = 'Fixture3#EUW'", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(champion, "Jinx");
+}
+
+type ParquetRow = std::collections::HashMap<String, parquet::record::Field>;
+
+/// The columns of a Parquet file as (name, optional) pairs, and its rows as name to field maps.
+fn read_parquet(path: &std::path::Path) -> (Vec<(String, bool)>, Vec<ParquetRow>) {
+    use parquet::basic::Repetition;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    let reader = SerializedFileReader::new(std::fs::File::open(path).unwrap()).unwrap();
+    let columns = reader
+        .metadata()
+        .file_metadata()
+        .schema_descr()
+        .columns()
+        .iter()
+        .map(|column| (column.name().to_string(), column.self_type().get_basic_info().repetition() == Repetition::OPTIONAL))
+        .collect();
+    let rows = reader
+        .get_row_iter(None)
+        .unwrap()
+        .map(|row| row.unwrap().get_column_iter().map(|(name, field)| (name.clone(), field.clone())).collect())
+        .collect();
+    (columns, rows)
+}
+
+#[tokio::test]
+async fn exports_matches_and_participants_as_parquet() {
+    use parquet::record::Field;
+
+    let env = TestEnv::start("parquet").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    let dir = env.home.join("parquet");
+    json_stdout(&env.run(&["--region", "EUW", "--json", "--export", "parquet", dir.to_str().unwrap(), "Fixture0#EUW", "Fixture3#EUW"]).await);
+
+    let (columns, rows) = read_parquet(&dir.join("matches.parquet"));
+    let optional: Vec<&str> = columns.iter().filter(|(_, optional)| *optional).map(|(name, _)| name.as_str()).collect();
+    assert_eq!(columns.len(), 14);
+    assert_eq!(optional, ["game_type", "gold_diff_at15", "game_flow", "league_of_graphs_link"]);
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0]["match_id"], Field::Str("EUW1_2".to_string()));
+    assert_eq!(rows[0]["game_type"], Field::Str("MATCHED_GAME".to_string()));
+    // Without --timeline there is no gold difference.
+    assert_eq!(rows[0]["gold_diff_at15"], Field::Null);
+    assert_eq!(rows[0]["league_of_graphs_link"], Field::Str("https://www.leagueofgraphs.com/match/euw/2".to_string()));
+
+    let (columns, rows) = read_parquet(&dir.join("participants.parquet"));
+    let names: Vec<&str> = columns.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["match_id", "player", "riot_id", "champion", "role", "kills", "deaths", "assists", "win"]);
+    assert!(columns.iter().all(|(_, optional)| !optional));
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[1]["riot_id"], Field::Str("Fixture3#EUW".to_string()));
+    assert_eq!(rows[1]["champion"], Field::Str("Jinx".to_string()));
+    assert_eq!(rows[1]["win"], Field::Bool(true));
+}
+
+/// Adds an `[integrations]` section with `settings` to the test config.
+fn configure_integrations(env: &TestEnv, settings: &str) {
+    let config_path = env.home.join(".config/ptg/config.toml");
+    let config = std::fs::read_to_string(&config_path).unwrap();
+    std::fs::write(&config_path, format!("{}\n[integrations]\n{}\n", config, settings)).unwrap();
+}
+
+/// Bodies of the requests the mock server received at `request_path`, as JSON.
+async fn posted_json(env: &TestEnv, request_path: &str) -> Vec<Value> {
+    env.server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.url.path() == request_path)
+        .map(|request| serde_json::from_slice(&request.body).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn sends_telegram_messages_within_the_length_limit() {
+    let env = TestEnv::start("telegram").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    Mock::given(method("POST"))
+        .and(path("/botTOKEN/sendMessage"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "ok": true })))
+        .mount(&env.server)
+        .await;
+    configure_integrations(
+        &env,
+        &format!("telegram_bot_token = \"TOKEN\"\ntelegram_chat_id = \"42\"\ntelegram_api_url = \"{}\"", env.server.uri()),
+    );
+    let args = ["--region", "EUW", "--json", "--notify-target", "telegram", "Fixture0#EUW", "Fixture3#EUW"];
+
+    json_stdout(&env.run(&args).await);
+    let bodies = posted_json(&env, "/botTOKEN/sendMessage").await;
+    assert_eq!(bodies.len(), 1);
+    assert_eq!(bodies[0]["chat_id"], "42");
+    assert_eq!(bodies[0]["disable_web_page_preview"], true);
+    assert_eq!(
+        bodies[0]["text"],
+        "Fixture0#EUW and Fixture3#EUW played 1 of 2 checked games together, 1 won.\nhttps://www.leagueofgraphs.com/match/euw/2"
+    );
+
+    // A nickname longer than a message is cut off at Telegram's limit.
+    env.run(&["contacts", "nick", "Fixture3#EUW", &"x".repeat(5000)]).await;
+    json_stdout(&env.run(&args).await);
+    let bodies = posted_json(&env, "/botTOKEN/sendMessage").await;
+    assert_eq!(bodies[1]["text"].as_str().unwrap().chars().count(), 4096);
+}
+
+#[tokio::test]
+async fn posts_slack_block_kit_messages() {
+    let env = TestEnv::start("slack").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    Mock::given(method("POST"))
+        .and(path("/slack"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&env.server)
+        .await;
+    configure_integrations(&env, &format!("slack_webhook_url = \"{}/slack\"", env.server.uri()));
+
+    json_stdout(&env.run(&["--region", "EUW", "--json", "--notify-target", "slack", "--notify-matches", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    let bodies = posted_json(&env, "/slack").await;
+    assert_eq!(bodies.len(), 2);
+    let (alert, summary) = if bodies[0].get("blocks").is_some() { (&bodies[1], &bodies[0]) } else { (&bodies[0], &bodies[1]) };
+    assert!(alert["text"].as_str().unwrap().starts_with("New game together: Fixture0#EUW (Garen) and Fixture3#EUW (Jinx)"), "{}", alert);
+    assert!(summary["text"].as_str().unwrap().starts_with("Fixture0#EUW and Fixture3#EUW played 1 of 2 checked games together"), "{}", summary);
+    let blocks = summary["blocks"].as_array().unwrap();
+    assert_eq!(blocks[0]["text"]["text"], "Fixture0#EUW + Fixture3#EUW");
+    assert_eq!(blocks[1]["fields"][0]["text"], "*Games together*\n1 of 2");
+    assert_eq!(blocks[1]["fields"][1]["text"], "*Wins*\n1 (100%)");
+    assert_eq!(blocks[2]["type"], "divider");
+    let match_lines = blocks[3]["text"]["text"].as_str().unwrap();
+    assert!(match_lines.starts_with(":white_check_mark: <https://www.leagueofgraphs.com/match/euw/2|"), "{}", match_lines);
+}
+
+#[tokio::test]
+async fn posts_the_results_to_a_webhook() {
+    let env = TestEnv::start("webhook").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .respond_with(ResponseTemplate::new(204))
+        .mount(&env.server)
+        .await;
+    let url = format!("{}/hook", env.server.uri());
+    let hook_requests = || async {
+        env.server
+            .received_requests()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|request| request.url.path() == "/hook")
+            .collect::<Vec<_>>()
+    };
+
+    let output = json_stdout(&env.run(&["--region", "EUW", "--json", "--webhook", &url, "Fixture0#EUW", "Fixture3#EUW"]).await);
+    let requests = hook_requests().await;
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].headers.get("content-type").unwrap(), "application/json");
+    let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(body["querySummary"], output["querySummary"]);
+    assert_eq!(body["foundMatches"][0]["matchId"], "EUW1_2");
+
+    let template = env.home.join("hook.tera");
+    std::fs::write(&template, "{{ querySummary.player1.gameName }}: {{ querySummary.matchesPlayedTogetherCount }} together").unwrap();
+    let args = ["--webhook-template", template.to_str().unwrap(), "--webhook-content-type", "text/plain"];
+    json_stdout(&env.run(&[&["--region", "EUW", "--json", "--webhook", &url][..], &args[..], &["Fixture0#EUW", "Fixture3#EUW"]].concat()).await);
+    let requests = hook_requests().await;
+    assert_eq!(requests[1].headers.get("content-type").unwrap(), "text/plain");
+    assert_eq!(String::from_utf8_lossy(&requests[1].body), "Fixture0: 1 together");
+}
+
+#[tokio::test]
+async fn exports_shared_games_as_calendar_events() {
+    let env = TestEnv::start("ics").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    let path = env.home.join("games.ics");
+
+    let output = env.run(&["--region", "EUW", "--json", "--export", "ics", path.to_str().unwrap(), "Fixture0#EUW", "Fixture3#EUW"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    let calendar = std::fs::read_to_string(&path).unwrap();
+    assert!(calendar.starts_with("BEGIN:VCALENDAR\r\n"));
+    assert_eq!(calendar.matches("BEGIN:VEVENT").count(), 1);
+    assert!(calendar.contains("UID:EUW1_2@playedtogether\r\n"));
+    assert!(calendar.contains("SUMMARY:Victory: Garen & Jinx"), "{}", calendar);
+    assert!(calendar.lines().all(|line| line.len() <= 75));
+}
+
+#[tokio::test]
+async fn builds_a_static_site_from_the_index() {
+    let env = TestEnv::start("site").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    json_stdout(&env.run(&["--region", "EUW", "--json", "Fixture0#EUW", "Fixture3#EUW"]).await);
+    env.run(&["--self", "Fixture0#EUW"]).await;
+    env.run(&["fav", "add", "Fixture3#EUW"]).await;
+    let dir = env.home.join("site");
+
+    let report = json_stdout(&env.run(&["--json", "site", "build", dir.to_str().unwrap()]).await);
+    assert_eq!(report["pairPages"], 1);
+    assert_eq!(report["matchPages"], 1);
+    let index = std::fs::read_to_string(dir.join("index.html")).unwrap();
+    assert!(index.contains("<a href=\"fixture3-euw/index.html\">Fixture3#EUW</a>"), "{}", index);
+    let pair_page = std::fs::read_to_string(dir.join("fixture3-euw").join("index.html")).unwrap();
+    assert!(pair_page.contains("<a href=\"EUW1_2.html\">"), "{}", pair_page);
+    let match_page = std::fs::read_to_string(dir.join("fixture3-euw").join("EUW1_2.html")).unwrap();
+    assert!(match_page.contains("Victory"));
+}
+
+#[tokio::test]
+async fn exports_a_png_summary_card() {
+    let env = TestEnv::start("png").await;
+    env.mock_fixture_history(&["EUW1_2", "EUW1_1"]).await;
+    let path = env.home.join("card.png");
+
+    let output = env.run(&["--region", "EUW", "--json", "--export", "png", path.to_str().unwrap(), "Fixture0#EUW", "Fixture3#EUW"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    let png = std::fs::read(&path).unwrap();
+    assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+    assert_eq!(&png[12..16], b"IHDR");
+    assert_eq!(u32::from_be_bytes(png[16..20].try_into().unwrap()), 600);
+    assert_eq!(u32::from_be_bytes(png[20..24].try_into().unwrap()), 315);
+    assert!(png.ends_with(b"IEND\xae\x42\x60\x82"));
+}
+
+#[tokio::test]
+async fn writes_a_rolling_win_rate_chart() {
+    let env = TestEnv::start("chart").await;
+    env.mock_fixture_history(&["EUW1_4", "EUW1_3", "EUW1_2", "EUW1_1"]).await;
+    let path = env.home.join("winrate.svg");
+
+    let output = env.run(&["--region", "EUW", "--json", "--chart", path.to_str().unwrap(), "Fixture0#EUW", "Fixture3#EUW"]).await;
+    assert!(output.status.success(), "{}", stderr(&output));
+    let svg = std::fs::read_to_string(&path).unwrap();
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.contains("<polyline points="));
+    assert!(svg.contains(">100%</text>"));
+
+    let output = env.run(&["--region", "EUW", "--json", "--chart", path.to_str().unwrap(), "Fixture0#EUW", "Fixture3#EUW", "-n", "1"]).await;
+    assert!(stderr(&output).contains("at least two shared games"), "{}", stderr(&output));
+}
@@ -0,0 +1,142 @@
+//! Test environment shared by the integration tests: a wiremock server standing in for the Riot API
+//! and private config and cache directories for the `ptg` binary.
+
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Output;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+pub const FIXTURE_MATCH: &str = include_str!("../fixtures/match.json");
+pub const PLAYER1_PUUID: &str = "fixture-puuid-0";
+pub const PLAYER2_PUUID: &str = "fixture-puuid-3";
+
+pub struct TestEnv {
+    pub server: MockServer,
+    pub home: PathBuf,
+}
+
+impl TestEnv {
+    pub async fn start(name: &str) -> Self {
+        let server = MockServer::start().await;
+        let home = std::env::temp_dir().join(format!("ptg-cli-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&home);
+        let config_dir = home.join(".config").join("ptg");
+        fs::create_dir_all(&config_dir).unwrap();
+        fs::write(
+            config_dir.join("config.toml"),
+            format!("api_key = \"RGAPI-test\"\n\n[network]\nbase_url = \"{}/{{}}\"\n", server.uri()),
+        )
+        .unwrap();
+        TestEnv { server, home }
+    }
+
+    pub async fn mock_account(&self, game_name: &str, puuid: &str) {
+        Mock::given(method("GET"))
+            .and(path(format!("/EUROPE/riot/account/v1/accounts/by-riot-id/{}/EUW", game_name)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "puuid": puuid,
+                "gameName": game_name,
+                "tagLine": "EUW",
+            })))
+            .mount(&self.server)
+            .await;
+    }
+
+    pub async fn mock_match_ids(&self, match_ids: &[&str]) {
+        Mock::given(method("GET"))
+            .and(path(format!("/EUROPE/lol/match/v5/matches/by-puuid/{}/ids", PLAYER1_PUUID)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(match_ids))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Serves the fixture match under `match_id`, with player2 replaced by a stranger unless `with_player2`.
+    pub async fn mock_match(&self, match_id: &str, with_player2: bool) {
+        let mut match_data: Value = serde_json::from_str(FIXTURE_MATCH).unwrap();
+        match_data["metadata"]["matchId"] = match_id.into();
+        if !with_player2 {
+            match_data["metadata"]["participants"][3] = "stranger-puuid".into();
+            match_data["info"]["participants"][3]["puuid"] = "stranger-puuid".into();
+        }
+        Mock::given(method("GET"))
+            .and(path(format!("/EUROPE/lol/match/v5/matches/{}", match_id)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(match_data))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Mocks both fixture players and a history of `match_ids`, every other one shared with player2.
+    pub async fn mock_fixture_history(&self, match_ids: &[&str]) {
+        self.mock_account("Fixture0", PLAYER1_PUUID).await;
+        self.mock_account("Fixture3", PLAYER2_PUUID).await;
+        self.mock_match_ids(match_ids).await;
+        for (i, match_id) in match_ids.iter().enumerate() {
+            self.mock_match(match_id, i % 2 == 0).await;
+        }
+    }
+
+    pub async fn run(&self, args: &[&str]) -> Output {
+        self.command(args).output().await.unwrap()
+    }
+
+    /// Runs `ptg` with `stdin` piped in, as in `ptg --json ... | ptg render -`.
+    #[allow(dead_code)] // Not every test crate sharing this module pipes input.
+    pub async fn run_with_stdin(&self, args: &[&str], stdin: &[u8]) -> Output {
+        use tokio::io::AsyncWriteExt;
+
+        let mut child = self.command(args)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut child_stdin = child.stdin.take().unwrap();
+        child_stdin.write_all(stdin).await.unwrap();
+        drop(child_stdin);
+        child.wait_with_output().await.unwrap()
+    }
+
+    /// Runs `ptg` with stdout on a pseudo-terminal, through util-linux `script`, so it pages its
+    /// output through `pager` like in an interactive shell.
+    #[cfg(target_os = "linux")]
+    #[allow(dead_code)] // Only the CLI tests page their output.
+    pub async fn run_in_terminal(&self, args: &[&str], pager: &str) -> Output {
+        let quoted: Vec<String> = std::iter::once(env!("CARGO_BIN_EXE_ptg"))
+            .chain(args.iter().copied())
+            .map(|arg| format!("'{}'", arg.replace('\'', "'\\''")))
+            .collect();
+        self.environment(tokio::process::Command::new("script"))
+            .args(["--quiet", "--return", "--command", &quoted.join(" "), "/dev/null"])
+            .env("PTG_PAGER", pager)
+            .output()
+            .await
+            .unwrap()
+    }
+
+    fn command(&self, args: &[&str]) -> tokio::process::Command {
+        let mut command = self.environment(tokio::process::Command::new(env!("CARGO_BIN_EXE_ptg")));
+        command.args(args);
+        command
+    }
+
+    /// `command` with the private config and cache directories of this environment.
+    fn environment(&self, mut command: tokio::process::Command) -> tokio::process::Command {
+        command
+            .env("HOME", &self.home)
+            .env("XDG_CONFIG_HOME", self.home.join(".config"))
+            .env("XDG_CACHE_HOME", self.home.join(".cache"))
+            .env("NO_COLOR", "1")
+            .env("TZ", "UTC")
+            .env_remove("RGAPI_KEY")
+            .env_remove("PTG_CONFIG_PASSPHRASE");
+        command
+    }
+}
+
+impl Drop for TestEnv {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.home);
+    }
+}
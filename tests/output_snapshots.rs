@@ -0,0 +1,117 @@
+//! Snapshots of every output format for a fixed fixture history, so formatting changes show up
+//! in review instead of silently breaking scripts that parse the output.
+//! Update them with `cargo insta review` after an intended change.
+
+mod common;
+
+use common::TestEnv;
+use ptg::api_client::OverallOutput;
+use std::io::Write;
+
+const QUERY: [&str; 4] = ["--region", "EUW", "Fixture0#EUW", "Fixture3#EUW"];
+const HISTORY: [&str; 3] = ["EUW1_3", "EUW1_2", "EUW1_1"];
+
+/// Runs a query over the fixture history and returns its stdout.
+async fn query_output(name: &str, extra_args: &[&str]) -> String {
+    let env = TestEnv::start(name).await;
+    env.mock_fixture_history(&HISTORY).await;
+    let args: Vec<&str> = QUERY.iter().chain(extra_args).copied().collect();
+    let output = env.run(&args).await;
+    assert!(output.status.success(), "ptg failed: {}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Snapshots `output` with the measured wall time masked, the only part that differs between runs.
+fn assert_output_snapshot(name: &str, output: String) {
+    insta::with_settings!({filters => vec![
+        (r"in \d+\.\ds\.", "in [WALL TIME]s."),
+        (r#""wallTimeMs": \d+"#, r#""wallTimeMs": "[WALL TIME]""#),
+    ]}, {
+        insta::assert_snapshot!(name, output);
+    });
+}
+
+#[tokio::test]
+async fn text_summary() {
+    let output = query_output("snapshot-text", &[]).await;
+    assert_output_snapshot("text_summary", output);
+}
+
+#[tokio::test]
+async fn stats_tables() {
+    let output = query_output("snapshot-stats", &["--stats", "--silent"]).await;
+    assert_output_snapshot("stats_tables", output);
+}
+
+#[tokio::test]
+async fn duo_timeline_chart() {
+    let output = query_output("snapshot-duo-timeline", &["--duo-timeline", "--silent"]).await;
+    assert_output_snapshot("duo_timeline_chart", output);
+}
+
+#[tokio::test]
+async fn verbose_match_boxes() {
+    let output = query_output("snapshot-verbose", &["--verbose"]).await;
+    assert_output_snapshot("verbose_match_boxes", output);
+}
+
+#[tokio::test]
+async fn oneline() {
+    let output = query_output("snapshot-oneline", &["--oneline"]).await;
+    assert_output_snapshot("oneline", output);
+}
+
+#[tokio::test]
+async fn json() {
+    let output = query_output("snapshot-json", &["--json"]).await;
+    assert_output_snapshot("json", output);
+}
+
+/// Records the size of every write it receives.
+#[derive(Default)]
+struct RecordingWriter {
+    bytes: Vec<u8>,
+    writes: Vec<usize>,
+}
+
+impl Write for RecordingWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.bytes.extend_from_slice(buf);
+        self.writes.push(buf.len());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn json_is_written_in_chunks() {
+    let mut output: serde_json::Value = serde_json::from_str(&query_output("snapshot-json-chunks", &["--json"]).await).unwrap();
+    // A deep history, far larger than one chunk.
+    let found_matches = output["foundMatches"].as_array().unwrap().clone();
+    output["foundMatches"] = found_matches.iter().cycle().take(300).cloned().collect();
+    let output: OverallOutput = serde_json::from_value(output).unwrap();
+
+    let mut writer = RecordingWriter::default();
+    output.write_json(&mut writer).unwrap();
+    assert!(writer.writes.len() > 1, "written at once");
+    assert!(writer.writes.iter().all(|&size| size <= 64 * 1024), "{:?}", writer.writes);
+    let expected = serde_json::to_string_pretty(&output).unwrap() + "\n";
+    assert_eq!(String::from_utf8(writer.bytes).unwrap(), expected);
+}
+
+#[tokio::test]
+async fn markdown_template() {
+    let template = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/report.md");
+    let output = query_output("snapshot-markdown", &["--template", template]).await;
+    assert_output_snapshot("markdown_template", output);
+}
+
+#[tokio::test]
+async fn html_template() {
+    let template = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/report.html");
+    let output = query_output("snapshot-html", &["--template", template]).await;
+    assert_output_snapshot("html_template", output);
+}
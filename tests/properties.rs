@@ -0,0 +1,297 @@
+//! Property-based checks of Riot ID parsing, the region tables and date ranges, run over generated
+//! inputs including unusual names (spaces, non-Latin scripts, punctuation), and focused checks of
+//! the building blocks behind the statistics, the index, the stored secrets, the integrations and the
+//! notifications.
+
+use proptest::prelude::*;
+use proptest::sample::select;
+use riven::consts::Queue;
+use riven::models::match_v5::{Match, Timeline};
+use std::collections::HashSet;
+use std::path::Path;
+
+use chrono::{Days, NaiveDate};
+use ptg::cli::UserFacingRegion;
+use ptg::date_range::DateRange;
+use ptg::index::MatchIndex;
+use ptg::integrations::sheets_append_url;
+use ptg::key_lock::parse_rate_limits;
+use ptg::notify::applescript_string;
+use ptg::rate_limit::is_retry_record;
+use ptg::ranked::RankedSummary;
+use ptg::riot_id::RiotId;
+use ptg::secrets::EncryptedSecret;
+use ptg::stats::{classify_game_flow, patch_of, GameFlow};
+use ptg::timeline::lane_matchup;
+use ptg::utils::sparkline;
+
+const FIXTURE_MATCH: &str = include_str!("fixtures/match.json");
+
+/// A non-empty Riot ID part: any characters except `#`.
+fn riot_id_part(max_len: usize) -> impl Strategy<Value = String> {
+    proptest::string::string_regex(&format!("[^#]{{1,{}}}", max_len)).unwrap()
+}
+
+fn region() -> impl Strategy<Value = UserFacingRegion> {
+    select(UserFacingRegion::ALL.to_vec())
+}
+
+fn day() -> impl Strategy<Value = NaiveDate> {
+    (0u64..365 * 50).prop_map(|offset| NaiveDate::from_ymd_opt(2000, 1, 1).unwrap() + Days::new(offset))
+}
+
+proptest! {
+    #[test]
+    fn riot_id_display_round_trips(game_name in riot_id_part(16), tag_line in riot_id_part(5)) {
+        let riot_id = RiotId { game_name: game_name.clone(), tag_line: tag_line.clone(), puuid: None };
+        let parsed: RiotId = riot_id.to_string().parse().unwrap();
+        prop_assert_eq!(parsed.game_name, game_name);
+        prop_assert_eq!(parsed.tag_line, tag_line);
+    }
+
+    #[test]
+    fn riot_id_without_tag_is_rejected(input in "[^#]*") {
+        // Only PUUIDs are accepted without a tag line.
+        if let Ok(riot_id) = input.parse::<RiotId>() {
+            prop_assert_eq!(riot_id.unresolved_puuid(), Some(input.as_str()));
+        }
+    }
+
+    #[test]
+    fn puuid_is_kept_unresolved(puuid in "[A-Za-z0-9_-]{78}") {
+        let parsed: RiotId = puuid.parse().unwrap();
+        prop_assert_eq!(parsed.unresolved_puuid(), Some(puuid.as_str()));
+    }
+
+    #[test]
+    fn riot_id_with_several_tags_is_rejected(parts in proptest::collection::vec("[^#]*", 3..6)) {
+        prop_assert!(parts.join("#").parse::<RiotId>().is_err());
+    }
+
+    #[test]
+    fn riot_id_with_empty_part_is_rejected(part in riot_id_part(16)) {
+        let without_game_name = format!("#{}", part);
+        let without_tag_line = format!("{}#", part);
+        prop_assert!(without_game_name.parse::<RiotId>().is_err());
+        prop_assert!(without_tag_line.parse::<RiotId>().is_err());
+    }
+
+    #[test]
+    fn region_parses_in_any_case(region in region(), uppercase in proptest::collection::vec(any::<bool>(), 4)) {
+        let name: String = region
+            .to_log_string()
+            .chars()
+            .zip(uppercase.iter().cycle())
+            .map(|(c, &upper)| if upper { c.to_ascii_uppercase() } else { c })
+            .collect();
+        let parsed: UserFacingRegion = name.parse().unwrap();
+        prop_assert_eq!(parsed.to_log_string(), region.to_log_string());
+    }
+
+    #[test]
+    fn region_routes_are_consistent(region in region()) {
+        // The match history cluster must be the one Riot assigns to the region's platform.
+        prop_assert_eq!(region.to_regional_route(), region.to_platform_route().to_regional());
+    }
+
+    #[test]
+    fn consecutive_days_tile_without_gaps(day in day()) {
+        let today = DateRange::on(day).unwrap();
+        let tomorrow = DateRange::on(day + Days::new(1)).unwrap();
+        prop_assert_eq!(today.end_ms, tomorrow.start_ms);
+        prop_assert!(today.contains(today.start_ms));
+        prop_assert!(!today.contains(today.end_ms));
+    }
+
+    #[test]
+    fn between_spans_both_ends(first in day(), length in 0u64..60) {
+        let last = first + Days::new(length);
+        let range = DateRange::between(first, last).unwrap();
+        prop_assert_eq!(range.start_ms, DateRange::on(first).unwrap().start_ms);
+        prop_assert_eq!(range.end_ms, DateRange::on(last).unwrap().end_ms);
+        prop_assert!(DateRange::between(last + Days::new(1), first).is_err());
+    }
+
+    #[test]
+    fn unknown_region_is_rejected(input in "[A-Za-z]{1,6}") {
+        let known = UserFacingRegion::ALL.iter().any(|r| r.to_log_string().eq_ignore_ascii_case(&input));
+        prop_assert_eq!(input.parse::<UserFacingRegion>().is_ok(), known);
+    }
+}
+
+#[test]
+fn region_tables_are_one_to_one() {
+    let log_strings: HashSet<_> = UserFacingRegion::ALL.iter().map(|r| r.to_log_string()).collect();
+    let platforms: HashSet<_> = UserFacingRegion::ALL.iter().map(|r| r.to_platform_route()).collect();
+    assert_eq!(log_strings.len(), UserFacingRegion::ALL.len());
+    assert_eq!(platforms.len(), UserFacingRegion::ALL.len());
+}
+
+proptest! {
+    #[test]
+    fn sparklines_have_one_bar_per_value(values in proptest::collection::vec(0.0f64..1000.0, 1..50)) {
+        let line: Vec<char> = sparkline(&values).chars().collect();
+        prop_assert_eq!(line.len(), values.len());
+        let max = values.iter().copied().fold(0.0, f64::max);
+        for (bar, value) in line.iter().zip(&values) {
+            if *value == max && max > 0.0 {
+                prop_assert_eq!(*bar, '█');
+            }
+            prop_assert!(('▁'..='█').contains(bar));
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn patch_is_the_first_two_parts_of_the_version(major in 0u32..100, minor in 0u32..30, build in 0u32..1000, revision in 0u32..10_000) {
+        prop_assert_eq!(patch_of(&format!("{}.{}.{}.{}", major, minor, build, revision)), format!("{}.{}", major, minor));
+    }
+
+    #[test]
+    fn game_flow_is_the_same_from_either_side(gold_diff in -20_000i32..20_000, early_surrender: bool, surrender: bool) {
+        prop_assert_eq!(
+            classify_game_flow(true, Some(gold_diff), early_surrender, surrender),
+            classify_game_flow(false, Some(-gold_diff), early_surrender, surrender)
+        );
+    }
+
+    #[test]
+    fn ranked_summary_counts_only_ranked_games(games in proptest::collection::vec((0usize..3, any::<bool>()), 0..40)) {
+        let queues = [Queue::SUMMONERS_RIFT_5V5_RANKED_SOLO, Queue::SUMMONERS_RIFT_5V5_RANKED_FLEX, Queue::SUMMONERS_RIFT_5V5_DRAFT_PICK];
+        let mut summary = RankedSummary::default();
+        for &(queue, won) in &games {
+            summary.record(queues[queue], won);
+        }
+        let ranked: Vec<bool> = games.iter().filter(|(queue, _)| *queue < 2).map(|&(_, won)| won).collect();
+        let net_wins = ranked.iter().filter(|&&won| won).count() as i32 - ranked.iter().filter(|&&won| !won).count() as i32;
+        prop_assert_eq!(summary.solo.games + summary.flex.games, ranked.len() as u32);
+        prop_assert_eq!(summary.net_wins, net_wins);
+        prop_assert_eq!(summary.estimated_net_lp, net_wins * 20);
+        prop_assert_eq!(summary.has_games(), !ranked.is_empty());
+    }
+}
+
+#[test]
+fn game_flow_follows_the_winners_gold_lead_at_15() {
+    assert_eq!(classify_game_flow(true, Some(6_000), false, false), Some(GameFlow::Stomp));
+    assert_eq!(classify_game_flow(true, Some(1_000), false, false), Some(GameFlow::Close));
+    assert_eq!(classify_game_flow(true, Some(-3_000), false, false), Some(GameFlow::Comeback));
+    assert_eq!(classify_game_flow(false, Some(3_000), false, false), Some(GameFlow::Comeback));
+    assert_eq!(classify_game_flow(true, Some(1_000), false, true), Some(GameFlow::Stomp));
+    assert_eq!(classify_game_flow(false, Some(1_000), true, false), Some(GameFlow::Stomp));
+    // Without the timeline only surrenders tell anything.
+    assert_eq!(classify_game_flow(true, None, false, true), Some(GameFlow::Stomp));
+    assert_eq!(classify_game_flow(true, None, false, false), None);
+}
+
+#[test]
+fn encrypted_secrets_open_only_with_their_passphrase() {
+    let plaintext = br#"["RGAPI-test","RGAPI-extra"]"#;
+    let secret = EncryptedSecret::encrypt(plaintext, "correct horse").unwrap();
+    assert_eq!(secret.decrypt("correct horse").unwrap(), plaintext);
+    assert!(secret.decrypt("wrong horse").is_err());
+    // A fresh salt and nonce every time, so equal keys do not give equal files.
+    let again = EncryptedSecret::encrypt(plaintext, "correct horse").unwrap();
+    assert_ne!(again.ciphertext, secret.ciphertext);
+    assert_eq!(again.decrypt("correct horse").unwrap(), plaintext);
+}
+
+/// A timeline frame entry with only the creep score of interest set.
+fn participant_frame(participant_id: i32, minions_killed: i32, jungle_minions_killed: i32) -> serde_json::Value {
+    let zeros = |fields: &[&str]| fields.iter().map(|field| (field.to_string(), serde_json::json!(0))).collect::<serde_json::Map<_, _>>();
+    serde_json::json!({
+        "championStats": zeros(&[
+            "abilityPower", "armor", "armorPen", "armorPenPercent", "attackDamage", "attackSpeed", "bonusArmorPenPercent",
+            "bonusMagicPenPercent", "ccReduction", "cooldownReduction", "health", "healthMax", "healthRegen", "lifesteal",
+            "magicPen", "magicPenPercent", "magicResist", "movementSpeed", "power", "powerMax", "powerRegen", "spellVamp",
+        ]),
+        "damageStats": zeros(&[
+            "magicDamageDone", "magicDamageDoneToChampions", "magicDamageTaken", "physicalDamageDone",
+            "physicalDamageDoneToChampions", "physicalDamageTaken", "totalDamageDone", "totalDamageDoneToChampions",
+            "totalDamageTaken", "trueDamageDone", "trueDamageDoneToChampions", "trueDamageTaken",
+        ]),
+        "currentGold": 0, "goldPerSecond": 0, "level": 1, "participantId": participant_id, "position": {"x": 0, "y": 0},
+        "timeEnemySpentControlled": 0, "totalGold": 0, "xp": 0,
+        "minionsKilled": minions_killed, "jungleMinionsKilled": jungle_minions_killed,
+    })
+}
+
+#[test]
+fn lane_matchup_counts_creeps_at_10_and_solo_kills_in_lane() {
+    let kill = |minute: i64, killer: i32, victim: i32, assists: &[i32]| {
+        serde_json::json!({"timestamp": minute * 60_000, "type": "CHAMPION_KILL", "killerId": killer, "victimId": victim, "assistingParticipantIds": assists})
+    };
+    let timeline: Timeline = serde_json::from_value(serde_json::json!({
+        "metadata": {"dataVersion": "2", "matchId": "EUW1_1", "participants": []},
+        "info": {"frameInterval": 60_000, "frames": [
+            {"timestamp": 300_000, "events": [kill(5, 1, 6, &[]), kill(6, 6, 1, &[7])]},
+            {"timestamp": 600_000, "events": [kill(10, 6, 1, &[])], "participantFrames": {
+                "1": participant_frame(1, 80, 4),
+                "6": participant_frame(6, 70, 0),
+            }},
+            {"timestamp": 1_200_000, "events": [kill(20, 1, 6, &[])]},
+        ]},
+    }))
+    .unwrap();
+
+    let matchup = lane_matchup(&timeline, 1, 6);
+    assert_eq!(matchup.cs_diff_at10, Some(14));
+    // The assisted kill and the one after the laning phase do not count.
+    assert_eq!(matchup.player1_solo_kills, 1);
+    assert_eq!(matchup.player2_solo_kills, 1);
+}
+
+#[test]
+fn pruning_the_index_drops_the_oldest_matches_first() {
+    let index = MatchIndex::open_at(Path::new(":memory:")).unwrap();
+    let template: Match = serde_json::from_str(FIXTURE_MATCH).unwrap();
+    for i in 0..20 {
+        let mut match_data = template.clone();
+        match_data.metadata.match_id = format!("EUW1_{}", i);
+        match_data.info.game_start_timestamp += i * 3_600_000;
+        index.insert_match(&match_data).unwrap();
+    }
+    let size = index.size_bytes().unwrap();
+    assert_eq!(index.prune_to_size(size).unwrap(), 0);
+
+    let removed = index.prune_to_size(size / 2).unwrap();
+    assert!(removed > 0 && removed < 20, "removed {}", removed);
+    assert!(!index.contains_match("EUW1_0").unwrap());
+    assert!(index.contains_match("EUW1_19").unwrap());
+    assert!(index.size_bytes().unwrap() <= size / 2);
+}
+
+#[test]
+fn percent_encodes_the_sheet_range() {
+    let url = sheets_append_url("sheet-id", "Duo Log!A1").unwrap();
+    assert_eq!(url.as_str(), "https://sheets.googleapis.com/v4/spreadsheets/sheet-id/values/Duo%20Log!A1:append");
+    let url = sheets_append_url("sheet-id", "A/B#1").unwrap();
+    assert_eq!(url.path(), "/v4/spreadsheets/sheet-id/values/A%2FB%231:append");
+}
+
+#[test]
+fn escapes_applescript_strings() {
+    assert_eq!(applescript_string("Bob & Ann: 3 wins"), r#""Bob & Ann: 3 wins""#);
+    assert_eq!(applescript_string(r#"say "gg" \o/"#), r#""say \"gg\" \\o/""#);
+    // Rust's debug escapes (\u{..}, \') mean nothing to AppleScript, so other characters stay as they are.
+    assert_eq!(applescript_string("Zoë's\tgame"), "\"Zoë's\tgame\"");
+}
+
+#[test]
+fn parses_rate_limits_like_riot_lists_them() {
+    assert_eq!(parse_rate_limits("20:1,100:120").unwrap(), [(20, 1_000), (100, 120_000)]);
+    assert_eq!(parse_rate_limits("500:10, 30000:600").unwrap(), [(500, 10_000), (30_000, 600_000)]);
+    for invalid in ["", "20", "20:0", "0:1", "20:1,fast"] {
+        assert!(parse_rate_limits(invalid).is_err(), "{}", invalid);
+    }
+}
+
+#[test]
+fn recognizes_rivens_retry_records() {
+    assert!(is_retry_record("Response 429 Too Many Requests (retried 0 times), `retry-after` set, retrying after 1.5s."));
+    assert!(is_retry_record("Response 503 Service Unavailable (retried 1 times), NO `retry-after`, using exponential backoff, retrying after 4s."));
+    assert!(is_retry_record("Request failed with cause \"connection refused\", (retried 0 times), using exponential backoff, retrying after 1s."));
+    assert!(!is_retry_record("Response 404 Not Found (retried 0 times), failure, returning error."));
+    assert!(!is_retry_record("429 response, rate limit Method, retry-after header: `1`, delaying 1.5 secs."));
+}